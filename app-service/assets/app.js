@@ -1,19 +1,29 @@
 const loginLink = document.getElementById("login-link");
 const logoutLink = document.getElementById("logout-link");
 const protectImg = document.getElementById("protected-img");
+const userEmail = document.getElementById("user-email");
 
 logoutLink.addEventListener("click", (e) => {
     e.preventDefault();
 
     let url = logoutLink.href;
 
+    // Issued by the auth service along with the auth cookie
+    const csrfToken = document.cookie
+        .split("; ")
+        .find((cookie) => cookie.startsWith("csrf_token="));
+
     fetch(url, {
         method: 'POST',
         credentials: 'include', // This will include cookies in the request
+        headers: csrfToken === undefined
+            ? {}
+            : { 'X-CSRF-Token': csrfToken.substring("csrf_token=".length) },
     }).then(response => {
         if (response.ok) {
             loginLink.style.display = "block";
             logoutLink.style.display = "none";
+            userEmail.style.display = "none";
             protectImg.src = "/assets/default.jpg";
         } else {
             alert("Failed to logout");
@@ -28,6 +38,9 @@ logoutLink.addEventListener("click", (e) => {
             logoutLink.style.display = "block";
 
             response.json().then(data => {
+                userEmail.textContent = `Logged in as ${data.email}`;
+                userEmail.style.display = "block";
+
                 let img_url = data.img_url;
                 if (img_url !== undefined && img_url !== null && img_url !== "") {
                     protectImg.src = img_url;
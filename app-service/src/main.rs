@@ -1,22 +1,24 @@
 use std::env;
 
 use askama::Template;
+use auth_middleware::{AuthenticatedUser, TokenVerifier, DEFAULT_CACHE_TTL};
 use axum::{
-    http::StatusCode,
     response::{Html, IntoResponse},
     routing::get,
     Json, Router,
 };
-use axum_extra::extract::CookieJar;
 use serde::Serialize;
 use tower_http::services::ServeDir;
 
+const PROTECTED_IMG_URL: &str = "https://i.ibb.co/YP90j68/Light-Live-Bootcamp-Certificate.png";
+
 #[tokio::main]
 async fn main() {
     let app = Router::new()
         .nest_service("/assets", ServeDir::new("assets"))
         .route("/", get(root))
-        .route("/protected", get(protected));
+        .route("/protected", get(protected))
+        .with_state(token_verifier());
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:8000").await.unwrap();
 
@@ -46,43 +48,39 @@ async fn root() -> impl IntoResponse {
     Html(template.render().unwrap())
 }
 
-async fn protected(jar: CookieJar) -> impl IntoResponse {
-    let jwt_cookie = match jar.get("jwt") {
-        Some(cookie) => cookie,
-        None => {
-            return StatusCode::UNAUTHORIZED.into_response();
-        }
-    };
-
-    let api_client = reqwest::Client::builder().build().unwrap();
-
-    let verify_token_body = serde_json::json!({
-        "token": &jwt_cookie.value(),
-    });
+async fn protected(user: AuthenticatedUser) -> Json<ProtectedRouteResponse> {
+    Json(ProtectedRouteResponse {
+        img_url: PROTECTED_IMG_URL.to_owned(),
+        email: user.subject,
+    })
+}
 
-    let auth_hostname = env::var("AUTH_SERVICE_HOST_NAME").unwrap_or("0.0.0.0".to_owned());
-    let url = format!("http://{}:3000/verify-token", auth_hostname);
+/// Introspects tokens when registered as a client of the auth service, or else has it verify
+/// them. Verifying them with its JWT secret, which misses tokens revoked by logging out, has to
+/// be switched on with `LOCAL_TOKEN_VERIFICATION=true`.
+fn token_verifier() -> TokenVerifier {
+    let var = |name| env::var(name).ok().filter(|value| !value.is_empty());
 
-    let response = match api_client.post(&url).json(&verify_token_body).send().await {
-        Ok(response) => response,
-        Err(_) => {
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-        }
-    };
+    if var("LOCAL_TOKEN_VERIFICATION").as_deref() == Some("true") {
+        let jwt_secret = var("JWT_SECRET").expect("LOCAL_TOKEN_VERIFICATION needs JWT_SECRET");
+        return TokenVerifier::local(jwt_secret.as_bytes());
+    }
 
-    match response.status() {
-        reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::BAD_REQUEST => {
-            StatusCode::UNAUTHORIZED.into_response()
+    let auth_hostname = var("AUTH_SERVICE_HOST_NAME").unwrap_or("0.0.0.0".to_owned());
+    let url = format!("http://{}:3000", auth_hostname);
+    match (
+        var("APP_SERVICE_CLIENT_ID"),
+        var("APP_SERVICE_CLIENT_SECRET"),
+    ) {
+        (Some(client_id), Some(client_secret)) => {
+            TokenVerifier::remote(&url, client_id, client_secret, DEFAULT_CACHE_TTL)
         }
-        reqwest::StatusCode::OK => Json(ProtectedRouteResponse {
-            img_url: "https://i.ibb.co/YP90j68/Light-Live-Bootcamp-Certificate.png".to_owned(),
-        })
-        .into_response(),
-        _ => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        _ => TokenVerifier::remote_unregistered(&url, DEFAULT_CACHE_TTL),
     }
 }
 
 #[derive(Serialize)]
 pub struct ProtectedRouteResponse {
     pub img_url: String,
+    pub email: String,
 }
@@ -1,3 +1,25 @@
+// Set when an app sent the user here to log in through /oauth/authorize, or a reverse proxy
+// through /forward-auth. Only paths back to those endpoints are followed, so the parameter
+// can't be used to redirect anywhere else.
+const returnTo = new URLSearchParams(window.location.search).get("return_to");
+const safeReturnTo = returnTo !== null
+    && (returnTo.startsWith("/oauth/authorize?") || returnTo.startsWith("/forward-auth/return?"))
+    ? returnTo
+    : null;
+
+// Requests carrying the auth cookie, e.g. logging in again, must send back the CSRF token
+// issued with it.
+function jsonHeaders() {
+    const headers = { 'Content-Type': 'application/json' };
+    const csrfToken = document.cookie
+        .split("; ")
+        .find((cookie) => cookie.startsWith("csrf_token="));
+    if (csrfToken !== undefined) {
+        headers['X-CSRF-Token'] = csrfToken.substring("csrf_token=".length);
+    }
+    return headers;
+}
+
 const loginSection = document.getElementById("login-section");
 const twoFASection = document.getElementById("2fa-section");
 const signupSection = document.getElementById("signup-section");
@@ -44,9 +66,7 @@ loginButton.addEventListener("click", (e) => {
 
     fetch('/login', {
         method: 'POST',
-        headers: {
-            'Content-Type': 'application/json',
-        },
+        headers: jsonHeaders(),
         body: JSON.stringify({ email, password }),
     }).then(response => {
         if (response.status === 206) {
@@ -66,6 +86,10 @@ loginButton.addEventListener("click", (e) => {
             loginForm.email.value = "";
             loginForm.password.value = "";
             loginErrAlter.style.display = "none";
+            if (safeReturnTo !== null) {
+                window.location.assign(safeReturnTo);
+                return;
+            }
             alert("You have successfully logged in.");
         } else {
             response.json().then(data => {
@@ -94,9 +118,7 @@ signupButton.addEventListener("click", (e) => {
 
     fetch('/signup', {
         method: 'POST',
-        headers: {
-            'Content-Type': 'application/json',
-        },
+        headers: jsonHeaders(),
         body: JSON.stringify({ email, password, requires2FA }),
     }).then(response => {
         if (response.ok) {
@@ -135,9 +157,7 @@ TwoFAButton.addEventListener("click", (e) => {
 
     fetch('/verify-2fa', {
         method: 'POST',
-        headers: {
-            'Content-Type': 'application/json',
-        },
+        headers: jsonHeaders(),
         body: JSON.stringify({ email, loginAttemptId, "2FACode": TwoFACode }),
     }).then(response => {
         if (response.ok) {
@@ -145,6 +165,10 @@ TwoFAButton.addEventListener("click", (e) => {
             TwoFAForm.email_code.value = "";
             TwoFAForm.login_attempt_id.value = "";
             TwoFAErrAlter.style.display = "none";
+            if (safeReturnTo !== null) {
+                window.location.assign(safeReturnTo);
+                return;
+            }
             alert("You have successfully logged in.");
             loginSection.style.display = "block";
             twoFASection.style.display = "none";
@@ -1,5 +1,18 @@
 // generated by `sqlx migrate build-script`
-fn main() {
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     // trigger recompilation when a new migration is added
     println!("cargo:rerun-if-changed=migrations");
+    println!("cargo:rerun-if-changed=migrations_sqlite");
+
+    // So building doesn't need protoc installed
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    tonic_build::configure().compile_protos(
+        &[
+            "proto/token_service.proto",
+            "proto/envoy/service/auth/v3/external_auth.proto",
+        ],
+        &["proto"],
+    )?;
+
+    Ok(())
 }
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS audit_events;
+DROP FUNCTION IF EXISTS reject_audit_event_modification();
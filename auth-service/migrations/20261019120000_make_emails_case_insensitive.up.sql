@@ -0,0 +1,26 @@
+-- Add up migration script here
+-- Emails are unique regardless of case. Refuse to migrate while existing accounts collide,
+-- since deciding which of them to keep needs a human.
+DO $$
+DECLARE
+   collisions TEXT;
+BEGIN
+   SELECT string_agg(emails, '; ')
+   INTO collisions
+   FROM (
+      SELECT string_agg(email, ', ' ORDER BY email) AS emails
+      FROM users
+      GROUP BY lower(email)
+      HAVING COUNT(*) > 1
+   ) AS duplicates;
+
+   IF collisions IS NOT NULL THEN
+      RAISE EXCEPTION 'Accounts whose emails only differ in case must be merged first: %', collisions;
+   END IF;
+END
+$$;
+
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_lower_key ON users (lower(email));
+
+-- 2FA codes are now keyed by the lowercased email, pending ones for mixed-case emails are dropped
+DELETE FROM two_fa_codes WHERE email <> lower(email);
@@ -0,0 +1,6 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS email_domain_rules(
+   domain TEXT NOT NULL PRIMARY KEY,
+   rule TEXT NOT NULL CHECK (rule IN ('allow', 'deny')),
+   created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,13 @@
+-- Add up migration script here
+-- Only a SHA-256 hash of each key's secret is stored. The prefix is the public part of the key.
+CREATE TABLE IF NOT EXISTS api_keys(
+   prefix TEXT NOT NULL PRIMARY KEY,
+   email TEXT NOT NULL,
+   name TEXT NOT NULL,
+   secret_hash TEXT NOT NULL,
+   scopes TEXT[] NOT NULL,
+   created_at TIMESTAMPTZ NOT NULL,
+   expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS api_keys_email_idx ON api_keys (lower(email));
@@ -0,0 +1,16 @@
+-- Add up migration script here
+-- Emails are looked up by the key the service computes with `Email::lookup_key`. lower()
+-- only folds ASCII under some collations, so matching on it can miss letters Rust lowercases.
+-- Existing rows start out with lower(email) and the service corrects the keys that differ when
+-- it starts. Keys are required, so a row written without one can't slip past the unique index.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_key TEXT;
+UPDATE users SET email_key = lower(email) WHERE email_key IS NULL;
+ALTER TABLE users ALTER COLUMN email_key SET NOT NULL;
+DROP INDEX IF EXISTS users_email_lower_key;
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_key_key ON users (email_key);
+
+ALTER TABLE api_keys ADD COLUMN IF NOT EXISTS email_key TEXT;
+UPDATE api_keys SET email_key = lower(email) WHERE email_key IS NULL;
+ALTER TABLE api_keys ALTER COLUMN email_key SET NOT NULL;
+DROP INDEX IF EXISTS api_keys_email_idx;
+CREATE INDEX IF NOT EXISTS api_keys_email_key_idx ON api_keys (email_key);
@@ -0,0 +1,8 @@
+-- Add up migration script here
+-- Ids of magic links that were already used, kept until the links expire anyway
+CREATE TABLE IF NOT EXISTS used_magic_links(
+   link_id TEXT NOT NULL PRIMARY KEY,
+   expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS used_magic_links_expires_at_idx ON used_magic_links (expires_at);
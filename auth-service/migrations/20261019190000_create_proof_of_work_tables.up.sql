@@ -0,0 +1,19 @@
+-- Add up migration script here
+-- Attempts counted per action in fixed windows, to decide when proof of work is required
+CREATE TABLE IF NOT EXISTS proof_of_work_attempts(
+   action TEXT NOT NULL,
+   window_number BIGINT NOT NULL,
+   attempts BIGINT NOT NULL,
+   expires_at TIMESTAMPTZ NOT NULL,
+   PRIMARY KEY (action, window_number)
+);
+
+CREATE INDEX IF NOT EXISTS proof_of_work_attempts_expires_at_idx ON proof_of_work_attempts (expires_at);
+
+-- Ids of solved challenges, kept until the challenges expire anyway
+CREATE TABLE IF NOT EXISTS used_proof_of_work_challenges(
+   challenge_id TEXT NOT NULL PRIMARY KEY,
+   expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS used_proof_of_work_challenges_expires_at_idx ON used_proof_of_work_challenges (expires_at);
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TRIGGER IF EXISTS audit_events_reject_delete;
+DROP TRIGGER IF EXISTS audit_events_reject_update;
+DROP TABLE IF EXISTS audit_events;
@@ -0,0 +1,29 @@
+-- Add up migration script here
+-- occurred_at is stored as a Unix timestamp in microseconds
+CREATE TABLE IF NOT EXISTS audit_events(
+   id INTEGER PRIMARY KEY AUTOINCREMENT,
+   occurred_at INTEGER NOT NULL,
+   event_type TEXT NOT NULL,
+   email TEXT,
+   success BOOLEAN NOT NULL,
+   reason TEXT,
+   ip_address TEXT,
+   user_agent TEXT,
+   request_id TEXT
+);
+
+CREATE INDEX IF NOT EXISTS audit_events_email_occurred_at_idx ON audit_events (email, occurred_at);
+CREATE INDEX IF NOT EXISTS audit_events_occurred_at_idx ON audit_events (occurred_at);
+
+-- Audit events are append-only
+CREATE TRIGGER IF NOT EXISTS audit_events_reject_update
+   BEFORE UPDATE ON audit_events
+BEGIN
+   SELECT RAISE(ABORT, 'audit_events is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_events_reject_delete
+   BEFORE DELETE ON audit_events
+BEGIN
+   SELECT RAISE(ABORT, 'audit_events is append-only');
+END;
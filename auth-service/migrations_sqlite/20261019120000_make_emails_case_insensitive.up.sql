@@ -0,0 +1,22 @@
+-- Add up migration script here
+-- Emails are unique regardless of case. SQLite has no way to raise an error from a plain
+-- script, so colliding accounts trip the named CHECK constraint below and abort the migration.
+CREATE TEMP TABLE email_case_collisions(
+   count INTEGER NOT NULL CONSTRAINT accounts_whose_emails_only_differ_in_case_must_be_merged CHECK (count = 0)
+);
+
+INSERT INTO email_case_collisions (count)
+SELECT COUNT(*)
+FROM (
+   SELECT lower(email)
+   FROM users
+   GROUP BY lower(email)
+   HAVING COUNT(*) > 1
+);
+
+DROP TABLE email_case_collisions;
+
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_lower_key ON users (lower(email));
+
+-- 2FA codes are now keyed by the lowercased email, pending ones for mixed-case emails are dropped
+DELETE FROM two_fa_codes WHERE email <> lower(email);
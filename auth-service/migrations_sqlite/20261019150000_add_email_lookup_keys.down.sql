@@ -0,0 +1,8 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS api_keys_email_key_idx;
+ALTER TABLE api_keys DROP COLUMN email_key;
+CREATE INDEX IF NOT EXISTS api_keys_email_idx ON api_keys (lower(email));
+
+DROP INDEX IF EXISTS users_email_key_key;
+ALTER TABLE users DROP COLUMN email_key;
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_lower_key ON users (lower(email));
@@ -0,0 +1,33 @@
+-- Add up migration script here
+-- Emails are looked up by the key the service computes with `Email::lookup_key`. SQLite's
+-- lower() only folds ASCII, so matching on it misses letters Rust lowercases. Existing rows
+-- start out with lower(email) and the service corrects the keys that differ when it starts.
+-- Keys are required, so a row written without one can't slip past the unique index. SQLite
+-- can't add a NOT NULL column without a default, so both tables are rebuilt.
+CREATE TABLE users_with_email_keys(
+   email TEXT NOT NULL PRIMARY KEY,
+   email_key TEXT NOT NULL,
+   password_hash TEXT NOT NULL,
+   requires_2fa BOOLEAN NOT NULL DEFAULT FALSE
+);
+INSERT INTO users_with_email_keys (email, email_key, password_hash, requires_2fa)
+SELECT email, lower(email), password_hash, requires_2fa FROM users;
+DROP TABLE users;
+ALTER TABLE users_with_email_keys RENAME TO users;
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_key_key ON users (email_key);
+
+CREATE TABLE api_keys_with_email_keys(
+   prefix TEXT NOT NULL PRIMARY KEY,
+   email TEXT NOT NULL,
+   email_key TEXT NOT NULL,
+   name TEXT NOT NULL,
+   secret_hash TEXT NOT NULL,
+   scopes TEXT NOT NULL,
+   created_at INTEGER NOT NULL,
+   expires_at INTEGER NOT NULL
+);
+INSERT INTO api_keys_with_email_keys (prefix, email, email_key, name, secret_hash, scopes, created_at, expires_at)
+SELECT prefix, email, lower(email), name, secret_hash, scopes, created_at, expires_at FROM api_keys;
+DROP TABLE api_keys;
+ALTER TABLE api_keys_with_email_keys RENAME TO api_keys;
+CREATE INDEX IF NOT EXISTS api_keys_email_key_idx ON api_keys (email_key);
@@ -0,0 +1,11 @@
+-- Add up migration script here
+-- Logins through OAuth providers that haven't come back yet, keyed by their state parameter.
+-- Expiry times are stored as Unix timestamps in seconds.
+CREATE TABLE IF NOT EXISTS oauth_states(
+   state TEXT NOT NULL PRIMARY KEY,
+   provider TEXT NOT NULL,
+   code_verifier TEXT NOT NULL,
+   expires_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS oauth_states_expires_at_idx ON oauth_states (expires_at);
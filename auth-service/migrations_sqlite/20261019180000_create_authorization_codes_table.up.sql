@@ -0,0 +1,16 @@
+-- Add up migration script here
+-- Authorization codes issued to OpenID Connect clients that haven't been redeemed yet.
+-- Scopes are a JSON array, times are Unix timestamps in seconds.
+CREATE TABLE IF NOT EXISTS authorization_codes(
+   code TEXT NOT NULL PRIMARY KEY,
+   client_id TEXT NOT NULL,
+   redirect_uri TEXT NOT NULL,
+   subject TEXT NOT NULL,
+   scopes TEXT NOT NULL,
+   nonce TEXT,
+   code_challenge TEXT NOT NULL,
+   auth_time INTEGER NOT NULL,
+   expires_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS authorization_codes_expires_at_idx ON authorization_codes (expires_at);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS used_proof_of_work_challenges;
+DROP TABLE IF EXISTS proof_of_work_attempts;
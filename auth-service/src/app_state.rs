@@ -1,12 +1,32 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::domain::{BannedTokenStore, EmailClient, TwoFACodeStore, UserStore};
+use crate::{
+    domain::{
+        ApiKeyStore, AuditLogStore, AuthorizationCodeStore, BannedTokenStore,
+        BreachedPasswordChecker, EmailClient, EmailDomainRuleStore, MagicLinkStore, MxResolver,
+        OAuthStateStore, PasswordPolicy, ProofOfWorkStore, TwoFACodeStore, UserStore,
+    },
+    services::{
+        api_keys::ApiKeys, email_domain_policy::EmailDomainPolicy, forward_auth::ForwardAuth,
+        hashset_breached_password_checker::HashsetBreachedPasswordChecker, magic_link::MagicLinks,
+        oauth::OAuthClient, oidc::OidcProvider, proof_of_work::ProofOfWork,
+    },
+};
 
 pub type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
 pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
 pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>;
 pub type EmailClientType = Arc<dyn EmailClient + Send + Sync>;
+pub type AuditLogStoreType = Arc<RwLock<dyn AuditLogStore + Send + Sync>>;
+pub type BreachedPasswordCheckerType = Arc<dyn BreachedPasswordChecker + Send + Sync>;
+pub type EmailDomainRuleStoreType = Arc<RwLock<dyn EmailDomainRuleStore + Send + Sync>>;
+pub type MxResolverType = Arc<dyn MxResolver + Send + Sync>;
+pub type MagicLinkStoreType = Arc<RwLock<dyn MagicLinkStore + Send + Sync>>;
+pub type AuthorizationCodeStoreType = Arc<RwLock<dyn AuthorizationCodeStore + Send + Sync>>;
+pub type OAuthStateStoreType = Arc<RwLock<dyn OAuthStateStore + Send + Sync>>;
+pub type ProofOfWorkStoreType = Arc<RwLock<dyn ProofOfWorkStore + Send + Sync>>;
+pub type ApiKeyStoreType = Arc<RwLock<dyn ApiKeyStore + Send + Sync>>;
 
 #[derive(Clone)]
 pub struct AppState {
@@ -14,6 +34,16 @@ pub struct AppState {
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
     pub email_client: EmailClientType,
+    pub audit_log_store: AuditLogStoreType,
+    pub password_policy: PasswordPolicy,
+    pub breached_password_checker: BreachedPasswordCheckerType,
+    pub email_domain_policy: EmailDomainPolicy,
+    pub proof_of_work: ProofOfWork,
+    pub magic_links: MagicLinks,
+    pub oauth_client: OAuthClient,
+    pub oidc_provider: OidcProvider,
+    pub api_keys: ApiKeys,
+    pub forward_auth: ForwardAuth,
 }
 
 impl AppState {
@@ -22,12 +52,71 @@ impl AppState {
         banned_token_store: BannedTokenStoreType,
         two_fa_code_store: TwoFACodeStoreType,
         email_client: EmailClientType,
+        audit_log_store: AuditLogStoreType,
     ) -> Self {
         Self {
             user_store,
             banned_token_store,
             two_fa_code_store,
             email_client,
+            audit_log_store,
+            password_policy: PasswordPolicy::default(),
+            breached_password_checker: Arc::new(HashsetBreachedPasswordChecker::default()),
+            email_domain_policy: EmailDomainPolicy::default(),
+            proof_of_work: ProofOfWork::default(),
+            magic_links: MagicLinks::default(),
+            oauth_client: OAuthClient::default(),
+            oidc_provider: OidcProvider::default(),
+            api_keys: ApiKeys::default(),
+            forward_auth: ForwardAuth::default(),
         }
     }
+
+    pub fn with_password_policy(mut self, password_policy: PasswordPolicy) -> Self {
+        self.password_policy = password_policy;
+        self
+    }
+
+    pub fn with_breached_password_checker(
+        mut self,
+        breached_password_checker: BreachedPasswordCheckerType,
+    ) -> Self {
+        self.breached_password_checker = breached_password_checker;
+        self
+    }
+
+    pub fn with_email_domain_policy(mut self, email_domain_policy: EmailDomainPolicy) -> Self {
+        self.email_domain_policy = email_domain_policy;
+        self
+    }
+
+    pub fn with_proof_of_work(mut self, proof_of_work: ProofOfWork) -> Self {
+        self.proof_of_work = proof_of_work;
+        self
+    }
+
+    pub fn with_magic_links(mut self, magic_links: MagicLinks) -> Self {
+        self.magic_links = magic_links;
+        self
+    }
+
+    pub fn with_oauth_client(mut self, oauth_client: OAuthClient) -> Self {
+        self.oauth_client = oauth_client;
+        self
+    }
+
+    pub fn with_oidc_provider(mut self, oidc_provider: OidcProvider) -> Self {
+        self.oidc_provider = oidc_provider;
+        self
+    }
+
+    pub fn with_api_keys(mut self, api_keys: ApiKeys) -> Self {
+        self.api_keys = api_keys;
+        self
+    }
+
+    pub fn with_forward_auth(mut self, forward_auth: ForwardAuth) -> Self {
+        self.forward_auth = forward_auth;
+        self
+    }
 }
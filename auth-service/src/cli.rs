@@ -0,0 +1,198 @@
+use std::{fmt, path::PathBuf};
+
+use crate::{
+    app_state::UserStoreType,
+    domain::{
+        parse_email_domain, EmailDomainRule, UserListQuery, UserStoreError, MAX_USER_PAGE_SIZE,
+    },
+    services::password_hasher::Argon2PasswordHasher,
+};
+
+const USAGE: &str = "Usage: auth-service [serve | password-hash-report \
+    | import-users <users.jsonl|users.csv> | email-domain <allow|deny|remove> <domain> \
+    | email-domain list]";
+
+/// What the binary was asked to do. Running without arguments starts the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Serve,
+    PasswordHashReport,
+    ImportUsers {
+        path: PathBuf,
+    },
+    SetEmailDomainRule {
+        domain: String,
+        rule: EmailDomainRule,
+    },
+    RemoveEmailDomainRule {
+        domain: String,
+    },
+    ListEmailDomainRules,
+}
+
+impl Command {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let command = match args.next().as_deref() {
+            None | Some("serve") => Self::Serve,
+            Some("password-hash-report") => Self::PasswordHashReport,
+            Some("import-users") => match args.next() {
+                Some(path) => Self::ImportUsers { path: path.into() },
+                None => return Err(format!("Missing file to import\n{}", USAGE)),
+            },
+            Some("email-domain") => match args.next().as_deref() {
+                Some("list") => Self::ListEmailDomainRules,
+                Some(action @ ("allow" | "deny" | "remove")) => {
+                    let domain = args
+                        .next()
+                        .ok_or_else(|| format!("Missing domain\n{}", USAGE))
+                        .and_then(|domain| parse_email_domain(&domain))?;
+
+                    match action {
+                        "remove" => Self::RemoveEmailDomainRule { domain },
+                        rule => Self::SetEmailDomainRule {
+                            domain,
+                            rule: rule.parse()?,
+                        },
+                    }
+                }
+                Some(other) => {
+                    return Err(format!("Unknown email-domain action: {}\n{}", other, USAGE))
+                }
+                None => return Err(format!("Missing email-domain action\n{}", USAGE)),
+            },
+            Some(other) => return Err(format!("Unknown command: {}\n{}", other, USAGE)),
+        };
+
+        match args.next() {
+            Some(extra) => Err(format!("Unexpected argument: {}\n{}", extra, USAGE)),
+            None => Ok(command),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PasswordHashReport {
+    pub total_users: u64,
+    pub outdated_users: u64,
+}
+
+impl fmt::Display for PasswordHashReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} users have password hashes with outdated parameters",
+            self.outdated_users, self.total_users
+        )
+    }
+}
+
+/// Counts the users whose password hash would be replaced on their next login.
+pub async fn password_hash_report(
+    user_store: &UserStoreType,
+    password_hasher: &Argon2PasswordHasher,
+) -> Result<PasswordHashReport, UserStoreError> {
+    let user_store = user_store.read().await;
+    let mut report = PasswordHashReport::default();
+    let mut after = None;
+
+    loop {
+        let query = UserListQuery::new(after, MAX_USER_PAGE_SIZE)?;
+        let page = user_store.list_users(&query).await?;
+
+        for user in &page.users {
+            report.total_users += 1;
+            if password_hasher.needs_rehash(user.password.as_ref()) {
+                report.outdated_users += 1;
+            }
+        }
+
+        match page.next_cursor {
+            Some(cursor) => after = Some(cursor),
+            None => return Ok(report),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use argon2::Params;
+    use tokio::sync::RwLock;
+
+    use super::*;
+    use crate::{
+        domain::{Email, Password, User, UserStore},
+        services::data_stores::HashmapUserStore,
+    };
+
+    #[test]
+    fn parses_commands() {
+        let parse = |args: &[&str]| Command::parse(args.iter().map(|arg| arg.to_string()));
+
+        assert_eq!(parse(&[]), Ok(Command::Serve));
+        assert_eq!(parse(&["serve"]), Ok(Command::Serve));
+        assert_eq!(
+            parse(&["password-hash-report"]),
+            Ok(Command::PasswordHashReport)
+        );
+        assert_eq!(
+            parse(&["import-users", "users.csv"]),
+            Ok(Command::ImportUsers {
+                path: "users.csv".into()
+            })
+        );
+        assert!(parse(&["import-users"]).is_err());
+        assert_eq!(
+            parse(&["email-domain", "deny", "Spam.Example.com"]),
+            Ok(Command::SetEmailDomainRule {
+                domain: "spam.example.com".to_owned(),
+                rule: EmailDomainRule::Deny
+            })
+        );
+        assert_eq!(
+            parse(&["email-domain", "remove", "example.com"]),
+            Ok(Command::RemoveEmailDomainRule {
+                domain: "example.com".to_owned()
+            })
+        );
+        assert_eq!(
+            parse(&["email-domain", "list"]),
+            Ok(Command::ListEmailDomainRules)
+        );
+        assert!(parse(&["email-domain", "allow"]).is_err());
+        assert!(parse(&["email-domain", "allow", "not a domain"]).is_err());
+        assert!(parse(&["email-domain", "block", "example.com"]).is_err());
+        assert!(parse(&["unknown"]).is_err());
+        assert!(parse(&["serve", "extra"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn reports_users_hashed_with_other_params() {
+        let mut user_store = HashmapUserStore::default();
+        for email in ["a@example.com", "b@example.com"] {
+            let user = User::new(
+                Email::parse(email.to_owned()).unwrap(),
+                Password::parse("password123".to_owned()).unwrap(),
+                false,
+            );
+            user_store.add_user(user).await.unwrap();
+        }
+        let user_store: UserStoreType = Arc::new(RwLock::new(user_store));
+
+        let report = password_hash_report(&user_store, &Argon2PasswordHasher::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            report,
+            PasswordHashReport {
+                total_users: 2,
+                outdated_users: 0
+            }
+        );
+
+        let stronger = Argon2PasswordHasher::new(Params::new(19456, 2, 1, None).unwrap());
+        let report = password_hash_report(&user_store, &stronger).await.unwrap();
+        assert_eq!(report.outdated_users, 2);
+    }
+}
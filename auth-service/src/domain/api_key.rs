@@ -0,0 +1,182 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+const MAX_NAME_CHARS: usize = 100;
+const MAX_SCOPES: usize = 20;
+const MAX_SCOPE_CHARS: usize = 64;
+
+/// Users name the scopes of their own API keys within this namespace, so that a key can never
+/// carry a scope a service grants to registered clients.
+pub const USER_SCOPE_PREFIX: &str = "user:";
+/// Lets an API key list and revoke all of its owner's keys, which browser sessions always can.
+pub const MANAGE_API_KEYS_SCOPE: &str = "user:api-keys";
+
+/// A personal access token, minus its secret. Only a hash of the secret is kept, so the full
+/// key can't be shown again after it was created.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiKey {
+    /// The public part of the key, which it's looked up and revoked by.
+    pub prefix: String,
+    pub email: String,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl ApiKey {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|granted| granted == scope)
+    }
+}
+
+/// Why an API key can't be created as requested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyRejection {
+    InvalidName,
+    InvalidScope,
+    InvalidExpiry,
+    /// Keys created with another key can't have scopes that key doesn't have.
+    ScopeNotGranted,
+}
+
+impl ApiKeyRejection {
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::InvalidName => "API key names must be between 1 and 100 characters",
+            Self::InvalidScope => "API key scopes must be lowercase words such as user:deploy",
+            Self::InvalidExpiry => "Invalid API key expiry",
+            Self::ScopeNotGranted => "Cannot grant a scope the current API key does not have",
+        }
+    }
+}
+
+impl fmt::Display for ApiKeyRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+pub fn parse_api_key_name(s: &str) -> Result<String, ApiKeyRejection> {
+    let name = s.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+        return Err(ApiKeyRejection::InvalidName);
+    }
+
+    Ok(name.to_owned())
+}
+
+/// API key scopes are short lowercase words in the user namespace such as `user:deploy`, kept
+/// sorted and without duplicates.
+pub fn parse_api_key_scopes(scopes: Vec<String>) -> Result<Vec<String>, ApiKeyRejection> {
+    parse_scopes(scopes)
+        .filter(|scopes| scopes.iter().all(|scope| is_user_scope(scope)))
+        .ok_or(ApiKeyRejection::InvalidScope)
+}
+
+/// Scopes of registered clients, such as `read:users`. They're spelled like API key scopes but
+/// can't be in the user namespace.
+pub fn parse_service_scopes(scopes: Vec<String>) -> Option<Vec<String>> {
+    parse_scopes(scopes).filter(|scopes| !scopes.iter().any(|scope| is_user_scope(scope)))
+}
+
+fn is_user_scope(scope: &str) -> bool {
+    scope.len() > USER_SCOPE_PREFIX.len() && scope.starts_with(USER_SCOPE_PREFIX)
+}
+
+fn parse_scopes(scopes: Vec<String>) -> Option<Vec<String>> {
+    let mut scopes = scopes;
+    scopes.sort();
+    scopes.dedup();
+
+    let valid = scopes.len() <= MAX_SCOPES && scopes.iter().all(|scope| is_valid_scope(scope));
+    valid.then_some(scopes)
+}
+
+fn is_valid_scope(scope: &str) -> bool {
+    scope.len() <= MAX_SCOPE_CHARS
+        && scope.starts_with(|c: char| c.is_ascii_lowercase())
+        && scope
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || ":._-".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_trimmed_and_limited() {
+        assert_eq!(
+            parse_api_key_name("  CI deploys "),
+            Ok("CI deploys".to_owned())
+        );
+        assert_eq!(parse_api_key_name("   "), Err(ApiKeyRejection::InvalidName));
+        assert_eq!(
+            parse_api_key_name(&"a".repeat(MAX_NAME_CHARS + 1)),
+            Err(ApiKeyRejection::InvalidName)
+        );
+    }
+
+    #[test]
+    fn scopes_are_sorted_and_deduplicated() {
+        let scopes = vec![
+            "user:write".to_owned(),
+            "user:read".to_owned(),
+            "user:write".to_owned(),
+        ];
+        assert_eq!(
+            parse_api_key_scopes(scopes),
+            Ok(vec!["user:read".to_owned(), "user:write".to_owned()])
+        );
+        assert_eq!(parse_api_key_scopes(Vec::new()), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn malformed_scopes_are_rejected() {
+        for scope in [
+            "",
+            "user:",
+            "user:Read",
+            "user:read users",
+            "user:read*",
+            &format!("user:{}", "a".repeat(60)),
+        ] {
+            assert_eq!(
+                parse_api_key_scopes(vec![scope.to_owned()]),
+                Err(ApiKeyRejection::InvalidScope),
+                "{:?}",
+                scope
+            );
+        }
+
+        let too_many = (0..=MAX_SCOPES)
+            .map(|i| format!("user:scope{}", i))
+            .collect();
+        assert_eq!(
+            parse_api_key_scopes(too_many),
+            Err(ApiKeyRejection::InvalidScope)
+        );
+    }
+
+    #[test]
+    fn api_key_and_service_scopes_are_kept_apart() {
+        assert_eq!(
+            parse_api_key_scopes(vec!["read:users".to_owned()]),
+            Err(ApiKeyRejection::InvalidScope)
+        );
+        assert_eq!(
+            parse_service_scopes(vec!["read:users".to_owned()]),
+            Some(vec!["read:users".to_owned()])
+        );
+        assert_eq!(parse_service_scopes(vec!["user:deploy".to_owned()]), None);
+        assert_eq!(parse_service_scopes(vec!["Read".to_owned()]), None);
+    }
+}
@@ -0,0 +1,161 @@
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditEventType {
+    Signup,
+    Login,
+    TwoFACodeIssued,
+    TwoFAVerification,
+    MagicLinkIssued,
+    AuthorizationCodeIssued,
+    TokenIssued,
+    ApiKeyCreated,
+    ApiKeyRevoked,
+    Logout,
+    TokenRejected,
+}
+
+impl AuditEventType {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "signup" => Ok(Self::Signup),
+            "login" => Ok(Self::Login),
+            "two_fa_code_issued" => Ok(Self::TwoFACodeIssued),
+            "two_fa_verification" => Ok(Self::TwoFAVerification),
+            "magic_link_issued" => Ok(Self::MagicLinkIssued),
+            "authorization_code_issued" => Ok(Self::AuthorizationCodeIssued),
+            "token_issued" => Ok(Self::TokenIssued),
+            "api_key_created" => Ok(Self::ApiKeyCreated),
+            "api_key_revoked" => Ok(Self::ApiKeyRevoked),
+            "logout" => Ok(Self::Logout),
+            "token_rejected" => Ok(Self::TokenRejected),
+            _ => Err(format!("{} is not a valid audit event type.", s)),
+        }
+    }
+}
+
+impl AsRef<str> for AuditEventType {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Signup => "signup",
+            Self::Login => "login",
+            Self::TwoFACodeIssued => "two_fa_code_issued",
+            Self::TwoFAVerification => "two_fa_verification",
+            Self::MagicLinkIssued => "magic_link_issued",
+            Self::AuthorizationCodeIssued => "authorization_code_issued",
+            Self::TokenIssued => "token_issued",
+            Self::ApiKeyCreated => "api_key_created",
+            Self::ApiKeyRevoked => "api_key_revoked",
+            Self::Logout => "logout",
+            Self::TokenRejected => "token_rejected",
+        }
+    }
+}
+
+/// A single authentication event. Events are only ever appended, never updated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEvent {
+    pub event_type: AuditEventType,
+    pub email: Option<String>,
+    pub success: bool,
+    pub reason: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl AuditEvent {
+    pub fn new(event_type: AuditEventType) -> Self {
+        Self {
+            event_type,
+            email: None,
+            success: true,
+            reason: None,
+            ip_address: None,
+            user_agent: None,
+            request_id: None,
+            occurred_at: Utc::now(),
+        }
+    }
+
+    pub fn with_email(mut self, email: impl Into<String>) -> Self {
+        self.email = Some(email.into());
+        self
+    }
+
+    pub fn failed(mut self, reason: impl Into<String>) -> Self {
+        self.success = false;
+        self.reason = Some(reason.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuditEventQuery {
+    pub email: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: i64,
+}
+
+impl AuditEventQuery {
+    pub fn matches(&self, event: &AuditEvent) -> bool {
+        let email_matches = match &self.email {
+            Some(email) => event.email.as_ref() == Some(email),
+            None => true,
+        };
+        let after_from = self.from.filter(|from| event.occurred_at < *from).is_none();
+        let before_to = self.to.filter(|to| event.occurred_at >= *to).is_none();
+
+        email_matches && after_from && before_to
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_type_round_trips_through_its_string_form() {
+        let types = [
+            AuditEventType::Signup,
+            AuditEventType::Login,
+            AuditEventType::TwoFACodeIssued,
+            AuditEventType::TwoFAVerification,
+            AuditEventType::MagicLinkIssued,
+            AuditEventType::Logout,
+            AuditEventType::TokenRejected,
+        ];
+
+        for event_type in types {
+            assert_eq!(AuditEventType::parse(event_type.as_ref()), Ok(event_type));
+        }
+        assert!(AuditEventType::parse("unknown").is_err());
+    }
+
+    #[test]
+    fn query_filters_by_email_and_time_range() {
+        let event = AuditEvent::new(AuditEventType::Login).with_email("test@example.com");
+
+        let query = AuditEventQuery {
+            email: Some("test@example.com".to_owned()),
+            from: Some(event.occurred_at - chrono::Duration::minutes(1)),
+            to: Some(event.occurred_at + chrono::Duration::minutes(1)),
+            limit: 10,
+        };
+        assert!(query.matches(&event));
+
+        let query = AuditEventQuery {
+            email: Some("other@example.com".to_owned()),
+            ..query
+        };
+        assert!(!query.matches(&event));
+
+        let query = AuditEventQuery {
+            from: Some(event.occurred_at + chrono::Duration::minutes(1)),
+            ..Default::default()
+        };
+        assert!(!query.matches(&event));
+    }
+}
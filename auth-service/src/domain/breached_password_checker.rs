@@ -0,0 +1,9 @@
+use color_eyre::eyre::Result;
+
+use super::Password;
+
+/// Tells whether a password is known from a public data breach.
+#[async_trait::async_trait]
+pub trait BreachedPasswordChecker {
+    async fn is_breached(&self, password: &Password) -> Result<bool>;
+}
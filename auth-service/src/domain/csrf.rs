@@ -0,0 +1,30 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Why a request carrying the auth cookie was taken for cross-site request forgery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CsrfRejection {
+    /// `Origin` or `Referer` named a site other than this one.
+    CrossOrigin,
+    MissingToken,
+    /// The token wasn't issued with the auth cookie the request carries.
+    InvalidToken,
+}
+
+impl CsrfRejection {
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::CrossOrigin => "Cross-origin request rejected",
+            Self::MissingToken => "Missing CSRF token",
+            Self::InvalidToken => "Invalid CSRF token",
+        }
+    }
+}
+
+impl fmt::Display for CsrfRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
@@ -1,4 +1,7 @@
-use super::{Email, Password, User};
+use super::{
+    ApiKey, AuditEvent, AuditEventQuery, AuthorizationCode, Email, EmailDomainRule, OAuthState,
+    Password, ProofOfWorkAction, User, UserListQuery, UserPage,
+};
 use color_eyre::eyre::Report;
 use rand::Rng;
 use thiserror::Error;
@@ -6,9 +9,24 @@ use thiserror::Error;
 #[async_trait::async_trait]
 pub trait UserStore {
     async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
+    /// Adds a user whose `password` already holds a password hash, storing it unchanged.
+    async fn import_user(&mut self, user: User) -> Result<(), UserStoreError>;
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
     async fn validate_user(&self, email: &Email, password: &Password)
         -> Result<(), UserStoreError>;
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError>;
+    async fn set_requires_2fa(
+        &mut self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError>;
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    async fn count_users(&self) -> Result<u64, UserStoreError>;
+    async fn list_users(&self, query: &UserListQuery) -> Result<UserPage, UserStoreError>;
 }
 
 #[derive(Debug, Error)]
@@ -19,6 +37,8 @@ pub enum UserStoreError {
     UserNotFound,
     #[error("Invalid credentials")]
     InvalidCredentials,
+    #[error("Invalid page size")]
+    InvalidPageSize,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -30,6 +50,7 @@ impl PartialEq for UserStoreError {
             (Self::UserAlreadyExists, Self::UserAlreadyExists)
                 | (Self::UserNotFound, Self::UserNotFound)
                 | (Self::InvalidCredentials, Self::InvalidCredentials)
+                | (Self::InvalidPageSize, Self::InvalidPageSize)
                 | (Self::UnexpectedError(_), Self::UnexpectedError(_))
         )
     }
@@ -41,8 +62,9 @@ pub trait BannedTokenStore {
     async fn contains_token(&self, token: &str) -> Result<bool, BannedTokenStoreError>;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum BannedTokenStoreError {
+    #[error("Unexpected error")]
     UnexpectedError,
 }
 
@@ -61,9 +83,11 @@ pub trait TwoFACodeStore {
     ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Error, PartialEq)]
 pub enum TwoFACodeStoreError {
+    #[error("Login attempt ID not found")]
     LoginAttemptIdNotFound,
+    #[error("Unexpected error")]
     UnexpectedError,
 }
 
@@ -118,3 +142,201 @@ impl AsRef<str> for TwoFACode {
         &self.0
     }
 }
+
+#[async_trait::async_trait]
+pub trait AuditLogStore {
+    async fn add_event(&mut self, event: AuditEvent) -> Result<(), AuditLogStoreError>;
+    async fn get_events(
+        &self,
+        query: &AuditEventQuery,
+    ) -> Result<Vec<AuditEvent>, AuditLogStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum AuditLogStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+#[async_trait::async_trait]
+pub trait EmailDomainRuleStore {
+    async fn set_rule(
+        &mut self,
+        domain: &str,
+        rule: EmailDomainRule,
+    ) -> Result<(), EmailDomainRuleStoreError>;
+    async fn remove_rule(&mut self, domain: &str) -> Result<(), EmailDomainRuleStoreError>;
+    /// The rules set for any of `domains`, in no particular order.
+    async fn get_rules(
+        &self,
+        domains: &[String],
+    ) -> Result<Vec<(String, EmailDomainRule)>, EmailDomainRuleStoreError>;
+    /// Every rule, ordered by domain.
+    async fn list_rules(&self)
+        -> Result<Vec<(String, EmailDomainRule)>, EmailDomainRuleStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum EmailDomainRuleStoreError {
+    #[error("Rule not found")]
+    RuleNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for EmailDomainRuleStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::RuleNotFound, Self::RuleNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+/// Remembers which magic links were used, so each one logs in at most once.
+#[async_trait::async_trait]
+pub trait MagicLinkStore {
+    /// Marks a link as used, returning `false` if it already was. It only needs to be
+    /// remembered for `ttl_seconds`, after which the link has expired anyway.
+    async fn use_link(
+        &mut self,
+        link_id: &str,
+        ttl_seconds: u64,
+    ) -> Result<bool, MagicLinkStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum MagicLinkStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+/// Holds OAuth login attempts until the provider sends the user back.
+#[async_trait::async_trait]
+pub trait OAuthStateStore {
+    async fn add_state(
+        &mut self,
+        state: &str,
+        oauth_state: OAuthState,
+        ttl_seconds: u64,
+    ) -> Result<(), OAuthStateStoreError>;
+    /// Removes and returns the attempt, so every state can only be used once.
+    async fn take_state(&mut self, state: &str) -> Result<OAuthState, OAuthStateStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum OAuthStateStoreError {
+    #[error("OAuth state not found")]
+    StateNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for OAuthStateStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::StateNotFound, Self::StateNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+/// Holds authorization codes we issued as an OpenID Connect provider until clients redeem them.
+#[async_trait::async_trait]
+pub trait AuthorizationCodeStore {
+    async fn add_code(
+        &mut self,
+        code: &str,
+        authorization_code: AuthorizationCode,
+        ttl_seconds: u64,
+    ) -> Result<(), AuthorizationCodeStoreError>;
+    /// Removes and returns the code, so every code can only be redeemed once.
+    async fn take_code(
+        &mut self,
+        code: &str,
+    ) -> Result<AuthorizationCode, AuthorizationCodeStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum AuthorizationCodeStoreError {
+    #[error("Authorization code not found")]
+    CodeNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for AuthorizationCodeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::CodeNotFound, Self::CodeNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+/// Holds users' API keys, along with the hash of each key's secret.
+#[async_trait::async_trait]
+pub trait ApiKeyStore {
+    async fn add_key(&mut self, api_key: ApiKey, secret_hash: &str)
+        -> Result<(), ApiKeyStoreError>;
+    /// The key with `prefix` and the hash of its secret.
+    async fn get_key(&self, prefix: &str) -> Result<(ApiKey, String), ApiKeyStoreError>;
+    /// Every key of `email`, newest first.
+    async fn list_keys(&self, email: &Email) -> Result<Vec<ApiKey>, ApiKeyStoreError>;
+    /// Removes one of `email`'s keys. Keys of other users are never found.
+    async fn remove_key(&mut self, email: &Email, prefix: &str) -> Result<(), ApiKeyStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum ApiKeyStoreError {
+    #[error("API key already exists")]
+    KeyAlreadyExists,
+    #[error("API key not found")]
+    KeyNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for ApiKeyStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::KeyAlreadyExists, Self::KeyAlreadyExists)
+                | (Self::KeyNotFound, Self::KeyNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+/// Keeps the attempt counters that drive adaptive proof-of-work, and which challenges were spent.
+#[async_trait::async_trait]
+pub trait ProofOfWorkStore {
+    /// Counts an attempt at `action` in the fixed window numbered `window`, returning the new
+    /// total. Counters only need to outlive `ttl_seconds`.
+    async fn increment_attempts(
+        &mut self,
+        action: ProofOfWorkAction,
+        window: u64,
+        ttl_seconds: u64,
+    ) -> Result<u64, ProofOfWorkStoreError>;
+    async fn get_attempts(
+        &self,
+        action: ProofOfWorkAction,
+        window: u64,
+    ) -> Result<u64, ProofOfWorkStoreError>;
+    /// Marks a challenge as spent, returning `false` if it already was.
+    async fn use_challenge(
+        &mut self,
+        challenge_id: &str,
+        ttl_seconds: u64,
+    ) -> Result<bool, ProofOfWorkStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum ProofOfWorkStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
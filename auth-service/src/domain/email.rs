@@ -1,16 +1,75 @@
+use std::hash::{Hash, Hasher};
+
 use validator::validate_email;
 
-#[derive(Debug, Clone, PartialEq, Hash, Eq)]
+use crate::utils::constants::EMAIL_LOCAL_PART_CASE_FOLDING;
+
+/// An email address in canonical form: trimmed, with the domain lowercased and converted to
+/// punycode, and the local part lowercased unless `EMAIL_LOCAL_PART_CASE_FOLDING` is disabled.
+///
+/// Two emails are equal when their lookup keys are, so "Bob@Example.com" and
+/// "bob@example.com" are the same account unless local-part case folding is disabled.
+#[derive(Debug, Clone)]
 pub struct Email(String);
 
 impl Email {
     pub fn parse(s: String) -> Result<Email, String> {
-        if validate_email(&s) {
-            Ok(Self(s))
+        let email = canonicalize(&s, *EMAIL_LOCAL_PART_CASE_FOLDING)
+            .ok_or_else(|| format!("{} is not a valid email.", s))?;
+
+        if validate_email(&email) {
+            Ok(Self(email))
         } else {
             Err(format!("{} is not a valid email.", s))
         }
     }
+
+    pub fn domain(&self) -> &str {
+        self.0.rsplit_once('@').map_or("", |(_, domain)| domain)
+    }
+
+    /// The identity of the email, used wherever emails are looked up. It ignores the case of the
+    /// local part unless `EMAIL_LOCAL_PART_CASE_FOLDING` is disabled. The databases store it in
+    /// `email_key` columns, as SQL lowercases differently.
+    pub fn lookup_key(&self) -> String {
+        self.key(*EMAIL_LOCAL_PART_CASE_FOLDING)
+    }
+
+    fn key(&self, fold_local_part: bool) -> String {
+        // The domain is already lowercased
+        if fold_local_part {
+            self.0.to_lowercase()
+        } else {
+            self.0.clone()
+        }
+    }
+}
+
+fn canonicalize(s: &str, fold_local_part: bool) -> Option<String> {
+    let (local_part, domain) = s.trim().rsplit_once('@')?;
+    let domain = idna::domain_to_ascii(domain).ok()?;
+
+    let local_part = if fold_local_part {
+        local_part.to_lowercase()
+    } else {
+        local_part.to_owned()
+    };
+
+    Some(format!("{}@{}", local_part, domain))
+}
+
+impl PartialEq for Email {
+    fn eq(&self, other: &Self) -> bool {
+        self.lookup_key() == other.lookup_key()
+    }
+}
+
+impl Eq for Email {}
+
+impl Hash for Email {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.lookup_key().hash(state);
+    }
 }
 
 impl AsRef<str> for Email {
@@ -21,7 +80,7 @@ impl AsRef<str> for Email {
 
 #[cfg(test)]
 mod tests {
-    use super::Email;
+    use super::*;
 
     use fake::faker::internet::en::SafeEmail;
     use fake::Fake;
@@ -42,6 +101,41 @@ mod tests {
         assert!(Email::parse(email).is_err());
     }
 
+    #[test]
+    fn domain_is_lowercased_and_converted_to_punycode() {
+        let email = Email::parse(" Bob@Bücher.Example ".to_owned()).unwrap();
+        assert_eq!(email.lookup_key(), "bob@xn--bcher-kva.example");
+        assert!(email.as_ref().ends_with("@xn--bcher-kva.example"));
+    }
+
+    #[test]
+    fn local_part_case_folding_is_configurable() {
+        assert_eq!(
+            canonicalize("Bob.Smith@Example.COM", true).as_deref(),
+            Some("bob.smith@example.com")
+        );
+        assert_eq!(
+            canonicalize("Bob.Smith@Example.COM", false).as_deref(),
+            Some("Bob.Smith@example.com")
+        );
+    }
+
+    #[test]
+    fn local_part_case_is_kept_in_lookup_keys_unless_folded() {
+        let email = Email(canonicalize("Bob@Example.com", false).unwrap());
+        assert_eq!(email.key(true), "bob@example.com");
+        assert_eq!(email.key(false), "Bob@example.com");
+    }
+
+    #[test]
+    fn emails_differing_only_in_case_are_equal() {
+        let unfolded = Email(canonicalize("Bob@Example.com", false).unwrap());
+        let folded = Email::parse("bob@example.com".to_owned()).unwrap();
+
+        assert_eq!(unfolded, folded);
+        assert!(std::collections::HashSet::from([unfolded]).contains(&folded));
+    }
+
     #[derive(Debug, Clone)]
     struct ValidEmailFixture(pub String);
 
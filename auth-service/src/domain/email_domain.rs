@@ -0,0 +1,141 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// An admin decision about signups from a domain and its subdomains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EmailDomainRule {
+    /// Accepted even if it would otherwise be rejected, e.g. a disposable-looking domain.
+    Allow,
+    Deny,
+}
+
+impl EmailDomainRule {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Deny => "deny",
+        }
+    }
+}
+
+impl fmt::Display for EmailDomainRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for EmailDomainRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(Self::Allow),
+            "deny" => Ok(Self::Deny),
+            other => Err(format!("Unknown email domain rule: {}", other)),
+        }
+    }
+}
+
+/// Why signups from an email domain are refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailDomainRejection {
+    /// On the admin-managed deny list.
+    Blocked,
+    /// A known throwaway email provider.
+    Disposable,
+    /// Has no usable MX records, so it can't receive email.
+    NoMailServer,
+}
+
+impl EmailDomainRejection {
+    /// The reason failed signups are audited with.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Blocked => "blocked_email_domain",
+            Self::Disposable => "disposable_email_domain",
+            Self::NoMailServer => "no_mail_server",
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::Blocked => "Signups from this email domain are not allowed",
+            Self::Disposable => "Disposable email addresses are not allowed",
+            Self::NoMailServer => "Email domain cannot receive email",
+        }
+    }
+}
+
+impl fmt::Display for EmailDomainRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+/// Turns user input into the canonical form domains are stored and compared in.
+pub fn parse_email_domain(s: &str) -> Result<String, String> {
+    let domain = idna::domain_to_ascii(s.trim().trim_end_matches('.'))
+        .map_err(|_| format!("{} is not a valid domain.", s))?;
+
+    let valid = domain.contains('.')
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+
+    if valid {
+        Ok(domain)
+    } else {
+        Err(format!("{} is not a valid domain.", s))
+    }
+}
+
+/// The domain followed by each of its parents, most specific first, without the bare TLD.
+pub fn domain_and_parents(domain: &str) -> Vec<String> {
+    let labels: Vec<&str> = domain.split('.').collect();
+
+    (0..labels.len().saturating_sub(1))
+        .map(|i| labels[i..].join("."))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn domains_are_canonicalized() {
+        assert_eq!(
+            parse_email_domain(" Mail.Example.COM. "),
+            Ok("mail.example.com".to_owned())
+        );
+        assert_eq!(
+            parse_email_domain("bücher.de"),
+            Ok("xn--bcher-kva.de".to_owned())
+        );
+        assert!(parse_email_domain("localhost").is_err());
+        assert!(parse_email_domain("user@example.com").is_err());
+        assert!(parse_email_domain("-bad.example.com").is_err());
+    }
+
+    #[test]
+    fn lists_parent_domains_most_specific_first() {
+        assert_eq!(
+            domain_and_parents("a.mail.example.com"),
+            vec!["a.mail.example.com", "mail.example.com", "example.com"]
+        );
+        assert_eq!(domain_and_parents("example.com"), vec!["example.com"]);
+    }
+
+    #[test]
+    fn rules_round_trip_through_strings() {
+        for rule in [EmailDomainRule::Allow, EmailDomainRule::Deny] {
+            assert_eq!(rule.as_str().parse(), Ok(rule));
+        }
+        assert!("block".parse::<EmailDomainRule>().is_err());
+    }
+}
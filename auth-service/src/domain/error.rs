@@ -1,18 +1,47 @@
 use color_eyre::eyre::Report;
 use thiserror::Error;
 
+use super::{
+    ApiKeyRejection, CsrfRejection, EmailDomainRejection, OAuthErrorCode, OAuthFailure,
+    PasswordPolicyViolation, ProofOfWorkAction,
+};
+
 #[derive(Debug, Error)]
 pub enum AuthAPIError {
     #[error("User already exists")]
     UserAlreadyExists,
     #[error("Invalid credentials")]
     InvalidCredentials,
+    #[error("Password does not meet the password policy")]
+    PasswordPolicyViolation(Vec<PasswordPolicyViolation>),
+    #[error("{0}")]
+    EmailDomainNotAllowed(EmailDomainRejection),
+    #[error("Proof of work required")]
+    ProofOfWorkRequired(ProofOfWorkAction),
+    #[error("Unknown OAuth provider")]
+    UnknownOAuthProvider,
+    #[error("Invalid OAuth state")]
+    InvalidOAuthState,
+    #[error("OAuth login failed")]
+    OAuthLoginFailed(OAuthFailure),
+    /// Errors of the OAuth endpoints we serve as a provider, which answer in the format of
+    /// RFC 6749 instead of ours.
+    #[error("OAuth error: {}", .0.as_str())]
+    OAuth(OAuthErrorCode),
+    #[error("{0}")]
+    InvalidApiKey(ApiKeyRejection),
+    #[error("API key not found")]
+    ApiKeyNotFound,
     #[error("Incorrect credentials")]
     IncorrectCredentials,
     #[error("Missing token")]
     MissingToken,
     #[error("Invalid token")]
     InvalidToken,
+    #[error("Insufficient scope")]
+    InsufficientScope,
+    #[error("{0}")]
+    CsrfCheckFailed(CsrfRejection),
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
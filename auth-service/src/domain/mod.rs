@@ -1,13 +1,33 @@
+pub mod api_key;
+pub mod audit_event;
+pub mod breached_password_checker;
+pub mod csrf;
 pub mod data_stores;
 pub mod email;
 pub mod email_client;
+pub mod email_domain;
 pub mod error;
+pub mod mx_resolver;
+pub mod oauth;
+pub mod oidc;
 pub mod password;
+pub mod password_policy;
+pub mod proof_of_work;
 pub mod user;
 
+pub use api_key::*;
+pub use audit_event::*;
+pub use breached_password_checker::*;
+pub use csrf::*;
 pub use data_stores::*;
 pub use email::*;
 pub use email_client::*;
+pub use email_domain::*;
 pub use error::*;
+pub use mx_resolver::*;
+pub use oauth::*;
+pub use oidc::*;
 pub use password::*;
+pub use password_policy::*;
+pub use proof_of_work::*;
 pub use user::*;
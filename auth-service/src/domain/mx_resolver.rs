@@ -0,0 +1,8 @@
+use color_eyre::eyre::Result;
+
+/// Looks up the mail exchangers for a domain.
+#[async_trait::async_trait]
+pub trait MxResolver {
+    /// The MX hosts of `domain`, empty when it has none.
+    async fn lookup_mx(&self, domain: &str) -> Result<Vec<String>>;
+}
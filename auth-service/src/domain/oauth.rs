@@ -0,0 +1,55 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Which kind of identity provider a configured OAuth provider is. GitHub and Google come with
+/// their endpoints filled in, anything else speaking OpenID Connect is `Oidc`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OAuthProviderKind {
+    Github,
+    Google,
+    Oidc,
+}
+
+impl FromStr for OAuthProviderKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "github" => Ok(Self::Github),
+            "google" => Ok(Self::Google),
+            "oidc" => Ok(Self::Oidc),
+            other => Err(format!("Unknown OAuth provider kind: {}", other)),
+        }
+    }
+}
+
+/// What we remember between sending a user to a provider and them coming back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OAuthState {
+    pub provider: String,
+    pub code_verifier: String,
+}
+
+/// Why logging in through a provider didn't work out, once the user made it back to us.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OAuthFailure {
+    /// The user declined, or the provider refused to authorize them.
+    AccessDenied,
+    /// The provider rejected the code exchange or sent something we couldn't use.
+    ProviderError,
+    /// Accounts are only linked by an email address the provider has verified.
+    EmailNotVerified,
+}
+
+impl OAuthFailure {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AccessDenied => "access_denied",
+            Self::ProviderError => "provider_error",
+            Self::EmailNotVerified => "email_not_verified",
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+/// What an authorization code stands for, from `/oauth/authorize` until the client redeems it
+/// at `/oauth/token`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthorizationCode {
+    pub client_id: String,
+    pub redirect_uri: String,
+    /// The email of the user who logged in.
+    pub subject: String,
+    pub scopes: Vec<String>,
+    pub nonce: Option<String>,
+    /// S256 PKCE challenge the code verifier has to match.
+    pub code_challenge: String,
+    /// When the user logged in, as a Unix timestamp.
+    pub auth_time: u64,
+}
+
+/// The error codes of RFC 6749 that our OAuth endpoints answer with, so standard clients know
+/// what went wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OAuthErrorCode {
+    InvalidRequest,
+    InvalidClient,
+    InvalidGrant,
+    UnauthorizedClient,
+    UnsupportedGrantType,
+    UnsupportedResponseType,
+    InvalidScope,
+    /// RFC 8707: the requested audience is unknown or not allowed for the client.
+    InvalidTarget,
+    LoginRequired,
+}
+
+impl OAuthErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::InvalidRequest => "invalid_request",
+            Self::InvalidClient => "invalid_client",
+            Self::InvalidGrant => "invalid_grant",
+            Self::UnauthorizedClient => "unauthorized_client",
+            Self::UnsupportedGrantType => "unsupported_grant_type",
+            Self::UnsupportedResponseType => "unsupported_response_type",
+            Self::InvalidScope => "invalid_scope",
+            Self::InvalidTarget => "invalid_target",
+            Self::LoginRequired => "login_required",
+        }
+    }
+}
@@ -1,29 +1,74 @@
-#[derive(Debug, Clone, PartialEq)]
-pub struct Password(String);
+use std::fmt;
+
+use unicode_normalization::UnicodeNormalization;
+
+/// Hard bounds that hold for every password, whatever the configured `PasswordPolicy` says.
+/// The upper bound keeps huge inputs from ever reaching the password hasher.
+pub const MIN_PASSWORD_CHARS: usize = 8;
+pub const MAX_PASSWORD_CHARS: usize = 1024;
+
+#[derive(Clone)]
+pub struct Password {
+    normalized: String,
+    // The password as typed, when normalizing changed it. Hashes made before passwords were
+    // normalized, or imported from other systems, only match this form.
+    as_typed: Option<String>,
+}
 
 impl Password {
+    /// Normalizes to NFKC, so visually identical passwords typed on different devices
+    /// hash the same, and checks the length in characters rather than bytes.
     pub fn parse(s: String) -> Result<Password, String> {
-        if validate_password(&s) {
-            Ok(Self(s))
+        let normalized: String = s.nfkc().collect();
+
+        if validate_password(&normalized) {
+            let as_typed = (normalized != s).then_some(s);
+            Ok(Self {
+                normalized,
+                as_typed,
+            })
         } else {
             Err("Failed to parse string to a Password type".to_owned())
         }
     }
+
+    pub fn char_count(&self) -> usize {
+        self.normalized.chars().count()
+    }
+
+    /// The password as it was typed, if NFKC normalization changed it. Only for verifying
+    /// existing hashes, new ones are always computed from the normalized password.
+    pub fn as_typed(&self) -> Option<&str> {
+        self.as_typed.as_deref()
+    }
+}
+
+impl PartialEq for Password {
+    fn eq(&self, other: &Self) -> bool {
+        self.normalized == other.normalized
+    }
+}
+
+// Formatted like a tuple struct, which log redaction recognizes
+impl fmt::Debug for Password {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Password").field(&self.normalized).finish()
+    }
 }
 
 fn validate_password(s: &str) -> bool {
-    s.len() >= 8
+    (MIN_PASSWORD_CHARS..=MAX_PASSWORD_CHARS).contains(&s.chars().count())
 }
 
 impl AsRef<str> for Password {
     fn as_ref(&self) -> &str {
-        &self.0
+        &self.normalized
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Password;
+    use super::*;
 
     use fake::faker::internet::en::Password as FakePassword;
     use fake::Fake;
@@ -38,6 +83,34 @@ mod tests {
         let password = "1234567".to_owned();
         assert!(Password::parse(password).is_err());
     }
+    #[test]
+    fn length_is_counted_in_characters() {
+        assert!(Password::parse("ééééééé".to_owned()).is_err());
+        assert!(Password::parse("éééééééé".to_owned()).is_ok());
+        assert!(Password::parse("a".repeat(MAX_PASSWORD_CHARS + 1)).is_err());
+    }
+    #[test]
+    fn password_is_nfkc_normalized() {
+        let composed = Password::parse("caf\u{e9}-password".to_owned()).unwrap();
+        let decomposed = Password::parse("cafe\u{301}-password".to_owned()).unwrap();
+        let fullwidth =
+            Password::parse("\u{ff50}\u{ff41}\u{ff53}\u{ff53}word1".to_owned()).unwrap();
+
+        assert_eq!(composed, decomposed);
+        assert_eq!(fullwidth.as_ref(), "password1");
+    }
+    #[test]
+    fn password_as_typed_is_kept_when_normalizing_changes_it() {
+        let decomposed = Password::parse("cafe\u{301}-password".to_owned()).unwrap();
+        let ascii = Password::parse("cafe-password".to_owned()).unwrap();
+
+        assert_eq!(decomposed.as_typed(), Some("cafe\u{301}-password"));
+        assert_eq!(ascii.as_typed(), None);
+        assert_eq!(
+            format!("{:?}", decomposed),
+            "Password(\"caf\u{e9}-password\")"
+        );
+    }
 
     #[derive(Debug, Clone)]
     struct ValidPasswordFixture(pub String);
@@ -0,0 +1,231 @@
+use serde::{Deserialize, Serialize};
+use zxcvbn::zxcvbn;
+
+use crate::{
+    domain::{Email, Password, MAX_PASSWORD_CHARS, MIN_PASSWORD_CHARS},
+    utils::constants::{PASSWORD_MAX_CHARS, PASSWORD_MIN_CHARS, PASSWORD_MIN_STRENGTH},
+};
+
+// Shorter local parts ("jo@...") show up in too many passwords by chance
+const MIN_EMAIL_LOCAL_PART_CHARS: usize = 3;
+
+/// Requirements a new password must meet, on top of what `Password::parse` enforces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordPolicy {
+    min_chars: usize,
+    max_chars: usize,
+    min_strength: u8,
+}
+
+/// Why a password was rejected, serialized into the error response so clients can explain it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    tag = "code",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+pub enum PasswordPolicyViolation {
+    TooShort {
+        min_chars: usize,
+    },
+    TooLong {
+        max_chars: usize,
+    },
+    ContainsEmail,
+    /// Known from a public data breach, see `BreachedPasswordChecker`.
+    Breached,
+    TooWeak {
+        score: u8,
+        min_score: u8,
+        warning: Option<String>,
+        suggestions: Vec<String>,
+    },
+}
+
+impl PasswordPolicy {
+    /// `min_strength` is a zxcvbn score from 0 to 4. Lengths are in characters and are
+    /// clamped to the bounds `Password::parse` already enforces.
+    pub fn new(min_chars: usize, max_chars: usize, min_strength: u8) -> Result<Self, String> {
+        if min_chars > max_chars {
+            return Err(format!(
+                "Minimum password length {} is greater than the maximum {}",
+                min_chars, max_chars
+            ));
+        }
+        if min_strength > 4 {
+            return Err(format!(
+                "Minimum password strength must be between 0 and 4, got {}",
+                min_strength
+            ));
+        }
+
+        Ok(Self {
+            min_chars: min_chars.max(MIN_PASSWORD_CHARS),
+            max_chars: max_chars.min(MAX_PASSWORD_CHARS),
+            min_strength,
+        })
+    }
+
+    pub fn check(
+        &self,
+        password: &Password,
+        email: &Email,
+    ) -> Result<(), Vec<PasswordPolicyViolation>> {
+        let mut violations = Vec::new();
+
+        let chars = password.char_count();
+        if chars < self.min_chars {
+            violations.push(PasswordPolicyViolation::TooShort {
+                min_chars: self.min_chars,
+            });
+        }
+        if chars > self.max_chars {
+            violations.push(PasswordPolicyViolation::TooLong {
+                max_chars: self.max_chars,
+            });
+            // Not worth estimating the strength of something we'll never accept
+            return Err(violations);
+        }
+
+        let local_part = email_local_part(email);
+        if local_part.chars().count() >= MIN_EMAIL_LOCAL_PART_CHARS
+            && password.as_ref().to_lowercase().contains(&local_part)
+        {
+            violations.push(PasswordPolicyViolation::ContainsEmail);
+        }
+
+        let entropy = zxcvbn(password.as_ref(), &email_user_inputs(email));
+        let score = u8::from(entropy.score());
+        if score < self.min_strength {
+            let feedback = entropy.feedback();
+            violations.push(PasswordPolicyViolation::TooWeak {
+                score,
+                min_score: self.min_strength,
+                warning: feedback
+                    .and_then(|feedback| feedback.warning())
+                    .map(|warning| warning.to_string()),
+                suggestions: feedback
+                    .map(|feedback| {
+                        feedback
+                            .suggestions()
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            });
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+impl Default for PasswordPolicy {
+    /// Uses the limits configured through the `PASSWORD_*` environment variables.
+    fn default() -> Self {
+        Self::new(
+            *PASSWORD_MIN_CHARS,
+            *PASSWORD_MAX_CHARS,
+            *PASSWORD_MIN_STRENGTH,
+        )
+        .expect("Invalid password policy")
+    }
+}
+
+/// The lowercased local part without any `+tag`.
+fn email_local_part(email: &Email) -> String {
+    let local_part = email.as_ref().split('@').next().unwrap_or_default();
+    let local_part = local_part.split('+').next().unwrap_or_default();
+    local_part.to_lowercase()
+}
+
+/// Words from the email address the strength estimator should treat as trivially guessable.
+fn email_user_inputs(email: &Email) -> Vec<&str> {
+    email
+        .as_ref()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email() -> Email {
+        Email::parse("jane.doe+shop@example.com".to_owned()).unwrap()
+    }
+
+    fn password(s: &str) -> Password {
+        Password::parse(s.to_owned()).unwrap()
+    }
+
+    #[test]
+    fn accepts_strong_passwords() {
+        let policy = PasswordPolicy::new(8, 128, 3).unwrap();
+        assert_eq!(
+            policy.check(&password("spoon-galaxy-trumpet-47"), &email()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_weak_passwords_with_feedback() {
+        let policy = PasswordPolicy::new(8, 128, 3).unwrap();
+        let violations = policy.check(&password("aaaaaaaa"), &email()).unwrap_err();
+
+        assert!(matches!(
+            &violations[..],
+            [PasswordPolicyViolation::TooWeak {
+                score: 0,
+                min_score: 3,
+                warning: Some(_),
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn rejects_passwords_containing_the_email_local_part() {
+        let policy = PasswordPolicy::new(8, 128, 0).unwrap();
+        let violations = policy
+            .check(&password("my-Jane.Doe-password"), &email())
+            .unwrap_err();
+
+        assert_eq!(violations, vec![PasswordPolicyViolation::ContainsEmail]);
+    }
+
+    #[test]
+    fn enforces_configured_lengths_in_characters() {
+        let policy = PasswordPolicy::new(12, 16, 0).unwrap();
+
+        assert_eq!(
+            policy.check(&password("ééééééééééé"), &email()),
+            Err(vec![PasswordPolicyViolation::TooShort { min_chars: 12 }])
+        );
+        assert_eq!(
+            policy.check(&password("correct-horse-battery"), &email()),
+            Err(vec![PasswordPolicyViolation::TooLong { max_chars: 16 }])
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_configuration() {
+        assert!(PasswordPolicy::new(20, 10, 3).is_err());
+        assert!(PasswordPolicy::new(8, 128, 5).is_err());
+    }
+
+    #[test]
+    fn violations_serialize_with_a_code() {
+        let json =
+            serde_json::to_value(PasswordPolicyViolation::TooShort { min_chars: 12 }).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "code": "too_short", "minChars": 12 })
+        );
+    }
+}
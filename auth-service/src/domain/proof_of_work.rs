@@ -0,0 +1,61 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// What a proof-of-work challenge can be spent on. A challenge issued for one can't be used
+/// for the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofOfWorkAction {
+    Signup,
+    Login,
+}
+
+impl ProofOfWorkAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Signup => "signup",
+            Self::Login => "login",
+        }
+    }
+}
+
+impl fmt::Display for ProofOfWorkAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ProofOfWorkAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "signup" => Ok(Self::Signup),
+            "login" => Ok(Self::Login),
+            other => Err(format!("Unknown proof-of-work action: {}", other)),
+        }
+    }
+}
+
+/// When signup and login insist on a solved challenge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofOfWorkMode {
+    Off,
+    /// Only while the recent attempt rate is above the attack threshold.
+    Adaptive,
+    Always,
+}
+
+impl FromStr for ProofOfWorkMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "off" => Ok(Self::Off),
+            "adaptive" => Ok(Self::Adaptive),
+            "always" => Ok(Self::Always),
+            other => Err(format!("Unknown proof-of-work mode: {}", other)),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use super::{Email, Password};
+use super::{Email, Password, UserStoreError};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct User {
@@ -16,3 +16,70 @@ impl User {
         }
     }
 }
+
+pub const MAX_USER_PAGE_SIZE: u32 = 1000;
+
+/// One page of users ordered by email, starting after the `after` cursor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserListQuery {
+    after: Option<Email>,
+    limit: u32,
+}
+
+impl UserListQuery {
+    pub fn new(after: Option<Email>, limit: u32) -> Result<Self, UserStoreError> {
+        if (1..=MAX_USER_PAGE_SIZE).contains(&limit) {
+            Ok(Self { after, limit })
+        } else {
+            Err(UserStoreError::InvalidPageSize)
+        }
+    }
+
+    pub fn after(&self) -> Option<&Email> {
+        self.after.as_ref()
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserPage {
+    pub users: Vec<User>,
+    /// Pass as `after` to fetch the next page; `None` once the last page is reached.
+    pub next_cursor: Option<Email>,
+}
+
+impl UserPage {
+    /// Builds a page from up to `limit + 1` users, the extra one only signalling that more exist.
+    pub fn from_overfetched(mut users: Vec<User>, limit: u32) -> Self {
+        let limit = limit as usize;
+        let next_cursor = if users.len() > limit {
+            users.truncate(limit);
+            users.last().map(|user| user.email.clone())
+        } else {
+            None
+        };
+
+        Self { users, next_cursor }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_query_rejects_out_of_range_page_sizes() {
+        assert_eq!(
+            UserListQuery::new(None, 0),
+            Err(UserStoreError::InvalidPageSize)
+        );
+        assert_eq!(
+            UserListQuery::new(None, MAX_USER_PAGE_SIZE + 1),
+            Err(UserStoreError::InvalidPageSize)
+        );
+        assert!(UserListQuery::new(None, MAX_USER_PAGE_SIZE).is_ok());
+    }
+}
@@ -0,0 +1,168 @@
+use axum::http::{HeaderMap, HeaderName, HeaderValue};
+use axum_extra::extract::CookieJar;
+use tonic::{Code, Request, Response, Status};
+
+use super::proto::{
+    envoy::{
+        config::core::v3::{
+            header_value_option::HeaderAppendAction, HeaderValue as EnvoyHeader, HeaderValueOption,
+        },
+        r#type::v3::{HttpStatus, StatusCode},
+        service::auth::v3::{
+            authorization_server::Authorization, check_response::HttpResponse, CheckRequest,
+            CheckResponse, DeniedHttpResponse, OkHttpResponse,
+        },
+    },
+    google::rpc::Status as RpcStatus,
+};
+use crate::{
+    app_state::AppState,
+    domain::{AuditEvent, AuditEventType, AuthAPIError},
+    utils::{
+        audit::record_audit_event,
+        auth::{authenticate_token, request_token},
+        constants::{X_AUTH_ROLES_HEADER, X_AUTH_USER_HEADER},
+        request_context::RequestContext,
+    },
+    ErrorResponse,
+};
+
+/// The `context_extensions` key naming the service behind a route, so its service tokens are
+/// accepted there.
+pub const AUDIENCE_CONTEXT_EXTENSION: &str = "audience";
+
+/// Envoy's external authorization API, for the `ext_authz` filter. Like `/forward-auth`,
+/// requests with a valid token from the `jwt` cookie or bearer header go through with the user
+/// in `X-Auth-User` and a service token's scopes in `X-Auth-Roles`, others are denied with a 401.
+#[derive(Clone)]
+pub struct ExtAuthzService {
+    state: AppState,
+}
+
+impl ExtAuthzService {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+#[tonic::async_trait]
+impl Authorization for ExtAuthzService {
+    #[tracing::instrument(name = "Envoy ext_authz check", skip_all)]
+    async fn check(
+        &self,
+        request: Request<CheckRequest>,
+    ) -> Result<Response<CheckResponse>, Status> {
+        let peer = request.remote_addr().map(|addr| addr.ip());
+        let attributes = request.into_inner().attributes.unwrap_or_default();
+        let headers = attributes
+            .request
+            .and_then(|request| request.http)
+            .map(|http| header_map(http.headers))
+            .unwrap_or_default();
+        let audience = attributes
+            .context_extensions
+            .get(AUDIENCE_CONTEXT_EXTENSION)
+            .map(String::as_str);
+
+        let credentials = match request_token(&CookieJar::from_headers(&headers), &headers) {
+            Some(token) => authenticate_token(&token, audience, &self.state).await,
+            None => Err(AuthAPIError::MissingToken),
+        };
+
+        let response = match credentials {
+            Ok(credentials) => {
+                let roles = credentials.client_scopes().join(",");
+                ok_response(&[
+                    (X_AUTH_USER_HEADER, credentials.subject()),
+                    (X_AUTH_ROLES_HEADER, &roles),
+                ])
+            }
+            Err(AuthAPIError::UnexpectedError(e)) => {
+                tracing::error!(error = ?e, "ext_authz check failed");
+                // Left to Envoy's `failure_mode_allow`
+                return Err(Status::internal("Unexpected error"));
+            }
+            Err(e) => {
+                if let AuthAPIError::InvalidToken = e {
+                    let context = RequestContext::new(&headers, peer);
+                    let event =
+                        AuditEvent::new(AuditEventType::TokenRejected).failed("invalid_token");
+                    record_audit_event(&self.state.audit_log_store, &context, event).await;
+                }
+                denied_response(&e)
+            }
+        };
+
+        Ok(Response::new(response))
+    }
+}
+
+/// Empty headers are removed instead, so a value the client sent itself can't pass through.
+fn ok_response(headers: &[(&str, &str)]) -> CheckResponse {
+    let (set, removed): (Vec<_>, Vec<_>) = headers.iter().partition(|(_, value)| !value.is_empty());
+
+    CheckResponse {
+        status: Some(RpcStatus {
+            code: Code::Ok as i32,
+            message: String::new(),
+        }),
+        http_response: Some(HttpResponse::OkResponse(OkHttpResponse {
+            // Replacing any the client sent itself
+            headers: set
+                .into_iter()
+                .map(|(name, value)| HeaderValueOption {
+                    header: Some(EnvoyHeader {
+                        key: name.to_string(),
+                        value: value.to_string(),
+                    }),
+                    append_action: HeaderAppendAction::OverwriteIfExistsOrAdd as i32,
+                })
+                .collect(),
+            headers_to_remove: removed
+                .into_iter()
+                .map(|(name, _)| name.to_string())
+                .collect(),
+        })),
+    }
+}
+
+fn denied_response(e: &AuthAPIError) -> CheckResponse {
+    let body = serde_json::to_string(&ErrorResponse {
+        error: e.to_string(),
+        details: None,
+    })
+    .unwrap_or_default();
+
+    CheckResponse {
+        status: Some(RpcStatus {
+            code: Code::Unauthenticated as i32,
+            message: e.to_string(),
+        }),
+        http_response: Some(HttpResponse::DeniedResponse(DeniedHttpResponse {
+            status: Some(HttpStatus {
+                code: StatusCode::Unauthorized as i32,
+            }),
+            headers: vec![HeaderValueOption {
+                header: Some(EnvoyHeader {
+                    key: "content-type".to_owned(),
+                    value: "application/json".to_owned(),
+                }),
+                append_action: HeaderAppendAction::OverwriteIfExistsOrAdd as i32,
+            }],
+            body,
+        })),
+    }
+}
+
+/// Envoy sends headers as a map, with names already lowercased.
+fn header_map(headers: impl IntoIterator<Item = (String, String)>) -> HeaderMap {
+    headers
+        .into_iter()
+        .filter_map(|(name, value)| {
+            Some((
+                HeaderName::from_bytes(name.as_bytes()).ok()?,
+                HeaderValue::from_str(&value).ok()?,
+            ))
+        })
+        .collect()
+}
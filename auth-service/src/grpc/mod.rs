@@ -0,0 +1,82 @@
+use std::error::Error;
+
+use tonic::transport::server::{Router, TcpIncoming};
+
+use crate::app_state::AppState;
+
+pub use ext_authz::ExtAuthzService;
+pub use token_service::GrpcTokenService;
+
+mod ext_authz;
+mod token_service;
+
+/// Generated from `proto/token_service.proto`, and the parts of Envoy's API we implement under
+/// `envoy`.
+pub mod proto {
+    tonic::include_proto!("auth.v1");
+
+    pub mod envoy {
+        pub mod config {
+            pub mod core {
+                pub mod v3 {
+                    tonic::include_proto!("envoy.config.core.v3");
+                }
+            }
+        }
+        pub mod service {
+            pub mod auth {
+                pub mod v3 {
+                    tonic::include_proto!("envoy.service.auth.v3");
+                }
+            }
+        }
+        pub mod r#type {
+            pub mod v3 {
+                tonic::include_proto!("envoy.r#type.v3");
+            }
+        }
+    }
+
+    pub mod google {
+        pub mod rpc {
+            tonic::include_proto!("google.rpc");
+        }
+    }
+}
+
+/// Serves the gRPC API on its own port, next to the HTTP `Application`.
+pub struct GrpcServer {
+    router: Router,
+    incoming: TcpIncoming,
+    pub address: String,
+}
+
+impl GrpcServer {
+    pub async fn build(app_state: AppState, address: &str) -> Result<Self, Box<dyn Error>> {
+        let router = tonic::transport::Server::builder()
+            .add_service(proto::token_service_server::TokenServiceServer::new(
+                GrpcTokenService::new(app_state.clone()),
+            ))
+            .add_service(
+                proto::envoy::service::auth::v3::authorization_server::AuthorizationServer::new(
+                    ExtAuthzService::new(app_state),
+                ),
+            );
+
+        let listener = tokio::net::TcpListener::bind(address).await?;
+        let address = listener.local_addr()?.to_string();
+        let incoming =
+            TcpIncoming::from_listener(listener, true, None).map_err(|e| e as Box<dyn Error>)?;
+
+        Ok(GrpcServer {
+            router,
+            incoming,
+            address,
+        })
+    }
+
+    pub async fn run(self) -> Result<(), tonic::transport::Error> {
+        tracing::info!("gRPC listening on {}", &self.address);
+        self.router.serve_with_incoming(self.incoming).await
+    }
+}
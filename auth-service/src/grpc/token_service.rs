@@ -0,0 +1,175 @@
+use tonic::{Request, Response, Status};
+
+use super::proto::{
+    token_service_server::TokenService, IntrospectTokenRequest, IntrospectTokenResponse,
+    RevokeTokenRequest, RevokeTokenResponse, VerifyTokenRequest, VerifyTokenResponse,
+};
+use crate::{
+    app_state::AppState,
+    domain::{AuditEvent, AuditEventType, AuthAPIError},
+    routes::{authenticate_client, IntrospectionResponse},
+    services::oidc::OidcClient,
+    utils::{
+        audit::record_audit_event,
+        auth::{authenticate_access_token, authenticate_token, validate_token},
+        constants::REQUEST_ID_HEADER,
+        request_context::RequestContext,
+    },
+};
+
+/// The token checks of the HTTP API, over gRPC.
+#[derive(Clone)]
+pub struct GrpcTokenService {
+    state: AppState,
+}
+
+impl GrpcTokenService {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    /// The registered client making `request`, from `authorization: Basic` metadata as at
+    /// `/oauth/introspect`.
+    fn authenticate_client<T>(&self, request: &Request<T>) -> Option<&OidcClient> {
+        let headers = request.metadata().clone().into_headers();
+        authenticate_client(&self.state, &headers, None, None)
+    }
+
+    async fn reject_token(&self, context: &RequestContext, reason: &str) {
+        let event = AuditEvent::new(AuditEventType::TokenRejected).failed(reason);
+        record_audit_event(&self.state.audit_log_store, context, event).await;
+    }
+}
+
+#[tonic::async_trait]
+impl TokenService for GrpcTokenService {
+    #[tracing::instrument(name = "gRPC VerifyToken", skip_all)]
+    async fn verify_token(
+        &self,
+        request: Request<VerifyTokenRequest>,
+    ) -> Result<Response<VerifyTokenResponse>, Status> {
+        let context = request_context(&request);
+        let request = request.into_inner();
+
+        let credentials = match authenticate_token(
+            &request.token,
+            request.audience.as_deref(),
+            &self.state,
+        )
+        .await
+        {
+            Ok(credentials) => credentials,
+            Err(AuthAPIError::InvalidToken) => {
+                self.reject_token(&context, "invalid_token").await;
+                return Err(Status::unauthenticated("Invalid auth token"));
+            }
+            Err(e) => return Err(unexpected_error(e)),
+        };
+
+        if !credentials.grants(request.audience.as_deref(), &request.scopes) {
+            self.reject_token(&context, "insufficient_scope").await;
+            return Err(Status::permission_denied("Token lacks a required scope"));
+        }
+
+        Ok(Response::new(VerifyTokenResponse {
+            subject: credentials.subject().to_owned(),
+            token_type: credentials.token_type().to_owned(),
+            scopes: credentials
+                .scopes()
+                .into_iter()
+                .map(str::to_owned)
+                .collect(),
+        }))
+    }
+
+    #[tracing::instrument(name = "gRPC IntrospectToken", skip_all)]
+    async fn introspect_token(
+        &self,
+        request: Request<IntrospectTokenRequest>,
+    ) -> Result<Response<IntrospectTokenResponse>, Status> {
+        let context = request_context(&request);
+        let client = self
+            .authenticate_client(&request)
+            .ok_or_else(invalid_client)?;
+        let request = request.into_inner();
+
+        let response =
+            match authenticate_access_token(&request.token, Some(client.client_id()), &self.state)
+                .await
+            {
+                Ok(credentials) => IntrospectionResponse::from(credentials),
+                Err(AuthAPIError::InvalidToken) => {
+                    self.reject_token(&context, "invalid_token").await;
+                    IntrospectionResponse::default()
+                }
+                Err(e) => return Err(unexpected_error(e)),
+            };
+
+        Ok(Response::new(IntrospectTokenResponse {
+            active: response.active,
+            sub: response.sub,
+            exp: response.exp,
+            iat: response.iat,
+            scope: response.scope,
+            sid: response.sid,
+            token_type: response.token_type,
+            client_id: response.client_id,
+            aud: response.aud,
+        }))
+    }
+
+    #[tracing::instrument(name = "gRPC RevokeToken", skip_all)]
+    async fn revoke_token(
+        &self,
+        request: Request<RevokeTokenRequest>,
+    ) -> Result<Response<RevokeTokenResponse>, Status> {
+        let context = request_context(&request);
+        self.authenticate_client(&request)
+            .ok_or_else(invalid_client)?;
+        let token = request.into_inner().token;
+
+        let Ok(claims) = validate_token(&token, self.state.banned_token_store.clone()).await else {
+            self.reject_token(&context, "invalid_token").await;
+            return Err(Status::unauthenticated("Invalid auth token"));
+        };
+
+        self.state
+            .banned_token_store
+            .write()
+            .await
+            .add_token(token)
+            .await
+            .map_err(|e| unexpected_error(AuthAPIError::UnexpectedError(e.into())))?;
+
+        let event = AuditEvent::new(AuditEventType::Logout).with_email(claims.sub);
+        record_audit_event(&self.state.audit_log_store, &context, event).await;
+
+        Ok(Response::new(RevokeTokenResponse {}))
+    }
+}
+
+/// Who is calling, like `RequestContext` is for HTTP requests.
+fn request_context<T>(request: &Request<T>) -> RequestContext {
+    let metadata_value = |name: &str| {
+        request
+            .metadata()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(ToOwned::to_owned)
+    };
+
+    RequestContext {
+        ip_address: request.remote_addr().map(|addr| addr.ip().to_string()),
+        user_agent: metadata_value("user-agent"),
+        request_id: metadata_value(REQUEST_ID_HEADER),
+    }
+}
+
+fn invalid_client() -> Status {
+    Status::unauthenticated("Invalid client credentials")
+}
+
+fn unexpected_error(e: AuthAPIError) -> Status {
+    tracing::error!(error = %e, "gRPC request failed");
+    Status::internal("Unexpected error")
+}
@@ -1,64 +1,129 @@
-use std::error::Error;
+use std::{error::Error, net::SocketAddr, str::FromStr};
 
 use app_state::AppState;
 use axum::{
-    http::{Method, StatusCode},
+    extract::{connect_info::IntoMakeServiceWithConnectInfo, ConnectInfo},
+    http::{
+        header::{AUTHORIZATION, CACHE_CONTROL, CONTENT_TYPE},
+        HeaderName, HeaderValue, Method, StatusCode,
+    },
+    middleware::{self, AddExtension},
     response::{IntoResponse, Response},
-    routing::post,
+    routing::{any, delete, get, post},
     serve::Serve,
     Json, Router,
 };
-use domain::AuthAPIError;
+use domain::{AuthAPIError, OAuthErrorCode};
 use redis::{Client, RedisResult};
-use routes::{login, logout, signup, verify_2fa, verify_token};
+use routes::{
+    create_api_key, forward_auth, forward_auth_return, get_audit_events, get_challenge, jwks,
+    list_api_keys, login, logout, magic_link_callback, magic_link_page, oauth_authorize,
+    oauth_callback, oauth_introspect, oidc_authorize, oidc_token, oidc_userinfo,
+    openid_configuration, request_magic_link, revoke_api_key, signup, verify_2fa, verify_token,
+};
 use serde::{Deserialize, Serialize};
-use sqlx::{postgres::PgPoolOptions, PgPool};
-use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
+use sqlx::{
+    postgres::PgPoolOptions,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    PgPool, SqlitePool,
+};
+use tower_http::{
+    cors::CorsLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    services::ServeDir,
+    trace::TraceLayer,
+};
 
-use crate::utils::tracing::{make_span_with_request_id, on_request, on_response};
+use crate::utils::{
+    constants::{ALLOWED_ORIGINS, CSRF_TOKEN_HEADER, POW_CHALLENGE_HEADER, POW_NONCE_HEADER},
+    csrf::csrf_protection,
+    request_context::drop_invalid_request_id,
+    tracing::{make_span_with_request_id, on_request, on_response},
+};
 
 pub mod app_state;
+pub mod cli;
 pub mod domain;
+pub mod grpc;
 pub mod routes;
 pub mod services;
 pub mod utils;
 
 pub struct Application {
-    server: Serve<Router, Router>,
+    server: Serve<
+        IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+        AddExtension<Router, ConnectInfo<SocketAddr>>,
+    >,
     pub address: String,
 }
 
 impl Application {
     pub async fn build(app_state: AppState, address: &str) -> Result<Self, Box<dyn Error>> {
-        let allowed_origins = [
-            "http://localhost:8000".parse()?,
-            "http://[YOUR_DROPLET_IP]:8000".parse()?,
-        ];
+        let allowed_origins = ALLOWED_ORIGINS
+            .iter()
+            .map(|origin| origin.parse())
+            .collect::<Result<Vec<HeaderValue>, _>>()?;
 
         let cors = CorsLayer::new()
-            .allow_methods([Method::GET, Method::POST])
+            .allow_methods([Method::GET, Method::POST, Method::DELETE])
+            .allow_headers([
+                AUTHORIZATION,
+                CONTENT_TYPE,
+                HeaderName::from_static(POW_CHALLENGE_HEADER),
+                HeaderName::from_static(POW_NONCE_HEADER),
+                HeaderName::from_static(CSRF_TOKEN_HEADER),
+            ])
             .allow_credentials(true)
             .allow_origin(allowed_origins);
 
         let router = Router::new()
             .nest_service("/", ServeDir::new("assets"))
+            .route("/challenge", get(get_challenge))
             .route("/signup", post(signup))
             .route("/login", post(login))
+            .route("/login/magic-link", post(request_magic_link))
+            .route(
+                "/login/magic-link/callback",
+                get(magic_link_page).post(magic_link_callback),
+            )
+            .route(
+                "/.well-known/openid-configuration",
+                get(openid_configuration),
+            )
+            .route("/.well-known/jwks.json", get(jwks))
+            .route("/oauth/authorize", get(oidc_authorize))
+            .route("/oauth/token", post(oidc_token))
+            .route("/oauth/introspect", post(oauth_introspect))
+            .route("/oauth/userinfo", get(oidc_userinfo))
+            .route("/oauth/:provider/authorize", get(oauth_authorize))
+            .route("/oauth/:provider/callback", get(oauth_callback))
             .route("/verify-2fa", post(verify_2fa))
             .route("/logout", post(logout))
             .route("/verify-token", post(verify_token))
+            .route("/forward-auth", any(forward_auth))
+            .route("/forward-auth/return", get(forward_auth_return))
+            .route("/api-keys", get(list_api_keys).post(create_api_key))
+            .route("/api-keys/:prefix", delete(revoke_api_key))
+            .route("/audit-events", get(get_audit_events))
             .with_state(app_state)
+            .layer(middleware::from_fn(csrf_protection))
             .layer(cors)
+            .layer(PropagateRequestIdLayer::x_request_id())
             .layer(
                 TraceLayer::new_for_http()
                     .make_span_with(make_span_with_request_id)
                     .on_request(on_request)
                     .on_response(on_response),
-            );
+            )
+            .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+            .layer(middleware::map_request(drop_invalid_request_id));
 
         let listener = tokio::net::TcpListener::bind(address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        );
 
         Ok(Application { server, address })
     }
@@ -72,51 +137,161 @@ impl Application {
 #[derive(Serialize, Deserialize)]
 pub struct ErrorResponse {
     pub error: String,
+    /// Machine-readable reasons, for errors the client can act on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
 }
 
 impl IntoResponse for AuthAPIError {
     fn into_response(self) -> Response {
         log_error_chain(&self);
+        let details = match &self {
+            AuthAPIError::PasswordPolicyViolation(violations) => serde_json::to_value(violations)
+                .map(|violations| serde_json::json!({ "violations": violations }))
+                .ok(),
+            AuthAPIError::EmailDomainNotAllowed(rejection) => {
+                Some(serde_json::json!({ "reason": rejection }))
+            }
+            AuthAPIError::OAuthLoginFailed(failure) => {
+                Some(serde_json::json!({ "reason": failure }))
+            }
+            AuthAPIError::InvalidApiKey(rejection) => {
+                Some(serde_json::json!({ "reason": rejection }))
+            }
+            AuthAPIError::CsrfCheckFailed(rejection) => {
+                Some(serde_json::json!({ "reason": rejection }))
+            }
+            AuthAPIError::ProofOfWorkRequired(action) => Some(serde_json::json!({
+                "challengeUrl": format!("/challenge?action={}", action)
+            })),
+            _ => None,
+        };
         let (status, error_message) = match self {
             AuthAPIError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
             AuthAPIError::InvalidCredentials => (StatusCode::BAD_REQUEST, "Invalid credentials"),
+            AuthAPIError::PasswordPolicyViolation(_) => (
+                StatusCode::BAD_REQUEST,
+                "Password does not meet the password policy",
+            ),
+            AuthAPIError::EmailDomainNotAllowed(rejection) => {
+                (StatusCode::BAD_REQUEST, rejection.message())
+            }
+            AuthAPIError::InvalidApiKey(rejection) => {
+                (StatusCode::BAD_REQUEST, rejection.message())
+            }
+            AuthAPIError::ApiKeyNotFound => (StatusCode::NOT_FOUND, "API key not found"),
+            AuthAPIError::ProofOfWorkRequired(_) => (
+                StatusCode::PRECONDITION_REQUIRED,
+                "A solved proof-of-work challenge is required",
+            ),
+            AuthAPIError::UnknownOAuthProvider => (StatusCode::NOT_FOUND, "Unknown OAuth provider"),
+            AuthAPIError::InvalidOAuthState => (StatusCode::BAD_REQUEST, "Invalid OAuth state"),
+            AuthAPIError::OAuthLoginFailed(_) => (StatusCode::UNAUTHORIZED, "OAuth login failed"),
+            AuthAPIError::OAuth(code) => return oauth_error_response(code),
             AuthAPIError::IncorrectCredentials => {
                 (StatusCode::UNAUTHORIZED, "Incorrect credentials")
             }
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing auth token"),
             AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid auth token"),
+            AuthAPIError::InsufficientScope => {
+                (StatusCode::FORBIDDEN, "Token lacks a required scope")
+            }
+            AuthAPIError::CsrfCheckFailed(rejection) => {
+                (StatusCode::FORBIDDEN, rejection.message())
+            }
             AuthAPIError::UnexpectedError(_) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected error")
             }
         };
         let body = Json(ErrorResponse {
             error: error_message.to_string(),
+            details,
         });
         (status, body).into_response()
     }
 }
 
+/// An error response in the format of RFC 6749, section 5.2.
+fn oauth_error_response(code: OAuthErrorCode) -> Response {
+    let status = match code {
+        OAuthErrorCode::InvalidClient => StatusCode::UNAUTHORIZED,
+        _ => StatusCode::BAD_REQUEST,
+    };
+    let body = Json(serde_json::json!({ "error": code.as_str() }));
+
+    (status, [(CACHE_CONTROL, "no-store")], body).into_response()
+}
+
 pub async fn get_postgres_pool(url: &str) -> Result<PgPool, sqlx::Error> {
     PgPoolOptions::new().max_connections(5).connect(url).await
 }
 
+/// Opens the SQLite database at `url`, creating the file if it doesn't exist yet.
+pub async fn get_sqlite_pool(url: &str) -> Result<SqlitePool, sqlx::Error> {
+    let options = SqliteConnectOptions::from_str(url)?
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal);
+
+    SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect_with(options)
+        .await
+}
+
 pub fn get_redis_client(redis_hostname: String) -> RedisResult<Client> {
     let redis_url = format!("redis://{}/", redis_hostname);
     redis::Client::open(redis_url)
 }
 
-fn log_error_chain(e: &(dyn Error + 'static)) {
-    let separator =
-        "\n-----------------------------------------------------------------------------------\n";
+/// Which database the stores run on, picked from the scheme of the database URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatabaseBackend {
+    Postgres,
+    Sqlite,
+}
 
-    let mut report = format!("{}{:?}\n", separator, e);
-    let mut current = e.source();
+impl DatabaseBackend {
+    pub fn from_url(url: &str) -> Result<Self, String> {
+        let scheme = url.split(':').next().unwrap_or_default().to_lowercase();
 
-    while let Some(cause) = current {
-        let str = format!("Caused by:\n\n{:?}", cause);
-        report = format!("{}\n{}", report, str);
-        current = cause.source();
+        match scheme.as_str() {
+            "postgres" | "postgresql" => Ok(Self::Postgres),
+            "sqlite" => Ok(Self::Sqlite),
+            _ => Err("Unsupported database URL scheme, expected postgres:// or sqlite:".to_owned()),
+        }
     }
-    report = format!("{}\n{}", report, separator);
-    tracing::error!("{}", report);
+}
+
+/// Where banned tokens and 2FA codes are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenStoreBackend {
+    Redis,
+    Postgres,
+}
+
+impl FromStr for TokenStoreBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "redis" => Ok(Self::Redis),
+            "postgres" | "postgresql" => Ok(Self::Postgres),
+            other => Err(format!("Unknown token store backend: {}", other)),
+        }
+    }
+}
+
+fn log_error_chain(e: &(dyn Error + 'static)) {
+    let causes: Vec<String> = std::iter::successors(e.source(), |&cause| cause.source())
+        .map(|cause| cause.to_string())
+        .collect();
+
+    tracing::error!(
+        error.message = %e,
+        // Written out as an array by the JSON log format
+        error.causes = %serde_json::Value::from(causes.clone()),
+        error.cause_count = causes.len(),
+        error.root_cause = causes.last().map(String::as_str).unwrap_or_default(),
+        "Request failed"
+    );
 }
@@ -1,20 +1,53 @@
-use color_eyre;
-use sqlx::PgPool;
-use std::sync::Arc;
+use sqlx::{PgPool, SqlitePool};
+use std::{fs::File, path::Path, sync::Arc, time::Duration};
 use tokio::sync::RwLock;
 
 use auth_service::{
-    app_state::AppState,
-    get_postgres_pool, get_redis_client,
+    app_state::{
+        ApiKeyStoreType, AppState, AuditLogStoreType, AuthorizationCodeStoreType,
+        BannedTokenStoreType, EmailDomainRuleStoreType, MagicLinkStoreType, OAuthStateStoreType,
+        ProofOfWorkStoreType, TwoFACodeStoreType, UserStoreType,
+    },
+    cli::{password_hash_report, Command},
+    domain::{EmailDomainRule, EmailDomainRuleStoreError},
+    get_postgres_pool, get_redis_client, get_sqlite_pool,
+    grpc::GrpcServer,
     services::{
-        data_stores::{PostgresUserStore, RedisBannedTokenStore, RedisTwoFACodeStore},
+        api_keys::ApiKeys,
+        data_stores::{
+            PostgresApiKeyStore, PostgresAuditLogStore, PostgresAuthorizationCodeStore,
+            PostgresBannedTokenStore, PostgresEmailDomainRuleStore, PostgresMagicLinkStore,
+            PostgresOAuthStateStore, PostgresProofOfWorkStore, PostgresTwoFACodeStore,
+            PostgresUserStore, RedisAuthorizationCodeStore, RedisBannedTokenStore,
+            RedisMagicLinkStore, RedisOAuthStateStore, RedisProofOfWorkStore, RedisTwoFACodeStore,
+            SqliteApiKeyStore, SqliteAuditLogStore, SqliteAuthorizationCodeStore,
+            SqliteBannedTokenStore, SqliteEmailDomainRuleStore, SqliteMagicLinkStore,
+            SqliteOAuthStateStore, SqliteProofOfWorkStore, SqliteTwoFACodeStore, SqliteUserStore,
+        },
+        email_domain_policy::EmailDomainPolicy,
+        email_lookup_keys::{correct_email_lookup_keys, correct_sqlite_email_lookup_keys},
+        expired_rows_cleanup::{spawn_expired_rows_cleanup, spawn_sqlite_expired_rows_cleanup},
+        hibp_file_breached_password_checker::HibpFileBreachedPasswordChecker,
+        magic_link::MagicLinks,
         mock_email_client::MockEmailClient,
+        oauth::{load_oauth_providers, OAuthClient},
+        oidc::{load_oidc_clients, OidcProvider, SigningKey},
+        password_hasher::Argon2PasswordHasher,
+        proof_of_work::{ProofOfWork, ProofOfWorkConfig},
+        static_mx_resolver::StaticMxResolver,
+        user_import::{import_users, ImportFormat},
     },
     utils::{
-        constants::{prod, DATABASE_URL, REDIS_HOST_NAME},
+        constants::{
+            prod, BREACHED_PASSWORDS_FILE, DATABASE_URL, EXPIRED_ROWS_CLEANUP_INTERVAL_SECONDS,
+            GRPC_PORT, MAGIC_LINK_BASE_URL, MX_RECORDS_FILE, OAUTH_PROVIDERS_FILE,
+            OIDC_CLIENTS_FILE, OIDC_ISSUER, OIDC_SIGNING_KEY_FILE, REDIS_HOST_NAME,
+            TOKEN_STORE_BACKEND,
+        },
+        cookies::COOKIE_SETTINGS,
         tracing::init_tracing,
     },
-    Application,
+    Application, DatabaseBackend, TokenStoreBackend,
 };
 
 #[tokio::main]
@@ -22,29 +55,285 @@ async fn main() {
     color_eyre::install().expect("Issue installing colo eyre");
     init_tracing().expect("Failed to initialize tracing");
 
-    let pg_pool = configure_postgresql().await;
-    let redis_connection = Arc::new(RwLock::new(configure_redis()));
+    let command = Command::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+    let database_backend = DatabaseBackend::from_url(&DATABASE_URL).expect("Invalid database URL");
 
-    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-    let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(
-        redis_connection.clone(),
-    )));
-    let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_connection)));
+    match command {
+        Command::Serve => serve(database_backend).await,
+        Command::PasswordHashReport => report_password_hashes(database_backend).await,
+        Command::ImportUsers { path } => import_users_from_file(database_backend, &path).await,
+        Command::SetEmailDomainRule { domain, rule } => {
+            set_email_domain_rule(database_backend, &domain, rule).await
+        }
+        Command::RemoveEmailDomainRule { domain } => {
+            remove_email_domain_rule(database_backend, &domain).await
+        }
+        Command::ListEmailDomainRules => list_email_domain_rules(database_backend).await,
+    }
+}
+
+async fn serve(database_backend: DatabaseBackend) {
+    // Invalid cookie settings should stop the server here, not fail the first login
+    lazy_static::initialize(&COOKIE_SETTINGS);
+
+    let (
+        user_store,
+        banned_token_store,
+        two_fa_code_store,
+        audit_log_store,
+        email_domain_rule_store,
+        proof_of_work_store,
+        magic_link_store,
+        oauth_state_store,
+        authorization_code_store,
+        api_key_store,
+    ) = match database_backend {
+        DatabaseBackend::Postgres => configure_postgresql_stores().await,
+        DatabaseBackend::Sqlite => configure_sqlite_stores().await,
+    };
 
     let email_client = Arc::new(MockEmailClient);
 
-    let app_state = AppState::new(
+    let mut app_state = AppState::new(
         user_store,
         banned_token_store,
         two_fa_code_store,
         email_client,
+        audit_log_store,
     );
 
+    let mut email_domain_policy = EmailDomainPolicy::new(email_domain_rule_store);
+    if let Some(path) = MX_RECORDS_FILE.as_ref() {
+        let mx_resolver =
+            StaticMxResolver::from_file(path).expect("Failed to read MX records file");
+        email_domain_policy = email_domain_policy.with_mx_resolver(Arc::new(mx_resolver));
+    }
+    app_state = app_state.with_email_domain_policy(email_domain_policy);
+
+    app_state = app_state.with_proof_of_work(ProofOfWork::new(
+        ProofOfWorkConfig::default(),
+        proof_of_work_store,
+    ));
+    app_state = app_state.with_magic_links(MagicLinks::new(
+        magic_link_store,
+        MAGIC_LINK_BASE_URL.as_str(),
+    ));
+
+    if let Some(path) = OAUTH_PROVIDERS_FILE.as_ref() {
+        let providers = load_oauth_providers(path).expect("Failed to read OAuth providers file");
+        let oauth_client =
+            OAuthClient::new(providers, oauth_state_store).expect("Invalid OAuth providers");
+        app_state = app_state.with_oauth_client(oauth_client);
+    }
+
+    let oidc_clients = match OIDC_CLIENTS_FILE.as_ref() {
+        Some(path) => load_oidc_clients(path).expect("Failed to read OIDC clients file"),
+        None => Vec::new(),
+    };
+    let signing_key = match OIDC_SIGNING_KEY_FILE.as_ref() {
+        Some(path) => SigningKey::from_file(path).expect("Failed to read OIDC signing key"),
+        None => {
+            tracing::warn!("No OIDC signing key configured, id_tokens won't survive a restart");
+            SigningKey::generate()
+        }
+    };
+    let oidc_provider = OidcProvider::new(
+        OIDC_ISSUER.as_str(),
+        oidc_clients,
+        authorization_code_store,
+        signing_key,
+    )
+    .expect("Invalid OIDC clients");
+    app_state = app_state.with_oidc_provider(oidc_provider);
+    app_state = app_state.with_api_keys(ApiKeys::new(api_key_store));
+
+    if let Some(path) = BREACHED_PASSWORDS_FILE.as_ref() {
+        let checker = HibpFileBreachedPasswordChecker::open(path)
+            .expect("Failed to open breached passwords file");
+        app_state = app_state.with_breached_password_checker(Arc::new(checker));
+    }
+
+    let grpc_server = GrpcServer::build(app_state.clone(), &format!("0.0.0.0:{}", *GRPC_PORT))
+        .await
+        .expect("Failed to build gRPC server");
     let app = Application::build(app_state, prod::APP_ADDRESS)
         .await
         .expect("Failed to build app");
 
-    app.run().await.expect("Failed to run app");
+    tokio::select! {
+        result = app.run() => result.expect("Failed to run app"),
+        result = grpc_server.run() => result.expect("Failed to run gRPC server"),
+    }
+}
+
+async fn report_password_hashes(database_backend: DatabaseBackend) {
+    let user_store = configure_user_store(database_backend).await;
+
+    let report = password_hash_report(&user_store, &Argon2PasswordHasher::default())
+        .await
+        .expect("Failed to check password hashes");
+
+    println!("{}", report);
+}
+
+async fn import_users_from_file(database_backend: DatabaseBackend, path: &Path) {
+    let format = ImportFormat::from_path(path).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+    let file = File::open(path).expect("Failed to open import file");
+    let user_store = configure_user_store(database_backend).await;
+
+    let report = import_users(file, format, &user_store, &Argon2PasswordHasher::default())
+        .await
+        .expect("Failed to import users");
+
+    println!("{}", report);
+}
+
+async fn set_email_domain_rule(
+    database_backend: DatabaseBackend,
+    domain: &str,
+    rule: EmailDomainRule,
+) {
+    let rule_store = configure_email_domain_rule_store(database_backend).await;
+
+    rule_store
+        .write()
+        .await
+        .set_rule(domain, rule)
+        .await
+        .expect("Failed to set email domain rule");
+
+    println!("{} {}", rule, domain);
+}
+
+async fn remove_email_domain_rule(database_backend: DatabaseBackend, domain: &str) {
+    let rule_store = configure_email_domain_rule_store(database_backend).await;
+
+    let result = rule_store.write().await.remove_rule(domain).await;
+    match result {
+        Ok(()) => println!("Removed rule for {}", domain),
+        Err(EmailDomainRuleStoreError::RuleNotFound) => {
+            eprintln!("No rule for {}", domain);
+            std::process::exit(1);
+        }
+        Err(e) => panic!("Failed to remove email domain rule: {:?}", e),
+    }
+}
+
+async fn list_email_domain_rules(database_backend: DatabaseBackend) {
+    let rule_store = configure_email_domain_rule_store(database_backend).await;
+
+    let rules = rule_store
+        .read()
+        .await
+        .list_rules()
+        .await
+        .expect("Failed to list email domain rules");
+
+    for (domain, rule) in rules {
+        println!("{} {}", rule, domain);
+    }
+}
+
+async fn configure_email_domain_rule_store(
+    database_backend: DatabaseBackend,
+) -> EmailDomainRuleStoreType {
+    match database_backend {
+        DatabaseBackend::Postgres => Arc::new(RwLock::new(PostgresEmailDomainRuleStore::new(
+            configure_postgresql().await,
+        ))),
+        DatabaseBackend::Sqlite => Arc::new(RwLock::new(SqliteEmailDomainRuleStore::new(
+            configure_sqlite().await,
+        ))),
+    }
+}
+
+async fn configure_user_store(database_backend: DatabaseBackend) -> UserStoreType {
+    match database_backend {
+        DatabaseBackend::Postgres => Arc::new(RwLock::new(PostgresUserStore::new(
+            configure_postgresql().await,
+        ))),
+        DatabaseBackend::Sqlite => {
+            Arc::new(RwLock::new(SqliteUserStore::new(configure_sqlite().await)))
+        }
+    }
+}
+
+type Stores = (
+    UserStoreType,
+    BannedTokenStoreType,
+    TwoFACodeStoreType,
+    AuditLogStoreType,
+    EmailDomainRuleStoreType,
+    ProofOfWorkStoreType,
+    MagicLinkStoreType,
+    OAuthStateStoreType,
+    AuthorizationCodeStoreType,
+    ApiKeyStoreType,
+);
+
+async fn configure_postgresql_stores() -> Stores {
+    let pg_pool = configure_postgresql().await;
+    let (
+        banned_token_store,
+        two_fa_code_store,
+        proof_of_work_store,
+        magic_link_store,
+        oauth_state_store,
+        authorization_code_store,
+    ) = configure_token_stores(&pg_pool);
+
+    (
+        Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone()))),
+        banned_token_store,
+        two_fa_code_store,
+        Arc::new(RwLock::new(PostgresAuditLogStore::new(pg_pool.clone()))),
+        Arc::new(RwLock::new(PostgresEmailDomainRuleStore::new(
+            pg_pool.clone(),
+        ))),
+        proof_of_work_store,
+        magic_link_store,
+        oauth_state_store,
+        authorization_code_store,
+        Arc::new(RwLock::new(PostgresApiKeyStore::new(pg_pool))),
+    )
+}
+
+/// Keeps everything, including banned tokens, 2FA codes, proof-of-work counters, used magic
+/// links, pending OAuth logins and authorization codes, in a single SQLite database.
+async fn configure_sqlite_stores() -> Stores {
+    let sqlite_pool = configure_sqlite().await;
+
+    spawn_sqlite_expired_rows_cleanup(
+        sqlite_pool.clone(),
+        Duration::from_secs(*EXPIRED_ROWS_CLEANUP_INTERVAL_SECONDS),
+    );
+
+    (
+        Arc::new(RwLock::new(SqliteUserStore::new(sqlite_pool.clone()))),
+        Arc::new(RwLock::new(SqliteBannedTokenStore::new(
+            sqlite_pool.clone(),
+        ))),
+        Arc::new(RwLock::new(SqliteTwoFACodeStore::new(sqlite_pool.clone()))),
+        Arc::new(RwLock::new(SqliteAuditLogStore::new(sqlite_pool.clone()))),
+        Arc::new(RwLock::new(SqliteEmailDomainRuleStore::new(
+            sqlite_pool.clone(),
+        ))),
+        Arc::new(RwLock::new(SqliteProofOfWorkStore::new(
+            sqlite_pool.clone(),
+        ))),
+        Arc::new(RwLock::new(SqliteMagicLinkStore::new(sqlite_pool.clone()))),
+        Arc::new(RwLock::new(SqliteOAuthStateStore::new(sqlite_pool.clone()))),
+        Arc::new(RwLock::new(SqliteAuthorizationCodeStore::new(
+            sqlite_pool.clone(),
+        ))),
+        Arc::new(RwLock::new(SqliteApiKeyStore::new(sqlite_pool))),
+    )
 }
 
 async fn configure_postgresql() -> PgPool {
@@ -56,10 +345,90 @@ async fn configure_postgresql() -> PgPool {
         .run(&pg_pool)
         .await
         .expect("Failed to run migrations");
+    correct_email_lookup_keys(&pg_pool)
+        .await
+        .expect("Failed to correct email lookup keys");
 
     pg_pool
 }
 
+async fn configure_sqlite() -> SqlitePool {
+    let sqlite_pool = get_sqlite_pool(&DATABASE_URL)
+        .await
+        .expect("Failed to create SQLite connection pool!");
+
+    sqlx::migrate!("./migrations_sqlite")
+        .run(&sqlite_pool)
+        .await
+        .expect("Failed to run migrations");
+    correct_sqlite_email_lookup_keys(&sqlite_pool)
+        .await
+        .expect("Failed to correct email lookup keys");
+
+    sqlite_pool
+}
+
+/// Banned tokens, 2FA codes and the other short-lived state go to Redis or PostgreSQL,
+/// whichever `TOKEN_STORE_BACKEND` names.
+fn configure_token_stores(
+    pg_pool: &PgPool,
+) -> (
+    BannedTokenStoreType,
+    TwoFACodeStoreType,
+    ProofOfWorkStoreType,
+    MagicLinkStoreType,
+    OAuthStateStoreType,
+    AuthorizationCodeStoreType,
+) {
+    let backend = TOKEN_STORE_BACKEND
+        .parse::<TokenStoreBackend>()
+        .expect("Invalid token store backend");
+
+    match backend {
+        TokenStoreBackend::Redis => {
+            let redis_connection = Arc::new(RwLock::new(configure_redis()));
+
+            (
+                Arc::new(RwLock::new(RedisBannedTokenStore::new(
+                    redis_connection.clone(),
+                ))),
+                Arc::new(RwLock::new(RedisTwoFACodeStore::new(
+                    redis_connection.clone(),
+                ))),
+                Arc::new(RwLock::new(RedisProofOfWorkStore::new(
+                    redis_connection.clone(),
+                ))),
+                Arc::new(RwLock::new(RedisMagicLinkStore::new(
+                    redis_connection.clone(),
+                ))),
+                Arc::new(RwLock::new(RedisOAuthStateStore::new(
+                    redis_connection.clone(),
+                ))),
+                Arc::new(RwLock::new(RedisAuthorizationCodeStore::new(
+                    redis_connection,
+                ))),
+            )
+        }
+        TokenStoreBackend::Postgres => {
+            spawn_expired_rows_cleanup(
+                pg_pool.clone(),
+                Duration::from_secs(*EXPIRED_ROWS_CLEANUP_INTERVAL_SECONDS),
+            );
+
+            (
+                Arc::new(RwLock::new(PostgresBannedTokenStore::new(pg_pool.clone()))),
+                Arc::new(RwLock::new(PostgresTwoFACodeStore::new(pg_pool.clone()))),
+                Arc::new(RwLock::new(PostgresProofOfWorkStore::new(pg_pool.clone()))),
+                Arc::new(RwLock::new(PostgresMagicLinkStore::new(pg_pool.clone()))),
+                Arc::new(RwLock::new(PostgresOAuthStateStore::new(pg_pool.clone()))),
+                Arc::new(RwLock::new(PostgresAuthorizationCodeStore::new(
+                    pg_pool.clone(),
+                ))),
+            )
+        }
+    }
+}
+
 fn configure_redis() -> redis::Connection {
     get_redis_client(REDIS_HOST_NAME.to_owned())
         .expect("Failed to get Redis client")
@@ -0,0 +1,192 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{ApiKey, AuditEvent, AuditEventType, AuthAPIError, Email, MANAGE_API_KEYS_SCOPE},
+    services::api_keys::{ApiKeyError, NewApiKey},
+    utils::{
+        audit::record_audit_event,
+        auth::{authenticate_token, request_token, Credentials},
+        request_context::RequestContext,
+    },
+};
+
+/// Creates a key for the logged in user. The response is the only time the full key is shown.
+#[tracing::instrument(name = "Create API key", skip_all)]
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    context: RequestContext,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<CreateApiKeyResponse>), AuthAPIError> {
+    let credentials = authenticate(&state, &context, &jar, &headers).await?;
+    let email = credentials_email(&credentials)?;
+
+    let new_key = NewApiKey {
+        name: request.name,
+        scopes: request.scopes,
+        expires_in_days: request.expires_in_days,
+    };
+    let (api_key, key) = state
+        .api_keys
+        .create(&email, new_key, credentials.api_key())
+        .await
+        .map_err(|e| match e {
+            ApiKeyError::Rejected(rejection) => AuthAPIError::InvalidApiKey(rejection),
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    let event = AuditEvent::new(AuditEventType::ApiKeyCreated).with_email(email.as_ref());
+    record_audit_event(&state.audit_log_store, &context, event).await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateApiKeyResponse {
+            key,
+            api_key: api_key.into(),
+        }),
+    ))
+}
+
+#[tracing::instrument(name = "List API keys", skip_all)]
+pub async fn list_api_keys(
+    State(state): State<AppState>,
+    context: RequestContext,
+    jar: CookieJar,
+    headers: HeaderMap,
+) -> Result<Json<ApiKeysResponse>, AuthAPIError> {
+    let credentials = authenticate(&state, &context, &jar, &headers).await?;
+    require_key_management(&state, &context, &credentials).await?;
+    let email = credentials_email(&credentials)?;
+
+    let api_keys = state
+        .api_keys
+        .list(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(ApiKeysResponse {
+        api_keys: api_keys.into_iter().map(ApiKeyResponse::from).collect(),
+    }))
+}
+
+#[tracing::instrument(name = "Revoke API key", skip_all)]
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    context: RequestContext,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Path(prefix): Path<String>,
+) -> Result<StatusCode, AuthAPIError> {
+    let credentials = authenticate(&state, &context, &jar, &headers).await?;
+    require_key_management(&state, &context, &credentials).await?;
+    let email = credentials_email(&credentials)?;
+
+    match state.api_keys.revoke(&email, &prefix).await {
+        Ok(()) => {}
+        Err(ApiKeyError::KeyNotFound) => return Err(AuthAPIError::ApiKeyNotFound),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let event = AuditEvent::new(AuditEventType::ApiKeyRevoked).with_email(email.as_ref());
+    record_audit_event(&state.audit_log_store, &context, event).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Accepts the auth cookie, or an auth token or API key as a bearer token.
+async fn authenticate(
+    state: &AppState,
+    context: &RequestContext,
+    jar: &CookieJar,
+    headers: &HeaderMap,
+) -> Result<Credentials, AuthAPIError> {
+    let token = request_token(jar, headers).ok_or(AuthAPIError::MissingToken)?;
+
+    match authenticate_token(&token, None, state).await {
+        Err(AuthAPIError::InvalidToken) => {
+            let event = AuditEvent::new(AuditEventType::TokenRejected).failed("invalid_token");
+            record_audit_event(&state.audit_log_store, context, event).await;
+            Err(AuthAPIError::InvalidToken)
+        }
+        result => result,
+    }
+}
+
+/// Only sessions and keys with `MANAGE_API_KEYS_SCOPE` may see and revoke the user's keys.
+async fn require_key_management(
+    state: &AppState,
+    context: &RequestContext,
+    credentials: &Credentials,
+) -> Result<(), AuthAPIError> {
+    match credentials.api_key() {
+        Some(api_key) if !api_key.has_scope(MANAGE_API_KEYS_SCOPE) => {
+            let event = AuditEvent::new(AuditEventType::TokenRejected)
+                .with_email(api_key.email.as_str())
+                .failed("insufficient_scope");
+            record_audit_event(&state.audit_log_store, context, event).await;
+            Err(AuthAPIError::InsufficientScope)
+        }
+        _ => Ok(()),
+    }
+}
+
+fn credentials_email(credentials: &Credentials) -> Result<Email, AuthAPIError> {
+    Email::parse(credentials.subject().to_owned())
+        .map_err(|e| AuthAPIError::UnexpectedError(eyre!(e)))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// Defaults to `DEFAULT_API_KEY_TTL_DAYS`.
+    pub expires_in_days: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiKeyResponse {
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKeyResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeysResponse {
+    pub api_keys: Vec<ApiKeyResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyResponse {
+    pub prefix: String,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(api_key: ApiKey) -> Self {
+        Self {
+            prefix: api_key.prefix,
+            name: api_key.name,
+            scopes: api_key.scopes,
+            created_at: api_key.created_at,
+            expires_at: api_key.expires_at,
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditEvent, AuditEventQuery, AuthAPIError},
+    utils::{
+        auth::{bearer_token, secrets_match},
+        constants::AUDIT_API_TOKEN,
+    },
+};
+
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 1000;
+
+#[tracing::instrument(name = "Get audit events", skip_all)]
+pub async fn get_audit_events(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<AuditEventsParams>,
+) -> Result<Json<AuditEventsResponse>, AuthAPIError> {
+    let token = bearer_token(&headers).ok_or(AuthAPIError::MissingToken)?;
+
+    match AUDIT_API_TOKEN.as_deref() {
+        Some(expected) if secrets_match(token.as_bytes(), expected.as_bytes()) => {}
+        _ => return Err(AuthAPIError::InvalidToken),
+    }
+
+    let query = AuditEventQuery {
+        email: params.email,
+        from: params.from,
+        to: params.to,
+        limit: params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+    };
+
+    let events = state
+        .audit_log_store
+        .read()
+        .await
+        .get_events(&query)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(AuditEventsResponse {
+        events: events.into_iter().map(AuditEventResponse::from).collect(),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditEventsParams {
+    pub email: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditEventsResponse {
+    pub events: Vec<AuditEventResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEventResponse {
+    pub event_type: String,
+    pub email: Option<String>,
+    pub success: bool,
+    pub reason: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl From<AuditEvent> for AuditEventResponse {
+    fn from(event: AuditEvent) -> Self {
+        Self {
+            event_type: event.event_type.as_ref().to_owned(),
+            email: event.email,
+            success: event.success,
+            reason: event.reason,
+            ip_address: event.ip_address,
+            user_agent: event.user_agent,
+            request_id: event.request_id,
+            occurred_at: event.occurred_at,
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuthAPIError, ProofOfWorkAction},
+    services::proof_of_work::{ProofOfWorkError, ProofOfWorkSolution},
+};
+
+/// Hands out a proof-of-work puzzle for signup or login, whether or not one is required right
+/// now, so clients can always fetch one up front.
+#[tracing::instrument(name = "Get challenge", skip_all)]
+pub async fn get_challenge(
+    State(state): State<AppState>,
+    Query(params): Query<ChallengeParams>,
+) -> Result<Json<ChallengeResponse>, AuthAPIError> {
+    let challenge = state
+        .proof_of_work
+        .issue_challenge(params.action)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok(Json(ChallengeResponse {
+        challenge: challenge.challenge,
+        algorithm: "sha256".to_owned(),
+        difficulty: challenge.difficulty,
+        expires_in: challenge.expires_in,
+        required: challenge.required,
+    }))
+}
+
+/// Turns away requests for `action` without the solved challenge it currently needs. They
+/// aren't audited, `ProofOfWork` only counts them.
+pub(crate) async fn require_proof_of_work(
+    state: &AppState,
+    headers: &HeaderMap,
+    action: ProofOfWorkAction,
+) -> Result<(), AuthAPIError> {
+    let solution = ProofOfWorkSolution::from_headers(headers);
+    match state.proof_of_work.check(action, solution.as_ref()).await {
+        Ok(()) => Ok(()),
+        Err(ProofOfWorkError::UnexpectedError(e)) => Err(AuthAPIError::UnexpectedError(e)),
+        Err(_) => Err(AuthAPIError::ProofOfWorkRequired(action)),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChallengeParams {
+    pub action: ProofOfWorkAction,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ChallengeResponse {
+    pub challenge: String,
+    pub algorithm: String,
+    /// Leading zero bits the SHA-256 of `challenge:nonce` must have.
+    pub difficulty: u8,
+    pub expires_in: u64,
+    pub required: bool,
+}
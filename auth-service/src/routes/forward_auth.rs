@@ -0,0 +1,107 @@
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+};
+use axum_extra::extract::CookieJar;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditEvent, AuditEventType, AuthAPIError},
+    utils::{
+        audit::record_audit_event,
+        auth::{authenticate_token, request_token},
+        constants::{X_AUTH_ROLES_HEADER, X_AUTH_USER_HEADER},
+        request_context::RequestContext,
+    },
+};
+
+/// Checks requests for reverse proxies, as nginx `auth_request` or Traefik `ForwardAuth`.
+/// Requests with a valid auth token, API key or service token for `audience` get a 200 naming
+/// the user in `X-Auth-User` and a service token's scopes in `X-Auth-Roles`, everyone else a
+/// 401. With `redirect=true`, they're sent to the login page instead, coming back to the URL
+/// they asked for afterwards.
+#[tracing::instrument(name = "Forward auth", skip_all)]
+pub async fn forward_auth(
+    State(state): State<AppState>,
+    context: RequestContext,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Query(params): Query<ForwardAuthParams>,
+) -> Result<Response, AuthAPIError> {
+    let credentials = match request_token(&jar, &headers) {
+        Some(token) => authenticate_token(&token, params.audience.as_deref(), &state).await,
+        None => Err(AuthAPIError::MissingToken),
+    };
+
+    match credentials {
+        Ok(credentials) => {
+            let user = HeaderValue::try_from(credentials.subject())
+                .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+            let roles = HeaderValue::try_from(credentials.client_scopes().join(","))
+                .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+            Ok((
+                StatusCode::OK,
+                [(X_AUTH_USER_HEADER, user), (X_AUTH_ROLES_HEADER, roles)],
+            )
+                .into_response())
+        }
+        Err(AuthAPIError::UnexpectedError(e)) => Err(AuthAPIError::UnexpectedError(e)),
+        Err(e) => {
+            if let AuthAPIError::InvalidToken = e {
+                let event = AuditEvent::new(AuditEventType::TokenRejected).failed("invalid_token");
+                record_audit_event(&state.audit_log_store, &context, event).await;
+            }
+
+            if params.redirect {
+                let login_url = state
+                    .forward_auth
+                    .login_redirect(original_url(&headers).as_deref());
+                return Ok(Redirect::to(&login_url).into_response());
+            }
+            Ok(StatusCode::UNAUTHORIZED.into_response())
+        }
+    }
+}
+
+/// Where users come back to after logging in. The URL is checked again, as anyone can link
+/// here.
+pub async fn forward_auth_return(
+    State(state): State<AppState>,
+    Query(params): Query<ForwardAuthReturnParams>,
+) -> Redirect {
+    match params.url {
+        Some(url) if state.forward_auth.allows_return_url(&url) => Redirect::to(&url),
+        _ => Redirect::to("/"),
+    }
+}
+
+/// The URL the user asked the proxy for, from `X-Original-URL` as nginx is usually set up to
+/// send, or else the `X-Forwarded-*` headers Traefik sends.
+fn original_url(headers: &HeaderMap) -> Option<String> {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+
+    if let Some(url) = header("x-original-url") {
+        return Some(url.to_owned());
+    }
+
+    let proto = header("x-forwarded-proto")?;
+    let host = header("x-forwarded-host")?;
+    let uri = header("x-forwarded-uri").unwrap_or("/");
+    Some(format!("{}://{}{}", proto, host, uri))
+}
+
+#[derive(Deserialize)]
+pub struct ForwardAuthParams {
+    #[serde(default)]
+    pub redirect: bool,
+    /// Accepts service tokens issued for it.
+    pub audience: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ForwardAuthReturnParams {
+    pub url: Option<String>,
+}
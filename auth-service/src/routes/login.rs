@@ -1,48 +1,83 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
 use axum_extra::extract::CookieJar;
+use color_eyre::eyre::eyre;
 use serde::{Deserialize, Serialize};
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, Password, TwoFACode},
-    utils::auth::generate_auth_cookie,
+    domain::{
+        AuditEvent, AuditEventType, AuthAPIError, Email, LoginAttemptId, Password,
+        ProofOfWorkAction, TwoFACode,
+    },
+    utils::{
+        audit::record_audit_event, auth::generate_auth_cookie, csrf::create_csrf_cookie,
+        request_context::RequestContext,
+    },
 };
 
+use super::require_proof_of_work;
+
 pub async fn login(
     State(state): State<AppState>,
+    context: RequestContext,
     jar: CookieJar,
+    headers: HeaderMap,
     Json(request): Json<LoginRequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
-    let password = match Password::parse(request.password) {
-        Ok(password) => password,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
-    };
+    if let Err(e) = require_proof_of_work(&state, &headers, ProofOfWorkAction::Login).await {
+        return (jar, Err(e));
+    }
 
-    let email = match Email::parse(request.email) {
-        Ok(email) => email,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    let audit_event = AuditEvent::new(AuditEventType::Login);
+
+    let (email, password) = match (
+        Email::parse(request.email),
+        Password::parse(request.password),
+    ) {
+        (Ok(email), Ok(password)) => (email, password),
+        (email, _) => {
+            let event = match email {
+                Ok(email) => audit_event.with_email(email.as_ref()),
+                Err(_) => audit_event,
+            };
+            let event = event.failed("invalid_input");
+            record_audit_event(&state.audit_log_store, &context, event).await;
+            return (jar, Err(AuthAPIError::InvalidCredentials));
+        }
     };
+    let audit_event = audit_event.with_email(email.as_ref());
 
     let user_store = &state.user_store.read().await;
 
-    if user_store.validate_user(&email, &password).await.is_err() {
-        return (jar, Err(AuthAPIError::IncorrectCredentials));
-    }
+    let user = match user_store.validate_user(&email, &password).await {
+        Ok(()) => user_store.get_user(&email).await,
+        Err(e) => Err(e),
+    };
 
-    let user = match user_store.get_user(&email).await {
+    let user = match user {
         Ok(user) => user,
-        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+        Err(_) => {
+            let event = audit_event.failed("incorrect_credentials");
+            record_audit_event(&state.audit_log_store, &context, event).await;
+            return (jar, Err(AuthAPIError::IncorrectCredentials));
+        }
     };
 
     match user.requires_2fa {
-        true => handle_2fa(&user.email, &state, jar).await,
-        false => handle_no_2fa(&user.email, jar).await,
+        true => handle_2fa(&user.email, &state, &context, jar).await,
+        false => handle_no_2fa(&user.email, &state, &context, jar).await,
     }
 }
 
-async fn handle_2fa(
+pub(crate) async fn handle_2fa(
     email: &Email,
     state: &AppState,
+    context: &RequestContext,
     jar: CookieJar,
 ) -> (
     CookieJar,
@@ -51,26 +86,27 @@ async fn handle_2fa(
     let login_attempt_id = LoginAttemptId::default();
     let two_fa_code = TwoFACode::default();
 
-    if state
+    if let Err(e) = state
         .two_fa_code_store
         .write()
         .await
         .add_code(email.clone(), login_attempt_id.clone(), two_fa_code.clone())
         .await
-        .is_err()
     {
-        return (jar, Err(AuthAPIError::UnexpectedError));
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
-    if state
+    if let Err(e) = state
         .email_client
         .send_email(email, "2FA Code", two_fa_code.as_ref())
         .await
-        .is_err()
     {
-        return (jar, Err(AuthAPIError::UnexpectedError));
+        return (jar, Err(AuthAPIError::UnexpectedError(eyre!(e))));
     }
 
+    let event = AuditEvent::new(AuditEventType::TwoFACodeIssued).with_email(email.as_ref());
+    record_audit_event(&state.audit_log_store, context, event).await;
+
     let response = Json(LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
         message: "2FA required".to_owned(),
         login_attempt_id: login_attempt_id.as_ref().to_owned(),
@@ -79,8 +115,10 @@ async fn handle_2fa(
     (jar, Ok((StatusCode::PARTIAL_CONTENT, response)))
 }
 
-async fn handle_no_2fa(
+pub(crate) async fn handle_no_2fa(
     email: &Email,
+    state: &AppState,
+    context: &RequestContext,
     jar: CookieJar,
 ) -> (
     CookieJar,
@@ -88,10 +126,14 @@ async fn handle_no_2fa(
 ) {
     let auth_cookie = match generate_auth_cookie(email) {
         Ok(cookie) => cookie,
-        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
     };
 
-    let updated_jar = jar.add(auth_cookie);
+    let csrf_cookie = create_csrf_cookie(auth_cookie.value());
+    let updated_jar = jar.add(auth_cookie).add(csrf_cookie);
+
+    let event = AuditEvent::new(AuditEventType::Login).with_email(email.as_ref());
+    record_audit_event(&state.audit_log_store, context, event).await;
 
     (
         updated_jar,
@@ -1,42 +1,59 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse};
-use axum_extra::extract::{cookie, CookieJar};
+use axum_extra::extract::CookieJar;
 
 use crate::{
     app_state::AppState,
-    domain::AuthAPIError,
-    utils::{auth::validate_token, constants::JWT_COOKIE_NAME},
+    domain::{AuditEvent, AuditEventType, AuthAPIError},
+    utils::{
+        audit::record_audit_event, auth::validate_token, constants::CSRF_COOKIE_NAME,
+        cookies::COOKIE_SETTINGS, request_context::RequestContext,
+    },
 };
 
 pub async fn logout(
     State(state): State<AppState>,
+    context: RequestContext,
     jar: CookieJar,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
-    let cookie = match jar.get(JWT_COOKIE_NAME) {
+    let auth_cookie_name = COOKIE_SETTINGS.auth_cookie_name();
+    let cookie = match jar.get(auth_cookie_name) {
         Some(cookie) => cookie,
-        None => return (jar, Err(AuthAPIError::MissingToken)),
+        None => {
+            let event = AuditEvent::new(AuditEventType::TokenRejected).failed("missing_token");
+            record_audit_event(&state.audit_log_store, &context, event).await;
+            return (jar, Err(AuthAPIError::MissingToken));
+        }
     };
 
     // Validate token
     let token = cookie.value().to_owned();
-    let _ = match validate_token(&token, state.banned_token_store.clone()).await {
+    let claims = match validate_token(&token, state.banned_token_store.clone()).await {
         Ok(claims) => claims,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+        Err(_) => {
+            let event = AuditEvent::new(AuditEventType::TokenRejected).failed("invalid_token");
+            record_audit_event(&state.audit_log_store, &context, event).await;
+            return (jar, Err(AuthAPIError::InvalidToken));
+        }
     };
 
     // Add token to banned list
-    if state
+    if let Err(e) = state
         .banned_token_store
         .write()
         .await
         .add_token(token.to_owned())
         .await
-        .is_err()
     {
-        return (jar, Err(AuthAPIError::UnexpectedError));
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
-    // Remove jwt cookie
-    let jar = jar.remove(cookie::Cookie::from(JWT_COOKIE_NAME));
+    // Remove jwt and CSRF cookies, with the attributes they were set with so browsers match them
+    let jar = jar
+        .remove(COOKIE_SETTINGS.removal(auth_cookie_name))
+        .remove(COOKIE_SETTINGS.removal(CSRF_COOKIE_NAME));
+
+    let event = AuditEvent::new(AuditEventType::Logout).with_email(claims.sub);
+    record_audit_event(&state.audit_log_store, &context, event).await;
 
     (jar, Ok(StatusCode::OK))
 }
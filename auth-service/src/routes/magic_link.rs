@@ -0,0 +1,159 @@
+use axum::{
+    extract::{Query, State},
+    http::{
+        header::{CACHE_CONTROL, REFERRER_POLICY},
+        HeaderMap, StatusCode,
+    },
+    response::{Html, IntoResponse},
+    Json,
+};
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::eyre;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditEvent, AuditEventType, AuthAPIError, Email, ProofOfWorkAction, UserStoreError},
+    services::magic_link::{MagicLinkError, MAGIC_LINK_TTL_SECONDS},
+    utils::{audit::record_audit_event, request_context::RequestContext},
+};
+
+use super::{handle_2fa, handle_no_2fa, require_proof_of_work};
+
+const MAGIC_LINK_PAGE: &str = include_str!("../../assets/magic-link.html");
+
+/// Emails a login link to the user. Responds the same whether or not the account exists, so
+/// it can't be used to find out who has one.
+#[tracing::instrument(name = "Request magic link", skip_all)]
+pub async fn request_magic_link(
+    State(state): State<AppState>,
+    context: RequestContext,
+    headers: HeaderMap,
+    Json(request): Json<MagicLinkRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    require_proof_of_work(&state, &headers, ProofOfWorkAction::Login).await?;
+
+    let audit_event = AuditEvent::new(AuditEventType::MagicLinkIssued);
+
+    let email = match Email::parse(request.email) {
+        Ok(email) => email,
+        Err(_) => {
+            let event = audit_event.failed("invalid_input");
+            record_audit_event(&state.audit_log_store, &context, event).await;
+            return Err(AuthAPIError::InvalidCredentials);
+        }
+    };
+    let audit_event = audit_event.with_email(email.as_ref());
+
+    let response = Json(MagicLinkResponse {
+        message: "If the account exists, a login link has been sent".to_owned(),
+    });
+
+    // Send it to the address as stored, like every other email
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => {
+            let event = audit_event.failed("user_not_found");
+            record_audit_event(&state.audit_log_store, &context, event).await;
+            return Ok((StatusCode::OK, response));
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    let link = state
+        .magic_links
+        .issue(&user.email)
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let content = format!(
+        "Use this link to log in: {}\n\nIt expires in {} minutes and works only once. If you \
+         didn't ask for it, you can ignore this email.",
+        link,
+        MAGIC_LINK_TTL_SECONDS / 60
+    );
+
+    if let Err(e) = state
+        .email_client
+        .send_email(&user.email, "Your login link", &content)
+        .await
+    {
+        return Err(AuthAPIError::UnexpectedError(eyre!(e)));
+    }
+
+    record_audit_event(&state.audit_log_store, &context, audit_event).await;
+
+    Ok((StatusCode::OK, response))
+}
+
+/// Where magic links lead. Only asks the user to confirm, since mail scanners and link
+/// previews fetch links too and must neither use them up nor log in with them.
+#[tracing::instrument(name = "Magic link page", skip_all)]
+pub async fn magic_link_page() -> impl IntoResponse {
+    (
+        [
+            (CACHE_CONTROL, "no-store"),
+            (REFERRER_POLICY, "no-referrer"),
+        ],
+        Html(MAGIC_LINK_PAGE),
+    )
+}
+
+/// Where the page behind a magic link posts to. Logs the user in like `login` would after
+/// checking their password, so users with 2FA still have to enter the code sent to them.
+#[tracing::instrument(name = "Magic link callback", skip_all)]
+pub async fn magic_link_callback(
+    State(state): State<AppState>,
+    context: RequestContext,
+    jar: CookieJar,
+    Query(params): Query<MagicLinkCallbackParams>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let audit_event = AuditEvent::new(AuditEventType::Login);
+
+    let email = match state.magic_links.redeem(&params.token).await {
+        Ok(email) => email,
+        Err(MagicLinkError::UnexpectedError(e)) => {
+            return (jar, Err(AuthAPIError::UnexpectedError(e)))
+        }
+        Err(e) => {
+            let reason = match e {
+                MagicLinkError::AlreadyUsed => "magic_link_already_used",
+                _ => "invalid_magic_link",
+            };
+            let event = audit_event.failed(reason);
+            record_audit_event(&state.audit_log_store, &context, event).await;
+            return (jar, Err(AuthAPIError::InvalidToken));
+        }
+    };
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => {
+            // Deleted since the link was sent
+            let event = audit_event
+                .with_email(email.as_ref())
+                .failed("user_not_found");
+            record_audit_event(&state.audit_log_store, &context, event).await;
+            return (jar, Err(AuthAPIError::InvalidToken));
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    match user.requires_2fa {
+        true => handle_2fa(&user.email, &state, &context, jar).await,
+        false => handle_no_2fa(&user.email, &state, &context, jar).await,
+    }
+}
+
+#[derive(Deserialize)]
+pub struct MagicLinkRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct MagicLinkResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+pub struct MagicLinkCallbackParams {
+    pub token: String,
+}
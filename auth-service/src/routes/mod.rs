@@ -1,11 +1,25 @@
+mod api_keys;
+mod audit_events;
+mod challenge;
+mod forward_auth;
 mod login;
 mod logout;
+mod magic_link;
+mod oauth;
+mod oidc;
 mod signup;
 mod verify_2fa;
 mod verify_token;
 
+pub use api_keys::*;
+pub use audit_events::*;
+pub use challenge::*;
+pub use forward_auth::*;
 pub use login::*;
 pub use logout::*;
+pub use magic_link::*;
+pub use oauth::*;
+pub use oidc::*;
 pub use signup::*;
 pub use verify_2fa::*;
 pub use verify_token::*;
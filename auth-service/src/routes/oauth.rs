@@ -0,0 +1,174 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Redirect},
+};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use color_eyre::eyre::eyre;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditEvent, AuditEventType, AuthAPIError, Email, Password, User, UserStoreError},
+    services::{email_domain_policy::EmailDomainPolicyError, oauth::OAuthError},
+    utils::{
+        audit::record_audit_event, constants::OAUTH_STATE_COOKIE_NAME,
+        request_context::RequestContext,
+    },
+};
+
+use super::{handle_2fa, handle_no_2fa};
+
+const OAUTH_COOKIE_PATH: &str = "/oauth";
+// Accounts created through a provider get a password nobody knows
+const GENERATED_PASSWORD_CHARS: usize = 64;
+
+/// Sends the user off to log in with `provider`. The state is also kept in a cookie, so the
+/// callback can tell the login was started in this browser.
+#[tracing::instrument(name = "OAuth authorize", skip_all, fields(provider = %provider))]
+pub async fn oauth_authorize(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(provider): Path<String>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let (url, oauth_state) = match state.oauth_client.authorization_url(&provider).await {
+        Ok(authorization) => authorization,
+        Err(OAuthError::UnknownProvider) => return (jar, Err(AuthAPIError::UnknownOAuthProvider)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    let cookie = Cookie::build((OAUTH_STATE_COOKIE_NAME, oauth_state))
+        .path(OAUTH_COOKIE_PATH)
+        .http_only(true)
+        // Lax still sends it along on the provider's top-level redirect back to us
+        .same_site(SameSite::Lax)
+        .build();
+
+    (jar.add(cookie), Ok(Redirect::to(&url)))
+}
+
+/// Where providers send users back to. Logs in the account with the email address the
+/// provider verified, creating one if there isn't any yet, then carries on like `login`.
+#[tracing::instrument(name = "OAuth callback", skip_all, fields(provider = %provider))]
+pub async fn oauth_callback(
+    State(state): State<AppState>,
+    context: RequestContext,
+    jar: CookieJar,
+    Path(provider): Path<String>,
+    Query(params): Query<OAuthCallbackParams>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let audit_event = AuditEvent::new(AuditEventType::Login);
+
+    let cookie_state = jar
+        .get(OAUTH_STATE_COOKIE_NAME)
+        .map(|cookie| cookie.value().to_owned());
+    let jar = jar.remove(Cookie::build(OAUTH_STATE_COOKIE_NAME).path(OAUTH_COOKIE_PATH));
+
+    let oauth_state = match (params.state, cookie_state) {
+        (Some(state), Some(cookie_state)) if state == cookie_state => state,
+        _ => {
+            let event = audit_event.failed("invalid_oauth_state");
+            record_audit_event(&state.audit_log_store, &context, event).await;
+            return (jar, Err(AuthAPIError::InvalidOAuthState));
+        }
+    };
+
+    if let Some(error) = &params.error {
+        tracing::info!(error = %error, "OAuth provider returned an error");
+    }
+
+    let email = match state
+        .oauth_client
+        .complete(&provider, &oauth_state, params.code.as_deref())
+        .await
+    {
+        Ok(email) => email,
+        Err(OAuthError::UnknownProvider) => return (jar, Err(AuthAPIError::UnknownOAuthProvider)),
+        Err(OAuthError::InvalidState) => {
+            let event = audit_event.failed("invalid_oauth_state");
+            record_audit_event(&state.audit_log_store, &context, event).await;
+            return (jar, Err(AuthAPIError::InvalidOAuthState));
+        }
+        Err(OAuthError::Failed(failure)) => {
+            let event = audit_event.failed(failure.as_str());
+            record_audit_event(&state.audit_log_store, &context, event).await;
+            return (jar, Err(AuthAPIError::OAuthLoginFailed(failure)));
+        }
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => Some(user),
+        Err(UserStoreError::UserNotFound) => None,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+    let user = match user {
+        Some(user) => user,
+        None => match create_user(&state, &context, email).await {
+            Ok(user) => user,
+            Err(e) => return (jar, Err(e)),
+        },
+    };
+
+    match user.requires_2fa {
+        true => handle_2fa(&user.email, &state, &context, jar).await,
+        false => handle_no_2fa(&user.email, &state, &context, jar).await,
+    }
+}
+
+/// Signs up someone logging in through a provider for the first time, subject to the same
+/// email domain rules as `signup`.
+async fn create_user(
+    state: &AppState,
+    context: &RequestContext,
+    email: Email,
+) -> Result<User, AuthAPIError> {
+    let audit_event = AuditEvent::new(AuditEventType::Signup).with_email(email.as_ref());
+
+    match state.email_domain_policy.check(&email).await {
+        Ok(()) => {}
+        Err(EmailDomainPolicyError::Rejected(rejection)) => {
+            let event = audit_event.failed(rejection.as_str());
+            record_audit_event(&state.audit_log_store, context, event).await;
+            return Err(AuthAPIError::EmailDomainNotAllowed(rejection));
+        }
+        Err(EmailDomainPolicyError::UnexpectedError(e)) => {
+            return Err(AuthAPIError::UnexpectedError(e))
+        }
+    }
+
+    let password: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(GENERATED_PASSWORD_CHARS)
+        .map(char::from)
+        .collect();
+    let password =
+        Password::parse(password).map_err(|e| AuthAPIError::UnexpectedError(eyre!(e)))?;
+    let user = User::new(email, password, false);
+
+    if let Err(e) = state.user_store.write().await.add_user(user.clone()).await {
+        // Another callback for the same address may have just created it
+        if e != UserStoreError::UserAlreadyExists {
+            return Err(AuthAPIError::UnexpectedError(e.into()));
+        }
+        return state
+            .user_store
+            .read()
+            .await
+            .get_user(&user.email)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()));
+    }
+
+    record_audit_event(&state.audit_log_store, context, audit_event).await;
+
+    Ok(user)
+}
+
+#[derive(Deserialize)]
+pub struct OAuthCallbackParams {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    /// Set instead of `code` when the user didn't grant access.
+    pub error: Option<String>,
+}
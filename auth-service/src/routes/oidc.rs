@@ -0,0 +1,441 @@
+use axum::{
+    extract::{Query, State},
+    http::{
+        header::{AUTHORIZATION, CACHE_CONTROL},
+        HeaderMap, Uri,
+    },
+    response::{IntoResponse, Redirect, Response},
+    Form, Json,
+};
+use axum_extra::extract::CookieJar;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use url::{form_urlencoded, Url};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditEvent, AuditEventType, AuthAPIError, AuthorizationCode, OAuthErrorCode},
+    services::oidc::{OidcClient, OidcError, SUPPORTED_SCOPES},
+    utils::{
+        audit::record_audit_event,
+        auth::{authenticate_access_token, bearer_token, validate_token, Credentials},
+        cookies::COOKIE_SETTINGS,
+        request_context::RequestContext,
+    },
+};
+
+/// Where users who aren't logged in yet are sent, with the authorization request to come back
+/// to afterwards.
+const LOGIN_PAGE_PATH: &str = "/";
+
+pub async fn openid_configuration(State(state): State<AppState>) -> Json<serde_json::Value> {
+    Json(state.oidc_provider.discovery_document())
+}
+
+pub async fn jwks(State(state): State<AppState>) -> Json<serde_json::Value> {
+    Json(state.oidc_provider.jwks())
+}
+
+/// The authorization endpoint of the code flow. Users who are logged in are sent straight back
+/// to the client with a code, everyone else goes through the login page first.
+#[tracing::instrument(name = "OIDC authorize", skip_all)]
+pub async fn oidc_authorize(
+    State(state): State<AppState>,
+    context: RequestContext,
+    jar: CookieJar,
+    uri: Uri,
+    Query(params): Query<AuthorizeParams>,
+) -> Result<Redirect, AuthAPIError> {
+    let client = params
+        .client_id
+        .as_deref()
+        .and_then(|client_id| state.oidc_provider.client(client_id));
+    // Errors only go back to the client once we know the redirect URI is really theirs
+    let (client, redirect_uri) = match (client, params.redirect_uri.as_deref()) {
+        (Some(client), Some(redirect_uri)) if client.allows_redirect_uri(redirect_uri) => {
+            (client, redirect_uri)
+        }
+        _ => return Err(AuthAPIError::OAuth(OAuthErrorCode::InvalidRequest)),
+    };
+    let redirect_with_error = |code: OAuthErrorCode| {
+        redirect_to_client(
+            redirect_uri,
+            &[("error", code.as_str())],
+            params.state.as_deref(),
+        )
+    };
+
+    if params.response_type.as_deref() != Some("code") {
+        return redirect_with_error(OAuthErrorCode::UnsupportedResponseType);
+    }
+
+    let requested_scopes: Vec<&str> = params
+        .scope
+        .as_deref()
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect();
+    if !requested_scopes.contains(&"openid") {
+        return redirect_with_error(OAuthErrorCode::InvalidScope);
+    }
+    // Scopes we don't know are left out rather than rejected
+    let scopes = SUPPORTED_SCOPES
+        .iter()
+        .filter(|scope| requested_scopes.contains(scope))
+        .map(|scope| scope.to_string())
+        .collect();
+
+    // Every client has to use PKCE, including confidential ones
+    let code_challenge = match (&params.code_challenge, &params.code_challenge_method) {
+        (Some(code_challenge), Some(method)) if method == "S256" => code_challenge.clone(),
+        _ => return redirect_with_error(OAuthErrorCode::InvalidRequest),
+    };
+
+    let claims = match jar.get(COOKIE_SETTINGS.auth_cookie_name()) {
+        Some(cookie) => validate_token(cookie.value(), state.banned_token_store.clone())
+            .await
+            .ok(),
+        None => None,
+    };
+    let Some(claims) = claims else {
+        if params.prompt.as_deref() == Some("none") {
+            return redirect_with_error(OAuthErrorCode::LoginRequired);
+        }
+        let return_to = uri
+            .path_and_query()
+            .map(|path_and_query| path_and_query.as_str())
+            .unwrap_or_default();
+        let query = form_urlencoded::Serializer::new(String::new())
+            .append_pair("return_to", return_to)
+            .finish();
+        return Ok(Redirect::to(&format!("{}?{}", LOGIN_PAGE_PATH, query)));
+    };
+
+    let authorization_code = AuthorizationCode {
+        client_id: client.client_id().to_owned(),
+        redirect_uri: redirect_uri.to_owned(),
+        subject: claims.sub.clone(),
+        scopes,
+        nonce: params.nonce.clone(),
+        code_challenge,
+        auth_time: claims.iat as u64,
+    };
+    let code = state
+        .oidc_provider
+        .issue_code(authorization_code)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let event = AuditEvent::new(AuditEventType::AuthorizationCodeIssued).with_email(&claims.sub);
+    record_audit_event(&state.audit_log_store, &context, event).await;
+
+    redirect_to_client(redirect_uri, &[("code", &code)], params.state.as_deref())
+}
+
+/// The token endpoint, where clients redeem authorization codes and services get tokens of
+/// their own with the client credentials grant.
+#[tracing::instrument(name = "OIDC token", skip_all)]
+pub async fn oidc_token(
+    State(state): State<AppState>,
+    context: RequestContext,
+    headers: HeaderMap,
+    Form(request): Form<TokenRequest>,
+) -> Result<Response, AuthAPIError> {
+    let audit_event = AuditEvent::new(AuditEventType::TokenIssued);
+
+    let client = authenticate_client(
+        &state,
+        &headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    );
+    let Some(client) = client else {
+        let event = audit_event.failed("invalid_client");
+        record_audit_event(&state.audit_log_store, &context, event).await;
+        return Err(AuthAPIError::OAuth(OAuthErrorCode::InvalidClient));
+    };
+
+    match request.grant_type.as_deref() {
+        Some("authorization_code") => {}
+        Some("client_credentials") => {
+            let tokens = match state.oidc_provider.issue_service_token(
+                client,
+                request.audience.as_deref(),
+                request.scope.as_deref(),
+            ) {
+                Ok(tokens) => tokens,
+                Err(e) => {
+                    let code = match e {
+                        OidcError::UnauthorizedClient => OAuthErrorCode::UnauthorizedClient,
+                        OidcError::InvalidTarget => OAuthErrorCode::InvalidTarget,
+                        OidcError::InvalidScope => OAuthErrorCode::InvalidScope,
+                        OidcError::InvalidClient | OidcError::InvalidGrant => {
+                            OAuthErrorCode::InvalidGrant
+                        }
+                        OidcError::UnexpectedError(e) => {
+                            return Err(AuthAPIError::UnexpectedError(e))
+                        }
+                    };
+                    let event = audit_event.failed(code.as_str());
+                    record_audit_event(&state.audit_log_store, &context, event).await;
+                    return Err(AuthAPIError::OAuth(code));
+                }
+            };
+            record_audit_event(&state.audit_log_store, &context, audit_event).await;
+
+            return Ok(([(CACHE_CONTROL, "no-store")], Json(tokens)).into_response());
+        }
+        _ => return Err(AuthAPIError::OAuth(OAuthErrorCode::UnsupportedGrantType)),
+    }
+    let (Some(code), Some(redirect_uri), Some(code_verifier)) =
+        (&request.code, &request.redirect_uri, &request.code_verifier)
+    else {
+        return Err(AuthAPIError::OAuth(OAuthErrorCode::InvalidRequest));
+    };
+
+    let tokens = match state
+        .oidc_provider
+        .redeem_code(client, code, redirect_uri, code_verifier)
+        .await
+    {
+        Ok(tokens) => tokens,
+        Err(OidcError::UnexpectedError(e)) => return Err(AuthAPIError::UnexpectedError(e)),
+        Err(_) => {
+            let event = audit_event.failed("invalid_grant");
+            record_audit_event(&state.audit_log_store, &context, event).await;
+            return Err(AuthAPIError::OAuth(OAuthErrorCode::InvalidGrant));
+        }
+    };
+
+    record_audit_event(&state.audit_log_store, &context, audit_event).await;
+
+    Ok(([(CACHE_CONTROL, "no-store")], Json(tokens)).into_response())
+}
+
+/// Token introspection as in RFC 7662, for registered clients to learn whether a token is
+/// still good and who it belongs to. Service tokens are only active for the client they were
+/// issued for as audience, and access tokens for the client they were issued to.
+#[tracing::instrument(name = "OAuth introspect", skip_all)]
+pub async fn oauth_introspect(
+    State(state): State<AppState>,
+    context: RequestContext,
+    headers: HeaderMap,
+    Form(request): Form<IntrospectionRequest>,
+) -> Result<Response, AuthAPIError> {
+    let client = authenticate_client(
+        &state,
+        &headers,
+        request.client_id.as_deref(),
+        request.client_secret.as_deref(),
+    )
+    .ok_or(AuthAPIError::OAuth(OAuthErrorCode::InvalidClient))?;
+
+    let Some(token) = request.token else {
+        return Err(AuthAPIError::OAuth(OAuthErrorCode::InvalidRequest));
+    };
+
+    let response = match authenticate_access_token(&token, Some(client.client_id()), &state).await {
+        Ok(credentials) => IntrospectionResponse::from(credentials),
+        Err(AuthAPIError::InvalidToken) => {
+            let event = AuditEvent::new(AuditEventType::TokenRejected).failed("invalid_token");
+            record_audit_event(&state.audit_log_store, &context, event).await;
+            IntrospectionResponse::default()
+        }
+        Err(e) => return Err(e),
+    };
+
+    Ok(([(CACHE_CONTROL, "no-store")], Json(response)).into_response())
+}
+
+/// Tells clients who the access token belongs to. Auth tokens and API keys are accepted as
+/// access tokens too.
+#[tracing::instrument(name = "OIDC userinfo", skip_all)]
+pub async fn oidc_userinfo(
+    State(state): State<AppState>,
+    context: RequestContext,
+    headers: HeaderMap,
+) -> Result<Json<UserInfoResponse>, AuthAPIError> {
+    let token = bearer_token(&headers).ok_or(AuthAPIError::MissingToken)?;
+
+    match authenticate_access_token(token, None, &state).await {
+        Ok(credentials) => Ok(Json(UserInfoResponse {
+            sub: credentials.subject().to_owned(),
+            email: credentials.subject().to_owned(),
+        })),
+        Err(AuthAPIError::InvalidToken) => {
+            let event = AuditEvent::new(AuditEventType::TokenRejected).failed("invalid_token");
+            record_audit_event(&state.audit_log_store, &context, event).await;
+            Err(AuthAPIError::InvalidToken)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Sends the user back to the client with `params` and the client's `state`.
+fn redirect_to_client(
+    redirect_uri: &str,
+    params: &[(&str, &str)],
+    state: Option<&str>,
+) -> Result<Redirect, AuthAPIError> {
+    let mut url = Url::parse(redirect_uri).map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    {
+        let mut query = url.query_pairs_mut();
+        query.extend_pairs(params);
+        if let Some(state) = state {
+            query.append_pair("state", state);
+        }
+    }
+
+    Ok(Redirect::to(url.as_str()))
+}
+
+/// The registered client a request was made by, if its credentials check out.
+pub(crate) fn authenticate_client<'a>(
+    state: &'a AppState,
+    headers: &HeaderMap,
+    client_id: Option<&str>,
+    client_secret: Option<&str>,
+) -> Option<&'a OidcClient> {
+    let (client_id, client_secret) = client_credentials(headers, client_id, client_secret)?;
+    state
+        .oidc_provider
+        .authenticate_client(&client_id, &client_secret)
+        .ok()
+}
+
+/// The client's id and secret from HTTP Basic auth, or else from the form.
+fn client_credentials(
+    headers: &HeaderMap,
+    client_id: Option<&str>,
+    client_secret: Option<&str>,
+) -> Option<(String, String)> {
+    let basic = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "))
+        .and_then(|credentials| STANDARD.decode(credentials).ok())
+        .and_then(|credentials| String::from_utf8(credentials).ok());
+
+    match basic {
+        Some(credentials) => credentials
+            .split_once(':')
+            .map(|(client_id, client_secret)| (client_id.to_owned(), client_secret.to_owned())),
+        None => client_id
+            .zip(client_secret)
+            .map(|(client_id, client_secret)| (client_id.to_owned(), client_secret.to_owned())),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AuthorizeParams {
+    pub client_id: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub response_type: Option<String>,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub nonce: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    /// Only `none` is supported, for clients checking for a session without showing a login.
+    pub prompt: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TokenRequest {
+    pub grant_type: Option<String>,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    /// For the client credentials grant, the service the token is for.
+    pub audience: Option<String>,
+    pub scope: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct IntrospectionRequest {
+    pub token: Option<String>,
+    /// Accepted as RFC 7662 requires, but tokens tell their kind apart by themselves.
+    pub token_type_hint: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+/// Only `active` is set for tokens that are invalid, expired or revoked, so nothing leaks
+/// about them.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exp: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat: Option<u64>,
+    /// Space-separated. Left out for browser sessions, which have none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// The session of an auth token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+    /// `session`, `api_key`, `service` or `access`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    /// The client a service or access token was issued to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+}
+
+impl From<Credentials> for IntrospectionResponse {
+    fn from(credentials: Credentials) -> Self {
+        let active = Self {
+            active: true,
+            token_type: Some(credentials.token_type().to_owned()),
+            ..Default::default()
+        };
+
+        match credentials {
+            Credentials::Session(claims) => Self {
+                sub: Some(claims.sub),
+                exp: Some(claims.exp as u64),
+                iat: Some(claims.iat as u64),
+                sid: Some(claims.sid).filter(|sid| !sid.is_empty()),
+                ..active
+            },
+            Credentials::ApiKey(api_key) => Self {
+                sub: Some(api_key.email),
+                exp: api_key.expires_at.timestamp().try_into().ok(),
+                iat: api_key.created_at.timestamp().try_into().ok(),
+                scope: Some(api_key.scopes.join(" ")),
+                ..active
+            },
+            Credentials::Service(claims) => Self {
+                client_id: Some(claims.sub.clone()),
+                sub: Some(claims.sub),
+                exp: Some(claims.exp as u64),
+                iat: Some(claims.iat as u64),
+                scope: Some(claims.scope),
+                aud: Some(claims.aud),
+                ..active
+            },
+            Credentials::Access(claims) => Self {
+                sub: Some(claims.sub),
+                exp: Some(claims.exp as u64),
+                iat: Some(claims.iat as u64),
+                scope: Some(claims.scope),
+                client_id: Some(claims.aud.clone()),
+                aud: Some(claims.aud),
+                ..active
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct UserInfoResponse {
+    pub sub: String,
+    pub email: String,
+}
@@ -1,26 +1,88 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, Password, User},
+    domain::{
+        AuditEvent, AuditEventType, AuthAPIError, Email, Password, PasswordPolicyViolation,
+        ProofOfWorkAction, User,
+    },
+    services::email_domain_policy::EmailDomainPolicyError,
+    utils::{audit::record_audit_event, request_context::RequestContext},
 };
 
+use super::require_proof_of_work;
+
 #[tracing::instrument(name = "Signup", skip_all)]
 pub async fn signup(
     State(state): State<AppState>,
+    context: RequestContext,
+    headers: HeaderMap,
     Json(request): Json<SignupRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
-    let email =
-        Email::parse(request.email.clone()).map_err(|_| AuthAPIError::InvalidCredentials)?;
-    let password =
-        Password::parse(request.password.clone()).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    require_proof_of_work(&state, &headers, ProofOfWorkAction::Signup).await?;
+
+    let audit_event = AuditEvent::new(AuditEventType::Signup);
+
+    let (email, password) = match (
+        Email::parse(request.email.clone()),
+        Password::parse(request.password.clone()),
+    ) {
+        (Ok(email), Ok(password)) => (email, password),
+        (email, _) => {
+            let event = match email {
+                Ok(email) => audit_event.with_email(email.as_ref()),
+                Err(_) => audit_event,
+            };
+            let event = event.failed("invalid_input");
+            record_audit_event(&state.audit_log_store, &context, event).await;
+            return Err(AuthAPIError::InvalidCredentials);
+        }
+    };
+    let audit_event = audit_event.with_email(email.as_ref());
+
+    match state.email_domain_policy.check(&email).await {
+        Ok(()) => {}
+        Err(EmailDomainPolicyError::Rejected(rejection)) => {
+            let event = audit_event.failed(rejection.as_str());
+            record_audit_event(&state.audit_log_store, &context, event).await;
+            return Err(AuthAPIError::EmailDomainNotAllowed(rejection));
+        }
+        Err(EmailDomainPolicyError::UnexpectedError(e)) => {
+            return Err(AuthAPIError::UnexpectedError(e))
+        }
+    }
+
+    if let Err(violations) = state.password_policy.check(&password, &email) {
+        let event = audit_event.failed("weak_password");
+        record_audit_event(&state.audit_log_store, &context, event).await;
+        return Err(AuthAPIError::PasswordPolicyViolation(violations));
+    }
+
+    match state.breached_password_checker.is_breached(&password).await {
+        Ok(false) => {}
+        Ok(true) => {
+            let event = audit_event.failed("breached_password");
+            record_audit_event(&state.audit_log_store, &context, event).await;
+            return Err(AuthAPIError::PasswordPolicyViolation(vec![
+                PasswordPolicyViolation::Breached,
+            ]));
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e)),
+    }
 
     let user = User::new(email, password, request.requires_2fa);
 
     let mut user_store = state.user_store.write().await;
 
     if user_store.get_user(&user.email).await.is_ok() {
+        let event = audit_event.failed("user_already_exists");
+        record_audit_event(&state.audit_log_store, &context, event).await;
         return Err(AuthAPIError::UserAlreadyExists);
     }
 
@@ -28,6 +90,8 @@ pub async fn signup(
         return Err(AuthAPIError::UnexpectedError(e.into())); // Updated!
     }
 
+    record_audit_event(&state.audit_log_store, &context, audit_event).await;
+
     let response = Json(SignupResponse {
         message: "User created successfully!".to_string(),
     });
@@ -4,51 +4,68 @@ use serde::Deserialize;
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode},
-    utils::auth::generate_auth_cookie,
+    domain::{AuditEvent, AuditEventType, AuthAPIError, Email, LoginAttemptId, TwoFACode},
+    utils::{
+        audit::record_audit_event, auth::generate_auth_cookie, csrf::create_csrf_cookie,
+        request_context::RequestContext,
+    },
 };
 
 pub async fn verify_2fa(
     State(state): State<AppState>,
+    context: RequestContext,
     jar: CookieJar,
     Json(request): Json<Verify2FARequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
-    let email = match Email::parse(request.email.clone()) {
-        Ok(email) => email,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
-    };
-
-    let login_attempt_id = match LoginAttemptId::parse(request.login_attempt_id.clone()) {
-        Ok(login_attempt_id) => login_attempt_id,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
-    };
+    let audit_event = AuditEvent::new(AuditEventType::TwoFAVerification).with_email(&request.email);
 
-    let two_fa_code = match TwoFACode::parse(request.two_fa_code) {
-        Ok(two_fa_code) => two_fa_code,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    let (email, login_attempt_id, two_fa_code) = match (
+        Email::parse(request.email.clone()),
+        LoginAttemptId::parse(request.login_attempt_id.clone()),
+        TwoFACode::parse(request.two_fa_code),
+    ) {
+        (Ok(email), Ok(login_attempt_id), Ok(two_fa_code)) => {
+            (email, login_attempt_id, two_fa_code)
+        }
+        _ => {
+            let event = audit_event.failed("invalid_input");
+            record_audit_event(&state.audit_log_store, &context, event).await;
+            return (jar, Err(AuthAPIError::InvalidCredentials));
+        }
     };
 
     let mut two_fa_code_store = state.two_fa_code_store.write().await;
 
     let code_tuple = match two_fa_code_store.get_code(&email).await {
         Ok(code_tuple) => code_tuple,
-        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+        Err(_) => {
+            let event = audit_event.failed("login_attempt_not_found");
+            record_audit_event(&state.audit_log_store, &context, event).await;
+            return (jar, Err(AuthAPIError::IncorrectCredentials));
+        }
     };
 
     if !code_tuple.0.eq(&login_attempt_id) || !code_tuple.1.eq(&two_fa_code) {
+        let event = audit_event.failed("incorrect_code");
+        record_audit_event(&state.audit_log_store, &context, event).await;
         return (jar, Err(AuthAPIError::IncorrectCredentials));
     }
 
-    if two_fa_code_store.remove_code(&email).await.is_err() {
-        return (jar, Err(AuthAPIError::UnexpectedError));
+    if let Err(e) = two_fa_code_store.remove_code(&email).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
     let cookie = match generate_auth_cookie(&email) {
         Ok(cookie) => cookie,
-        Err(_) => return (jar, Err(AuthAPIError::UnexpectedError)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
     };
 
-    let updated_jar = jar.add(cookie);
+    let csrf_cookie = create_csrf_cookie(cookie.value());
+    let updated_jar = jar.add(cookie).add(csrf_cookie);
+
+    record_audit_event(&state.audit_log_store, &context, audit_event).await;
+    let event = AuditEvent::new(AuditEventType::Login).with_email(email.as_ref());
+    record_audit_event(&state.audit_log_store, &context, event).await;
 
     (updated_jar, Ok(()))
 }
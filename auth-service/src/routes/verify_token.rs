@@ -1,19 +1,48 @@
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{extract::State, Json};
 use serde::Deserialize;
 
-use crate::{app_state::AppState, domain::AuthAPIError, utils::auth::validate_token};
+use super::IntrospectionResponse;
+use crate::{
+    app_state::AppState,
+    domain::{AuditEvent, AuditEventType, AuthAPIError},
+    utils::{audit::record_audit_event, auth::authenticate_token, request_context::RequestContext},
+};
 
+/// Accepts auth tokens and API keys alike, and service tokens when they were issued for the
+/// given audience. Once the caller names its audience or asks for scopes, only service tokens
+/// pass. Answers who the token belongs to like `/oauth/introspect`, for services that aren't
+/// registered clients.
 pub async fn verify_token(
     State(state): State<AppState>,
+    context: RequestContext,
     Json(request): Json<VerifyTokenRequest>,
-) -> Result<StatusCode, AuthAPIError> {
-    match validate_token(&request.token, state.banned_token_store.clone()).await {
-        Ok(_) => Ok(StatusCode::OK),
-        Err(_) => Err(AuthAPIError::InvalidToken),
+) -> Result<Json<IntrospectionResponse>, AuthAPIError> {
+    let credentials =
+        match authenticate_token(&request.token, request.audience.as_deref(), &state).await {
+            Ok(credentials) => credentials,
+            Err(AuthAPIError::InvalidToken) => {
+                let event = AuditEvent::new(AuditEventType::TokenRejected).failed("invalid_token");
+                record_audit_event(&state.audit_log_store, &context, event).await;
+                return Err(AuthAPIError::InvalidToken);
+            }
+            Err(e) => return Err(e),
+        };
+
+    if !credentials.grants(request.audience.as_deref(), &request.scopes) {
+        let event = AuditEvent::new(AuditEventType::TokenRejected).failed("insufficient_scope");
+        record_audit_event(&state.audit_log_store, &context, event).await;
+        return Err(AuthAPIError::InsufficientScope);
     }
+
+    Ok(Json(IntrospectionResponse::from(credentials)))
 }
 
 #[derive(Debug, Deserialize)]
 pub struct VerifyTokenRequest {
     token: String,
+    /// The service asking, required to accept service tokens.
+    audience: Option<String>,
+    /// Scopes the token must have been granted, which only service tokens can be.
+    #[serde(default)]
+    scopes: Vec<String>,
 }
@@ -0,0 +1,265 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use color_eyre::eyre::Report;
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+use crate::{
+    app_state::ApiKeyStoreType,
+    domain::{
+        parse_api_key_name, parse_api_key_scopes, ApiKey, ApiKeyRejection, ApiKeyStoreError, Email,
+    },
+    services::data_stores::HashmapApiKeyStore,
+};
+
+/// What every API key starts with, which is how they're told apart from auth tokens.
+pub const API_KEY_PREFIX: &str = "ak_";
+pub const DEFAULT_API_KEY_TTL_DAYS: u32 = 30;
+pub const MAX_API_KEY_TTL_DAYS: u32 = 365;
+const KEY_PREFIX_CHARS: usize = 12;
+const KEY_SECRET_CHARS: usize = 32;
+
+/// Creates and checks personal access tokens.
+///
+/// A key reads `ak_<prefix>_<secret>`. The prefix finds the stored key and the secret is only
+/// kept as a SHA-256 hash. Secrets are random, so unlike passwords they don't need a slow hash.
+#[derive(Clone)]
+pub struct ApiKeys {
+    store: ApiKeyStoreType,
+}
+
+#[derive(Debug, Error)]
+pub enum ApiKeyError {
+    #[error("{0}")]
+    Rejected(ApiKeyRejection),
+    #[error("Invalid or expired API key")]
+    InvalidKey,
+    #[error("API key not found")]
+    KeyNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+/// What a user asked for when creating a key.
+#[derive(Debug, Clone)]
+pub struct NewApiKey {
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub expires_in_days: Option<u32>,
+}
+
+impl ApiKeys {
+    pub fn new(store: ApiKeyStoreType) -> Self {
+        Self { store }
+    }
+
+    /// Creates a key for `email`, returning it along with the full key, which can't be shown
+    /// again later. When the request was made with another key, `creator`, the new key can't
+    /// have scopes it lacks or outlive it.
+    #[tracing::instrument(name = "Creating API key", skip_all)]
+    pub async fn create(
+        &self,
+        email: &Email,
+        new_key: NewApiKey,
+        creator: Option<&ApiKey>,
+    ) -> Result<(ApiKey, String), ApiKeyError> {
+        let name = parse_api_key_name(&new_key.name).map_err(ApiKeyError::Rejected)?;
+        let scopes = parse_api_key_scopes(new_key.scopes).map_err(ApiKeyError::Rejected)?;
+
+        let ttl_days = new_key.expires_in_days.unwrap_or(DEFAULT_API_KEY_TTL_DAYS);
+        if !(1..=MAX_API_KEY_TTL_DAYS).contains(&ttl_days) {
+            return Err(ApiKeyError::Rejected(ApiKeyRejection::InvalidExpiry));
+        }
+        let created_at = Utc::now();
+        let mut expires_at = created_at + Duration::days(ttl_days.into());
+
+        if let Some(creator) = creator {
+            if !scopes.iter().all(|scope| creator.has_scope(scope)) {
+                return Err(ApiKeyError::Rejected(ApiKeyRejection::ScopeNotGranted));
+            }
+            expires_at = expires_at.min(creator.expires_at);
+        }
+
+        let prefix = random_chars(KEY_PREFIX_CHARS);
+        let secret = random_chars(KEY_SECRET_CHARS);
+        let api_key = ApiKey {
+            prefix: prefix.clone(),
+            email: email.as_ref().to_owned(),
+            name,
+            scopes,
+            created_at,
+            expires_at,
+        };
+
+        self.store
+            .write()
+            .await
+            .add_key(api_key.clone(), &hash_secret(&secret))
+            .await
+            .map_err(|e| ApiKeyError::UnexpectedError(e.into()))?;
+
+        Ok((api_key, format!("{}{}_{}", API_KEY_PREFIX, prefix, secret)))
+    }
+
+    /// The stored key matching the full `key`, as long as it hasn't expired or been revoked.
+    #[tracing::instrument(name = "Authenticating API key", skip_all)]
+    pub async fn authenticate(&self, key: &str) -> Result<ApiKey, ApiKeyError> {
+        let (prefix, secret) = key
+            .strip_prefix(API_KEY_PREFIX)
+            .and_then(|key| key.split_once('_'))
+            .ok_or(ApiKeyError::InvalidKey)?;
+
+        let (api_key, secret_hash) = match self.store.read().await.get_key(prefix).await {
+            Ok(stored) => stored,
+            Err(ApiKeyStoreError::KeyNotFound) => return Err(ApiKeyError::InvalidKey),
+            Err(e) => return Err(ApiKeyError::UnexpectedError(e.into())),
+        };
+
+        if hash_secret(secret) != secret_hash || api_key.is_expired() {
+            return Err(ApiKeyError::InvalidKey);
+        }
+
+        Ok(api_key)
+    }
+
+    pub async fn list(&self, email: &Email) -> Result<Vec<ApiKey>, ApiKeyError> {
+        self.store
+            .read()
+            .await
+            .list_keys(email)
+            .await
+            .map_err(|e| ApiKeyError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Revoking API key", skip_all)]
+    pub async fn revoke(&self, email: &Email, prefix: &str) -> Result<(), ApiKeyError> {
+        match self.store.write().await.remove_key(email, prefix).await {
+            Ok(()) => Ok(()),
+            Err(ApiKeyStoreError::KeyNotFound) => Err(ApiKeyError::KeyNotFound),
+            Err(e) => Err(ApiKeyError::UnexpectedError(e.into())),
+        }
+    }
+}
+
+impl Default for ApiKeys {
+    fn default() -> Self {
+        Self::new(Arc::new(RwLock::new(HashmapApiKeyStore::default())))
+    }
+}
+
+/// Whether `token` looks like an API key rather than an auth token.
+pub fn is_api_key(token: &str) -> bool {
+    token.starts_with(API_KEY_PREFIX)
+}
+
+fn random_chars(count: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(count)
+        .map(char::from)
+        .collect()
+}
+
+fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email() -> Email {
+        Email::parse("test@example.com".to_owned()).unwrap()
+    }
+
+    fn new_key(scopes: &[&str]) -> NewApiKey {
+        NewApiKey {
+            name: "CI".to_owned(),
+            scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+            expires_in_days: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn created_keys_authenticate() {
+        let api_keys = ApiKeys::default();
+        let (api_key, key) = api_keys
+            .create(&email(), new_key(&["user:read"]), None)
+            .await
+            .unwrap();
+
+        assert!(is_api_key(&key));
+        assert!(key.contains(&api_key.prefix));
+        assert_eq!(
+            api_key.expires_at - api_key.created_at,
+            Duration::days(DEFAULT_API_KEY_TTL_DAYS.into())
+        );
+        assert_eq!(api_keys.authenticate(&key).await.unwrap(), api_key);
+    }
+
+    #[tokio::test]
+    async fn wrong_secrets_and_revoked_keys_are_rejected() {
+        let api_keys = ApiKeys::default();
+        let (api_key, key) = api_keys.create(&email(), new_key(&[]), None).await.unwrap();
+
+        let wrong_secret = format!("{}{}_{}", API_KEY_PREFIX, api_key.prefix, "x".repeat(32));
+        for key in [wrong_secret.as_str(), "ak_nounderscore", "not-a-key"] {
+            assert!(matches!(
+                api_keys.authenticate(key).await,
+                Err(ApiKeyError::InvalidKey)
+            ));
+        }
+
+        api_keys.revoke(&email(), &api_key.prefix).await.unwrap();
+        assert!(matches!(
+            api_keys.authenticate(&key).await,
+            Err(ApiKeyError::InvalidKey)
+        ));
+        assert!(matches!(
+            api_keys.revoke(&email(), &api_key.prefix).await,
+            Err(ApiKeyError::KeyNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn expiry_must_be_within_limits() {
+        let api_keys = ApiKeys::default();
+
+        for days in [0, MAX_API_KEY_TTL_DAYS + 1] {
+            let request = NewApiKey {
+                expires_in_days: Some(days),
+                ..new_key(&[])
+            };
+            assert!(matches!(
+                api_keys.create(&email(), request, None).await,
+                Err(ApiKeyError::Rejected(ApiKeyRejection::InvalidExpiry))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn keys_created_with_a_key_stay_within_its_grant() {
+        let api_keys = ApiKeys::default();
+        let request = NewApiKey {
+            expires_in_days: Some(1),
+            ..new_key(&["user:read"])
+        };
+        let (creator, _) = api_keys.create(&email(), request, None).await.unwrap();
+
+        assert!(matches!(
+            api_keys
+                .create(&email(), new_key(&["user:write"]), Some(&creator))
+                .await,
+            Err(ApiKeyError::Rejected(ApiKeyRejection::ScopeNotGranted))
+        ));
+
+        let (api_key, _) = api_keys
+            .create(&email(), new_key(&["user:read"]), Some(&creator))
+            .await
+            .unwrap();
+        assert_eq!(api_key.expires_at, creator.expires_at);
+    }
+}
@@ -0,0 +1,144 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use crate::domain::{
+    data_stores::{ApiKeyStore, ApiKeyStoreError},
+    ApiKey, Email,
+};
+
+#[derive(Default)]
+pub struct HashmapApiKeyStore {
+    keys: HashMap<String, (ApiKey, String)>,
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for HashmapApiKeyStore {
+    async fn add_key(
+        &mut self,
+        api_key: ApiKey,
+        secret_hash: &str,
+    ) -> Result<(), ApiKeyStoreError> {
+        if self.keys.contains_key(&api_key.prefix) {
+            return Err(ApiKeyStoreError::KeyAlreadyExists);
+        }
+
+        self.keys
+            .insert(api_key.prefix.clone(), (api_key, secret_hash.to_owned()));
+        Ok(())
+    }
+
+    async fn get_key(&self, prefix: &str) -> Result<(ApiKey, String), ApiKeyStoreError> {
+        self.keys
+            .get(prefix)
+            .cloned()
+            .ok_or(ApiKeyStoreError::KeyNotFound)
+    }
+
+    async fn list_keys(&self, email: &Email) -> Result<Vec<ApiKey>, ApiKeyStoreError> {
+        let mut keys: Vec<ApiKey> = self
+            .keys
+            .values()
+            .filter(|(api_key, _)| belongs_to(api_key, email))
+            .map(|(api_key, _)| api_key.clone())
+            .collect();
+        keys.sort_by_key(|key| Reverse(key.created_at));
+        Ok(keys)
+    }
+
+    async fn remove_key(&mut self, email: &Email, prefix: &str) -> Result<(), ApiKeyStoreError> {
+        match self.keys.get(prefix) {
+            Some((api_key, _)) if belongs_to(api_key, email) => {
+                self.keys.remove(prefix);
+                Ok(())
+            }
+            _ => Err(ApiKeyStoreError::KeyNotFound),
+        }
+    }
+}
+
+fn belongs_to(api_key: &ApiKey, email: &Email) -> bool {
+    api_key.email.to_lowercase() == email.lookup_key()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use super::*;
+
+    fn api_key(prefix: &str, email: &str, age_days: i64) -> ApiKey {
+        let created_at = Utc::now() - Duration::days(age_days);
+        ApiKey {
+            prefix: prefix.to_owned(),
+            email: email.to_owned(),
+            name: "CI".to_owned(),
+            scopes: vec!["user:read".to_owned()],
+            created_at,
+            expires_at: created_at + Duration::days(30),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_key() {
+        let mut store = HashmapApiKeyStore::default();
+        let key = api_key("abc123", "test@example.com", 0);
+
+        assert_eq!(store.add_key(key.clone(), "hash").await, Ok(()));
+        assert_eq!(
+            store.add_key(key.clone(), "other-hash").await,
+            Err(ApiKeyStoreError::KeyAlreadyExists)
+        );
+        assert_eq!(store.get_key("abc123").await, Ok((key, "hash".to_owned())));
+        assert_eq!(
+            store.get_key("unknown").await,
+            Err(ApiKeyStoreError::KeyNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_keys_newest_first() {
+        let mut store = HashmapApiKeyStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        store
+            .add_key(api_key("older", "test@example.com", 2), "hash")
+            .await
+            .unwrap();
+        store
+            .add_key(api_key("newer", "test@example.com", 1), "hash")
+            .await
+            .unwrap();
+        store
+            .add_key(api_key("other", "other@example.com", 0), "hash")
+            .await
+            .unwrap();
+
+        let prefixes: Vec<String> = store
+            .list_keys(&email)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|key| key.prefix)
+            .collect();
+        assert_eq!(prefixes, vec!["newer", "older"]);
+    }
+
+    #[tokio::test]
+    async fn test_remove_only_own_keys() {
+        let mut store = HashmapApiKeyStore::default();
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let other = Email::parse("other@example.com".to_owned()).unwrap();
+        store
+            .add_key(api_key("abc123", "test@example.com", 0), "hash")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.remove_key(&other, "abc123").await,
+            Err(ApiKeyStoreError::KeyNotFound)
+        );
+        assert_eq!(store.remove_key(&email, "abc123").await, Ok(()));
+        assert_eq!(
+            store.remove_key(&email, "abc123").await,
+            Err(ApiKeyStoreError::KeyNotFound)
+        );
+    }
+}
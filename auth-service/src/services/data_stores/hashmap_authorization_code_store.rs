@@ -0,0 +1,91 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::domain::{
+    data_stores::{AuthorizationCodeStore, AuthorizationCodeStoreError},
+    AuthorizationCode,
+};
+
+#[derive(Default)]
+pub struct HashmapAuthorizationCodeStore {
+    codes: HashMap<String, (AuthorizationCode, Instant)>,
+}
+
+#[async_trait::async_trait]
+impl AuthorizationCodeStore for HashmapAuthorizationCodeStore {
+    async fn add_code(
+        &mut self,
+        code: &str,
+        authorization_code: AuthorizationCode,
+        ttl_seconds: u64,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        let now = Instant::now();
+        self.codes.retain(|_, (_, expires_at)| *expires_at > now);
+
+        self.codes.insert(
+            code.to_owned(),
+            (authorization_code, now + Duration::from_secs(ttl_seconds)),
+        );
+        Ok(())
+    }
+
+    async fn take_code(
+        &mut self,
+        code: &str,
+    ) -> Result<AuthorizationCode, AuthorizationCodeStoreError> {
+        match self.codes.remove(code) {
+            Some((authorization_code, expires_at)) if expires_at > Instant::now() => {
+                Ok(authorization_code)
+            }
+            _ => Err(AuthorizationCodeStoreError::CodeNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authorization_code() -> AuthorizationCode {
+        AuthorizationCode {
+            client_id: "app".to_owned(),
+            redirect_uri: "https://app.example.com/callback".to_owned(),
+            subject: "test@example.com".to_owned(),
+            scopes: vec!["openid".to_owned()],
+            nonce: None,
+            code_challenge: "challenge".to_owned(),
+            auth_time: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn codes_can_only_be_taken_once() {
+        let mut store = HashmapAuthorizationCodeStore::default();
+        store
+            .add_code("code", authorization_code(), 60)
+            .await
+            .unwrap();
+
+        assert_eq!(store.take_code("code").await, Ok(authorization_code()));
+        assert_eq!(
+            store.take_code("code").await,
+            Err(AuthorizationCodeStoreError::CodeNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_codes_are_not_found() {
+        let mut store = HashmapAuthorizationCodeStore::default();
+        store
+            .add_code("code", authorization_code(), 0)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.take_code("code").await,
+            Err(AuthorizationCodeStoreError::CodeNotFound)
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{EmailDomainRuleStore, EmailDomainRuleStoreError},
+    EmailDomainRule,
+};
+
+#[derive(Default)]
+pub struct HashmapEmailDomainRuleStore {
+    rules: HashMap<String, EmailDomainRule>,
+}
+
+#[async_trait::async_trait]
+impl EmailDomainRuleStore for HashmapEmailDomainRuleStore {
+    async fn set_rule(
+        &mut self,
+        domain: &str,
+        rule: EmailDomainRule,
+    ) -> Result<(), EmailDomainRuleStoreError> {
+        self.rules.insert(domain.to_owned(), rule);
+        Ok(())
+    }
+
+    async fn remove_rule(&mut self, domain: &str) -> Result<(), EmailDomainRuleStoreError> {
+        self.rules
+            .remove(domain)
+            .map(|_| ())
+            .ok_or(EmailDomainRuleStoreError::RuleNotFound)
+    }
+
+    async fn get_rules(
+        &self,
+        domains: &[String],
+    ) -> Result<Vec<(String, EmailDomainRule)>, EmailDomainRuleStoreError> {
+        Ok(domains
+            .iter()
+            .filter_map(|domain| self.rules.get(domain).map(|rule| (domain.clone(), *rule)))
+            .collect())
+    }
+
+    async fn list_rules(
+        &self,
+    ) -> Result<Vec<(String, EmailDomainRule)>, EmailDomainRuleStoreError> {
+        let mut rules: Vec<_> = self
+            .rules
+            .iter()
+            .map(|(domain, rule)| (domain.clone(), *rule))
+            .collect();
+        rules.sort();
+        Ok(rules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_set_get_and_remove_rules() {
+        let mut store = HashmapEmailDomainRuleStore::default();
+        store
+            .set_rule("example.com", EmailDomainRule::Deny)
+            .await
+            .unwrap();
+        store
+            .set_rule("example.com", EmailDomainRule::Allow)
+            .await
+            .unwrap();
+
+        let rules = store
+            .get_rules(&["mail.example.com".to_owned(), "example.com".to_owned()])
+            .await
+            .unwrap();
+        assert_eq!(
+            rules,
+            vec![("example.com".to_owned(), EmailDomainRule::Allow)]
+        );
+
+        assert_eq!(store.remove_rule("example.com").await, Ok(()));
+        assert_eq!(
+            store.remove_rule("example.com").await,
+            Err(EmailDomainRuleStoreError::RuleNotFound)
+        );
+        assert!(store.list_rules().await.unwrap().is_empty());
+    }
+}
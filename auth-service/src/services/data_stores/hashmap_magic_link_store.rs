@@ -0,0 +1,53 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::domain::data_stores::{MagicLinkStore, MagicLinkStoreError};
+
+#[derive(Default)]
+pub struct HashmapMagicLinkStore {
+    used_links: HashMap<String, Instant>,
+}
+
+#[async_trait::async_trait]
+impl MagicLinkStore for HashmapMagicLinkStore {
+    async fn use_link(
+        &mut self,
+        link_id: &str,
+        ttl_seconds: u64,
+    ) -> Result<bool, MagicLinkStoreError> {
+        let now = Instant::now();
+        self.used_links.retain(|_, expires_at| *expires_at > now);
+
+        if self.used_links.contains_key(link_id) {
+            return Ok(false);
+        }
+
+        self.used_links
+            .insert(link_id.to_owned(), now + Duration::from_secs(ttl_seconds));
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn links_can_only_be_used_once() {
+        let mut store = HashmapMagicLinkStore::default();
+
+        assert!(store.use_link("link", 900).await.unwrap());
+        assert!(!store.use_link("link", 900).await.unwrap());
+        assert!(store.use_link("other", 900).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn forgets_links_after_their_ttl() {
+        let mut store = HashmapMagicLinkStore::default();
+
+        assert!(store.use_link("link", 0).await.unwrap());
+        assert!(store.use_link("link", 0).await.unwrap());
+    }
+}
@@ -0,0 +1,75 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::domain::{
+    data_stores::{OAuthStateStore, OAuthStateStoreError},
+    OAuthState,
+};
+
+#[derive(Default)]
+pub struct HashmapOAuthStateStore {
+    states: HashMap<String, (OAuthState, Instant)>,
+}
+
+#[async_trait::async_trait]
+impl OAuthStateStore for HashmapOAuthStateStore {
+    async fn add_state(
+        &mut self,
+        state: &str,
+        oauth_state: OAuthState,
+        ttl_seconds: u64,
+    ) -> Result<(), OAuthStateStoreError> {
+        let now = Instant::now();
+        self.states.retain(|_, (_, expires_at)| *expires_at > now);
+
+        self.states.insert(
+            state.to_owned(),
+            (oauth_state, now + Duration::from_secs(ttl_seconds)),
+        );
+        Ok(())
+    }
+
+    async fn take_state(&mut self, state: &str) -> Result<OAuthState, OAuthStateStoreError> {
+        match self.states.remove(state) {
+            Some((oauth_state, expires_at)) if expires_at > Instant::now() => Ok(oauth_state),
+            _ => Err(OAuthStateStoreError::StateNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn oauth_state() -> OAuthState {
+        OAuthState {
+            provider: "github".to_owned(),
+            code_verifier: "verifier".to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn states_can_only_be_taken_once() {
+        let mut store = HashmapOAuthStateStore::default();
+        store.add_state("state", oauth_state(), 600).await.unwrap();
+
+        assert_eq!(store.take_state("state").await, Ok(oauth_state()));
+        assert_eq!(
+            store.take_state("state").await,
+            Err(OAuthStateStoreError::StateNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_states_are_not_found() {
+        let mut store = HashmapOAuthStateStore::default();
+        store.add_state("state", oauth_state(), 0).await.unwrap();
+
+        assert_eq!(
+            store.take_state("state").await,
+            Err(OAuthStateStoreError::StateNotFound)
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::domain::{
+    data_stores::{ProofOfWorkStore, ProofOfWorkStoreError},
+    ProofOfWorkAction,
+};
+
+/// In-memory counters, for single-instance deployments and tests.
+#[derive(Default)]
+pub struct HashmapProofOfWorkStore {
+    attempts: HashMap<(ProofOfWorkAction, u64), u64>,
+    used_challenges: HashMap<String, Instant>,
+}
+
+#[async_trait::async_trait]
+impl ProofOfWorkStore for HashmapProofOfWorkStore {
+    async fn increment_attempts(
+        &mut self,
+        action: ProofOfWorkAction,
+        window: u64,
+        _ttl_seconds: u64,
+    ) -> Result<u64, ProofOfWorkStoreError> {
+        // Only the current and previous windows are ever read
+        self.attempts
+            .retain(|(_, counted_window), _| counted_window.saturating_add(1) >= window);
+
+        let attempts = self.attempts.entry((action, window)).or_default();
+        *attempts += 1;
+        Ok(*attempts)
+    }
+
+    async fn get_attempts(
+        &self,
+        action: ProofOfWorkAction,
+        window: u64,
+    ) -> Result<u64, ProofOfWorkStoreError> {
+        Ok(self
+            .attempts
+            .get(&(action, window))
+            .copied()
+            .unwrap_or_default())
+    }
+
+    async fn use_challenge(
+        &mut self,
+        challenge_id: &str,
+        ttl_seconds: u64,
+    ) -> Result<bool, ProofOfWorkStoreError> {
+        let now = Instant::now();
+        self.used_challenges
+            .retain(|_, expires_at| *expires_at > now);
+
+        if self.used_challenges.contains_key(challenge_id) {
+            return Ok(false);
+        }
+
+        self.used_challenges.insert(
+            challenge_id.to_owned(),
+            now + Duration::from_secs(ttl_seconds),
+        );
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn counts_attempts_per_action_and_window() {
+        let mut store = HashmapProofOfWorkStore::default();
+
+        store
+            .increment_attempts(ProofOfWorkAction::Signup, 10, 120)
+            .await
+            .unwrap();
+        let attempts = store
+            .increment_attempts(ProofOfWorkAction::Signup, 10, 120)
+            .await
+            .unwrap();
+        store
+            .increment_attempts(ProofOfWorkAction::Login, 10, 120)
+            .await
+            .unwrap();
+
+        assert_eq!(attempts, 2);
+        assert_eq!(
+            store
+                .get_attempts(ProofOfWorkAction::Signup, 10)
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            store
+                .get_attempts(ProofOfWorkAction::Signup, 11)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn forgets_windows_older_than_the_previous_one() {
+        let mut store = HashmapProofOfWorkStore::default();
+
+        for window in [1, 2, 3] {
+            store
+                .increment_attempts(ProofOfWorkAction::Login, window, 120)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(
+            store
+                .get_attempts(ProofOfWorkAction::Login, 1)
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            store
+                .get_attempts(ProofOfWorkAction::Login, 2)
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn challenges_can_only_be_used_once() {
+        let mut store = HashmapProofOfWorkStore::default();
+
+        assert!(store.use_challenge("challenge", 300).await.unwrap());
+        assert!(!store.use_challenge("challenge", 300).await.unwrap());
+        assert!(store.use_challenge("other", 300).await.unwrap());
+    }
+}
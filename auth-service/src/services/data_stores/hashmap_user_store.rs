@@ -1,24 +1,55 @@
 use std::collections::HashMap;
 
-use crate::domain::{Email, Password, User, UserStore, UserStoreError};
+use color_eyre::eyre::eyre;
+use tokio::sync::RwLock;
 
+use crate::{
+    domain::{Email, Password, User, UserListQuery, UserPage, UserStore, UserStoreError},
+    services::password_hasher::Argon2PasswordHasher,
+};
+
+/// Keeps users in memory, storing the password hash just like the database-backed stores.
+///
+/// The map sits behind a lock so `validate_user` can persist rehashed passwords through `&self`.
 #[derive(Default)]
 pub struct HashmapUserStore {
-    users: HashMap<Email, User>,
+    users: RwLock<HashMap<Email, User>>,
+    password_hasher: Argon2PasswordHasher,
+}
+
+impl HashmapUserStore {
+    async fn hash(&self, password: &Password) -> Result<Password, UserStoreError> {
+        let password_hash = self
+            .password_hasher
+            .compute_password_hash(password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        Password::parse(password_hash).map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))
+    }
 }
 
 #[async_trait::async_trait]
 impl UserStore for HashmapUserStore {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
-        if self.users.contains_key(&user.email) {
+    async fn add_user(&mut self, mut user: User) -> Result<(), UserStoreError> {
+        if self.users.get_mut().contains_key(&user.email) {
             return Err(UserStoreError::UserAlreadyExists);
         }
-        self.users.insert(user.email.clone(), user);
+        user.password = self.hash(&user.password).await?;
+        self.users.get_mut().insert(user.email.clone(), user);
+        Ok(())
+    }
+
+    async fn import_user(&mut self, user: User) -> Result<(), UserStoreError> {
+        if self.users.get_mut().contains_key(&user.email) {
+            return Err(UserStoreError::UserAlreadyExists);
+        }
+        self.users.get_mut().insert(user.email.clone(), user);
         Ok(())
     }
 
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
-        match self.users.get(email) {
+        match self.users.read().await.get(email) {
             Some(user) => Ok(user.clone()),
             None => Err(UserStoreError::UserNotFound),
         }
@@ -29,22 +60,98 @@ impl UserStore for HashmapUserStore {
         email: &Email,
         password: &Password,
     ) -> Result<(), UserStoreError> {
-        match self.users.get(email) {
-            Some(user) => {
-                if user.password.eq(password) {
-                    Ok(())
-                } else {
-                    Err(UserStoreError::InvalidCredentials)
+        let user = self.get_user(email).await?;
+
+        let matched_as_typed = self
+            .password_hasher
+            .verify_password(user.password.as_ref(), password)
+            .await
+            .map_err(|_| UserStoreError::InvalidCredentials)?;
+
+        if matched_as_typed || self.password_hasher.needs_rehash(user.password.as_ref()) {
+            let password_hash = self.hash(password).await?;
+
+            // Leave the entry alone if the password was changed while we were hashing
+            if let Some(stored) = self.users.write().await.get_mut(email) {
+                if stored.password == user.password {
+                    stored.password = password_hash;
                 }
             }
-            None => Err(UserStoreError::UserNotFound),
         }
+
+        Ok(())
+    }
+
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        if !self.users.get_mut().contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        let password_hash = self.hash(&password).await?;
+
+        let user = self
+            .users
+            .get_mut()
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.password = password_hash;
+        Ok(())
+    }
+
+    async fn set_requires_2fa(
+        &mut self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut()
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        user.requires_2fa = requires_2fa;
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        self.users
+            .get_mut()
+            .remove(email)
+            .map(|_| ())
+            .ok_or(UserStoreError::UserNotFound)
+    }
+
+    async fn count_users(&self) -> Result<u64, UserStoreError> {
+        Ok(self.users.read().await.len() as u64)
+    }
+
+    async fn list_users(&self, query: &UserListQuery) -> Result<UserPage, UserStoreError> {
+        let stored = self.users.read().await;
+        let mut users: Vec<&User> = stored
+            .values()
+            .filter(|user| match query.after() {
+                Some(after) => user.email.as_ref() > after.as_ref(),
+                None => true,
+            })
+            .collect();
+        users.sort_by(|a, b| a.email.as_ref().cmp(b.email.as_ref()));
+
+        let users = users
+            .into_iter()
+            .take(query.limit() as usize + 1)
+            .cloned()
+            .collect();
+
+        Ok(UserPage::from_overfetched(users, query.limit()))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use argon2::Params;
 
     #[tokio::test]
     async fn test_add_user() {
@@ -75,10 +182,11 @@ mod tests {
             requires_2fa: false,
         };
 
-        // Test getting a user that exists
-        user_store.users.insert(email.clone(), user.clone());
-        let result = user_store.get_user(&email).await;
-        assert_eq!(result, Ok(user));
+        // Test getting a user that exists, whose password is only stored hashed
+        user_store.add_user(user.clone()).await.unwrap();
+        let result = user_store.get_user(&email).await.unwrap();
+        assert_eq!(result.email, user.email);
+        assert_ne!(result.password, user.password);
 
         // Test getting a user that doesn't exist
         let result = user_store
@@ -101,7 +209,7 @@ mod tests {
         };
 
         // Test validating a user that exists with correct password
-        user_store.users.insert(email.clone(), user.clone());
+        user_store.add_user(user.clone()).await.unwrap();
         let result = user_store.validate_user(&email, &password).await;
         assert_eq!(result, Ok(()));
 
@@ -120,4 +228,134 @@ mod tests {
 
         assert_eq!(result, Err(UserStoreError::UserNotFound));
     }
+
+    fn user(email: &str) -> User {
+        User {
+            email: Email::parse(email.to_owned()).unwrap(),
+            password: Password::parse("password".to_owned()).unwrap(),
+            requires_2fa: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_password() {
+        let mut user_store = HashmapUserStore::default();
+        let user = user("test@example.com");
+        user_store.add_user(user.clone()).await.unwrap();
+
+        let new_password = Password::parse("newpassword".to_owned()).unwrap();
+        let result = user_store
+            .update_password(&user.email, new_password.clone())
+            .await;
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            user_store.validate_user(&user.email, &new_password).await,
+            Ok(())
+        );
+
+        let missing = Email::parse("nonexistent@example.com".to_owned()).unwrap();
+        let result = user_store.update_password(&missing, new_password).await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_set_requires_2fa() {
+        let mut user_store = HashmapUserStore::default();
+        let user = user("test@example.com");
+        user_store
+            .users
+            .get_mut()
+            .insert(user.email.clone(), user.clone());
+
+        let result = user_store.set_requires_2fa(&user.email, true).await;
+        assert_eq!(result, Ok(()));
+        assert!(user_store.users.get_mut()[&user.email].requires_2fa);
+    }
+
+    #[tokio::test]
+    async fn test_delete_user() {
+        let mut user_store = HashmapUserStore::default();
+        let user = user("test@example.com");
+        user_store
+            .users
+            .get_mut()
+            .insert(user.email.clone(), user.clone());
+
+        assert_eq!(user_store.delete_user(&user.email).await, Ok(()));
+        assert_eq!(user_store.count_users().await, Ok(0));
+        assert_eq!(
+            user_store.delete_user(&user.email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_users_paginates_in_email_order() {
+        let mut user_store = HashmapUserStore::default();
+        for email in ["c@example.com", "a@example.com", "b@example.com"] {
+            let user = user(email);
+            user_store.users.get_mut().insert(user.email.clone(), user);
+        }
+        assert_eq!(user_store.count_users().await, Ok(3));
+
+        let query = UserListQuery::new(None, 2).unwrap();
+        let page = user_store.list_users(&query).await.unwrap();
+        assert_eq!(
+            page.users,
+            vec![user("a@example.com"), user("b@example.com")]
+        );
+        assert_eq!(page.next_cursor, Some(user("b@example.com").email));
+
+        let query = UserListQuery::new(page.next_cursor, 2).unwrap();
+        let page = user_store.list_users(&query).await.unwrap();
+        assert_eq!(page.users, vec![user("c@example.com")]);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[tokio::test]
+    async fn test_validate_user_rehashes_outdated_password_hash() {
+        let mut user_store = HashmapUserStore {
+            password_hasher: Argon2PasswordHasher::new(Params::new(8192, 1, 1, None).unwrap()),
+            ..Default::default()
+        };
+        let user = user("test@example.com");
+        user_store.add_user(user.clone()).await.unwrap();
+        let old_hash = user_store.get_user(&user.email).await.unwrap().password;
+
+        user_store.password_hasher =
+            Argon2PasswordHasher::new(Params::new(8192, 2, 1, None).unwrap());
+        let result = user_store.validate_user(&user.email, &user.password).await;
+        assert_eq!(result, Ok(()));
+
+        let new_hash = user_store.get_user(&user.email).await.unwrap().password;
+        assert_ne!(new_hash, old_hash);
+        assert!(!user_store.password_hasher.needs_rehash(new_hash.as_ref()));
+        assert_eq!(
+            user_store.validate_user(&user.email, &user.password).await,
+            Ok(())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_imported_legacy_hash_is_upgraded_on_login() {
+        let mut user_store = HashmapUserStore::default();
+        let password = Password::parse("password123".to_owned()).unwrap();
+        let user = User {
+            password: Password::parse(bcrypt::hash(password.as_ref(), 4).unwrap()).unwrap(),
+            ..user("test@example.com")
+        };
+        user_store.import_user(user.clone()).await.unwrap();
+        assert_eq!(
+            user_store.import_user(user.clone()).await,
+            Err(UserStoreError::UserAlreadyExists)
+        );
+
+        assert_eq!(
+            user_store.validate_user(&user.email, &password).await,
+            Ok(())
+        );
+
+        let upgraded = user_store.get_user(&user.email).await.unwrap().password;
+        assert!(upgraded.as_ref().starts_with("$argon2id$"));
+    }
 }
@@ -1,13 +1,73 @@
+mod hashmap_api_key_store;
+mod hashmap_authorization_code_store;
+mod hashmap_email_domain_rule_store;
+mod hashmap_magic_link_store;
+mod hashmap_oauth_state_store;
+mod hashmap_proof_of_work_store;
 mod hashmap_two_fa_code_store;
 mod hashmap_user_store;
 mod hashset_banned_token_store;
+mod postgres_api_key_store;
+mod postgres_audit_log_store;
+mod postgres_authorization_code_store;
+mod postgres_banned_token_store;
+mod postgres_email_domain_rule_store;
+mod postgres_magic_link_store;
+mod postgres_oauth_state_store;
+mod postgres_proof_of_work_store;
+mod postgres_two_fa_code_store;
 mod postgres_user_store;
+mod redis_authorization_code_store;
 mod redis_banned_token_store;
+mod redis_magic_link_store;
+mod redis_oauth_state_store;
+mod redis_proof_of_work_store;
 mod redis_two_fa_code_store;
+mod sqlite_api_key_store;
+mod sqlite_audit_log_store;
+mod sqlite_authorization_code_store;
+mod sqlite_banned_token_store;
+mod sqlite_email_domain_rule_store;
+mod sqlite_magic_link_store;
+mod sqlite_oauth_state_store;
+mod sqlite_proof_of_work_store;
+mod sqlite_two_fa_code_store;
+mod sqlite_user_store;
+mod vec_audit_log_store;
 
+pub use hashmap_api_key_store::*;
+pub use hashmap_authorization_code_store::*;
+pub use hashmap_email_domain_rule_store::*;
+pub use hashmap_magic_link_store::*;
+pub use hashmap_oauth_state_store::*;
+pub use hashmap_proof_of_work_store::*;
 pub use hashmap_two_fa_code_store::*;
 pub use hashmap_user_store::*;
 pub use hashset_banned_token_store::*;
+pub use postgres_api_key_store::*;
+pub use postgres_audit_log_store::*;
+pub use postgres_authorization_code_store::*;
+pub use postgres_banned_token_store::*;
+pub use postgres_email_domain_rule_store::*;
+pub use postgres_magic_link_store::*;
+pub use postgres_oauth_state_store::*;
+pub use postgres_proof_of_work_store::*;
+pub use postgres_two_fa_code_store::*;
 pub use postgres_user_store::*;
+pub use redis_authorization_code_store::*;
 pub use redis_banned_token_store::*;
+pub use redis_magic_link_store::*;
+pub use redis_oauth_state_store::*;
+pub use redis_proof_of_work_store::*;
 pub use redis_two_fa_code_store::*;
+pub use sqlite_api_key_store::*;
+pub use sqlite_audit_log_store::*;
+pub use sqlite_authorization_code_store::*;
+pub use sqlite_banned_token_store::*;
+pub use sqlite_email_domain_rule_store::*;
+pub use sqlite_magic_link_store::*;
+pub use sqlite_oauth_state_store::*;
+pub use sqlite_proof_of_work_store::*;
+pub use sqlite_two_fa_code_store::*;
+pub use sqlite_user_store::*;
+pub use vec_audit_log_store::*;
@@ -0,0 +1,134 @@
+use color_eyre::eyre::eyre;
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{ApiKeyStore, ApiKeyStoreError},
+    ApiKey, Email,
+};
+
+pub struct PostgresApiKeyStore {
+    pool: PgPool,
+}
+
+impl PostgresApiKeyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for PostgresApiKeyStore {
+    #[tracing::instrument(name = "Adding API key to PostgreSQL", skip_all)]
+    async fn add_key(
+        &mut self,
+        api_key: ApiKey,
+        secret_hash: &str,
+    ) -> Result<(), ApiKeyStoreError> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO api_keys (prefix, email, email_key, name, secret_hash, scopes, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (prefix) DO NOTHING
+            "#,
+            api_key.prefix,
+            api_key.email,
+            email_key(&api_key.email)?,
+            api_key.name,
+            secret_hash,
+            &api_key.scopes,
+            api_key.created_at,
+            api_key.expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(ApiKeyStoreError::KeyAlreadyExists);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving API key from PostgreSQL", skip_all)]
+    async fn get_key(&self, prefix: &str) -> Result<(ApiKey, String), ApiKeyStoreError> {
+        sqlx::query!(
+            r#"
+            SELECT prefix, email, name, secret_hash, scopes, created_at, expires_at
+            FROM api_keys
+            WHERE prefix = $1
+            "#,
+            prefix
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?
+        .map(|row| {
+            let api_key = ApiKey {
+                prefix: row.prefix,
+                email: row.email,
+                name: row.name,
+                scopes: row.scopes,
+                created_at: row.created_at,
+                expires_at: row.expires_at,
+            };
+            (api_key, row.secret_hash)
+        })
+        .ok_or(ApiKeyStoreError::KeyNotFound)
+    }
+
+    #[tracing::instrument(name = "Listing API keys from PostgreSQL", skip_all)]
+    async fn list_keys(&self, email: &Email) -> Result<Vec<ApiKey>, ApiKeyStoreError> {
+        let keys = sqlx::query!(
+            r#"
+            SELECT prefix, email, name, scopes, created_at, expires_at
+            FROM api_keys
+            WHERE email_key = $1
+            ORDER BY created_at DESC
+            "#,
+            email.lookup_key()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| ApiKey {
+            prefix: row.prefix,
+            email: row.email,
+            name: row.name,
+            scopes: row.scopes,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+        })
+        .collect();
+
+        Ok(keys)
+    }
+
+    #[tracing::instrument(name = "Removing API key from PostgreSQL", skip_all)]
+    async fn remove_key(&mut self, email: &Email, prefix: &str) -> Result<(), ApiKeyStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM api_keys
+            WHERE prefix = $1 AND email_key = $2
+            "#,
+            prefix,
+            email.lookup_key()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(ApiKeyStoreError::KeyNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+fn email_key(email: &str) -> Result<String, ApiKeyStoreError> {
+    Email::parse(email.to_owned())
+        .map(|email| email.lookup_key())
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(eyre!(e)))
+}
@@ -0,0 +1,83 @@
+use color_eyre::eyre::eyre;
+use sqlx::PgPool;
+
+use crate::domain::{
+    AuditEvent, AuditEventQuery, AuditEventType, AuditLogStore, AuditLogStoreError,
+};
+
+pub struct PostgresAuditLogStore {
+    pool: PgPool,
+}
+
+impl PostgresAuditLogStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditLogStore for PostgresAuditLogStore {
+    #[tracing::instrument(name = "Adding audit event to PostgreSQL", skip_all)]
+    async fn add_event(&mut self, event: AuditEvent) -> Result<(), AuditLogStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO audit_events
+                (occurred_at, event_type, email, success, reason, ip_address, user_agent, request_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            event.occurred_at,
+            event.event_type.as_ref(),
+            event.email,
+            event.success,
+            event.reason,
+            event.ip_address,
+            event.user_agent,
+            event.request_id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuditLogStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving audit events from PostgreSQL", skip_all)]
+    async fn get_events(
+        &self,
+        query: &AuditEventQuery,
+    ) -> Result<Vec<AuditEvent>, AuditLogStoreError> {
+        sqlx::query!(
+            r#"
+            SELECT occurred_at, event_type, email, success, reason, ip_address, user_agent, request_id
+            FROM audit_events
+            WHERE ($1::TEXT IS NULL OR email = $1)
+              AND ($2::TIMESTAMPTZ IS NULL OR occurred_at >= $2)
+              AND ($3::TIMESTAMPTZ IS NULL OR occurred_at < $3)
+            ORDER BY occurred_at DESC, id DESC
+            LIMIT $4
+            "#,
+            query.email,
+            query.from,
+            query.to,
+            query.limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuditLogStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| {
+            Ok(AuditEvent {
+                event_type: AuditEventType::parse(&row.event_type)
+                    .map_err(|e| AuditLogStoreError::UnexpectedError(eyre!(e)))?,
+                email: row.email,
+                success: row.success,
+                reason: row.reason,
+                ip_address: row.ip_address,
+                user_agent: row.user_agent,
+                request_id: row.request_id,
+                occurred_at: row.occurred_at,
+            })
+        })
+        .collect()
+    }
+}
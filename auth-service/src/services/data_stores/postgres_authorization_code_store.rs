@@ -0,0 +1,107 @@
+use chrono::{Duration, Utc};
+use color_eyre::eyre::{eyre, Result};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{AuthorizationCodeStore, AuthorizationCodeStoreError},
+    AuthorizationCode,
+};
+
+pub struct PostgresAuthorizationCodeStore {
+    pool: PgPool,
+}
+
+impl PostgresAuthorizationCodeStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Removes codes that can no longer be redeemed, returning how many were deleted.
+    #[tracing::instrument(
+        name = "Deleting expired authorization codes from PostgreSQL",
+        skip_all
+    )]
+    pub async fn delete_expired(&self) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM authorization_codes
+            WHERE expires_at <= now()
+            "#
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthorizationCodeStore for PostgresAuthorizationCodeStore {
+    #[tracing::instrument(name = "Adding authorization code to PostgreSQL", skip_all)]
+    async fn add_code(
+        &mut self,
+        code: &str,
+        authorization_code: AuthorizationCode,
+        ttl_seconds: u64,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        let expires_at = i64::try_from(ttl_seconds)
+            .ok()
+            .and_then(Duration::try_seconds)
+            .and_then(|ttl| Utc::now().checked_add_signed(ttl))
+            .ok_or_else(|| AuthorizationCodeStoreError::UnexpectedError(eyre!("Invalid TTL")))?;
+        let auth_time = i64::try_from(authorization_code.auth_time)
+            .map_err(|e| AuthorizationCodeStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO authorization_codes
+                (code, client_id, redirect_uri, subject, scopes, nonce, code_challenge, auth_time, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+            code,
+            authorization_code.client_id,
+            authorization_code.redirect_uri,
+            authorization_code.subject,
+            &authorization_code.scopes,
+            authorization_code.nonce,
+            authorization_code.code_challenge,
+            auth_time,
+            expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuthorizationCodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Taking authorization code from PostgreSQL", skip_all)]
+    async fn take_code(
+        &mut self,
+        code: &str,
+    ) -> Result<AuthorizationCode, AuthorizationCodeStoreError> {
+        let row = sqlx::query!(
+            r#"
+            DELETE FROM authorization_codes
+            WHERE code = $1 AND expires_at > now()
+            RETURNING client_id, redirect_uri, subject, scopes, nonce, code_challenge, auth_time
+            "#,
+            code
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AuthorizationCodeStoreError::UnexpectedError(e.into()))?
+        .ok_or(AuthorizationCodeStoreError::CodeNotFound)?;
+
+        Ok(AuthorizationCode {
+            client_id: row.client_id,
+            redirect_uri: row.redirect_uri,
+            subject: row.subject,
+            scopes: row.scopes,
+            nonce: row.nonce,
+            code_challenge: row.code_challenge,
+            auth_time: u64::try_from(row.auth_time)
+                .map_err(|e| AuthorizationCodeStoreError::UnexpectedError(e.into()))?,
+        })
+    }
+}
@@ -0,0 +1,74 @@
+use chrono::{Duration, Utc};
+use color_eyre::eyre::Result;
+use sqlx::PgPool;
+
+use crate::{
+    domain::data_stores::{BannedTokenStore, BannedTokenStoreError},
+    utils::auth::TOKEN_TTL_SECONDS,
+};
+
+pub struct PostgresBannedTokenStore {
+    pool: PgPool,
+}
+
+impl PostgresBannedTokenStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Removes tokens whose JWT would have expired anyway, returning how many were deleted.
+    #[tracing::instrument(name = "Deleting expired banned tokens from PostgreSQL", skip_all)]
+    pub async fn delete_expired(&self) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM banned_tokens
+            WHERE expires_at <= now()
+            "#
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for PostgresBannedTokenStore {
+    #[tracing::instrument(name = "Adding banned token to PostgreSQL", skip_all)]
+    async fn add_token(&mut self, token: String) -> Result<(), BannedTokenStoreError> {
+        let expires_at = Duration::try_seconds(TOKEN_TTL_SECONDS)
+            .and_then(|ttl| Utc::now().checked_add_signed(ttl))
+            .ok_or(BannedTokenStoreError::UnexpectedError)?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO banned_tokens (token, expires_at)
+            VALUES ($1, $2)
+            ON CONFLICT (token) DO UPDATE SET expires_at = EXCLUDED.expires_at
+            "#,
+            token,
+            expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking banned token in PostgreSQL", skip_all)]
+    async fn contains_token(&self, token: &str) -> Result<bool, BannedTokenStoreError> {
+        sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM banned_tokens
+                WHERE token = $1 AND expires_at > now()
+            ) AS "exists!"
+            "#,
+            token
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|_| BannedTokenStoreError::UnexpectedError)
+    }
+}
@@ -0,0 +1,113 @@
+use color_eyre::eyre::eyre;
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{EmailDomainRuleStore, EmailDomainRuleStoreError},
+    EmailDomainRule,
+};
+
+pub struct PostgresEmailDomainRuleStore {
+    pool: PgPool,
+}
+
+impl PostgresEmailDomainRuleStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailDomainRuleStore for PostgresEmailDomainRuleStore {
+    #[tracing::instrument(name = "Setting email domain rule in PostgreSQL", skip_all)]
+    async fn set_rule(
+        &mut self,
+        domain: &str,
+        rule: EmailDomainRule,
+    ) -> Result<(), EmailDomainRuleStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO email_domain_rules (domain, rule)
+            VALUES ($1, $2)
+            ON CONFLICT (domain) DO UPDATE SET rule = EXCLUDED.rule
+            "#,
+            domain,
+            rule.as_str()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailDomainRuleStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing email domain rule from PostgreSQL", skip_all)]
+    async fn remove_rule(&mut self, domain: &str) -> Result<(), EmailDomainRuleStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM email_domain_rules
+            WHERE domain = $1
+            "#,
+            domain
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailDomainRuleStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(EmailDomainRuleStoreError::RuleNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving email domain rules from PostgreSQL", skip_all)]
+    async fn get_rules(
+        &self,
+        domains: &[String],
+    ) -> Result<Vec<(String, EmailDomainRule)>, EmailDomainRuleStoreError> {
+        sqlx::query!(
+            r#"
+            SELECT domain, rule
+            FROM email_domain_rules
+            WHERE domain = ANY($1)
+            "#,
+            domains
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| EmailDomainRuleStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| parse_rule(row.domain, &row.rule))
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Listing email domain rules from PostgreSQL", skip_all)]
+    async fn list_rules(
+        &self,
+    ) -> Result<Vec<(String, EmailDomainRule)>, EmailDomainRuleStoreError> {
+        sqlx::query!(
+            r#"
+            SELECT domain, rule
+            FROM email_domain_rules
+            ORDER BY domain
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| EmailDomainRuleStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| parse_rule(row.domain, &row.rule))
+        .collect()
+    }
+}
+
+fn parse_rule(
+    domain: String,
+    rule: &str,
+) -> Result<(String, EmailDomainRule), EmailDomainRuleStoreError> {
+    let rule = rule
+        .parse()
+        .map_err(|e: String| EmailDomainRuleStoreError::UnexpectedError(eyre!(e)))?;
+
+    Ok((domain, rule))
+}
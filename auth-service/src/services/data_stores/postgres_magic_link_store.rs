@@ -0,0 +1,63 @@
+use chrono::{Duration, Utc};
+use color_eyre::eyre::{eyre, Result};
+use sqlx::PgPool;
+
+use crate::domain::data_stores::{MagicLinkStore, MagicLinkStoreError};
+
+pub struct PostgresMagicLinkStore {
+    pool: PgPool,
+}
+
+impl PostgresMagicLinkStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Forgets used links that have expired anyway, returning how many were deleted.
+    #[tracing::instrument(name = "Deleting expired used magic links from PostgreSQL", skip_all)]
+    pub async fn delete_expired(&self) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM used_magic_links
+            WHERE expires_at <= now()
+            "#
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl MagicLinkStore for PostgresMagicLinkStore {
+    #[tracing::instrument(name = "Using magic link in PostgreSQL", skip_all)]
+    async fn use_link(
+        &mut self,
+        link_id: &str,
+        ttl_seconds: u64,
+    ) -> Result<bool, MagicLinkStoreError> {
+        let expires_at = i64::try_from(ttl_seconds)
+            .ok()
+            .and_then(Duration::try_seconds)
+            .and_then(|ttl| Utc::now().checked_add_signed(ttl))
+            .ok_or_else(|| MagicLinkStoreError::UnexpectedError(eyre!("Invalid TTL")))?;
+
+        // Nothing is inserted or updated while the link is remembered as used
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO used_magic_links (link_id, expires_at)
+            VALUES ($1, $2)
+            ON CONFLICT (link_id) DO UPDATE SET expires_at = EXCLUDED.expires_at
+            WHERE used_magic_links.expires_at <= now()
+            "#,
+            link_id,
+            expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| MagicLinkStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
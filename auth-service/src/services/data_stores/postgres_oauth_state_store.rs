@@ -0,0 +1,87 @@
+use chrono::{Duration, Utc};
+use color_eyre::eyre::{eyre, Result};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{OAuthStateStore, OAuthStateStoreError},
+    OAuthState,
+};
+
+pub struct PostgresOAuthStateStore {
+    pool: PgPool,
+}
+
+impl PostgresOAuthStateStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Removes logins that were never finished in time, returning how many were deleted.
+    #[tracing::instrument(name = "Deleting expired OAuth states from PostgreSQL", skip_all)]
+    pub async fn delete_expired(&self) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM oauth_states
+            WHERE expires_at <= now()
+            "#
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl OAuthStateStore for PostgresOAuthStateStore {
+    #[tracing::instrument(name = "Adding OAuth state to PostgreSQL", skip_all)]
+    async fn add_state(
+        &mut self,
+        state: &str,
+        oauth_state: OAuthState,
+        ttl_seconds: u64,
+    ) -> Result<(), OAuthStateStoreError> {
+        let expires_at = i64::try_from(ttl_seconds)
+            .ok()
+            .and_then(Duration::try_seconds)
+            .and_then(|ttl| Utc::now().checked_add_signed(ttl))
+            .ok_or_else(|| OAuthStateStoreError::UnexpectedError(eyre!("Invalid TTL")))?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO oauth_states (state, provider, code_verifier, expires_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (state) DO UPDATE
+            SET provider = EXCLUDED.provider,
+                code_verifier = EXCLUDED.code_verifier,
+                expires_at = EXCLUDED.expires_at
+            "#,
+            state,
+            oauth_state.provider,
+            oauth_state.code_verifier,
+            expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| OAuthStateStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Taking OAuth state from PostgreSQL", skip_all)]
+    async fn take_state(&mut self, state: &str) -> Result<OAuthState, OAuthStateStoreError> {
+        sqlx::query_as!(
+            OAuthState,
+            r#"
+            DELETE FROM oauth_states
+            WHERE state = $1 AND expires_at > now()
+            RETURNING provider, code_verifier
+            "#,
+            state
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| OAuthStateStoreError::UnexpectedError(e.into()))?
+        .ok_or(OAuthStateStoreError::StateNotFound)
+    }
+}
@@ -0,0 +1,130 @@
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{eyre, Result};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{ProofOfWorkStore, ProofOfWorkStoreError},
+    ProofOfWorkAction,
+};
+
+/// Shares counters between instances, so an attack spread across them is still noticed.
+pub struct PostgresProofOfWorkStore {
+    pool: PgPool,
+}
+
+impl PostgresProofOfWorkStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Removes counters of past windows and expired challenges, returning how many rows were
+    /// deleted.
+    #[tracing::instrument(name = "Deleting expired proof-of-work rows from PostgreSQL", skip_all)]
+    pub async fn delete_expired(&self) -> Result<u64> {
+        let attempts = sqlx::query!(
+            r#"
+            DELETE FROM proof_of_work_attempts
+            WHERE expires_at <= now()
+            "#
+        )
+        .execute(&self.pool)
+        .await?;
+        let challenges = sqlx::query!(
+            r#"
+            DELETE FROM used_proof_of_work_challenges
+            WHERE expires_at <= now()
+            "#
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(attempts.rows_affected() + challenges.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl ProofOfWorkStore for PostgresProofOfWorkStore {
+    #[tracing::instrument(name = "Counting proof-of-work attempt in PostgreSQL", skip_all)]
+    async fn increment_attempts(
+        &mut self,
+        action: ProofOfWorkAction,
+        window: u64,
+        ttl_seconds: u64,
+    ) -> Result<u64, ProofOfWorkStoreError> {
+        let attempts = sqlx::query_scalar!(
+            r#"
+            INSERT INTO proof_of_work_attempts (action, window_number, attempts, expires_at)
+            VALUES ($1, $2, 1, $3)
+            ON CONFLICT (action, window_number)
+            DO UPDATE SET attempts = proof_of_work_attempts.attempts + 1
+            RETURNING attempts
+            "#,
+            action.as_str(),
+            window_number(window)?,
+            expires_at(ttl_seconds)?
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ProofOfWorkStoreError::UnexpectedError(e.into()))?;
+
+        u64::try_from(attempts).map_err(|e| ProofOfWorkStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Retrieving proof-of-work attempts from PostgreSQL", skip_all)]
+    async fn get_attempts(
+        &self,
+        action: ProofOfWorkAction,
+        window: u64,
+    ) -> Result<u64, ProofOfWorkStoreError> {
+        let attempts = sqlx::query_scalar!(
+            r#"
+            SELECT attempts FROM proof_of_work_attempts
+            WHERE action = $1 AND window_number = $2
+            "#,
+            action.as_str(),
+            window_number(window)?
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ProofOfWorkStoreError::UnexpectedError(e.into()))?;
+
+        u64::try_from(attempts.unwrap_or_default())
+            .map_err(|e| ProofOfWorkStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Using proof-of-work challenge in PostgreSQL", skip_all)]
+    async fn use_challenge(
+        &mut self,
+        challenge_id: &str,
+        ttl_seconds: u64,
+    ) -> Result<bool, ProofOfWorkStoreError> {
+        // Nothing is inserted or updated while the challenge is remembered as used
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO used_proof_of_work_challenges (challenge_id, expires_at)
+            VALUES ($1, $2)
+            ON CONFLICT (challenge_id) DO UPDATE SET expires_at = EXCLUDED.expires_at
+            WHERE used_proof_of_work_challenges.expires_at <= now()
+            "#,
+            challenge_id,
+            expires_at(ttl_seconds)?
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ProofOfWorkStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
+
+fn window_number(window: u64) -> Result<i64, ProofOfWorkStoreError> {
+    i64::try_from(window).map_err(|e| ProofOfWorkStoreError::UnexpectedError(e.into()))
+}
+
+fn expires_at(ttl_seconds: u64) -> Result<DateTime<Utc>, ProofOfWorkStoreError> {
+    i64::try_from(ttl_seconds)
+        .ok()
+        .and_then(Duration::try_seconds)
+        .and_then(|ttl| Utc::now().checked_add_signed(ttl))
+        .ok_or_else(|| ProofOfWorkStoreError::UnexpectedError(eyre!("Invalid TTL")))
+}
@@ -0,0 +1,111 @@
+use chrono::{Duration, Utc};
+use color_eyre::eyre::Result;
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+    Email,
+};
+
+pub struct PostgresTwoFACodeStore {
+    pool: PgPool,
+}
+
+impl PostgresTwoFACodeStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Removes codes that can no longer be used, returning how many were deleted.
+    #[tracing::instrument(name = "Deleting expired 2FA codes from PostgreSQL", skip_all)]
+    pub async fn delete_expired(&self) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM two_fa_codes
+            WHERE expires_at <= now()
+            "#
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl TwoFACodeStore for PostgresTwoFACodeStore {
+    #[tracing::instrument(name = "Adding 2FA code to PostgreSQL", skip_all)]
+    async fn add_code(
+        &mut self,
+        email: Email,
+        login_attempt_id: LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let expires_at = Duration::try_seconds(TEN_MINUTES_IN_SECONDS)
+            .and_then(|ttl| Utc::now().checked_add_signed(ttl))
+            .ok_or(TwoFACodeStoreError::UnexpectedError)?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO two_fa_codes (email, login_attempt_id, code, expires_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (email) DO UPDATE SET
+                login_attempt_id = EXCLUDED.login_attempt_id,
+                code = EXCLUDED.code,
+                expires_at = EXCLUDED.expires_at
+            "#,
+            email.lookup_key(),
+            login_attempt_id.as_ref(),
+            code.as_ref(),
+            expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing 2FA code from PostgreSQL", skip_all)]
+    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM two_fa_codes
+            WHERE email = $1
+            "#,
+            email.lookup_key()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving 2FA code from PostgreSQL", skip_all)]
+    async fn get_code(
+        &self,
+        email: &Email,
+    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT login_attempt_id, code
+            FROM two_fa_codes
+            WHERE email = $1 AND expires_at > now()
+            "#,
+            email.lookup_key()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|_| TwoFACodeStoreError::UnexpectedError)?
+        .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        let login_attempt_id = LoginAttemptId::parse(row.login_attempt_id)
+            .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+        let code = TwoFACode::parse(row.code).map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok((login_attempt_id, code))
+    }
+}
+
+const TEN_MINUTES_IN_SECONDS: i64 = 600;
@@ -1,24 +1,48 @@
-use color_eyre::eyre::{eyre, Context, Result};
-
-use argon2::{
-    password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher,
-    PasswordVerifier, Version,
-};
-
+use color_eyre::eyre::{eyre, Result};
 use sqlx::PgPool;
 
-use crate::domain::{
-    data_stores::{UserStore, UserStoreError},
-    Email, Password, User,
+use crate::{
+    domain::{
+        data_stores::{UserStore, UserStoreError},
+        Email, Password, User, UserListQuery, UserPage,
+    },
+    services::password_hasher::Argon2PasswordHasher,
 };
 
 pub struct PostgresUserStore {
     pool: PgPool,
+    password_hasher: Argon2PasswordHasher,
 }
 
 impl PostgresUserStore {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            password_hasher: Argon2PasswordHasher::default(),
+        }
+    }
+    /// Replaces a hash made with outdated parameters, unless the password changed meanwhile.
+    #[tracing::instrument(name = "Rehashing user password in PostgreSQL", skip_all)]
+    async fn rehash_password(&self, user: &User, password: &Password) -> Result<()> {
+        let password_hash = self
+            .password_hasher
+            .compute_password_hash(password.as_ref().to_owned())
+            .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_hash = $3
+            WHERE email_key = $1 AND password_hash = $2
+            "#,
+            user.email.lookup_key(),
+            user.password.as_ref(),
+            &password_hash
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
     }
 }
 
@@ -26,23 +50,55 @@ impl PostgresUserStore {
 impl UserStore for PostgresUserStore {
     #[tracing::instrument(name = "Adding user to PostgreSQL", skip_all)]
     async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
-        let password_hash = compute_password_hash(user.password.as_ref().to_owned())
+        let password_hash = self
+            .password_hasher
+            .compute_password_hash(user.password.as_ref().to_owned())
             .await
             .map_err(UserStoreError::UnexpectedError)?;
 
         sqlx::query!(
             r#"
-            INSERT INTO users (email, password_hash, requires_2fa)
-            VALUES ($1, $2, $3)
+            INSERT INTO users (email, email_key, password_hash, requires_2fa)
+            VALUES ($1, $2, $3, $4)
             "#,
             user.email.as_ref(),
+            user.email.lookup_key(),
             &password_hash,
             user.requires_2fa
         )
         .execute(&self.pool)
         .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(ref db_error) if db_error.is_unique_violation() => {
+                UserStoreError::UserAlreadyExists
+            }
+            e => UserStoreError::UnexpectedError(e.into()),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Importing user into PostgreSQL", skip_all)]
+    async fn import_user(&mut self, user: User) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO users (email, email_key, password_hash, requires_2fa)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT DO NOTHING
+            "#,
+            user.email.as_ref(),
+            user.email.lookup_key(),
+            user.password.as_ref(),
+            user.requires_2fa
+        )
+        .execute(&self.pool)
+        .await
         .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
 
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserAlreadyExists);
+        }
+
         Ok(())
     }
 
@@ -52,22 +108,14 @@ impl UserStore for PostgresUserStore {
             r#"
             SELECT email, password_hash, requires_2fa
             FROM users
-            WHERE email = $1
+            WHERE email_key = $1
             "#,
-            email.as_ref()
+            email.lookup_key()
         )
         .fetch_optional(&self.pool)
         .await
         .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
-        .map(|row| {
-            Ok(User {
-                email: Email::parse(row.email)
-                    .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-                password: Password::parse(row.password_hash)
-                    .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
-                requires_2fa: row.requires_2fa,
-            })
-        })
+        .map(|row| parse_user(row.email, row.password_hash, row.requires_2fa))
         .ok_or(UserStoreError::UserNotFound)?
     }
 
@@ -79,56 +127,138 @@ impl UserStore for PostgresUserStore {
     ) -> Result<(), UserStoreError> {
         let user = self.get_user(email).await?;
 
-        verify_password_hash(
-            user.password.as_ref().to_owned(),
-            password.as_ref().to_owned(),
+        let matched_as_typed = self
+            .password_hasher
+            .verify_password(user.password.as_ref(), password)
+            .await
+            .map_err(|_| UserStoreError::InvalidCredentials)?;
+
+        if matched_as_typed || self.password_hasher.needs_rehash(user.password.as_ref()) {
+            if let Err(e) = self.rehash_password(&user, password).await {
+                tracing::warn!(error.message = %e, "Failed to rehash password");
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating user password in PostgreSQL", skip_all)]
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let password_hash = self
+            .password_hasher
+            .compute_password_hash(password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_hash = $2
+            WHERE email_key = $1
+            "#,
+            email.lookup_key(),
+            &password_hash
         )
+        .execute(&self.pool)
         .await
-        .map_err(|_| UserStoreError::InvalidCredentials)
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        ensure_user_found(result.rows_affected())
     }
-}
 
-#[tracing::instrument(name = "Verify password hash", skip_all)]
-async fn verify_password_hash(
-    expected_password_hash: String,
-    password_candidate: String,
-) -> Result<()> {
-    let current_span: tracing::Span = tracing::Span::current();
-
-    let result = tokio::task::spawn_blocking(move || {
-        current_span.in_scope(|| {
-            let expected_password_hash: PasswordHash<'_> =
-                PasswordHash::new(&expected_password_hash)?;
-            Argon2::default()
-                .verify_password(password_candidate.as_bytes(), &expected_password_hash)
-                .map_err(|e| e.into())
-        })
-    })
-    .await;
+    #[tracing::instrument(name = "Updating user 2FA requirement in PostgreSQL", skip_all)]
+    async fn set_requires_2fa(
+        &mut self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET requires_2fa = $2
+            WHERE email_key = $1
+            "#,
+            email.lookup_key(),
+            requires_2fa
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        ensure_user_found(result.rows_affected())
+    }
+
+    #[tracing::instrument(name = "Deleting user from PostgreSQL", skip_all)]
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM users
+            WHERE email_key = $1
+            "#,
+            email.lookup_key()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        ensure_user_found(result.rows_affected())
+    }
+
+    #[tracing::instrument(name = "Counting users in PostgreSQL", skip_all)]
+    async fn count_users(&self) -> Result<u64, UserStoreError> {
+        let count = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM users"#)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
 
-    result?
+        u64::try_from(count).map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Listing users from PostgreSQL", skip_all)]
+    async fn list_users(&self, query: &UserListQuery) -> Result<UserPage, UserStoreError> {
+        let users = sqlx::query!(
+            r#"
+            SELECT email, password_hash, requires_2fa
+            FROM users
+            WHERE ($1::TEXT IS NULL OR email > $1)
+            ORDER BY email
+            LIMIT $2
+            "#,
+            query.after().map(AsRef::as_ref),
+            i64::from(query.limit()) + 1
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|row| parse_user(row.email, row.password_hash, row.requires_2fa))
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(UserPage::from_overfetched(users, query.limit()))
+    }
 }
 
-#[tracing::instrument(name = "Computing password hash", skip_all)]
-async fn compute_password_hash(password: String) -> Result<String> {
-    let current_span: tracing::Span = tracing::Span::current();
-
-    let result = tokio::task::spawn_blocking(move || {
-        current_span.in_scope(|| {
-            let salt: SaltString = SaltString::generate(&mut rand::thread_rng());
-            let password_hash = Argon2::new(
-                Algorithm::Argon2id,
-                Version::V0x13,
-                Params::new(15000, 2, 1, None)?,
-            )
-            .hash_password(password.as_bytes(), &salt)?
-            .to_string();
-
-            // Ok(password_hash)
-            Err(eyre!("not good"))
-        })
+fn parse_user(
+    email: String,
+    password_hash: String,
+    requires_2fa: bool,
+) -> Result<User, UserStoreError> {
+    Ok(User {
+        email: Email::parse(email).map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+        password: Password::parse(password_hash)
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+        requires_2fa,
     })
-    .await;
+}
 
-    result?
+fn ensure_user_found(rows_affected: u64) -> Result<(), UserStoreError> {
+    if rows_affected == 0 {
+        Err(UserStoreError::UserNotFound)
+    } else {
+        Ok(())
+    }
 }
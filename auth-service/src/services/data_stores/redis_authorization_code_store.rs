@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{AuthorizationCodeStore, AuthorizationCodeStoreError},
+    AuthorizationCode,
+};
+
+pub struct RedisAuthorizationCodeStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisAuthorizationCodeStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthorizationCodeStore for RedisAuthorizationCodeStore {
+    async fn add_code(
+        &mut self,
+        code: &str,
+        authorization_code: AuthorizationCode,
+        ttl_seconds: u64,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        let serialized = serde_json::to_string(&authorization_code)
+            .map_err(|e| AuthorizationCodeStoreError::UnexpectedError(e.into()))?;
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(get_key(code), serialized, ttl_seconds)
+            .map_err(|e| AuthorizationCodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    async fn take_code(
+        &mut self,
+        code: &str,
+    ) -> Result<AuthorizationCode, AuthorizationCodeStoreError> {
+        let serialized: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get_del(get_key(code))
+            .map_err(|e| AuthorizationCodeStoreError::UnexpectedError(e.into()))?;
+
+        let serialized = serialized.ok_or(AuthorizationCodeStoreError::CodeNotFound)?;
+        serde_json::from_str(&serialized)
+            .map_err(|e| AuthorizationCodeStoreError::UnexpectedError(e.into()))
+    }
+}
+
+const AUTHORIZATION_CODE_KEY_PREFIX: &str = "authorization_code:";
+
+fn get_key(code: &str) -> String {
+    format!("{}{}", AUTHORIZATION_CODE_KEY_PREFIX, code)
+}
@@ -0,0 +1,41 @@
+use std::sync::Arc;
+
+use redis::Connection;
+use tokio::sync::RwLock;
+
+use crate::domain::data_stores::{MagicLinkStore, MagicLinkStoreError};
+
+pub struct RedisMagicLinkStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisMagicLinkStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl MagicLinkStore for RedisMagicLinkStore {
+    async fn use_link(
+        &mut self,
+        link_id: &str,
+        ttl_seconds: u64,
+    ) -> Result<bool, MagicLinkStoreError> {
+        let key = format!("{}{}", USED_MAGIC_LINK_KEY_PREFIX, link_id);
+
+        // SET NX only replies OK if the key didn't exist yet
+        let reply: Option<String> = redis::cmd("SET")
+            .arg(&key)
+            .arg(true)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_seconds)
+            .query(&mut *self.conn.write().await)
+            .map_err(|e| MagicLinkStoreError::UnexpectedError(e.into()))?;
+
+        Ok(reply.is_some())
+    }
+}
+
+const USED_MAGIC_LINK_KEY_PREFIX: &str = "magic_link_used:";
@@ -0,0 +1,60 @@
+use std::sync::Arc;
+
+use redis::{Commands, Connection};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{OAuthStateStore, OAuthStateStoreError},
+    OAuthState,
+};
+
+pub struct RedisOAuthStateStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisOAuthStateStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl OAuthStateStore for RedisOAuthStateStore {
+    async fn add_state(
+        &mut self,
+        state: &str,
+        oauth_state: OAuthState,
+        ttl_seconds: u64,
+    ) -> Result<(), OAuthStateStoreError> {
+        let serialized = serde_json::to_string(&oauth_state)
+            .map_err(|e| OAuthStateStoreError::UnexpectedError(e.into()))?;
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(get_key(state), serialized, ttl_seconds)
+            .map_err(|e| OAuthStateStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    async fn take_state(&mut self, state: &str) -> Result<OAuthState, OAuthStateStoreError> {
+        let serialized: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get_del(get_key(state))
+            .map_err(|e| OAuthStateStoreError::UnexpectedError(e.into()))?;
+
+        let serialized = serialized.ok_or(OAuthStateStoreError::StateNotFound)?;
+        serde_json::from_str(&serialized)
+            .map_err(|e| OAuthStateStoreError::UnexpectedError(e.into()))
+    }
+}
+
+const OAUTH_STATE_KEY_PREFIX: &str = "oauth_state:";
+
+fn get_key(state: &str) -> String {
+    format!("{}{}", OAUTH_STATE_KEY_PREFIX, state)
+}
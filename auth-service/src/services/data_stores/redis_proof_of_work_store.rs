@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Report;
+use redis::{Commands, Connection};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{ProofOfWorkStore, ProofOfWorkStoreError},
+    ProofOfWorkAction,
+};
+
+/// Shares counters between instances, so an attack spread across them is still noticed.
+pub struct RedisProofOfWorkStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisProofOfWorkStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl ProofOfWorkStore for RedisProofOfWorkStore {
+    async fn increment_attempts(
+        &mut self,
+        action: ProofOfWorkAction,
+        window: u64,
+        ttl_seconds: u64,
+    ) -> Result<u64, ProofOfWorkStoreError> {
+        let key = get_attempts_key(action, window);
+        let ttl: i64 = ttl_seconds
+            .try_into()
+            .map_err(|e| ProofOfWorkStoreError::UnexpectedError(Report::new(e)))?;
+
+        let mut conn = self.conn.write().await;
+        let attempts: u64 = conn
+            .incr(&key, 1)
+            .map_err(|e| ProofOfWorkStoreError::UnexpectedError(e.into()))?;
+        if attempts == 1 {
+            let _: () = conn
+                .expire(&key, ttl)
+                .map_err(|e| ProofOfWorkStoreError::UnexpectedError(e.into()))?;
+        }
+
+        Ok(attempts)
+    }
+
+    async fn get_attempts(
+        &self,
+        action: ProofOfWorkAction,
+        window: u64,
+    ) -> Result<u64, ProofOfWorkStoreError> {
+        let attempts: Option<u64> = self
+            .conn
+            .write()
+            .await
+            .get(get_attempts_key(action, window))
+            .map_err(|e| ProofOfWorkStoreError::UnexpectedError(e.into()))?;
+
+        Ok(attempts.unwrap_or_default())
+    }
+
+    async fn use_challenge(
+        &mut self,
+        challenge_id: &str,
+        ttl_seconds: u64,
+    ) -> Result<bool, ProofOfWorkStoreError> {
+        let key = format!("{}{}", USED_CHALLENGE_KEY_PREFIX, challenge_id);
+
+        // SET NX only replies OK if the key didn't exist yet
+        let reply: Option<String> = redis::cmd("SET")
+            .arg(&key)
+            .arg(true)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_seconds)
+            .query(&mut *self.conn.write().await)
+            .map_err(|e| ProofOfWorkStoreError::UnexpectedError(e.into()))?;
+
+        Ok(reply.is_some())
+    }
+}
+
+const ATTEMPTS_KEY_PREFIX: &str = "pow_attempts:";
+const USED_CHALLENGE_KEY_PREFIX: &str = "pow_used_challenge:";
+
+fn get_attempts_key(action: ProofOfWorkAction, window: u64) -> String {
+    format!("{}{}:{}", ATTEMPTS_KEY_PREFIX, action, window)
+}
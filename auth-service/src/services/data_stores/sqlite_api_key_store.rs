@@ -0,0 +1,221 @@
+use chrono::DateTime;
+use color_eyre::eyre::eyre;
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+
+use crate::domain::{
+    data_stores::{ApiKeyStore, ApiKeyStoreError},
+    ApiKey, Email,
+};
+
+pub struct SqliteApiKeyStore {
+    pool: SqlitePool,
+}
+
+impl SqliteApiKeyStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for SqliteApiKeyStore {
+    #[tracing::instrument(name = "Adding API key to SQLite", skip_all)]
+    async fn add_key(
+        &mut self,
+        api_key: ApiKey,
+        secret_hash: &str,
+    ) -> Result<(), ApiKeyStoreError> {
+        let scopes = serde_json::to_string(&api_key.scopes)
+            .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO api_keys (prefix, email, email_key, name, secret_hash, scopes, created_at, expires_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT (prefix) DO NOTHING
+            "#,
+        )
+        .bind(&api_key.prefix)
+        .bind(&api_key.email)
+        .bind(email_key(&api_key.email)?)
+        .bind(&api_key.name)
+        .bind(secret_hash)
+        .bind(scopes)
+        .bind(api_key.created_at.timestamp())
+        .bind(api_key.expires_at.timestamp())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(ApiKeyStoreError::KeyAlreadyExists);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving API key from SQLite", skip_all)]
+    async fn get_key(&self, prefix: &str) -> Result<(ApiKey, String), ApiKeyStoreError> {
+        let row = sqlx::query(
+            r#"
+            SELECT prefix, email, name, secret_hash, scopes, created_at, expires_at
+            FROM api_keys
+            WHERE prefix = ?
+            "#,
+        )
+        .bind(prefix)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?
+        .ok_or(ApiKeyStoreError::KeyNotFound)?;
+
+        let api_key = parse_api_key(&row).map_err(ApiKeyStoreError::UnexpectedError)?;
+        let secret_hash = row
+            .try_get("secret_hash")
+            .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        Ok((api_key, secret_hash))
+    }
+
+    #[tracing::instrument(name = "Listing API keys from SQLite", skip_all)]
+    async fn list_keys(&self, email: &Email) -> Result<Vec<ApiKey>, ApiKeyStoreError> {
+        sqlx::query(
+            r#"
+            SELECT prefix, email, name, scopes, created_at, expires_at
+            FROM api_keys
+            WHERE email_key = ?
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(email.lookup_key())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?
+        .iter()
+        .map(|row| parse_api_key(row).map_err(ApiKeyStoreError::UnexpectedError))
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Removing API key from SQLite", skip_all)]
+    async fn remove_key(&mut self, email: &Email, prefix: &str) -> Result<(), ApiKeyStoreError> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM api_keys
+            WHERE prefix = ? AND email_key = ?
+            "#,
+        )
+        .bind(prefix)
+        .bind(email.lookup_key())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(ApiKeyStoreError::KeyNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+fn email_key(email: &str) -> Result<String, ApiKeyStoreError> {
+    Email::parse(email.to_owned())
+        .map(|email| email.lookup_key())
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(eyre!(e)))
+}
+
+fn parse_api_key(row: &SqliteRow) -> color_eyre::Result<ApiKey> {
+    let scopes: String = row.try_get("scopes")?;
+    let created_at: i64 = row.try_get("created_at")?;
+    let expires_at: i64 = row.try_get("expires_at")?;
+
+    Ok(ApiKey {
+        prefix: row.try_get("prefix")?,
+        email: row.try_get("email")?,
+        name: row.try_get("name")?,
+        scopes: serde_json::from_str(&scopes)?,
+        created_at: DateTime::from_timestamp(created_at, 0)
+            .ok_or_else(|| eyre!("Invalid API key timestamp: {}", created_at))?,
+        expires_at: DateTime::from_timestamp(expires_at, 0)
+            .ok_or_else(|| eyre!("Invalid API key timestamp: {}", expires_at))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, SubsecRound, Utc};
+
+    use super::*;
+    use crate::get_sqlite_pool;
+
+    async fn api_key_store() -> SqliteApiKeyStore {
+        let pool = get_sqlite_pool("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .unwrap();
+        SqliteApiKeyStore::new(pool)
+    }
+
+    fn api_key(prefix: &str, email: &str, age_days: i64) -> ApiKey {
+        let created_at = Utc::now().trunc_subsecs(0) - Duration::days(age_days);
+        ApiKey {
+            prefix: prefix.to_owned(),
+            email: email.to_owned(),
+            name: "CI".to_owned(),
+            scopes: vec!["user:read".to_owned(), "user:write".to_owned()],
+            created_at,
+            expires_at: created_at + Duration::days(30),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_key() {
+        let mut store = api_key_store().await;
+        let key = api_key("abc123", "test@example.com", 0);
+
+        assert_eq!(store.add_key(key.clone(), "hash").await, Ok(()));
+        assert_eq!(
+            store.add_key(key.clone(), "other-hash").await,
+            Err(ApiKeyStoreError::KeyAlreadyExists)
+        );
+        assert_eq!(store.get_key("abc123").await, Ok((key, "hash".to_owned())));
+        assert_eq!(
+            store.get_key("unknown").await,
+            Err(ApiKeyStoreError::KeyNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_and_remove_keys() {
+        let mut store = api_key_store().await;
+        let email = Email::parse("Test@Example.com".to_owned()).unwrap();
+        let other = Email::parse("other@example.com".to_owned()).unwrap();
+        for (prefix, email, age_days) in [
+            ("older", "test@example.com", 2),
+            ("newer", "test@example.com", 1),
+            ("other", "other@example.com", 0),
+        ] {
+            store
+                .add_key(api_key(prefix, email, age_days), "hash")
+                .await
+                .unwrap();
+        }
+
+        let prefixes: Vec<String> = store
+            .list_keys(&email)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|key| key.prefix)
+            .collect();
+        assert_eq!(prefixes, vec!["newer", "older"]);
+
+        assert_eq!(
+            store.remove_key(&other, "older").await,
+            Err(ApiKeyStoreError::KeyNotFound)
+        );
+        assert_eq!(store.remove_key(&email, "older").await, Ok(()));
+        assert_eq!(store.list_keys(&email).await.unwrap().len(), 1);
+    }
+}
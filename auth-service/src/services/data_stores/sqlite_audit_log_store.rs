@@ -0,0 +1,149 @@
+use chrono::DateTime;
+use color_eyre::eyre::eyre;
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+
+use crate::domain::{
+    AuditEvent, AuditEventQuery, AuditEventType, AuditLogStore, AuditLogStoreError,
+};
+
+pub struct SqliteAuditLogStore {
+    pool: SqlitePool,
+}
+
+impl SqliteAuditLogStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditLogStore for SqliteAuditLogStore {
+    #[tracing::instrument(name = "Adding audit event to SQLite", skip_all)]
+    async fn add_event(&mut self, event: AuditEvent) -> Result<(), AuditLogStoreError> {
+        sqlx::query(
+            r#"
+            INSERT INTO audit_events
+                (occurred_at, event_type, email, success, reason, ip_address, user_agent, request_id)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(event.occurred_at.timestamp_micros())
+        .bind(event.event_type.as_ref())
+        .bind(event.email)
+        .bind(event.success)
+        .bind(event.reason)
+        .bind(event.ip_address)
+        .bind(event.user_agent)
+        .bind(event.request_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuditLogStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving audit events from SQLite", skip_all)]
+    async fn get_events(
+        &self,
+        query: &AuditEventQuery,
+    ) -> Result<Vec<AuditEvent>, AuditLogStoreError> {
+        sqlx::query(
+            r#"
+            SELECT occurred_at, event_type, email, success, reason, ip_address, user_agent, request_id
+            FROM audit_events
+            WHERE (?1 IS NULL OR email = ?1)
+              AND (?2 IS NULL OR occurred_at >= ?2)
+              AND (?3 IS NULL OR occurred_at < ?3)
+            ORDER BY occurred_at DESC, id DESC
+            LIMIT ?4
+            "#,
+        )
+        .bind(&query.email)
+        .bind(query.from.map(|from| from.timestamp_micros()))
+        .bind(query.to.map(|to| to.timestamp_micros()))
+        .bind(query.limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuditLogStoreError::UnexpectedError(e.into()))?
+        .iter()
+        .map(|row| parse_event(row).map_err(AuditLogStoreError::UnexpectedError))
+        .collect()
+    }
+}
+
+fn parse_event(row: &SqliteRow) -> color_eyre::Result<AuditEvent> {
+    let event_type: String = row.try_get("event_type")?;
+    let occurred_at: i64 = row.try_get("occurred_at")?;
+
+    Ok(AuditEvent {
+        event_type: AuditEventType::parse(&event_type).map_err(|e| eyre!(e))?,
+        email: row.try_get("email")?,
+        success: row.try_get("success")?,
+        reason: row.try_get("reason")?,
+        ip_address: row.try_get("ip_address")?,
+        user_agent: row.try_get("user_agent")?,
+        request_id: row.try_get("request_id")?,
+        occurred_at: DateTime::from_timestamp_micros(occurred_at)
+            .ok_or_else(|| eyre!("Invalid audit event timestamp: {}", occurred_at))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_sqlite_pool;
+
+    async fn audit_log_store() -> SqliteAuditLogStore {
+        let pool = get_sqlite_pool("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .unwrap();
+        SqliteAuditLogStore::new(pool)
+    }
+
+    // Timestamps are stored with microsecond precision
+    fn event(event_type: AuditEventType, email: &str) -> AuditEvent {
+        let mut event = AuditEvent::new(event_type).with_email(email);
+        event.occurred_at = DateTime::from_timestamp_micros(event.occurred_at.timestamp_micros())
+            .expect("valid timestamp");
+        event
+    }
+
+    #[tokio::test]
+    async fn test_get_events_returns_newest_first_filtered_and_limited() {
+        let mut store = audit_log_store().await;
+        let first = event(AuditEventType::Login, "test@example.com");
+        let other = event(AuditEventType::Login, "other@example.com");
+        let second = event(AuditEventType::Logout, "test@example.com").failed("missing_token");
+        for event in [first.clone(), other, second.clone()] {
+            store.add_event(event).await.unwrap();
+        }
+
+        let query = AuditEventQuery {
+            email: Some("test@example.com".to_owned()),
+            limit: 10,
+            ..Default::default()
+        };
+        let result = store.get_events(&query).await.unwrap();
+        assert_eq!(result, vec![second.clone(), first]);
+
+        let query = AuditEventQuery { limit: 1, ..query };
+        let result = store.get_events(&query).await.unwrap();
+        assert_eq!(result, vec![second]);
+    }
+
+    #[tokio::test]
+    async fn test_events_cannot_be_modified() {
+        let mut store = audit_log_store().await;
+        store
+            .add_event(AuditEvent::new(AuditEventType::Signup))
+            .await
+            .unwrap();
+
+        let result = sqlx::query("DELETE FROM audit_events")
+            .execute(&store.pool)
+            .await;
+        assert!(result.is_err());
+    }
+}
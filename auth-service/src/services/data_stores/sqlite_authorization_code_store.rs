@@ -0,0 +1,170 @@
+use chrono::Utc;
+use color_eyre::eyre::{eyre, Result};
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+
+use crate::domain::{
+    data_stores::{AuthorizationCodeStore, AuthorizationCodeStoreError},
+    AuthorizationCode,
+};
+
+pub struct SqliteAuthorizationCodeStore {
+    pool: SqlitePool,
+}
+
+impl SqliteAuthorizationCodeStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Removes codes that can no longer be redeemed, returning how many were deleted.
+    #[tracing::instrument(name = "Deleting expired authorization codes from SQLite", skip_all)]
+    pub async fn delete_expired(&self) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM authorization_codes
+            WHERE expires_at <= ?
+            "#,
+        )
+        .bind(Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl AuthorizationCodeStore for SqliteAuthorizationCodeStore {
+    #[tracing::instrument(name = "Adding authorization code to SQLite", skip_all)]
+    async fn add_code(
+        &mut self,
+        code: &str,
+        authorization_code: AuthorizationCode,
+        ttl_seconds: u64,
+    ) -> Result<(), AuthorizationCodeStoreError> {
+        let expires_at = i64::try_from(ttl_seconds)
+            .ok()
+            .and_then(|ttl| Utc::now().timestamp().checked_add(ttl))
+            .ok_or_else(|| AuthorizationCodeStoreError::UnexpectedError(eyre!("Invalid TTL")))?;
+        let auth_time = i64::try_from(authorization_code.auth_time)
+            .map_err(|e| AuthorizationCodeStoreError::UnexpectedError(e.into()))?;
+        let scopes = serde_json::to_string(&authorization_code.scopes)
+            .map_err(|e| AuthorizationCodeStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO authorization_codes
+                (code, client_id, redirect_uri, subject, scopes, nonce, code_challenge, auth_time, expires_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(code)
+        .bind(authorization_code.client_id)
+        .bind(authorization_code.redirect_uri)
+        .bind(authorization_code.subject)
+        .bind(scopes)
+        .bind(authorization_code.nonce)
+        .bind(authorization_code.code_challenge)
+        .bind(auth_time)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuthorizationCodeStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Taking authorization code from SQLite", skip_all)]
+    async fn take_code(
+        &mut self,
+        code: &str,
+    ) -> Result<AuthorizationCode, AuthorizationCodeStoreError> {
+        let row = sqlx::query(
+            r#"
+            DELETE FROM authorization_codes
+            WHERE code = ? AND expires_at > ?
+            RETURNING client_id, redirect_uri, subject, scopes, nonce, code_challenge, auth_time
+            "#,
+        )
+        .bind(code)
+        .bind(Utc::now().timestamp())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AuthorizationCodeStoreError::UnexpectedError(e.into()))?
+        .ok_or(AuthorizationCodeStoreError::CodeNotFound)?;
+
+        parse_authorization_code(&row).map_err(AuthorizationCodeStoreError::UnexpectedError)
+    }
+}
+
+fn parse_authorization_code(row: &SqliteRow) -> Result<AuthorizationCode> {
+    let scopes: String = row.try_get("scopes")?;
+    let auth_time: i64 = row.try_get("auth_time")?;
+
+    Ok(AuthorizationCode {
+        client_id: row.try_get("client_id")?,
+        redirect_uri: row.try_get("redirect_uri")?,
+        subject: row.try_get("subject")?,
+        scopes: serde_json::from_str(&scopes)?,
+        nonce: row.try_get("nonce")?,
+        code_challenge: row.try_get("code_challenge")?,
+        auth_time: u64::try_from(auth_time)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_sqlite_pool;
+
+    async fn authorization_code_store() -> SqliteAuthorizationCodeStore {
+        let pool = get_sqlite_pool("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .unwrap();
+        SqliteAuthorizationCodeStore::new(pool)
+    }
+
+    fn authorization_code() -> AuthorizationCode {
+        AuthorizationCode {
+            client_id: "client".to_owned(),
+            redirect_uri: "https://client.example.com/callback".to_owned(),
+            subject: "test@example.com".to_owned(),
+            scopes: vec!["openid".to_owned(), "email".to_owned()],
+            nonce: Some("nonce".to_owned()),
+            code_challenge: "challenge".to_owned(),
+            auth_time: 1_700_000_000,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_codes_can_only_be_taken_once() {
+        let mut store = authorization_code_store().await;
+        store
+            .add_code("code", authorization_code(), 60)
+            .await
+            .unwrap();
+
+        assert_eq!(store.take_code("code").await, Ok(authorization_code()));
+        assert_eq!(
+            store.take_code("code").await,
+            Err(AuthorizationCodeStoreError::CodeNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_codes_are_not_found() {
+        let mut store = authorization_code_store().await;
+        store
+            .add_code("code", authorization_code(), 0)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.take_code("code").await,
+            Err(AuthorizationCodeStoreError::CodeNotFound)
+        );
+        assert_eq!(store.delete_expired().await.unwrap(), 1);
+    }
+}
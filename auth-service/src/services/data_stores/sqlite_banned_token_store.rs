@@ -0,0 +1,116 @@
+use chrono::Utc;
+use color_eyre::eyre::Result;
+use sqlx::SqlitePool;
+
+use crate::{
+    domain::data_stores::{BannedTokenStore, BannedTokenStoreError},
+    utils::auth::TOKEN_TTL_SECONDS,
+};
+
+pub struct SqliteBannedTokenStore {
+    pool: SqlitePool,
+}
+
+impl SqliteBannedTokenStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Removes tokens whose JWT would have expired anyway, returning how many were deleted.
+    #[tracing::instrument(name = "Deleting expired banned tokens from SQLite", skip_all)]
+    pub async fn delete_expired(&self) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM banned_tokens
+            WHERE expires_at <= ?
+            "#,
+        )
+        .bind(Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for SqliteBannedTokenStore {
+    #[tracing::instrument(name = "Adding banned token to SQLite", skip_all)]
+    async fn add_token(&mut self, token: String) -> Result<(), BannedTokenStoreError> {
+        let expires_at = Utc::now().timestamp() + TOKEN_TTL_SECONDS;
+
+        sqlx::query(
+            r#"
+            INSERT INTO banned_tokens (token, expires_at)
+            VALUES (?, ?)
+            ON CONFLICT (token) DO UPDATE SET expires_at = excluded.expires_at
+            "#,
+        )
+        .bind(token)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|_| BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking banned token in SQLite", skip_all)]
+    async fn contains_token(&self, token: &str) -> Result<bool, BannedTokenStoreError> {
+        sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM banned_tokens
+                WHERE token = ? AND expires_at > ?
+            )
+            "#,
+        )
+        .bind(token)
+        .bind(Utc::now().timestamp())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|_| BannedTokenStoreError::UnexpectedError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_sqlite_pool;
+
+    async fn banned_token_store() -> SqliteBannedTokenStore {
+        let pool = get_sqlite_pool("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .unwrap();
+        SqliteBannedTokenStore::new(pool)
+    }
+
+    #[tokio::test]
+    async fn test_add_and_contains_token() {
+        let mut store = banned_token_store().await;
+        let token = "test_token".to_owned();
+
+        assert!(!store.contains_token(&token).await.unwrap());
+
+        store.add_token(token.clone()).await.unwrap();
+        store.add_token(token.clone()).await.unwrap();
+
+        assert!(store.contains_token(&token).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_delete_expired() {
+        let store = banned_token_store().await;
+        sqlx::query("INSERT INTO banned_tokens (token, expires_at) VALUES (?, ?)")
+            .bind("expired_token")
+            .bind(Utc::now().timestamp() - 1)
+            .execute(&store.pool)
+            .await
+            .unwrap();
+
+        assert!(!store.contains_token("expired_token").await.unwrap());
+        assert_eq!(store.delete_expired().await.unwrap(), 1);
+    }
+}
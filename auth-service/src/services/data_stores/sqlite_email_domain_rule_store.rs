@@ -0,0 +1,192 @@
+use color_eyre::eyre::eyre;
+use sqlx::SqlitePool;
+
+use crate::domain::{
+    data_stores::{EmailDomainRuleStore, EmailDomainRuleStoreError},
+    EmailDomainRule,
+};
+
+pub struct SqliteEmailDomainRuleStore {
+    pool: SqlitePool,
+}
+
+impl SqliteEmailDomainRuleStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailDomainRuleStore for SqliteEmailDomainRuleStore {
+    #[tracing::instrument(name = "Setting email domain rule in SQLite", skip_all)]
+    async fn set_rule(
+        &mut self,
+        domain: &str,
+        rule: EmailDomainRule,
+    ) -> Result<(), EmailDomainRuleStoreError> {
+        sqlx::query(
+            r#"
+            INSERT INTO email_domain_rules (domain, rule)
+            VALUES (?, ?)
+            ON CONFLICT (domain) DO UPDATE SET rule = excluded.rule
+            "#,
+        )
+        .bind(domain)
+        .bind(rule.as_str())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailDomainRuleStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing email domain rule from SQLite", skip_all)]
+    async fn remove_rule(&mut self, domain: &str) -> Result<(), EmailDomainRuleStoreError> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM email_domain_rules
+            WHERE domain = ?
+            "#,
+        )
+        .bind(domain)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| EmailDomainRuleStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(EmailDomainRuleStoreError::RuleNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving email domain rules from SQLite", skip_all)]
+    async fn get_rules(
+        &self,
+        domains: &[String],
+    ) -> Result<Vec<(String, EmailDomainRule)>, EmailDomainRuleStoreError> {
+        let domains = serde_json::to_string(domains)
+            .map_err(|e| EmailDomainRuleStoreError::UnexpectedError(e.into()))?;
+
+        sqlx::query_as::<_, (String, String)>(
+            r#"
+            SELECT domain, rule
+            FROM email_domain_rules
+            WHERE domain IN (SELECT value FROM json_each(?))
+            "#,
+        )
+        .bind(domains)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| EmailDomainRuleStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|(domain, rule)| parse_rule(domain, &rule))
+        .collect()
+    }
+
+    #[tracing::instrument(name = "Listing email domain rules from SQLite", skip_all)]
+    async fn list_rules(
+        &self,
+    ) -> Result<Vec<(String, EmailDomainRule)>, EmailDomainRuleStoreError> {
+        sqlx::query_as::<_, (String, String)>(
+            r#"
+            SELECT domain, rule
+            FROM email_domain_rules
+            ORDER BY domain
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| EmailDomainRuleStoreError::UnexpectedError(e.into()))?
+        .into_iter()
+        .map(|(domain, rule)| parse_rule(domain, &rule))
+        .collect()
+    }
+}
+
+fn parse_rule(
+    domain: String,
+    rule: &str,
+) -> Result<(String, EmailDomainRule), EmailDomainRuleStoreError> {
+    let rule = rule
+        .parse()
+        .map_err(|e: String| EmailDomainRuleStoreError::UnexpectedError(eyre!(e)))?;
+
+    Ok((domain, rule))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_sqlite_pool;
+
+    async fn rule_store() -> SqliteEmailDomainRuleStore {
+        let pool = get_sqlite_pool("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .unwrap();
+        SqliteEmailDomainRuleStore::new(pool)
+    }
+
+    #[tokio::test]
+    async fn test_set_and_get_rules() {
+        let mut store = rule_store().await;
+        store
+            .set_rule("example.com", EmailDomainRule::Deny)
+            .await
+            .unwrap();
+        store
+            .set_rule("mail.example.com", EmailDomainRule::Deny)
+            .await
+            .unwrap();
+        store
+            .set_rule("mail.example.com", EmailDomainRule::Allow)
+            .await
+            .unwrap();
+        store
+            .set_rule("other.com", EmailDomainRule::Deny)
+            .await
+            .unwrap();
+
+        let mut rules = store
+            .get_rules(&["mail.example.com".to_owned(), "example.com".to_owned()])
+            .await
+            .unwrap();
+        rules.sort();
+        assert_eq!(
+            rules,
+            vec![
+                ("example.com".to_owned(), EmailDomainRule::Deny),
+                ("mail.example.com".to_owned(), EmailDomainRule::Allow),
+            ]
+        );
+
+        let domains: Vec<String> = store
+            .list_rules()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(domain, _)| domain)
+            .collect();
+        assert_eq!(
+            domains,
+            vec!["example.com", "mail.example.com", "other.com"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remove_rule() {
+        let mut store = rule_store().await;
+        store
+            .set_rule("example.com", EmailDomainRule::Deny)
+            .await
+            .unwrap();
+
+        assert_eq!(store.remove_rule("example.com").await, Ok(()));
+        assert_eq!(
+            store.remove_rule("example.com").await,
+            Err(EmailDomainRuleStoreError::RuleNotFound)
+        );
+    }
+}
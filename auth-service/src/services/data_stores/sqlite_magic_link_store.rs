@@ -0,0 +1,98 @@
+use chrono::Utc;
+use color_eyre::eyre::{eyre, Result};
+use sqlx::SqlitePool;
+
+use crate::domain::data_stores::{MagicLinkStore, MagicLinkStoreError};
+
+pub struct SqliteMagicLinkStore {
+    pool: SqlitePool,
+}
+
+impl SqliteMagicLinkStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Forgets used links that have expired anyway, returning how many were deleted.
+    #[tracing::instrument(name = "Deleting expired used magic links from SQLite", skip_all)]
+    pub async fn delete_expired(&self) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM used_magic_links
+            WHERE expires_at <= ?
+            "#,
+        )
+        .bind(Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl MagicLinkStore for SqliteMagicLinkStore {
+    #[tracing::instrument(name = "Using magic link in SQLite", skip_all)]
+    async fn use_link(
+        &mut self,
+        link_id: &str,
+        ttl_seconds: u64,
+    ) -> Result<bool, MagicLinkStoreError> {
+        let now = Utc::now().timestamp();
+        let expires_at = i64::try_from(ttl_seconds)
+            .ok()
+            .and_then(|ttl| now.checked_add(ttl))
+            .ok_or_else(|| MagicLinkStoreError::UnexpectedError(eyre!("Invalid TTL")))?;
+
+        // Nothing is inserted or updated while the link is remembered as used
+        let result = sqlx::query(
+            r#"
+            INSERT INTO used_magic_links (link_id, expires_at)
+            VALUES (?1, ?2)
+            ON CONFLICT (link_id) DO UPDATE SET expires_at = excluded.expires_at
+            WHERE used_magic_links.expires_at <= ?3
+            "#,
+        )
+        .bind(link_id)
+        .bind(expires_at)
+        .bind(now)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| MagicLinkStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_sqlite_pool;
+
+    async fn magic_link_store() -> SqliteMagicLinkStore {
+        let pool = get_sqlite_pool("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .unwrap();
+        SqliteMagicLinkStore::new(pool)
+    }
+
+    #[tokio::test]
+    async fn test_links_can_only_be_used_once() {
+        let mut store = magic_link_store().await;
+
+        assert!(store.use_link("link", 900).await.unwrap());
+        assert!(!store.use_link("link", 900).await.unwrap());
+        assert!(store.use_link("other", 900).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_forgets_links_after_their_ttl() {
+        let mut store = magic_link_store().await;
+
+        assert!(store.use_link("link", 0).await.unwrap());
+        assert!(store.use_link("link", 0).await.unwrap());
+        assert_eq!(store.delete_expired().await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,142 @@
+use chrono::Utc;
+use color_eyre::eyre::{eyre, Result};
+use sqlx::{Row, SqlitePool};
+
+use crate::domain::{
+    data_stores::{OAuthStateStore, OAuthStateStoreError},
+    OAuthState,
+};
+
+pub struct SqliteOAuthStateStore {
+    pool: SqlitePool,
+}
+
+impl SqliteOAuthStateStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Removes logins that were never finished in time, returning how many were deleted.
+    #[tracing::instrument(name = "Deleting expired OAuth states from SQLite", skip_all)]
+    pub async fn delete_expired(&self) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM oauth_states
+            WHERE expires_at <= ?
+            "#,
+        )
+        .bind(Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl OAuthStateStore for SqliteOAuthStateStore {
+    #[tracing::instrument(name = "Adding OAuth state to SQLite", skip_all)]
+    async fn add_state(
+        &mut self,
+        state: &str,
+        oauth_state: OAuthState,
+        ttl_seconds: u64,
+    ) -> Result<(), OAuthStateStoreError> {
+        let expires_at = i64::try_from(ttl_seconds)
+            .ok()
+            .and_then(|ttl| Utc::now().timestamp().checked_add(ttl))
+            .ok_or_else(|| OAuthStateStoreError::UnexpectedError(eyre!("Invalid TTL")))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO oauth_states (state, provider, code_verifier, expires_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT (state) DO UPDATE
+            SET provider = excluded.provider,
+                code_verifier = excluded.code_verifier,
+                expires_at = excluded.expires_at
+            "#,
+        )
+        .bind(state)
+        .bind(oauth_state.provider)
+        .bind(oauth_state.code_verifier)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| OAuthStateStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Taking OAuth state from SQLite", skip_all)]
+    async fn take_state(&mut self, state: &str) -> Result<OAuthState, OAuthStateStoreError> {
+        let row = sqlx::query(
+            r#"
+            DELETE FROM oauth_states
+            WHERE state = ? AND expires_at > ?
+            RETURNING provider, code_verifier
+            "#,
+        )
+        .bind(state)
+        .bind(Utc::now().timestamp())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| OAuthStateStoreError::UnexpectedError(e.into()))?
+        .ok_or(OAuthStateStoreError::StateNotFound)?;
+
+        Ok(OAuthState {
+            provider: row
+                .try_get("provider")
+                .map_err(|e| OAuthStateStoreError::UnexpectedError(e.into()))?,
+            code_verifier: row
+                .try_get("code_verifier")
+                .map_err(|e| OAuthStateStoreError::UnexpectedError(e.into()))?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_sqlite_pool;
+
+    async fn oauth_state_store() -> SqliteOAuthStateStore {
+        let pool = get_sqlite_pool("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .unwrap();
+        SqliteOAuthStateStore::new(pool)
+    }
+
+    fn oauth_state() -> OAuthState {
+        OAuthState {
+            provider: "github".to_owned(),
+            code_verifier: "verifier".to_owned(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_states_can_only_be_taken_once() {
+        let mut store = oauth_state_store().await;
+        store.add_state("state", oauth_state(), 600).await.unwrap();
+
+        assert_eq!(store.take_state("state").await, Ok(oauth_state()));
+        assert_eq!(
+            store.take_state("state").await,
+            Err(OAuthStateStoreError::StateNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_states_are_not_found() {
+        let mut store = oauth_state_store().await;
+        store.add_state("state", oauth_state(), 0).await.unwrap();
+
+        assert_eq!(
+            store.take_state("state").await,
+            Err(OAuthStateStoreError::StateNotFound)
+        );
+        assert_eq!(store.delete_expired().await.unwrap(), 1);
+    }
+}
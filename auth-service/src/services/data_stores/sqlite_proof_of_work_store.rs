@@ -0,0 +1,205 @@
+use chrono::Utc;
+use color_eyre::eyre::{eyre, Result};
+use sqlx::SqlitePool;
+
+use crate::domain::{
+    data_stores::{ProofOfWorkStore, ProofOfWorkStoreError},
+    ProofOfWorkAction,
+};
+
+pub struct SqliteProofOfWorkStore {
+    pool: SqlitePool,
+}
+
+impl SqliteProofOfWorkStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Removes counters of past windows and expired challenges, returning how many rows were
+    /// deleted.
+    #[tracing::instrument(name = "Deleting expired proof-of-work rows from SQLite", skip_all)]
+    pub async fn delete_expired(&self) -> Result<u64> {
+        let now = Utc::now().timestamp();
+        let attempts = sqlx::query(
+            r#"
+            DELETE FROM proof_of_work_attempts
+            WHERE expires_at <= ?
+            "#,
+        )
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+        let challenges = sqlx::query(
+            r#"
+            DELETE FROM used_proof_of_work_challenges
+            WHERE expires_at <= ?
+            "#,
+        )
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(attempts.rows_affected() + challenges.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl ProofOfWorkStore for SqliteProofOfWorkStore {
+    #[tracing::instrument(name = "Counting proof-of-work attempt in SQLite", skip_all)]
+    async fn increment_attempts(
+        &mut self,
+        action: ProofOfWorkAction,
+        window: u64,
+        ttl_seconds: u64,
+    ) -> Result<u64, ProofOfWorkStoreError> {
+        let attempts: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO proof_of_work_attempts (action, window_number, attempts, expires_at)
+            VALUES (?, ?, 1, ?)
+            ON CONFLICT (action, window_number)
+            DO UPDATE SET attempts = proof_of_work_attempts.attempts + 1
+            RETURNING attempts
+            "#,
+        )
+        .bind(action.as_str())
+        .bind(window_number(window)?)
+        .bind(expires_at(ttl_seconds)?)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| ProofOfWorkStoreError::UnexpectedError(e.into()))?;
+
+        u64::try_from(attempts).map_err(|e| ProofOfWorkStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Retrieving proof-of-work attempts from SQLite", skip_all)]
+    async fn get_attempts(
+        &self,
+        action: ProofOfWorkAction,
+        window: u64,
+    ) -> Result<u64, ProofOfWorkStoreError> {
+        let attempts: Option<i64> = sqlx::query_scalar(
+            r#"
+            SELECT attempts FROM proof_of_work_attempts
+            WHERE action = ? AND window_number = ?
+            "#,
+        )
+        .bind(action.as_str())
+        .bind(window_number(window)?)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ProofOfWorkStoreError::UnexpectedError(e.into()))?;
+
+        u64::try_from(attempts.unwrap_or_default())
+            .map_err(|e| ProofOfWorkStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Using proof-of-work challenge in SQLite", skip_all)]
+    async fn use_challenge(
+        &mut self,
+        challenge_id: &str,
+        ttl_seconds: u64,
+    ) -> Result<bool, ProofOfWorkStoreError> {
+        // Nothing is inserted or updated while the challenge is remembered as used
+        let result = sqlx::query(
+            r#"
+            INSERT INTO used_proof_of_work_challenges (challenge_id, expires_at)
+            VALUES (?1, ?2)
+            ON CONFLICT (challenge_id) DO UPDATE SET expires_at = excluded.expires_at
+            WHERE used_proof_of_work_challenges.expires_at <= ?3
+            "#,
+        )
+        .bind(challenge_id)
+        .bind(expires_at(ttl_seconds)?)
+        .bind(Utc::now().timestamp())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ProofOfWorkStoreError::UnexpectedError(e.into()))?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
+
+fn window_number(window: u64) -> Result<i64, ProofOfWorkStoreError> {
+    i64::try_from(window).map_err(|e| ProofOfWorkStoreError::UnexpectedError(e.into()))
+}
+
+fn expires_at(ttl_seconds: u64) -> Result<i64, ProofOfWorkStoreError> {
+    i64::try_from(ttl_seconds)
+        .ok()
+        .and_then(|ttl| Utc::now().timestamp().checked_add(ttl))
+        .ok_or_else(|| ProofOfWorkStoreError::UnexpectedError(eyre!("Invalid TTL")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_sqlite_pool;
+
+    async fn proof_of_work_store() -> SqliteProofOfWorkStore {
+        let pool = get_sqlite_pool("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .unwrap();
+        SqliteProofOfWorkStore::new(pool)
+    }
+
+    #[tokio::test]
+    async fn test_counts_attempts_per_action_and_window() {
+        let mut store = proof_of_work_store().await;
+
+        store
+            .increment_attempts(ProofOfWorkAction::Signup, 10, 120)
+            .await
+            .unwrap();
+        let attempts = store
+            .increment_attempts(ProofOfWorkAction::Signup, 10, 120)
+            .await
+            .unwrap();
+        assert_eq!(attempts, 2);
+
+        assert_eq!(
+            store
+                .get_attempts(ProofOfWorkAction::Signup, 10)
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            store
+                .get_attempts(ProofOfWorkAction::Login, 10)
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            store
+                .get_attempts(ProofOfWorkAction::Signup, 11)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_challenges_can_only_be_used_once() {
+        let mut store = proof_of_work_store().await;
+
+        assert!(store.use_challenge("challenge", 300).await.unwrap());
+        assert!(!store.use_challenge("challenge", 300).await.unwrap());
+        assert!(store.use_challenge("other", 300).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_delete_expired() {
+        let mut store = proof_of_work_store().await;
+        store
+            .increment_attempts(ProofOfWorkAction::Login, 10, 0)
+            .await
+            .unwrap();
+        store.use_challenge("challenge", 0).await.unwrap();
+
+        assert_eq!(store.delete_expired().await.unwrap(), 2);
+    }
+}
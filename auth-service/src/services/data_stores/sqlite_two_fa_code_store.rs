@@ -0,0 +1,183 @@
+use chrono::Utc;
+use color_eyre::eyre::Result;
+use sqlx::{Row, SqlitePool};
+
+use crate::domain::{
+    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+    Email,
+};
+
+pub struct SqliteTwoFACodeStore {
+    pool: SqlitePool,
+}
+
+impl SqliteTwoFACodeStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Removes codes that can no longer be used, returning how many were deleted.
+    #[tracing::instrument(name = "Deleting expired 2FA codes from SQLite", skip_all)]
+    pub async fn delete_expired(&self) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM two_fa_codes
+            WHERE expires_at <= ?
+            "#,
+        )
+        .bind(Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+#[async_trait::async_trait]
+impl TwoFACodeStore for SqliteTwoFACodeStore {
+    #[tracing::instrument(name = "Adding 2FA code to SQLite", skip_all)]
+    async fn add_code(
+        &mut self,
+        email: Email,
+        login_attempt_id: LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let expires_at = Utc::now().timestamp() + TEN_MINUTES_IN_SECONDS;
+
+        sqlx::query(
+            r#"
+            INSERT INTO two_fa_codes (email, login_attempt_id, code, expires_at)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT (email) DO UPDATE SET
+                login_attempt_id = excluded.login_attempt_id,
+                code = excluded.code,
+                expires_at = excluded.expires_at
+            "#,
+        )
+        .bind(email.lookup_key())
+        .bind(login_attempt_id.as_ref())
+        .bind(code.as_ref())
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Removing 2FA code from SQLite", skip_all)]
+    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        sqlx::query(
+            r#"
+            DELETE FROM two_fa_codes
+            WHERE email = ?
+            "#,
+        )
+        .bind(email.lookup_key())
+        .execute(&self.pool)
+        .await
+        .map_err(|_| TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving 2FA code from SQLite", skip_all)]
+    async fn get_code(
+        &self,
+        email: &Email,
+    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
+        let row = sqlx::query(
+            r#"
+            SELECT login_attempt_id, code
+            FROM two_fa_codes
+            WHERE email = ? AND expires_at > ?
+            "#,
+        )
+        .bind(email.lookup_key())
+        .bind(Utc::now().timestamp())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|_| TwoFACodeStoreError::UnexpectedError)?
+        .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+
+        let login_attempt_id = row
+            .try_get("login_attempt_id")
+            .ok()
+            .and_then(|id| LoginAttemptId::parse(id).ok())
+            .ok_or(TwoFACodeStoreError::UnexpectedError)?;
+        let code = row
+            .try_get("code")
+            .ok()
+            .and_then(|code| TwoFACode::parse(code).ok())
+            .ok_or(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok((login_attempt_id, code))
+    }
+}
+
+const TEN_MINUTES_IN_SECONDS: i64 = 600;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_sqlite_pool;
+
+    async fn two_fa_code_store() -> SqliteTwoFACodeStore {
+        let pool = get_sqlite_pool("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .unwrap();
+        SqliteTwoFACodeStore::new(pool)
+    }
+
+    #[tokio::test]
+    async fn test_add_get_and_remove_code() {
+        let mut store = two_fa_code_store().await;
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let login_attempt_id = LoginAttemptId::default();
+        let code = TwoFACode::default();
+
+        store
+            .add_code(
+                email.clone(),
+                LoginAttemptId::default(),
+                TwoFACode::default(),
+            )
+            .await
+            .unwrap();
+        store
+            .add_code(email.clone(), login_attempt_id.clone(), code.clone())
+            .await
+            .unwrap();
+        assert_eq!(store.get_code(&email).await, Ok((login_attempt_id, code)));
+
+        store.remove_code(&email).await.unwrap();
+        assert_eq!(
+            store.get_code(&email).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_codes_are_ignored_and_deleted() {
+        let store = two_fa_code_store().await;
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        sqlx::query(
+            "INSERT INTO two_fa_codes (email, login_attempt_id, code, expires_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(email.as_ref())
+        .bind(LoginAttemptId::default().as_ref())
+        .bind(TwoFACode::default().as_ref())
+        .bind(Utc::now().timestamp() - 1)
+        .execute(&store.pool)
+        .await
+        .unwrap();
+
+        assert_eq!(
+            store.get_code(&email).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+        assert_eq!(store.delete_expired().await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,482 @@
+use color_eyre::eyre::{eyre, Result};
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+
+use crate::{
+    domain::{
+        data_stores::{UserStore, UserStoreError},
+        Email, Password, User, UserListQuery, UserPage,
+    },
+    services::password_hasher::Argon2PasswordHasher,
+};
+
+pub struct SqliteUserStore {
+    pool: SqlitePool,
+    password_hasher: Argon2PasswordHasher,
+}
+
+impl SqliteUserStore {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            password_hasher: Argon2PasswordHasher::default(),
+        }
+    }
+    /// Replaces a hash made with outdated parameters, unless the password changed meanwhile.
+    #[tracing::instrument(name = "Rehashing user password in SQLite", skip_all)]
+    async fn rehash_password(&self, user: &User, password: &Password) -> Result<()> {
+        let password_hash = self
+            .password_hasher
+            .compute_password_hash(password.as_ref().to_owned())
+            .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET password_hash = ?3
+            WHERE email_key = ?1 AND password_hash = ?2
+            "#,
+        )
+        .bind(user.email.lookup_key())
+        .bind(user.password.as_ref())
+        .bind(&password_hash)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl UserStore for SqliteUserStore {
+    #[tracing::instrument(name = "Adding user to SQLite", skip_all)]
+    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
+        let password_hash = self
+            .password_hasher
+            .compute_password_hash(user.password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO users (email, email_key, password_hash, requires_2fa)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(user.email.as_ref())
+        .bind(user.email.lookup_key())
+        .bind(&password_hash)
+        .bind(user.requires_2fa)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(ref db_error) if db_error.is_unique_violation() => {
+                UserStoreError::UserAlreadyExists
+            }
+            e => UserStoreError::UnexpectedError(e.into()),
+        })?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Importing user into SQLite", skip_all)]
+    async fn import_user(&mut self, user: User) -> Result<(), UserStoreError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO users (email, email_key, password_hash, requires_2fa)
+            VALUES (?, ?, ?, ?)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(user.email.as_ref())
+        .bind(user.email.lookup_key())
+        .bind(user.password.as_ref())
+        .bind(user.requires_2fa)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserAlreadyExists);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving user from SQLite", skip_all)]
+    async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
+        let row = sqlx::query(
+            r#"
+            SELECT email, password_hash, requires_2fa
+            FROM users
+            WHERE email_key = ?
+            "#,
+        )
+        .bind(email.lookup_key())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .ok_or(UserStoreError::UserNotFound)?;
+
+        parse_user(&row)
+    }
+
+    #[tracing::instrument(name = "Validating user credentials in SQLite", skip_all)]
+    async fn validate_user(
+        &self,
+        email: &Email,
+        password: &Password,
+    ) -> Result<(), UserStoreError> {
+        let user = self.get_user(email).await?;
+
+        let matched_as_typed = self
+            .password_hasher
+            .verify_password(user.password.as_ref(), password)
+            .await
+            .map_err(|_| UserStoreError::InvalidCredentials)?;
+
+        if matched_as_typed || self.password_hasher.needs_rehash(user.password.as_ref()) {
+            if let Err(e) = self.rehash_password(&user, password).await {
+                tracing::warn!(error.message = %e, "Failed to rehash password");
+            }
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Updating user password in SQLite", skip_all)]
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let password_hash = self
+            .password_hasher
+            .compute_password_hash(password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE users
+            SET password_hash = ?
+            WHERE email_key = ?
+            "#,
+        )
+        .bind(&password_hash)
+        .bind(email.lookup_key())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        ensure_user_found(result.rows_affected())
+    }
+
+    #[tracing::instrument(name = "Updating user 2FA requirement in SQLite", skip_all)]
+    async fn set_requires_2fa(
+        &mut self,
+        email: &Email,
+        requires_2fa: bool,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE users
+            SET requires_2fa = ?
+            WHERE email_key = ?
+            "#,
+        )
+        .bind(requires_2fa)
+        .bind(email.lookup_key())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        ensure_user_found(result.rows_affected())
+    }
+
+    #[tracing::instrument(name = "Deleting user from SQLite", skip_all)]
+    async fn delete_user(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM users
+            WHERE email_key = ?
+            "#,
+        )
+        .bind(email.lookup_key())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        ensure_user_found(result.rows_affected())
+    }
+
+    #[tracing::instrument(name = "Counting users in SQLite", skip_all)]
+    async fn count_users(&self) -> Result<u64, UserStoreError> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        u64::try_from(count).map_err(|e| UserStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Listing users from SQLite", skip_all)]
+    async fn list_users(&self, query: &UserListQuery) -> Result<UserPage, UserStoreError> {
+        let users = sqlx::query(
+            r#"
+            SELECT email, password_hash, requires_2fa
+            FROM users
+            WHERE (?1 IS NULL OR email > ?1)
+            ORDER BY email
+            LIMIT ?2
+            "#,
+        )
+        .bind(query.after().map(AsRef::as_ref))
+        .bind(i64::from(query.limit()) + 1)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?
+        .iter()
+        .map(parse_user)
+        .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(UserPage::from_overfetched(users, query.limit()))
+    }
+}
+
+fn parse_user(row: &SqliteRow) -> Result<User, UserStoreError> {
+    let email: String = row
+        .try_get("email")
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+    let password_hash: String = row
+        .try_get("password_hash")
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+    let requires_2fa: bool = row
+        .try_get("requires_2fa")
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+    Ok(User {
+        email: Email::parse(email).map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+        password: Password::parse(password_hash)
+            .map_err(|e| UserStoreError::UnexpectedError(eyre!(e)))?,
+        requires_2fa,
+    })
+}
+
+fn ensure_user_found(rows_affected: u64) -> Result<(), UserStoreError> {
+    if rows_affected == 0 {
+        Err(UserStoreError::UserNotFound)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{get_sqlite_pool, services::email_lookup_keys::correct_sqlite_email_lookup_keys};
+    use argon2::Params;
+
+    async fn user_store() -> SqliteUserStore {
+        let pool = get_sqlite_pool("sqlite::memory:").await.unwrap();
+        sqlx::migrate!("./migrations_sqlite")
+            .run(&pool)
+            .await
+            .unwrap();
+        SqliteUserStore::new(pool)
+    }
+
+    fn user() -> User {
+        User {
+            email: Email::parse("test@example.com".to_owned()).unwrap(),
+            password: Password::parse("password".to_owned()).unwrap(),
+            requires_2fa: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_user() {
+        let mut user_store = user_store().await;
+
+        let result = user_store.add_user(user()).await;
+        assert!(result.is_ok());
+
+        let result = user_store.add_user(user()).await;
+        assert_eq!(result, Err(UserStoreError::UserAlreadyExists));
+    }
+
+    #[tokio::test]
+    async fn test_get_user() {
+        let mut user_store = user_store().await;
+        let user = user();
+        user_store.add_user(user.clone()).await.unwrap();
+
+        let stored = user_store.get_user(&user.email).await.unwrap();
+        assert_eq!(stored.email, user.email);
+        assert!(stored.requires_2fa);
+        assert_ne!(stored.password, user.password);
+
+        let result = user_store
+            .get_user(&Email::parse("nonexistent@example.com".to_owned()).unwrap())
+            .await;
+        assert_eq!(result.err(), Some(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_emails_are_matched_regardless_of_case() {
+        let mut user_store = user_store().await;
+        sqlx::query(
+            "INSERT INTO users (email, email_key, password_hash) VALUES ('Bob@Example.com', 'bob@example.com', 'not-a-real-hash')",
+        )
+            .execute(&user_store.pool)
+            .await
+            .unwrap();
+        correct_sqlite_email_lookup_keys(&user_store.pool)
+            .await
+            .unwrap();
+
+        let email = Email::parse("bob@EXAMPLE.com".to_owned()).unwrap();
+        assert!(user_store.get_user(&email).await.is_ok());
+
+        let user = User::new(
+            email,
+            Password::parse("password".to_owned()).unwrap(),
+            false,
+        );
+        assert_eq!(
+            user_store.add_user(user).await,
+            Err(UserStoreError::UserAlreadyExists)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_case_insensitive_migration_rejects_colliding_emails() {
+        let pool = get_sqlite_pool("sqlite::memory:").await.unwrap();
+        for migration in [
+            include_str!("../../../migrations_sqlite/20261019110000_create_users_table.up.sql"),
+            include_str!(
+                "../../../migrations_sqlite/20261019110100_create_token_store_tables.up.sql"
+            ),
+        ] {
+            sqlx::raw_sql(migration).execute(&pool).await.unwrap();
+        }
+        sqlx::query(
+            "INSERT INTO users (email, password_hash) VALUES ('bob@example.com', 'a'), ('Bob@example.com', 'b')",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let result = sqlx::raw_sql(include_str!(
+            "../../../migrations_sqlite/20261019120000_make_emails_case_insensitive.up.sql"
+        ))
+        .execute(&pool)
+        .await;
+
+        let error = result.unwrap_err().to_string();
+        assert!(
+            error.contains("accounts_whose_emails_only_differ_in_case_must_be_merged"),
+            "{}",
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_user() {
+        let mut user_store = user_store().await;
+        let user = user();
+        user_store.add_user(user.clone()).await.unwrap();
+
+        let result = user_store.validate_user(&user.email, &user.password).await;
+        assert_eq!(result, Ok(()));
+
+        let wrong_password = Password::parse("wrongpassword".to_owned()).unwrap();
+        let result = user_store.validate_user(&user.email, &wrong_password).await;
+        assert_eq!(result, Err(UserStoreError::InvalidCredentials));
+    }
+
+    #[tokio::test]
+    async fn test_update_user() {
+        let mut user_store = user_store().await;
+        let user = user();
+        user_store.add_user(user.clone()).await.unwrap();
+
+        let new_password = Password::parse("newpassword".to_owned()).unwrap();
+        user_store
+            .update_password(&user.email, new_password.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            user_store.validate_user(&user.email, &new_password).await,
+            Ok(())
+        );
+
+        user_store
+            .set_requires_2fa(&user.email, false)
+            .await
+            .unwrap();
+        assert!(!user_store.get_user(&user.email).await.unwrap().requires_2fa);
+
+        let missing = Email::parse("nonexistent@example.com".to_owned()).unwrap();
+        assert_eq!(
+            user_store.set_requires_2fa(&missing, true).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_and_count_users() {
+        let mut user_store = user_store().await;
+        let user = user();
+        user_store.add_user(user.clone()).await.unwrap();
+        assert_eq!(user_store.count_users().await, Ok(1));
+
+        assert_eq!(user_store.delete_user(&user.email).await, Ok(()));
+        assert_eq!(user_store.count_users().await, Ok(0));
+        assert_eq!(
+            user_store.delete_user(&user.email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_users_paginates_in_email_order() {
+        let mut user_store = user_store().await;
+        for email in ["c@example.com", "a@example.com", "b@example.com"] {
+            let user = User {
+                email: Email::parse(email.to_owned()).unwrap(),
+                ..user()
+            };
+            user_store.add_user(user).await.unwrap();
+        }
+
+        let query = UserListQuery::new(None, 2).unwrap();
+        let page = user_store.list_users(&query).await.unwrap();
+        let emails: Vec<&str> = page.users.iter().map(|u| u.email.as_ref()).collect();
+        assert_eq!(emails, vec!["a@example.com", "b@example.com"]);
+
+        let query = UserListQuery::new(page.next_cursor, 2).unwrap();
+        let page = user_store.list_users(&query).await.unwrap();
+        let emails: Vec<&str> = page.users.iter().map(|u| u.email.as_ref()).collect();
+        assert_eq!(emails, vec!["c@example.com"]);
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[tokio::test]
+    async fn test_validate_user_rehashes_outdated_password_hash() {
+        let mut user_store = user_store().await;
+        user_store.password_hasher =
+            Argon2PasswordHasher::new(Params::new(8192, 1, 1, None).unwrap());
+        let user = user();
+        user_store.add_user(user.clone()).await.unwrap();
+        let old_hash = user_store.get_user(&user.email).await.unwrap().password;
+
+        user_store.password_hasher =
+            Argon2PasswordHasher::new(Params::new(8192, 2, 1, None).unwrap());
+        let result = user_store.validate_user(&user.email, &user.password).await;
+        assert_eq!(result, Ok(()));
+
+        let new_hash = user_store.get_user(&user.email).await.unwrap().password;
+        assert_ne!(new_hash, old_hash);
+        assert!(!user_store.password_hasher.needs_rehash(new_hash.as_ref()));
+    }
+}
@@ -0,0 +1,68 @@
+use crate::domain::{AuditEvent, AuditEventQuery, AuditLogStore, AuditLogStoreError};
+
+#[derive(Default)]
+pub struct VecAuditLogStore {
+    events: Vec<AuditEvent>,
+}
+
+#[async_trait::async_trait]
+impl AuditLogStore for VecAuditLogStore {
+    async fn add_event(&mut self, event: AuditEvent) -> Result<(), AuditLogStoreError> {
+        self.events.push(event);
+        Ok(())
+    }
+
+    async fn get_events(
+        &self,
+        query: &AuditEventQuery,
+    ) -> Result<Vec<AuditEvent>, AuditLogStoreError> {
+        let limit = usize::try_from(query.limit).unwrap_or_default();
+
+        Ok(self
+            .events
+            .iter()
+            .rev()
+            .filter(|event| query.matches(event))
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::AuditEventType;
+
+    #[tokio::test]
+    async fn test_add_event() {
+        let mut store = VecAuditLogStore::default();
+        let event = AuditEvent::new(AuditEventType::Signup).with_email("test@example.com");
+
+        let result = store.add_event(event.clone()).await;
+
+        assert!(result.is_ok());
+        assert_eq!(store.events, vec![event]);
+    }
+
+    #[tokio::test]
+    async fn test_get_events_returns_newest_first_filtered_and_limited() {
+        let mut store = VecAuditLogStore::default();
+        let first = AuditEvent::new(AuditEventType::Login).with_email("test@example.com");
+        let other = AuditEvent::new(AuditEventType::Login).with_email("other@example.com");
+        let second = AuditEvent::new(AuditEventType::Logout).with_email("test@example.com");
+        store.events = vec![first.clone(), other, second.clone()];
+
+        let query = AuditEventQuery {
+            email: Some("test@example.com".to_owned()),
+            limit: 10,
+            ..Default::default()
+        };
+        let result = store.get_events(&query).await.unwrap();
+        assert_eq!(result, vec![second.clone(), first]);
+
+        let query = AuditEventQuery { limit: 1, ..query };
+        let result = store.get_events(&query).await.unwrap();
+        assert_eq!(result, vec![second]);
+    }
+}
@@ -0,0 +1,229 @@
+use std::{collections::HashSet, net::IpAddr, sync::Arc};
+
+use color_eyre::eyre::Report;
+use lazy_static::lazy_static;
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+use crate::{
+    app_state::{EmailDomainRuleStoreType, MxResolverType},
+    domain::{domain_and_parents, Email, EmailDomainRejection, EmailDomainRule},
+    services::data_stores::HashmapEmailDomainRuleStore,
+};
+
+lazy_static! {
+    static ref DISPOSABLE_DOMAINS: HashSet<&'static str> =
+        include_str!("disposable_email_domains.txt")
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+}
+
+/// Decides which email domains may sign up.
+///
+/// Admin rules come first, so an allow rule overrides every other check and a deny rule
+/// rejects the domain outright. Rules and the bundled disposable list also cover subdomains,
+/// with the most specific rule winning. The MX check only runs when a resolver is configured.
+#[derive(Clone)]
+pub struct EmailDomainPolicy {
+    rule_store: EmailDomainRuleStoreType,
+    mx_resolver: Option<MxResolverType>,
+}
+
+#[derive(Debug, Error)]
+pub enum EmailDomainPolicyError {
+    #[error("{0}")]
+    Rejected(EmailDomainRejection),
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl EmailDomainPolicy {
+    pub fn new(rule_store: EmailDomainRuleStoreType) -> Self {
+        Self {
+            rule_store,
+            mx_resolver: None,
+        }
+    }
+
+    pub fn with_mx_resolver(mut self, mx_resolver: MxResolverType) -> Self {
+        self.mx_resolver = Some(mx_resolver);
+        self
+    }
+
+    #[tracing::instrument(name = "Checking email domain policy", skip_all)]
+    pub async fn check(&self, email: &Email) -> Result<(), EmailDomainPolicyError> {
+        let domains = domain_and_parents(email.domain());
+
+        let rules = self
+            .rule_store
+            .read()
+            .await
+            .get_rules(&domains)
+            .await
+            .map_err(|e| EmailDomainPolicyError::UnexpectedError(e.into()))?;
+        let most_specific_rule = domains.iter().find_map(|domain| {
+            rules
+                .iter()
+                .find(|(ruled_domain, _)| ruled_domain == domain)
+                .map(|(_, rule)| *rule)
+        });
+
+        match most_specific_rule {
+            Some(EmailDomainRule::Allow) => return Ok(()),
+            Some(EmailDomainRule::Deny) => {
+                return Err(EmailDomainPolicyError::Rejected(
+                    EmailDomainRejection::Blocked,
+                ))
+            }
+            None => {}
+        }
+
+        if domains
+            .iter()
+            .any(|domain| DISPOSABLE_DOMAINS.contains(domain.as_str()))
+        {
+            return Err(EmailDomainPolicyError::Rejected(
+                EmailDomainRejection::Disposable,
+            ));
+        }
+
+        if let Some(mx_resolver) = &self.mx_resolver {
+            let hosts = mx_resolver
+                .lookup_mx(email.domain())
+                .await
+                .map_err(EmailDomainPolicyError::UnexpectedError)?;
+
+            if !hosts.iter().any(|host| is_plausible_mx_host(host)) {
+                return Err(EmailDomainPolicyError::Rejected(
+                    EmailDomainRejection::NoMailServer,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for EmailDomainPolicy {
+    /// Only the bundled disposable list, with no admin rules and no MX check.
+    fn default() -> Self {
+        Self::new(Arc::new(
+            RwLock::new(HashmapEmailDomainRuleStore::default()),
+        ))
+    }
+}
+
+/// Whether an MX host could actually accept mail: a public hostname rather than the null MX
+/// (".", RFC 7505), an IP address or a local name.
+fn is_plausible_mx_host(host: &str) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+
+    if host.is_empty() || host.parse::<IpAddr>().is_ok() {
+        return false;
+    }
+
+    let labels: Vec<&str> = host.split('.').collect();
+    labels.len() >= 2
+        && !matches!(labels[labels.len() - 1], "localhost" | "local" | "invalid")
+        && labels.iter().all(|label| {
+            (1..=63).contains(&label.len())
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::services::static_mx_resolver::StaticMxResolver;
+
+    fn email(s: &str) -> Email {
+        Email::parse(s.to_owned()).unwrap()
+    }
+
+    fn rejection(result: Result<(), EmailDomainPolicyError>) -> Option<EmailDomainRejection> {
+        match result {
+            Err(EmailDomainPolicyError::Rejected(rejection)) => Some(rejection),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_disposable_domains_and_their_subdomains() {
+        let policy = EmailDomainPolicy::default();
+
+        assert!(policy.check(&email("user@example.com")).await.is_ok());
+        for address in ["user@mailinator.com", "user@eu.Mailinator.com"] {
+            assert_eq!(
+                rejection(policy.check(&email(address)).await),
+                Some(EmailDomainRejection::Disposable)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn most_specific_admin_rule_wins() {
+        let policy = EmailDomainPolicy::default();
+        {
+            let mut rule_store = policy.rule_store.write().await;
+            rule_store
+                .set_rule("example.com", EmailDomainRule::Deny)
+                .await
+                .unwrap();
+            rule_store
+                .set_rule("staff.example.com", EmailDomainRule::Allow)
+                .await
+                .unwrap();
+            rule_store
+                .set_rule("yopmail.com", EmailDomainRule::Allow)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(
+            rejection(policy.check(&email("user@example.com")).await),
+            Some(EmailDomainRejection::Blocked)
+        );
+        assert_eq!(
+            rejection(policy.check(&email("user@eu.example.com")).await),
+            Some(EmailDomainRejection::Blocked)
+        );
+        assert!(policy.check(&email("user@staff.example.com")).await.is_ok());
+        assert!(policy.check(&email("user@yopmail.com")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn requires_plausible_mx_records_when_a_resolver_is_set() {
+        let records = HashMap::from([
+            ("example.com".to_owned(), vec!["mx.example.com.".to_owned()]),
+            ("null.example".to_owned(), vec![".".to_owned()]),
+            ("ip.example".to_owned(), vec!["192.0.2.1".to_owned()]),
+            (
+                "local.example".to_owned(),
+                vec!["mail.localhost".to_owned()],
+            ),
+        ]);
+        let policy =
+            EmailDomainPolicy::default().with_mx_resolver(Arc::new(StaticMxResolver::new(records)));
+
+        assert!(policy.check(&email("user@example.com")).await.is_ok());
+        for address in [
+            "user@null.example",
+            "user@ip.example",
+            "user@local.example",
+            "user@missing.example",
+        ] {
+            assert_eq!(
+                rejection(policy.check(&email(address)).await),
+                Some(EmailDomainRejection::NoMailServer),
+                "{}",
+                address
+            );
+        }
+    }
+}
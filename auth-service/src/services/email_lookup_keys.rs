@@ -0,0 +1,219 @@
+use color_eyre::eyre::{Result, WrapErr};
+use sqlx::{PgPool, SqlitePool};
+
+use crate::domain::Email;
+
+/// Corrects the lookup keys of users and API keys, which the migration that added them could
+/// only fill in with SQL's lower(), and which change when `EMAIL_LOCAL_PART_CASE_FOLDING` does.
+/// Rows whose email no longer parses are logged and left alone. Fails when two users turn out
+/// to share a key, since deciding which account to keep needs a human.
+#[tracing::instrument(name = "Correcting email lookup keys in PostgreSQL", skip_all)]
+pub async fn correct_email_lookup_keys(pool: &PgPool) -> Result<()> {
+    let users = sqlx::query!("SELECT email, email_key FROM users")
+        .fetch_all(pool)
+        .await?;
+    for user in users {
+        let Some(key) = changed_lookup_key(&user.email, &user.email_key) else {
+            continue;
+        };
+        sqlx::query!(
+            "UPDATE users SET email_key = $2 WHERE email = $1",
+            &user.email,
+            key
+        )
+        .execute(pool)
+        .await
+        .wrap_err_with(|| collision_message(&user.email))?;
+    }
+
+    let api_keys = sqlx::query!("SELECT DISTINCT email, email_key FROM api_keys")
+        .fetch_all(pool)
+        .await?;
+    for api_key in api_keys {
+        let Some(key) = changed_lookup_key(&api_key.email, &api_key.email_key) else {
+            continue;
+        };
+        sqlx::query!(
+            "UPDATE api_keys SET email_key = $2 WHERE email = $1",
+            &api_key.email,
+            key
+        )
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Like `correct_email_lookup_keys`, for SQLite.
+#[tracing::instrument(name = "Correcting email lookup keys in SQLite", skip_all)]
+pub async fn correct_sqlite_email_lookup_keys(pool: &SqlitePool) -> Result<()> {
+    let users: Vec<(String, String)> = sqlx::query_as("SELECT email, email_key FROM users")
+        .fetch_all(pool)
+        .await?;
+    for (email, email_key) in users {
+        let Some(key) = changed_lookup_key(&email, &email_key) else {
+            continue;
+        };
+        sqlx::query("UPDATE users SET email_key = ? WHERE email = ?")
+            .bind(key)
+            .bind(&email)
+            .execute(pool)
+            .await
+            .wrap_err_with(|| collision_message(&email))?;
+    }
+
+    let api_keys: Vec<(String, String)> =
+        sqlx::query_as("SELECT DISTINCT email, email_key FROM api_keys")
+            .fetch_all(pool)
+            .await?;
+    for (email, email_key) in api_keys {
+        let Some(key) = changed_lookup_key(&email, &email_key) else {
+            continue;
+        };
+        sqlx::query("UPDATE api_keys SET email_key = ? WHERE email = ?")
+            .bind(key)
+            .bind(&email)
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// The key `email` should be stored under, if it isn't `stored_key`.
+fn changed_lookup_key(email: &str, stored_key: &str) -> Option<String> {
+    match Email::parse(email.to_owned()) {
+        Ok(email) => Some(email.lookup_key()).filter(|key| key != stored_key),
+        Err(e) => {
+            tracing::warn!(error = %e, "Skipping a stored email that no longer parses");
+            None
+        }
+    }
+}
+
+fn collision_message(email: &str) -> String {
+    format!(
+        "Accounts whose emails only differ in case must be merged first: {}",
+        email
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::{
+            data_stores::{ApiKeyStore, UserStore},
+            ApiKey,
+        },
+        get_sqlite_pool,
+        services::data_stores::{SqliteApiKeyStore, SqliteUserStore},
+    };
+
+    /// A database migrated up to, but not including, the migration that added lookup keys.
+    async fn legacy_pool() -> SqlitePool {
+        let pool = get_sqlite_pool("sqlite::memory:").await.unwrap();
+        for migration in sqlx::migrate!("./migrations_sqlite").iter() {
+            if migration.version < LOOKUP_KEYS_MIGRATION
+                && !migration.migration_type.is_down_migration()
+            {
+                sqlx::raw_sql(&migration.sql).execute(&pool).await.unwrap();
+            }
+        }
+        pool
+    }
+
+    async fn add_lookup_keys(pool: &SqlitePool) {
+        sqlx::raw_sql(include_str!(
+            "../../migrations_sqlite/20261019150000_add_email_lookup_keys.up.sql"
+        ))
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    const LOOKUP_KEYS_MIGRATION: i64 = 20261019150000;
+
+    /// Starts with the Kelvin sign, which Rust lowercases to "k" but SQLite's lower() leaves
+    /// alone.
+    const KELVIN_EMAIL: &str = "\u{212A}elvin@example.com";
+
+    #[tokio::test]
+    async fn corrects_keys_sql_gets_wrong() {
+        let pool = legacy_pool().await;
+        sqlx::query("INSERT INTO users (email, password_hash) VALUES (?, 'not-a-real-hash')")
+            .bind(KELVIN_EMAIL)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO api_keys (prefix, email, name, secret_hash, scopes, created_at, expires_at)
+             VALUES ('abc123', ?, 'CI', 'hash', '[]', 0, 4102444800)",
+        )
+        .bind(KELVIN_EMAIL)
+        .execute(&pool)
+        .await
+        .unwrap();
+        add_lookup_keys(&pool).await;
+
+        correct_sqlite_email_lookup_keys(&pool).await.unwrap();
+
+        let email = Email::parse("kelvin@example.com".to_owned()).unwrap();
+        assert!(SqliteUserStore::new(pool.clone())
+            .get_user(&email)
+            .await
+            .is_ok());
+        let keys: Vec<ApiKey> = SqliteApiKeyStore::new(pool)
+            .list_keys(&email)
+            .await
+            .unwrap();
+        assert_eq!(keys.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn skips_emails_that_no_longer_parse() {
+        let pool = legacy_pool().await;
+        sqlx::query(
+            "INSERT INTO users (email, password_hash) VALUES ('not-an-email', 'a'), (?, 'not-a-real-hash')",
+        )
+        .bind(KELVIN_EMAIL)
+        .execute(&pool)
+        .await
+        .unwrap();
+        add_lookup_keys(&pool).await;
+
+        correct_sqlite_email_lookup_keys(&pool).await.unwrap();
+
+        let email = Email::parse("kelvin@example.com".to_owned()).unwrap();
+        assert!(SqliteUserStore::new(pool).get_user(&email).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn refuses_to_correct_colliding_keys() {
+        let pool = legacy_pool().await;
+        sqlx::query(
+            "INSERT INTO users (email, password_hash) VALUES ('kelvin@example.com', 'a'), (?, 'b')",
+        )
+        .bind(KELVIN_EMAIL)
+        .execute(&pool)
+        .await
+        .unwrap();
+        add_lookup_keys(&pool).await;
+
+        let error = correct_sqlite_email_lookup_keys(&pool).await.unwrap_err();
+        assert!(error.to_string().contains("must be merged"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn lookup_keys_are_required() {
+        let pool = legacy_pool().await;
+        add_lookup_keys(&pool).await;
+
+        let result =
+            sqlx::query("INSERT INTO users (email, password_hash) VALUES ('bob@example.com', 'a')")
+                .execute(&pool)
+                .await;
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use color_eyre::eyre::Result;
+use sqlx::{PgPool, SqlitePool};
+use tokio::task::JoinHandle;
+
+use super::data_stores::{
+    PostgresAuthorizationCodeStore, PostgresBannedTokenStore, PostgresMagicLinkStore,
+    PostgresOAuthStateStore, PostgresProofOfWorkStore, PostgresTwoFACodeStore,
+    SqliteAuthorizationCodeStore, SqliteBannedTokenStore, SqliteMagicLinkStore,
+    SqliteOAuthStateStore, SqliteProofOfWorkStore, SqliteTwoFACodeStore,
+};
+
+/// Periodically deletes expired banned tokens, 2FA codes, used magic links, OAuth states,
+/// authorization codes and proof-of-work rows from PostgreSQL.
+pub fn spawn_expired_rows_cleanup(pool: PgPool, interval: Duration) -> JoinHandle<()> {
+    let banned_token_store = PostgresBannedTokenStore::new(pool.clone());
+    let two_fa_code_store = PostgresTwoFACodeStore::new(pool.clone());
+    let magic_link_store = PostgresMagicLinkStore::new(pool.clone());
+    let oauth_state_store = PostgresOAuthStateStore::new(pool.clone());
+    let authorization_code_store = PostgresAuthorizationCodeStore::new(pool.clone());
+    let proof_of_work_store = PostgresProofOfWorkStore::new(pool);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+            log_cleanup("banned tokens", banned_token_store.delete_expired().await);
+            log_cleanup("2FA codes", two_fa_code_store.delete_expired().await);
+            log_cleanup("used magic links", magic_link_store.delete_expired().await);
+            log_cleanup("OAuth states", oauth_state_store.delete_expired().await);
+            log_cleanup(
+                "authorization codes",
+                authorization_code_store.delete_expired().await,
+            );
+            log_cleanup(
+                "proof-of-work rows",
+                proof_of_work_store.delete_expired().await,
+            );
+        }
+    })
+}
+
+/// Periodically deletes expired banned tokens, 2FA codes, used magic links, OAuth states,
+/// authorization codes and proof-of-work rows from SQLite.
+pub fn spawn_sqlite_expired_rows_cleanup(pool: SqlitePool, interval: Duration) -> JoinHandle<()> {
+    let banned_token_store = SqliteBannedTokenStore::new(pool.clone());
+    let two_fa_code_store = SqliteTwoFACodeStore::new(pool.clone());
+    let magic_link_store = SqliteMagicLinkStore::new(pool.clone());
+    let oauth_state_store = SqliteOAuthStateStore::new(pool.clone());
+    let authorization_code_store = SqliteAuthorizationCodeStore::new(pool.clone());
+    let proof_of_work_store = SqliteProofOfWorkStore::new(pool);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+            log_cleanup("banned tokens", banned_token_store.delete_expired().await);
+            log_cleanup("2FA codes", two_fa_code_store.delete_expired().await);
+            log_cleanup("used magic links", magic_link_store.delete_expired().await);
+            log_cleanup("OAuth states", oauth_state_store.delete_expired().await);
+            log_cleanup(
+                "authorization codes",
+                authorization_code_store.delete_expired().await,
+            );
+            log_cleanup(
+                "proof-of-work rows",
+                proof_of_work_store.delete_expired().await,
+            );
+        }
+    })
+}
+
+fn log_cleanup(rows: &str, result: Result<u64>) {
+    match result {
+        Ok(deleted) => tracing::debug!(deleted, "Deleted expired {}", rows),
+        Err(e) => tracing::error!(error.message = %e, "Failed to delete expired {}", rows),
+    }
+}
@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use url::{form_urlencoded, Url};
+
+use crate::utils::constants::{FORWARD_AUTH_ALLOWED_HOSTS, FORWARD_AUTH_LOGIN_URL};
+
+/// Where the login page sends users back to after a forward-auth redirect. It checks the URL
+/// again, since the login page can't know which hosts are allowed.
+pub const FORWARD_AUTH_RETURN_PATH: &str = "/forward-auth/return";
+
+/// Decides where reverse proxies send users who aren't logged in, and where those users may
+/// return to afterwards.
+#[derive(Clone)]
+pub struct ForwardAuth {
+    login_url: String,
+    allowed_hosts: Arc<Vec<String>>,
+}
+
+impl ForwardAuth {
+    /// `login_url` is this service's login page, e.g. `https://auth.example.com/`. Users only
+    /// return to URLs on `allowed_hosts`, where `.example.com` allows any subdomain.
+    pub fn new(login_url: impl Into<String>, allowed_hosts: Vec<String>) -> Self {
+        Self {
+            login_url: login_url.into(),
+            allowed_hosts: Arc::new(
+                allowed_hosts
+                    .into_iter()
+                    .map(|host| host.trim().to_lowercase())
+                    .filter(|host| !host.is_empty())
+                    .collect(),
+            ),
+        }
+    }
+
+    /// The login page, coming back to `return_url` afterwards if it's allowed.
+    pub fn login_redirect(&self, return_url: Option<&str>) -> String {
+        let Some(return_url) = return_url.filter(|url| self.allows_return_url(url)) else {
+            return self.login_url.clone();
+        };
+
+        let return_path = format!(
+            "{}?{}",
+            FORWARD_AUTH_RETURN_PATH,
+            form_urlencoded::Serializer::new(String::new())
+                .append_pair("url", return_url)
+                .finish()
+        );
+        let query = form_urlencoded::Serializer::new(String::new())
+            .append_pair("return_to", &return_path)
+            .finish();
+        format!("{}?{}", self.login_url, query)
+    }
+
+    /// Whether users may be redirected to `url`, so the redirect can't be used for phishing.
+    pub fn allows_return_url(&self, url: &str) -> bool {
+        let Ok(url) = Url::parse(url) else {
+            return false;
+        };
+        if !matches!(url.scheme(), "http" | "https")
+            || !url.username().is_empty()
+            || url.password().is_some()
+        {
+            return false;
+        }
+
+        url.host_str().is_some_and(|host| {
+            self.allowed_hosts.iter().any(|allowed| {
+                host == allowed || (allowed.starts_with('.') && host.ends_with(allowed.as_str()))
+            })
+        })
+    }
+}
+
+impl Default for ForwardAuth {
+    /// Configured by `FORWARD_AUTH_LOGIN_URL` and `FORWARD_AUTH_ALLOWED_HOSTS`.
+    fn default() -> Self {
+        Self::new(
+            FORWARD_AUTH_LOGIN_URL.as_str(),
+            FORWARD_AUTH_ALLOWED_HOSTS.clone(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forward_auth() -> ForwardAuth {
+        ForwardAuth::new(
+            "https://auth.example.com/",
+            vec![
+                "app.example.com".to_owned(),
+                " .Apps.Example.com".to_owned(),
+            ],
+        )
+    }
+
+    #[test]
+    fn only_allows_returning_to_allowed_hosts() {
+        let forward_auth = forward_auth();
+
+        for url in [
+            "https://app.example.com/page?q=1",
+            "http://app.example.com:8080/",
+            "https://wiki.apps.example.com/",
+        ] {
+            assert!(forward_auth.allows_return_url(url), "{}", url);
+        }
+        for url in [
+            "https://evil.com/",
+            "https://app.example.com.evil.com/",
+            "https://apps.example.com/",
+            "https://user@app.example.com/",
+            "javascript://app.example.com/%0aalert(1)",
+            "/relative",
+        ] {
+            assert!(!forward_auth.allows_return_url(url), "{}", url);
+        }
+    }
+
+    #[test]
+    fn login_redirects_only_keep_allowed_return_urls() {
+        let forward_auth = forward_auth();
+
+        assert_eq!(
+            forward_auth.login_redirect(Some("https://app.example.com/a?b=c")),
+            "https://auth.example.com/?return_to=%2Fforward-auth%2Freturn%3Furl%3Dhttps%253A%252F%252Fapp.example.com%252Fa%253Fb%253Dc"
+        );
+        assert_eq!(
+            forward_auth.login_redirect(Some("https://evil.com/")),
+            "https://auth.example.com/"
+        );
+        assert_eq!(
+            forward_auth.login_redirect(None),
+            "https://auth.example.com/"
+        );
+    }
+}
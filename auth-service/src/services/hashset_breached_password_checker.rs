@@ -0,0 +1,50 @@
+use std::collections::HashSet;
+
+use color_eyre::eyre::Result;
+
+use crate::domain::{BreachedPasswordChecker, Password};
+
+/// Keeps the breached passwords in memory. Empty by default, so nothing is rejected.
+#[derive(Default)]
+pub struct HashsetBreachedPasswordChecker {
+    passwords: HashSet<String>,
+}
+
+impl HashsetBreachedPasswordChecker {
+    pub fn new<I, S>(passwords: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            passwords: passwords.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl BreachedPasswordChecker for HashsetBreachedPasswordChecker {
+    async fn is_breached(&self, password: &Password) -> Result<bool> {
+        Ok(self.passwords.contains(password.as_ref()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reports_only_listed_passwords() {
+        let checker = HashsetBreachedPasswordChecker::new(["correct-horse-battery-staple"]);
+
+        let breached = Password::parse("correct-horse-battery-staple".to_owned()).unwrap();
+        let fresh = Password::parse("spoon-galaxy-trumpet-47".to_owned()).unwrap();
+
+        assert!(checker.is_breached(&breached).await.unwrap());
+        assert!(!checker.is_breached(&fresh).await.unwrap());
+        assert!(!HashsetBreachedPasswordChecker::default()
+            .is_breached(&breached)
+            .await
+            .unwrap());
+    }
+}
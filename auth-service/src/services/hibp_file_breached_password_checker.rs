@@ -0,0 +1,189 @@
+use std::{
+    cmp::Ordering,
+    fs::File,
+    io::{self, BufRead, BufReader, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::Result;
+use sha1::{Digest, Sha1};
+
+use crate::domain::{BreachedPasswordChecker, Password};
+
+/// Looks passwords up in a local copy of the Have I Been Pwned password list.
+///
+/// The file must be the SHA-1 version ordered by hash, one `HASH:COUNT` line per password, as
+/// produced by the official downloader. It is binary searched on every check, so even the full
+/// list only takes a few dozen reads and is never loaded into memory.
+#[derive(Debug, Clone)]
+pub struct HibpFileBreachedPasswordChecker {
+    path: PathBuf,
+}
+
+impl HibpFileBreachedPasswordChecker {
+    /// Fails early if the file can't be read, rather than on the first signup.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        File::open(&path)?;
+
+        Ok(Self { path })
+    }
+}
+
+#[async_trait::async_trait]
+impl BreachedPasswordChecker for HibpFileBreachedPasswordChecker {
+    #[tracing::instrument(name = "Checking breached passwords file", skip_all)]
+    async fn is_breached(&self, password: &Password) -> Result<bool> {
+        let hash = hex::encode_upper(Sha1::digest(password.as_ref().as_bytes()));
+        let path = self.path.clone();
+
+        let count = tokio::task::spawn_blocking(move || find_hash(&path, &hash)).await??;
+
+        Ok(count.is_some())
+    }
+}
+
+/// Returns the breach count for `hash`, or `None` if it isn't in the file.
+fn find_hash(path: &Path, hash: &str) -> io::Result<Option<u64>> {
+    let file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+
+    // Invariant: if the hash is in the file, its line starts within [low, high)
+    let mut low = 0;
+    let mut high = len;
+    while low < high {
+        let middle = low + (high - low) / 2;
+
+        let Some((start, end, line)) = line_starting_from(&mut reader, middle)? else {
+            high = middle;
+            continue;
+        };
+        if start >= high {
+            high = middle;
+            continue;
+        }
+
+        let (line_hash, count) = line.split_once(':').unwrap_or((line.as_str(), ""));
+        match line_hash.to_ascii_uppercase().as_str().cmp(hash) {
+            Ordering::Equal => return Ok(Some(count.trim().parse().unwrap_or(1))),
+            Ordering::Less => low = end,
+            Ordering::Greater => high = middle,
+        }
+    }
+
+    Ok(None)
+}
+
+/// Reads the first full line starting at or after `offset`, returning its start and end
+/// offsets along with the line without its line ending.
+fn line_starting_from(
+    reader: &mut BufReader<File>,
+    offset: u64,
+) -> io::Result<Option<(u64, u64, String)>> {
+    let mut buffer = Vec::new();
+
+    let start = if offset == 0 {
+        reader.seek(SeekFrom::Start(0))?;
+        0
+    } else {
+        // Starting one byte early finds the line that begins exactly at `offset` too
+        reader.seek(SeekFrom::Start(offset - 1))?;
+        offset - 1 + reader.read_until(b'\n', &mut buffer)? as u64
+    };
+
+    buffer.clear();
+    let read = reader.read_until(b'\n', &mut buffer)?;
+    if read == 0 {
+        return Ok(None);
+    }
+
+    let line = String::from_utf8_lossy(&buffer)
+        .trim_end_matches(['\r', '\n'])
+        .to_owned();
+
+    Ok(Some((start, start + read as u64, line)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn sha1_hex(password: &str) -> String {
+        hex::encode_upper(Sha1::digest(password.as_bytes()))
+    }
+
+    fn write_hibp_file(passwords: &[&str]) -> PathBuf {
+        let mut lines: Vec<String> = passwords
+            .iter()
+            .enumerate()
+            .map(|(i, password)| format!("{}:{}\r\n", sha1_hex(password), i + 1))
+            .collect();
+        lines.sort();
+
+        let path = std::env::temp_dir().join(format!("hibp-{}.txt", Uuid::new_v4()));
+        let mut file = File::create(&path).unwrap();
+        for line in lines {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+
+        path
+    }
+
+    #[tokio::test]
+    async fn finds_every_listed_password() {
+        let breached: Vec<String> = (0..200)
+            .map(|i| format!("breached-password-{}", i))
+            .collect();
+        let breached: Vec<&str> = breached.iter().map(String::as_str).collect();
+        let path = write_hibp_file(&breached);
+        let checker = HibpFileBreachedPasswordChecker::open(&path).unwrap();
+
+        for password in &breached {
+            let password = Password::parse(password.to_string()).unwrap();
+            assert!(
+                checker.is_breached(&password).await.unwrap(),
+                "{:?}",
+                password
+            );
+        }
+
+        for i in 0..200 {
+            let password = Password::parse(format!("fresh-password-{}", i)).unwrap();
+            assert!(!checker.is_breached(&password).await.unwrap());
+        }
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn returns_the_breach_count() {
+        let path = write_hibp_file(&["password123", "spoon-galaxy-trumpet-47"]);
+
+        assert_eq!(find_hash(&path, &sha1_hex("password123")).unwrap(), Some(1));
+        assert_eq!(
+            find_hash(&path, &sha1_hex("spoon-galaxy-trumpet-47")).unwrap(),
+            Some(2)
+        );
+        assert_eq!(find_hash(&path, &"0".repeat(40)).unwrap(), None);
+        assert_eq!(find_hash(&path, &"F".repeat(40)).unwrap(), None);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn handles_empty_files() {
+        let path = write_hibp_file(&[]);
+        assert_eq!(find_hash(&path, &sha1_hex("password123")).unwrap(), None);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn missing_file_is_rejected_up_front() {
+        assert!(HibpFileBreachedPasswordChecker::open("/nonexistent/pwned-passwords.txt").is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use color_eyre::eyre::Report;
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+use crate::{
+    app_state::MagicLinkStoreType,
+    domain::Email,
+    services::data_stores::HashmapMagicLinkStore,
+    utils::{auth::derive_secret, constants::MAGIC_LINK_BASE_URL},
+};
+
+pub const MAGIC_LINK_TTL_SECONDS: u64 = 900;
+pub const MAGIC_LINK_CALLBACK_PATH: &str = "/login/magic-link/callback";
+// Used links are remembered until they'd fail validation anyway, including leeway
+const USED_LINK_TTL_SECONDS: u64 = MAGIC_LINK_TTL_SECONDS + 60;
+
+/// Issues and redeems passwordless login links.
+///
+/// The link carries a signed token naming the user, so nothing is stored when one is sent.
+/// Redeeming it records its id, which is what makes every link single-use.
+#[derive(Clone)]
+pub struct MagicLinks {
+    store: MagicLinkStoreType,
+    base_url: String,
+}
+
+#[derive(Debug, Error)]
+pub enum MagicLinkError {
+    #[error("Invalid or expired magic link")]
+    InvalidLink,
+    #[error("Magic link was already used")]
+    AlreadyUsed,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MagicLinkClaims {
+    sub: String,
+    jti: String,
+    exp: u64,
+}
+
+impl MagicLinks {
+    /// `base_url` is where users reach this service, e.g. `https://auth.example.com`.
+    pub fn new(store: MagicLinkStoreType, base_url: impl Into<String>) -> Self {
+        Self {
+            store,
+            base_url: base_url.into().trim_end_matches('/').to_owned(),
+        }
+    }
+
+    /// A link that logs in as `email` once, within `MAGIC_LINK_TTL_SECONDS`.
+    pub fn issue(&self, email: &Email) -> Result<String, MagicLinkError> {
+        let claims = MagicLinkClaims {
+            sub: email.as_ref().to_owned(),
+            jti: uuid::Uuid::new_v4().to_string(),
+            exp: now_seconds()? + MAGIC_LINK_TTL_SECONDS,
+        };
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(&magic_link_secret()),
+        )
+        .map_err(|e| MagicLinkError::UnexpectedError(e.into()))?;
+
+        Ok(format!(
+            "{}{}?token={}",
+            self.base_url, MAGIC_LINK_CALLBACK_PATH, token
+        ))
+    }
+
+    /// Checks the token from a link and marks the link as used, returning who it logs in.
+    #[tracing::instrument(name = "Redeeming magic link", skip_all)]
+    pub async fn redeem(&self, token: &str) -> Result<Email, MagicLinkError> {
+        let claims = decode::<MagicLinkClaims>(
+            token,
+            &DecodingKey::from_secret(&magic_link_secret()),
+            &Validation::default(),
+        )
+        .map_err(|_| MagicLinkError::InvalidLink)?
+        .claims;
+        let email = Email::parse(claims.sub).map_err(|_| MagicLinkError::InvalidLink)?;
+
+        let unused = self
+            .store
+            .write()
+            .await
+            .use_link(&claims.jti, USED_LINK_TTL_SECONDS)
+            .await
+            .map_err(|e| MagicLinkError::UnexpectedError(e.into()))?;
+        if !unused {
+            return Err(MagicLinkError::AlreadyUsed);
+        }
+
+        Ok(email)
+    }
+}
+
+impl Default for MagicLinks {
+    /// Remembers used links in memory and builds links from `MAGIC_LINK_BASE_URL`.
+    fn default() -> Self {
+        Self::new(
+            Arc::new(RwLock::new(HashmapMagicLinkStore::default())),
+            MAGIC_LINK_BASE_URL.as_str(),
+        )
+    }
+}
+
+fn magic_link_secret() -> Vec<u8> {
+    derive_secret("magic-link")
+}
+
+fn now_seconds() -> Result<u64, MagicLinkError> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .map_err(|e| MagicLinkError::UnexpectedError(e.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::auth::validate_token;
+
+    use super::*;
+
+    fn magic_links() -> MagicLinks {
+        MagicLinks::new(
+            Arc::new(RwLock::new(HashmapMagicLinkStore::default())),
+            "https://auth.example.com/",
+        )
+    }
+
+    fn email() -> Email {
+        Email::parse("test@example.com".to_owned()).unwrap()
+    }
+
+    fn token(link: &str) -> &str {
+        link.split_once("?token=").unwrap().1
+    }
+
+    #[test]
+    fn links_point_at_the_callback() {
+        let link = magic_links().issue(&email()).unwrap();
+
+        assert!(link.starts_with("https://auth.example.com/login/magic-link/callback?token="));
+    }
+
+    #[tokio::test]
+    async fn links_can_only_be_redeemed_once() {
+        let magic_links = magic_links();
+        let link = magic_links.issue(&email()).unwrap();
+
+        assert_eq!(magic_links.redeem(token(&link)).await.unwrap(), email());
+        assert!(matches!(
+            magic_links.redeem(token(&link)).await,
+            Err(MagicLinkError::AlreadyUsed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_tampered_and_expired_links() {
+        let magic_links = magic_links();
+        let link = magic_links.issue(&email()).unwrap();
+        let tampered = format!("{}x", token(&link));
+
+        assert!(matches!(
+            magic_links.redeem(&tampered).await,
+            Err(MagicLinkError::InvalidLink)
+        ));
+
+        let expired = encode(
+            &Header::default(),
+            &MagicLinkClaims {
+                sub: "test@example.com".to_owned(),
+                jti: "expired".to_owned(),
+                exp: now_seconds().unwrap() - 3600,
+            },
+            &EncodingKey::from_secret(&magic_link_secret()),
+        )
+        .unwrap();
+        assert!(matches!(
+            magic_links.redeem(&expired).await,
+            Err(MagicLinkError::InvalidLink)
+        ));
+    }
+
+    #[tokio::test]
+    async fn link_tokens_are_not_auth_tokens() {
+        let link = magic_links().issue(&email()).unwrap();
+        let banned_token_store = Arc::new(RwLock::new(
+            crate::services::data_stores::HashsetBannedTokenStore::default(),
+        ));
+
+        assert!(validate_token(token(&link), banned_token_store)
+            .await
+            .is_err());
+    }
+}
@@ -1,2 +1,16 @@
+pub mod api_keys;
 pub mod data_stores;
+pub mod email_domain_policy;
+pub mod email_lookup_keys;
+pub mod expired_rows_cleanup;
+pub mod forward_auth;
+pub mod hashset_breached_password_checker;
+pub mod hibp_file_breached_password_checker;
+pub mod magic_link;
 pub mod mock_email_client;
+pub mod oauth;
+pub mod oidc;
+pub mod password_hasher;
+pub mod proof_of_work;
+pub mod static_mx_resolver;
+pub mod user_import;
@@ -0,0 +1,521 @@
+use std::{collections::HashMap, fs, io, path::Path, sync::Arc};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use color_eyre::eyre::Report;
+use rand::{distributions::Alphanumeric, Rng};
+use reqwest::{
+    header::{ACCEPT, USER_AGENT},
+    Url,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+use crate::{
+    app_state::OAuthStateStoreType,
+    domain::{Email, OAuthFailure, OAuthProviderKind, OAuthState, OAuthStateStoreError},
+    services::data_stores::HashmapOAuthStateStore,
+};
+
+/// How long a user has to get through the provider's login and consent screens.
+pub const OAUTH_STATE_TTL_SECONDS: u64 = 600;
+const STATE_CHARS: usize = 32;
+// RFC 7636 allows 43 to 128 characters
+const CODE_VERIFIER_CHARS: usize = 64;
+
+/// One entry of the providers file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct OAuthProviderConfig {
+    /// Used in the login URLs, e.g. `/oauth/github/authorize`.
+    pub name: String,
+    pub kind: OAuthProviderKind,
+    pub client_id: String,
+    pub client_secret: String,
+    /// Must match the redirect URI registered with the provider, which has to lead to
+    /// `/oauth/<name>/callback`.
+    pub redirect_uri: String,
+    /// The endpoints and scopes only need to be set for `oidc` providers.
+    pub authorization_endpoint: Option<String>,
+    pub token_endpoint: Option<String>,
+    pub userinfo_endpoint: Option<String>,
+    pub scopes: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OAuthProvider {
+    name: String,
+    kind: OAuthProviderKind,
+    client_id: String,
+    client_secret: String,
+    redirect_uri: String,
+    authorization_endpoint: Url,
+    token_endpoint: Url,
+    userinfo_endpoint: Url,
+    scopes: Vec<String>,
+}
+
+impl OAuthProvider {
+    pub fn from_config(config: OAuthProviderConfig) -> Result<Self, String> {
+        let valid_name = !config.name.is_empty()
+            && config
+                .name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !valid_name {
+            return Err(format!(
+                "OAuth provider names may only contain a-z, 0-9 and -, got {:?}",
+                config.name
+            ));
+        }
+
+        let (authorization_endpoint, token_endpoint, userinfo_endpoint, scopes) = match config.kind
+        {
+            OAuthProviderKind::Github => (
+                "https://github.com/login/oauth/authorize",
+                "https://github.com/login/oauth/access_token",
+                // The only place GitHub says whether an address is verified
+                "https://api.github.com/user/emails",
+                &["user:email"][..],
+            ),
+            OAuthProviderKind::Google => (
+                "https://accounts.google.com/o/oauth2/v2/auth",
+                "https://oauth2.googleapis.com/token",
+                "https://openidconnect.googleapis.com/v1/userinfo",
+                &["openid", "email"][..],
+            ),
+            OAuthProviderKind::Oidc => ("", "", "", &["openid", "email"][..]),
+        };
+
+        let endpoint = |configured: Option<String>, preset: &str, field: &str| {
+            let endpoint = configured.unwrap_or_else(|| preset.to_owned());
+            Url::parse(&endpoint).map_err(|_| {
+                format!(
+                    "OAuth provider {} needs a valid {}, got {:?}",
+                    config.name, field, endpoint
+                )
+            })
+        };
+
+        Ok(Self {
+            authorization_endpoint: endpoint(
+                config.authorization_endpoint,
+                authorization_endpoint,
+                "authorizationEndpoint",
+            )?,
+            token_endpoint: endpoint(config.token_endpoint, token_endpoint, "tokenEndpoint")?,
+            userinfo_endpoint: endpoint(
+                config.userinfo_endpoint,
+                userinfo_endpoint,
+                "userinfoEndpoint",
+            )?,
+            scopes: config
+                .scopes
+                .unwrap_or_else(|| scopes.iter().map(|scope| scope.to_string()).collect()),
+            name: config.name,
+            kind: config.kind,
+            client_id: config.client_id,
+            client_secret: config.client_secret,
+            redirect_uri: config.redirect_uri,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Reads the providers from a JSON file holding a list of `OAuthProviderConfig`s.
+pub fn load_oauth_providers(path: impl AsRef<Path>) -> io::Result<Vec<OAuthProvider>> {
+    let configs: Vec<OAuthProviderConfig> = serde_json::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    configs
+        .into_iter()
+        .map(OAuthProvider::from_config)
+        .collect::<Result<_, _>>()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Logs users in through external OAuth2 / OpenID Connect providers, using the authorization
+/// code flow with PKCE.
+///
+/// All we take from a provider is a verified email address, which is what links the login to
+/// an account. The access token is only used once, to ask the provider for that address.
+#[derive(Clone)]
+pub struct OAuthClient {
+    providers: Arc<HashMap<String, OAuthProvider>>,
+    state_store: OAuthStateStoreType,
+    http_client: reqwest::Client,
+}
+
+#[derive(Debug, Error)]
+pub enum OAuthError {
+    #[error("Unknown OAuth provider")]
+    UnknownProvider,
+    #[error("Invalid OAuth state")]
+    InvalidState,
+    #[error("OAuth login failed: {}", .0.as_str())]
+    Failed(OAuthFailure),
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GithubEmail {
+    email: String,
+    primary: bool,
+    verified: bool,
+}
+
+impl OAuthClient {
+    pub fn new(
+        providers: Vec<OAuthProvider>,
+        state_store: OAuthStateStoreType,
+    ) -> Result<Self, String> {
+        let mut providers_by_name = HashMap::new();
+        for provider in providers {
+            if providers_by_name.contains_key(provider.name()) {
+                return Err(format!("Duplicate OAuth provider: {}", provider.name()));
+            }
+            providers_by_name.insert(provider.name().to_owned(), provider);
+        }
+
+        Ok(Self {
+            providers: Arc::new(providers_by_name),
+            state_store,
+            http_client: reqwest::Client::new(),
+        })
+    }
+
+    /// Where to send the user to log in with `provider`, and the state they'll come back with.
+    #[tracing::instrument(name = "Starting OAuth login", skip(self))]
+    pub async fn authorization_url(&self, provider: &str) -> Result<(String, String), OAuthError> {
+        let provider = self
+            .providers
+            .get(provider)
+            .ok_or(OAuthError::UnknownProvider)?;
+
+        let state = random_string(STATE_CHARS);
+        let code_verifier = random_string(CODE_VERIFIER_CHARS);
+        let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(&code_verifier));
+
+        self.state_store
+            .write()
+            .await
+            .add_state(
+                &state,
+                OAuthState {
+                    provider: provider.name.clone(),
+                    code_verifier,
+                },
+                OAUTH_STATE_TTL_SECONDS,
+            )
+            .await
+            .map_err(|e| OAuthError::UnexpectedError(e.into()))?;
+
+        let mut url = provider.authorization_endpoint.clone();
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &provider.client_id)
+            .append_pair("redirect_uri", &provider.redirect_uri)
+            .append_pair("scope", &provider.scopes.join(" "))
+            .append_pair("state", &state)
+            .append_pair("code_challenge", &code_challenge)
+            .append_pair("code_challenge_method", "S256");
+
+        Ok((url.to_string(), state))
+    }
+
+    /// Finishes a login the provider sent back with `state`, returning the verified email
+    /// address of the user. `code` is `None` when the provider reported an error instead.
+    #[tracing::instrument(name = "Completing OAuth login", skip_all, fields(provider = provider))]
+    pub async fn complete(
+        &self,
+        provider: &str,
+        state: &str,
+        code: Option<&str>,
+    ) -> Result<Email, OAuthError> {
+        let provider = self
+            .providers
+            .get(provider)
+            .ok_or(OAuthError::UnknownProvider)?;
+
+        let oauth_state = match self.state_store.write().await.take_state(state).await {
+            Ok(oauth_state) => oauth_state,
+            Err(OAuthStateStoreError::StateNotFound) => return Err(OAuthError::InvalidState),
+            Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+        };
+        if oauth_state.provider != provider.name {
+            return Err(OAuthError::InvalidState);
+        }
+
+        let code = code.ok_or(OAuthError::Failed(OAuthFailure::AccessDenied))?;
+        let access_token = self
+            .exchange_code(provider, code, &oauth_state.code_verifier)
+            .await?;
+        let (email, email_verified) = self.fetch_email(provider, &access_token).await?;
+
+        if !email_verified {
+            return Err(OAuthError::Failed(OAuthFailure::EmailNotVerified));
+        }
+
+        Email::parse(email).map_err(|_| OAuthError::Failed(OAuthFailure::ProviderError))
+    }
+
+    async fn exchange_code(
+        &self,
+        provider: &OAuthProvider,
+        code: &str,
+        code_verifier: &str,
+    ) -> Result<String, OAuthError> {
+        let response = self
+            .http_client
+            .post(provider.token_endpoint.clone())
+            // GitHub answers with a form-encoded body otherwise
+            .header(ACCEPT, "application/json")
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", &provider.redirect_uri),
+                ("client_id", &provider.client_id),
+                ("client_secret", &provider.client_secret),
+                ("code_verifier", code_verifier),
+            ])
+            .send()
+            .await
+            .map_err(provider_error)?;
+
+        // Some providers report errors with a 200
+        let token = response
+            .error_for_status()
+            .map_err(provider_error)?
+            .json::<TokenResponse>()
+            .await
+            .map_err(provider_error)?;
+
+        match (token.access_token, token.error) {
+            (Some(access_token), None) => Ok(access_token),
+            (_, error) => {
+                tracing::warn!(error = ?error, "OAuth provider rejected the code exchange");
+                Err(OAuthError::Failed(OAuthFailure::ProviderError))
+            }
+        }
+    }
+
+    /// The user's email address and whether the provider verified it.
+    async fn fetch_email(
+        &self,
+        provider: &OAuthProvider,
+        access_token: &str,
+    ) -> Result<(String, bool), OAuthError> {
+        let response = self
+            .http_client
+            .get(provider.userinfo_endpoint.clone())
+            .bearer_auth(access_token)
+            // GitHub's API rejects requests without one
+            .header(USER_AGENT, "auth-service")
+            .header(ACCEPT, "application/json")
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(provider_error)?;
+
+        match provider.kind {
+            OAuthProviderKind::Github => {
+                let emails = response
+                    .json::<Vec<GithubEmail>>()
+                    .await
+                    .map_err(provider_error)?;
+
+                emails
+                    .into_iter()
+                    .find(|email| email.primary)
+                    .map(|email| (email.email, email.verified))
+                    .ok_or(OAuthError::Failed(OAuthFailure::ProviderError))
+            }
+            OAuthProviderKind::Google | OAuthProviderKind::Oidc => {
+                let userinfo = response
+                    .json::<serde_json::Value>()
+                    .await
+                    .map_err(provider_error)?;
+
+                let email = userinfo["email"]
+                    .as_str()
+                    .ok_or(OAuthError::Failed(OAuthFailure::ProviderError))?;
+                // Some providers send the flag as a string
+                let email_verified = match &userinfo["email_verified"] {
+                    serde_json::Value::Bool(verified) => *verified,
+                    serde_json::Value::String(verified) => verified == "true",
+                    _ => false,
+                };
+
+                Ok((email.to_owned(), email_verified))
+            }
+        }
+    }
+}
+
+impl Default for OAuthClient {
+    /// No providers, so every OAuth login is for an unknown provider.
+    fn default() -> Self {
+        Self::new(
+            Vec::new(),
+            Arc::new(RwLock::new(HashmapOAuthStateStore::default())),
+        )
+        .expect("No providers can't have duplicates")
+    }
+}
+
+fn provider_error(e: reqwest::Error) -> OAuthError {
+    tracing::warn!(error = %e, "OAuth provider request failed");
+    OAuthError::Failed(OAuthFailure::ProviderError)
+}
+
+fn random_string(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(kind: OAuthProviderKind) -> OAuthProviderConfig {
+        OAuthProviderConfig {
+            name: "provider".to_owned(),
+            kind,
+            client_id: "client-id".to_owned(),
+            client_secret: "client-secret".to_owned(),
+            redirect_uri: "https://auth.example.com/oauth/provider/callback".to_owned(),
+            authorization_endpoint: None,
+            token_endpoint: None,
+            userinfo_endpoint: None,
+            scopes: None,
+        }
+    }
+
+    fn oidc_config() -> OAuthProviderConfig {
+        OAuthProviderConfig {
+            authorization_endpoint: Some("https://idp.example.com/authorize?tenant=1".to_owned()),
+            token_endpoint: Some("https://idp.example.com/token".to_owned()),
+            userinfo_endpoint: Some("https://idp.example.com/userinfo".to_owned()),
+            ..config(OAuthProviderKind::Oidc)
+        }
+    }
+
+    fn client(config: OAuthProviderConfig) -> OAuthClient {
+        OAuthClient::new(
+            vec![OAuthProvider::from_config(config).unwrap()],
+            Arc::new(RwLock::new(HashmapOAuthStateStore::default())),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn fills_in_endpoints_for_known_providers() {
+        let provider = OAuthProvider::from_config(config(OAuthProviderKind::Github)).unwrap();
+
+        assert_eq!(
+            provider.token_endpoint.as_str(),
+            "https://github.com/login/oauth/access_token"
+        );
+        assert_eq!(provider.scopes, vec!["user:email"]);
+    }
+
+    #[test]
+    fn oidc_providers_need_their_endpoints() {
+        assert!(OAuthProvider::from_config(config(OAuthProviderKind::Oidc)).is_err());
+        assert!(OAuthProvider::from_config(oidc_config()).is_ok());
+    }
+
+    #[test]
+    fn rejects_names_that_dont_fit_in_a_url() {
+        let config = OAuthProviderConfig {
+            name: "My Provider".to_owned(),
+            ..oidc_config()
+        };
+
+        assert!(OAuthProvider::from_config(config).is_err());
+    }
+
+    #[test]
+    fn rejects_duplicate_providers() {
+        let provider = OAuthProvider::from_config(oidc_config()).unwrap();
+        let result = OAuthClient::new(
+            vec![provider.clone(), provider],
+            Arc::new(RwLock::new(HashmapOAuthStateStore::default())),
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn authorization_url_carries_state_and_pkce_challenge() {
+        let client = client(oidc_config());
+
+        let (url, state) = client.authorization_url("provider").await.unwrap();
+        let url = Url::parse(&url).unwrap();
+        let params: HashMap<_, _> = url.query_pairs().into_owned().collect();
+
+        assert_eq!(url.path(), "/authorize");
+        assert_eq!(params["tenant"], "1");
+        assert_eq!(params["state"], state);
+        assert_eq!(params["scope"], "openid email");
+        assert_eq!(params["code_challenge_method"], "S256");
+
+        let oauth_state = client
+            .state_store
+            .write()
+            .await
+            .take_state(&state)
+            .await
+            .unwrap();
+        assert_eq!(
+            params["code_challenge"],
+            URL_SAFE_NO_PAD.encode(Sha256::digest(&oauth_state.code_verifier))
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_providers_and_states() {
+        let client = client(oidc_config());
+
+        assert!(matches!(
+            client.authorization_url("other").await,
+            Err(OAuthError::UnknownProvider)
+        ));
+        assert!(matches!(
+            client
+                .complete("provider", "unknown-state", Some("code"))
+                .await,
+            Err(OAuthError::InvalidState)
+        ));
+    }
+
+    #[tokio::test]
+    async fn reports_access_denied_without_a_code() {
+        let client = client(oidc_config());
+        let (_, state) = client.authorization_url("provider").await.unwrap();
+
+        assert!(matches!(
+            client.complete("provider", &state, None).await,
+            Err(OAuthError::Failed(OAuthFailure::AccessDenied))
+        ));
+        // The state is used up either way
+        assert!(matches!(
+            client.complete("provider", &state, Some("code")).await,
+            Err(OAuthError::InvalidState)
+        ));
+    }
+}
@@ -0,0 +1,695 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::Path,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use color_eyre::eyre::Report;
+use jsonwebtoken::{encode, Algorithm, DecodingKey, EncodingKey, Header};
+use rand::{distributions::Alphanumeric, Rng};
+use reqwest::Url;
+use ring::{
+    rand::SystemRandom,
+    signature::{Ed25519KeyPair, KeyPair},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+use crate::{
+    app_state::AuthorizationCodeStoreType,
+    domain::{parse_service_scopes, AuthorizationCode, AuthorizationCodeStoreError},
+    services::data_stores::HashmapAuthorizationCodeStore,
+    utils::{
+        auth::{generate_access_token, generate_service_token, secrets_match, TOKEN_TTL_SECONDS},
+        constants::OIDC_ISSUER,
+    },
+};
+
+/// Clients redeem codes right after the redirect, so they don't need to live long.
+pub const AUTHORIZATION_CODE_TTL_SECONDS: u64 = 60;
+pub const SUPPORTED_SCOPES: [&str; 2] = ["openid", "email"];
+const AUTHORIZATION_CODE_CHARS: usize = 32;
+
+/// One entry of the clients file. Apps logging users in have redirect URIs, backend services
+/// getting tokens for themselves have audiences, and clients that only introspect tokens need
+/// neither.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct OidcClientConfig {
+    pub client_id: String,
+    /// Hex-encoded SHA-256 of the client secret, e.g. from `printf %s "$SECRET" | sha256sum`.
+    /// Secrets are random and long, so they don't need a slow hash like passwords do.
+    pub client_secret_hash: String,
+    /// Where users may be sent back to, compared exactly.
+    #[serde(default)]
+    pub redirect_uris: Vec<String>,
+    /// The services the client may get tokens for with the client credentials grant.
+    #[serde(default)]
+    pub audiences: Vec<String>,
+    /// The scopes those tokens may carry.
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+/// An app that logs its users in through us, or a backend service getting tokens of its own.
+#[derive(Debug, Clone)]
+pub struct OidcClient {
+    client_id: String,
+    client_secret_hash: Vec<u8>,
+    redirect_uris: Vec<String>,
+    audiences: Vec<String>,
+    scopes: Vec<String>,
+}
+
+impl OidcClient {
+    pub fn from_config(config: OidcClientConfig) -> Result<Self, String> {
+        let client_secret_hash = hex::decode(&config.client_secret_hash)
+            .ok()
+            .filter(|hash| hash.len() == 32)
+            .ok_or_else(|| {
+                format!(
+                    "The client secret hash of OIDC client {} must be a hex-encoded SHA-256",
+                    config.client_id
+                )
+            })?;
+
+        for redirect_uri in &config.redirect_uris {
+            let valid = Url::parse(redirect_uri)
+                .map(|url| url.fragment().is_none())
+                .unwrap_or(false);
+            if !valid {
+                return Err(format!(
+                    "OIDC client {} has an invalid redirect URI: {:?}",
+                    config.client_id, redirect_uri
+                ));
+            }
+        }
+
+        let scopes = parse_service_scopes(config.scopes).ok_or_else(|| {
+            format!(
+                "OIDC client {} has an invalid scope, scopes are lowercase words such as read:users \
+                 outside the user: namespace",
+                config.client_id
+            )
+        })?;
+
+        Ok(Self {
+            client_id: config.client_id,
+            client_secret_hash,
+            redirect_uris: config.redirect_uris,
+            audiences: config.audiences,
+            scopes,
+        })
+    }
+
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    pub fn verify_secret(&self, client_secret: &str) -> bool {
+        secrets_match(&Sha256::digest(client_secret), &self.client_secret_hash)
+    }
+
+    pub fn allows_redirect_uri(&self, redirect_uri: &str) -> bool {
+        self.redirect_uris.iter().any(|uri| uri == redirect_uri)
+    }
+}
+
+/// Reads the clients from a JSON file holding a list of `OidcClientConfig`s.
+pub fn load_oidc_clients(path: impl AsRef<Path>) -> io::Result<Vec<OidcClient>> {
+    let configs: Vec<OidcClientConfig> = serde_json::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    configs
+        .into_iter()
+        .map(OidcClient::from_config)
+        .collect::<Result<_, _>>()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// The Ed25519 key id_tokens are signed with. Clients fetch the public half from the JWKS
+/// endpoint.
+#[derive(Clone)]
+pub struct SigningKey {
+    key_id: String,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    public_key: Vec<u8>,
+}
+
+impl SigningKey {
+    /// Reads a PKCS#8 PEM file, e.g. from `openssl genpkey -algorithm ed25519`.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let pem = pem::parse(fs::read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if pem.tag() != "PRIVATE KEY" {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Expected a PKCS#8 private key",
+            ));
+        }
+
+        Self::from_pkcs8(pem.contents()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// A fresh key. Tokens it signs can't be verified once the process exits, so this is only
+    /// meant for development and tests.
+    pub fn generate() -> Self {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .expect("Failed to generate a signing key");
+        Self::from_pkcs8(pkcs8.as_ref()).expect("Generated keys are valid")
+    }
+
+    fn from_pkcs8(pkcs8: &[u8]) -> Result<Self, String> {
+        let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(pkcs8)
+            .map_err(|e| format!("Invalid Ed25519 key: {}", e))?;
+        let public_key = key_pair.public_key().as_ref().to_vec();
+
+        Ok(Self {
+            // Stable for the same key, so clients' key caches survive restarts
+            key_id: URL_SAFE_NO_PAD.encode(&Sha256::digest(&public_key)[..12]),
+            encoding_key: EncodingKey::from_ed_der(pkcs8),
+            decoding_key: DecodingKey::from_ed_der(&public_key),
+            public_key,
+        })
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    pub fn decoding_key(&self) -> &DecodingKey {
+        &self.decoding_key
+    }
+
+    pub fn jwk(&self) -> serde_json::Value {
+        serde_json::json!({
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "use": "sig",
+            "alg": "EdDSA",
+            "kid": self.key_id,
+            "x": URL_SAFE_NO_PAD.encode(&self.public_key),
+        })
+    }
+}
+
+/// Lets registered apps log users in through us with OpenID Connect, using the authorization
+/// code flow with PKCE. Users authenticate with the usual login and 2FA flows, and the access
+/// tokens handed out are bound to the client they were issued to, so they can't pass for the
+/// user's own auth tokens.
+#[derive(Clone)]
+pub struct OidcProvider {
+    issuer: String,
+    clients: Arc<HashMap<String, OidcClient>>,
+    code_store: AuthorizationCodeStoreType,
+    signing_key: SigningKey,
+}
+
+#[derive(Debug, Error)]
+pub enum OidcError {
+    #[error("Invalid client")]
+    InvalidClient,
+    #[error("Invalid grant")]
+    InvalidGrant,
+    #[error("Client is not allowed to use this grant")]
+    UnauthorizedClient,
+    #[error("Scope not allowed for this client")]
+    InvalidScope,
+    #[error("Audience not allowed for this client")]
+    InvalidTarget,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: u64,
+    pub id_token: String,
+    pub scope: String,
+}
+
+/// What the client credentials grant answers with. There's no user, so there's no id_token.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ServiceTokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: u64,
+    pub scope: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: String,
+    pub exp: u64,
+    pub iat: u64,
+    pub auth_time: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+impl OidcProvider {
+    /// `issuer` is where clients reach this service, e.g. `https://auth.example.com`.
+    pub fn new(
+        issuer: impl Into<String>,
+        clients: Vec<OidcClient>,
+        code_store: AuthorizationCodeStoreType,
+        signing_key: SigningKey,
+    ) -> Result<Self, String> {
+        let mut clients_by_id = HashMap::new();
+        for client in clients {
+            if clients_by_id.contains_key(client.client_id()) {
+                return Err(format!("Duplicate OIDC client: {}", client.client_id()));
+            }
+            clients_by_id.insert(client.client_id().to_owned(), client);
+        }
+
+        Ok(Self {
+            issuer: issuer.into().trim_end_matches('/').to_owned(),
+            clients: Arc::new(clients_by_id),
+            code_store,
+            signing_key,
+        })
+    }
+
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    pub fn signing_key(&self) -> &SigningKey {
+        &self.signing_key
+    }
+
+    pub fn client(&self, client_id: &str) -> Option<&OidcClient> {
+        self.clients.get(client_id)
+    }
+
+    pub fn authenticate_client(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+    ) -> Result<&OidcClient, OidcError> {
+        self.client(client_id)
+            .filter(|client| client.verify_secret(client_secret))
+            .ok_or(OidcError::InvalidClient)
+    }
+
+    /// Stores what the user authorized and returns the code the client can redeem it with.
+    #[tracing::instrument(name = "Issuing authorization code", skip_all)]
+    pub async fn issue_code(
+        &self,
+        authorization_code: AuthorizationCode,
+    ) -> Result<String, OidcError> {
+        let code: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(AUTHORIZATION_CODE_CHARS)
+            .map(char::from)
+            .collect();
+
+        self.code_store
+            .write()
+            .await
+            .add_code(&code, authorization_code, AUTHORIZATION_CODE_TTL_SECONDS)
+            .await
+            .map_err(|e| OidcError::UnexpectedError(e.into()))?;
+
+        Ok(code)
+    }
+
+    /// Redeems a code for an access token and an id_token, checking it was issued to `client`
+    /// for `redirect_uri` and that `code_verifier` matches its PKCE challenge.
+    #[tracing::instrument(name = "Redeeming authorization code", skip_all)]
+    pub async fn redeem_code(
+        &self,
+        client: &OidcClient,
+        code: &str,
+        redirect_uri: &str,
+        code_verifier: &str,
+    ) -> Result<TokenResponse, OidcError> {
+        let authorization_code = match self.code_store.write().await.take_code(code).await {
+            Ok(authorization_code) => authorization_code,
+            Err(AuthorizationCodeStoreError::CodeNotFound) => return Err(OidcError::InvalidGrant),
+            Err(e) => return Err(OidcError::UnexpectedError(e.into())),
+        };
+
+        let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier));
+        if authorization_code.client_id != client.client_id
+            || authorization_code.redirect_uri != redirect_uri
+            || authorization_code.code_challenge != code_challenge
+        {
+            return Err(OidcError::InvalidGrant);
+        }
+
+        let access_token = generate_access_token(
+            &authorization_code.subject,
+            &client.client_id,
+            &authorization_code.scopes,
+        )
+        .map_err(|e| OidcError::UnexpectedError(e.into()))?;
+        let id_token = self.id_token(&authorization_code)?;
+
+        Ok(TokenResponse {
+            access_token,
+            token_type: "Bearer".to_owned(),
+            expires_in: TOKEN_TTL_SECONDS as u64,
+            id_token,
+            scope: authorization_code.scopes.join(" "),
+        })
+    }
+
+    /// Issues `client` a token of its own for `audience`, which can be left out when the client
+    /// only has one. Without a `scope`, the token gets all of the client's scopes.
+    #[tracing::instrument(name = "Issuing service token", skip_all)]
+    pub fn issue_service_token(
+        &self,
+        client: &OidcClient,
+        audience: Option<&str>,
+        scope: Option<&str>,
+    ) -> Result<ServiceTokenResponse, OidcError> {
+        if client.audiences.is_empty() {
+            return Err(OidcError::UnauthorizedClient);
+        }
+
+        let audience = match (audience, client.audiences.as_slice()) {
+            (Some(audience), audiences) if audiences.iter().any(|a| a == audience) => audience,
+            (None, [audience]) => audience,
+            _ => return Err(OidcError::InvalidTarget),
+        };
+
+        let scopes = match scope {
+            Some(scope) => {
+                let requested: Vec<String> = scope.split_whitespace().map(str::to_owned).collect();
+                let scopes = parse_service_scopes(requested).ok_or(OidcError::InvalidScope)?;
+                if !scopes.iter().all(|scope| client.scopes.contains(scope)) {
+                    return Err(OidcError::InvalidScope);
+                }
+                scopes
+            }
+            None => client.scopes.clone(),
+        };
+
+        let access_token = generate_service_token(&client.client_id, audience, &scopes)
+            .map_err(|e| OidcError::UnexpectedError(e.into()))?;
+
+        Ok(ServiceTokenResponse {
+            access_token,
+            token_type: "Bearer".to_owned(),
+            expires_in: TOKEN_TTL_SECONDS as u64,
+            scope: scopes.join(" "),
+        })
+    }
+
+    fn id_token(&self, authorization_code: &AuthorizationCode) -> Result<String, OidcError> {
+        let now = now_seconds()?;
+        let email = authorization_code
+            .scopes
+            .iter()
+            .any(|scope| scope == "email")
+            .then(|| authorization_code.subject.clone());
+
+        let claims = IdTokenClaims {
+            iss: self.issuer.clone(),
+            sub: authorization_code.subject.clone(),
+            aud: authorization_code.client_id.clone(),
+            exp: now + TOKEN_TTL_SECONDS as u64,
+            iat: now,
+            auth_time: authorization_code.auth_time,
+            nonce: authorization_code.nonce.clone(),
+            email,
+        };
+
+        let mut header = Header::new(Algorithm::EdDSA);
+        header.kid = Some(self.signing_key.key_id.clone());
+
+        encode(&header, &claims, &self.signing_key.encoding_key)
+            .map_err(|e| OidcError::UnexpectedError(e.into()))
+    }
+
+    /// The discovery document served at `/.well-known/openid-configuration`.
+    pub fn discovery_document(&self) -> serde_json::Value {
+        serde_json::json!({
+            "issuer": self.issuer,
+            "authorization_endpoint": format!("{}/oauth/authorize", self.issuer),
+            "token_endpoint": format!("{}/oauth/token", self.issuer),
+            "userinfo_endpoint": format!("{}/oauth/userinfo", self.issuer),
+            "introspection_endpoint": format!("{}/oauth/introspect", self.issuer),
+            "jwks_uri": format!("{}/.well-known/jwks.json", self.issuer),
+            "response_types_supported": ["code"],
+            "grant_types_supported": ["authorization_code", "client_credentials"],
+            "subject_types_supported": ["public"],
+            "id_token_signing_alg_values_supported": ["EdDSA"],
+            "scopes_supported": SUPPORTED_SCOPES,
+            "token_endpoint_auth_methods_supported": ["client_secret_basic", "client_secret_post"],
+            "code_challenge_methods_supported": ["S256"],
+            "claims_supported": ["iss", "sub", "aud", "exp", "iat", "auth_time", "nonce", "email"],
+        })
+    }
+
+    pub fn jwks(&self) -> serde_json::Value {
+        serde_json::json!({ "keys": [self.signing_key.jwk()] })
+    }
+}
+
+impl Default for OidcProvider {
+    /// No clients, and a key that only lasts as long as the process.
+    fn default() -> Self {
+        Self::new(
+            OIDC_ISSUER.as_str(),
+            Vec::new(),
+            Arc::new(RwLock::new(HashmapAuthorizationCodeStore::default())),
+            SigningKey::generate(),
+        )
+        .expect("No clients can't have duplicates")
+    }
+}
+
+fn now_seconds() -> Result<u64, OidcError> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .map_err(|e| OidcError::UnexpectedError(e.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{decode, Validation};
+
+    use super::*;
+
+    const CLIENT_SECRET: &str = "client-secret";
+    const REDIRECT_URI: &str = "https://app.example.com/callback";
+    const CODE_VERIFIER: &str = "a-code-verifier-that-is-long-enough-for-pkce-43";
+
+    fn client_config() -> OidcClientConfig {
+        OidcClientConfig {
+            client_id: "app".to_owned(),
+            client_secret_hash: hex::encode(Sha256::digest(CLIENT_SECRET)),
+            redirect_uris: vec![REDIRECT_URI.to_owned()],
+            ..Default::default()
+        }
+    }
+
+    fn service_config(audiences: &[&str]) -> OidcClientConfig {
+        OidcClientConfig {
+            client_id: "service".to_owned(),
+            client_secret_hash: hex::encode(Sha256::digest(CLIENT_SECRET)),
+            audiences: audiences.iter().map(|a| a.to_string()).collect(),
+            scopes: vec!["read:users".to_owned(), "write:users".to_owned()],
+            ..Default::default()
+        }
+    }
+
+    fn provider() -> OidcProvider {
+        OidcProvider::new(
+            "https://auth.example.com/",
+            vec![OidcClient::from_config(client_config()).unwrap()],
+            Arc::new(RwLock::new(HashmapAuthorizationCodeStore::default())),
+            SigningKey::generate(),
+        )
+        .unwrap()
+    }
+
+    fn authorization_code() -> AuthorizationCode {
+        AuthorizationCode {
+            client_id: "app".to_owned(),
+            redirect_uri: REDIRECT_URI.to_owned(),
+            subject: "test@example.com".to_owned(),
+            scopes: vec!["openid".to_owned(), "email".to_owned()],
+            nonce: Some("nonce".to_owned()),
+            code_challenge: URL_SAFE_NO_PAD.encode(Sha256::digest(CODE_VERIFIER)),
+            auth_time: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_clients() {
+        let config = OidcClientConfig {
+            client_secret_hash: "not-hex".to_owned(),
+            ..client_config()
+        };
+        assert!(OidcClient::from_config(config).is_err());
+
+        let config = OidcClientConfig {
+            redirect_uris: vec!["https://app.example.com/callback#fragment".to_owned()],
+            ..client_config()
+        };
+        assert!(OidcClient::from_config(config).is_err());
+
+        let config = OidcClientConfig {
+            scopes: vec!["Read Users".to_owned()],
+            ..service_config(&["api"])
+        };
+        assert!(OidcClient::from_config(config).is_err());
+    }
+
+    #[test]
+    fn authenticates_clients_by_secret() {
+        let provider = provider();
+
+        assert!(provider.authenticate_client("app", CLIENT_SECRET).is_ok());
+        assert!(provider.authenticate_client("app", "wrong-secret").is_err());
+        assert!(provider
+            .authenticate_client("other", CLIENT_SECRET)
+            .is_err());
+    }
+
+    #[test]
+    fn redirect_uris_must_match_exactly() {
+        let client = OidcClient::from_config(client_config()).unwrap();
+
+        assert!(client.allows_redirect_uri(REDIRECT_URI));
+        assert!(!client.allows_redirect_uri("https://app.example.com/callback/"));
+        assert!(!client.allows_redirect_uri("https://app.example.com/callback?next=/"));
+    }
+
+    #[tokio::test]
+    async fn codes_redeem_for_a_signed_id_token_once() {
+        let provider = provider();
+        let client = provider.client("app").unwrap().clone();
+        let code = provider.issue_code(authorization_code()).await.unwrap();
+
+        let tokens = provider
+            .redeem_code(&client, &code, REDIRECT_URI, CODE_VERIFIER)
+            .await
+            .unwrap();
+        assert_eq!(tokens.token_type, "Bearer");
+        assert_eq!(tokens.scope, "openid email");
+
+        let header = jsonwebtoken::decode_header(&tokens.id_token).unwrap();
+        assert_eq!(header.kid.as_deref(), Some(provider.signing_key().key_id()));
+
+        let mut validation = Validation::new(Algorithm::EdDSA);
+        validation.set_audience(&["app"]);
+        validation.set_issuer(&["https://auth.example.com"]);
+        let claims = decode::<IdTokenClaims>(
+            &tokens.id_token,
+            provider.signing_key().decoding_key(),
+            &validation,
+        )
+        .unwrap()
+        .claims;
+        assert_eq!(claims.sub, "test@example.com");
+        assert_eq!(claims.email.as_deref(), Some("test@example.com"));
+        assert_eq!(claims.nonce.as_deref(), Some("nonce"));
+        assert_eq!(claims.auth_time, 1_700_000_000);
+
+        assert!(matches!(
+            provider
+                .redeem_code(&client, &code, REDIRECT_URI, CODE_VERIFIER)
+                .await,
+            Err(OidcError::InvalidGrant)
+        ));
+    }
+
+    #[tokio::test]
+    async fn codes_need_the_matching_verifier_and_redirect_uri() {
+        let provider = provider();
+        let client = provider.client("app").unwrap().clone();
+
+        let code = provider.issue_code(authorization_code()).await.unwrap();
+        assert!(matches!(
+            provider
+                .redeem_code(&client, &code, REDIRECT_URI, "another-verifier")
+                .await,
+            Err(OidcError::InvalidGrant)
+        ));
+
+        let code = provider.issue_code(authorization_code()).await.unwrap();
+        assert!(matches!(
+            provider
+                .redeem_code(&client, &code, "https://evil.example.com/", CODE_VERIFIER)
+                .await,
+            Err(OidcError::InvalidGrant)
+        ));
+    }
+
+    #[test]
+    fn service_tokens_are_limited_to_the_clients_audiences_and_scopes() {
+        let provider = provider();
+        let client = OidcClient::from_config(service_config(&["api"])).unwrap();
+
+        let tokens = provider
+            .issue_service_token(&client, None, Some("read:users"))
+            .unwrap();
+        assert_eq!(tokens.token_type, "Bearer");
+        assert_eq!(tokens.scope, "read:users");
+
+        let tokens = provider
+            .issue_service_token(&client, Some("api"), None)
+            .unwrap();
+        assert_eq!(tokens.scope, "read:users write:users");
+
+        assert!(matches!(
+            provider.issue_service_token(&client, Some("other"), None),
+            Err(OidcError::InvalidTarget)
+        ));
+        assert!(matches!(
+            provider.issue_service_token(&client, None, Some("admin")),
+            Err(OidcError::InvalidScope)
+        ));
+    }
+
+    #[test]
+    fn service_tokens_need_an_audience() {
+        let provider = provider();
+
+        let client = OidcClient::from_config(service_config(&["api", "billing"])).unwrap();
+        assert!(matches!(
+            provider.issue_service_token(&client, None, None),
+            Err(OidcError::InvalidTarget)
+        ));
+        assert!(provider
+            .issue_service_token(&client, Some("billing"), None)
+            .is_ok());
+
+        let client = provider.client("app").unwrap();
+        assert!(matches!(
+            provider.issue_service_token(client, None, None),
+            Err(OidcError::UnauthorizedClient)
+        ));
+    }
+
+    #[test]
+    fn publishes_the_public_key() {
+        let provider = provider();
+        let jwks = provider.jwks();
+
+        assert_eq!(jwks["keys"][0]["kid"], provider.signing_key().key_id());
+        assert_eq!(jwks["keys"][0]["crv"], "Ed25519");
+        assert_eq!(
+            provider.discovery_document()["jwks_uri"],
+            "https://auth.example.com/.well-known/jwks.json"
+        );
+    }
+}
@@ -0,0 +1,298 @@
+use argon2::{
+    password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher, Version,
+};
+use color_eyre::eyre::{eyre, Result};
+use pbkdf2::Pbkdf2;
+use scrypt::Scrypt;
+
+use crate::{
+    domain::Password,
+    utils::constants::{ARGON2_ITERATIONS, ARGON2_MEMORY_COST_KIB, ARGON2_PARALLELISM},
+};
+
+/// Hashes and verifies passwords with argon2id, off the async runtime.
+///
+/// Every `UserStore` goes through this, so the in-memory stores hash exactly like production.
+/// Hashes imported from older systems (bcrypt, PBKDF2 and scrypt) can still be verified, and
+/// are reported by `needs_rehash` so they get upgraded to argon2id on the next login.
+#[derive(Debug, Clone)]
+pub struct Argon2PasswordHasher {
+    params: Params,
+}
+
+impl Argon2PasswordHasher {
+    pub fn new(params: Params) -> Self {
+        Self { params }
+    }
+
+    #[tracing::instrument(name = "Computing password hash", skip_all)]
+    pub async fn compute_password_hash(&self, password: String) -> Result<String> {
+        let current_span: tracing::Span = tracing::Span::current();
+        let params = self.params.clone();
+
+        let result = tokio::task::spawn_blocking(move || {
+            current_span.in_scope(|| {
+                let salt: SaltString = SaltString::generate(&mut rand::thread_rng());
+                let password_hash = Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                    .hash_password(password.as_bytes(), &salt)?
+                    .to_string();
+
+                Ok(password_hash)
+            })
+        })
+        .await;
+
+        result?
+    }
+
+    #[tracing::instrument(name = "Verify password hash", skip_all)]
+    pub async fn verify_password_hash(
+        &self,
+        expected_password_hash: String,
+        password_candidate: String,
+    ) -> Result<()> {
+        let current_span: tracing::Span = tracing::Span::current();
+
+        let result = tokio::task::spawn_blocking(move || {
+            current_span.in_scope(|| {
+                verify_any_format(&expected_password_hash, password_candidate.as_bytes())
+            })
+        })
+        .await;
+
+        result?
+    }
+
+    /// Verifies `password`, falling back to the password as typed for hashes made before
+    /// passwords were NFKC-normalized. Returns whether only that fallback matched, in which case
+    /// the hash should be recomputed from the normalized password.
+    pub async fn verify_password(
+        &self,
+        expected_password_hash: &str,
+        password: &Password,
+    ) -> Result<bool> {
+        let result = self
+            .verify_password_hash(
+                expected_password_hash.to_owned(),
+                password.as_ref().to_owned(),
+            )
+            .await;
+
+        match (result, password.as_typed()) {
+            (Ok(()), _) => Ok(false),
+            (Err(_), Some(as_typed)) => self
+                .verify_password_hash(expected_password_hash.to_owned(), as_typed.to_owned())
+                .await
+                .map(|()| true),
+            (Err(e), None) => Err(e),
+        }
+    }
+
+    /// Whether `password_hash` is in one of the formats `verify_password_hash` understands.
+    pub fn is_supported_hash(&self, password_hash: &str) -> bool {
+        is_bcrypt_hash(password_hash)
+            || PasswordHash::new(password_hash)
+                .map(|hash| SUPPORTED_PHC_ALGORITHMS.contains(&hash.algorithm.as_str()))
+                .unwrap_or(false)
+    }
+
+    /// Whether `password_hash` was produced with a different algorithm, version or cost
+    /// parameters than the ones currently configured, and should be recomputed.
+    pub fn needs_rehash(&self, password_hash: &str) -> bool {
+        let Ok(password_hash) = PasswordHash::new(password_hash) else {
+            return true;
+        };
+
+        if password_hash.algorithm != Algorithm::Argon2id.ident()
+            || password_hash.version != Some(Version::V0x13.into())
+        {
+            return true;
+        }
+
+        match Params::try_from(&password_hash) {
+            Ok(params) => {
+                params.m_cost() != self.params.m_cost()
+                    || params.t_cost() != self.params.t_cost()
+                    || params.p_cost() != self.params.p_cost()
+            }
+            Err(_) => true,
+        }
+    }
+}
+
+impl Default for Argon2PasswordHasher {
+    /// Uses the parameters configured through the `ARGON2_*` environment variables.
+    fn default() -> Self {
+        let params = Params::new(
+            *ARGON2_MEMORY_COST_KIB,
+            *ARGON2_ITERATIONS,
+            *ARGON2_PARALLELISM,
+            None,
+        )
+        .expect("Invalid argon2 parameters");
+
+        Self::new(params)
+    }
+}
+
+const SUPPORTED_PHC_ALGORITHMS: &[&str] = &[
+    "argon2id",
+    "argon2i",
+    "argon2d",
+    "pbkdf2-sha256",
+    "pbkdf2-sha512",
+    "scrypt",
+];
+
+// bcrypt hashes use the modular crypt format (`$2b$12$...`) rather than PHC strings
+fn is_bcrypt_hash(password_hash: &str) -> bool {
+    password_hash.parse::<bcrypt::HashParts>().is_ok()
+}
+
+fn verify_any_format(password_hash: &str, password_candidate: &[u8]) -> Result<()> {
+    if is_bcrypt_hash(password_hash) {
+        return match bcrypt::verify(password_candidate, password_hash)? {
+            true => Ok(()),
+            false => Err(eyre!("Password does not match bcrypt hash")),
+        };
+    }
+
+    PasswordHash::new(password_hash)?
+        .verify_password(&[&Argon2::default(), &Pbkdf2, &Scrypt], password_candidate)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn hashes_with_argon2id_and_the_configured_params() {
+        let hasher = Argon2PasswordHasher::new(Params::new(8192, 1, 1, None).unwrap());
+
+        let hash = hasher
+            .compute_password_hash("password123".to_owned())
+            .await
+            .unwrap();
+        let parsed = PasswordHash::new(&hash).unwrap();
+
+        assert_eq!(parsed.algorithm, Algorithm::Argon2id.ident());
+        assert_eq!(Params::try_from(&parsed).unwrap().m_cost(), 8192);
+    }
+
+    #[tokio::test]
+    async fn verifies_only_the_original_password() {
+        let hasher = Argon2PasswordHasher::default();
+        let hash = hasher
+            .compute_password_hash("password123".to_owned())
+            .await
+            .unwrap();
+
+        assert!(hasher
+            .verify_password_hash(hash.clone(), "password123".to_owned())
+            .await
+            .is_ok());
+        assert!(hasher
+            .verify_password_hash(hash, "wrongpassword".to_owned())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn hashes_with_other_params_need_rehash() {
+        let old_hasher = Argon2PasswordHasher::new(Params::new(8192, 1, 1, None).unwrap());
+        let hasher = Argon2PasswordHasher::new(Params::new(8192, 2, 1, None).unwrap());
+
+        let old_hash = old_hasher
+            .compute_password_hash("password123".to_owned())
+            .await
+            .unwrap();
+        let hash = hasher
+            .compute_password_hash("password123".to_owned())
+            .await
+            .unwrap();
+
+        assert!(hasher.needs_rehash(&old_hash));
+        assert!(!hasher.needs_rehash(&hash));
+        assert!(hasher.needs_rehash("not a hash"));
+    }
+
+    #[tokio::test]
+    async fn verifies_legacy_hashes_and_flags_them_for_rehash() {
+        let hasher = Argon2PasswordHasher::default();
+        let salt = SaltString::generate(&mut rand::thread_rng());
+        let legacy_hashes = [
+            bcrypt::hash("password123", 4).unwrap(),
+            Pbkdf2
+                .hash_password_customized(
+                    b"password123",
+                    Some(pbkdf2::Algorithm::Pbkdf2Sha256.ident()),
+                    None,
+                    pbkdf2::Params {
+                        rounds: 1000,
+                        output_length: 32,
+                    },
+                    &salt,
+                )
+                .unwrap()
+                .to_string(),
+            Scrypt
+                .hash_password_customized(
+                    b"password123",
+                    None,
+                    None,
+                    scrypt::Params::new(4, 8, 1, 32).unwrap(),
+                    &salt,
+                )
+                .unwrap()
+                .to_string(),
+        ];
+
+        for hash in legacy_hashes {
+            assert!(hasher.is_supported_hash(&hash), "{}", hash);
+            assert!(hasher.needs_rehash(&hash));
+            assert!(hasher
+                .verify_password_hash(hash.clone(), "password123".to_owned())
+                .await
+                .is_ok());
+            assert!(hasher
+                .verify_password_hash(hash, "wrongpassword".to_owned())
+                .await
+                .is_err());
+        }
+
+        assert!(!hasher.is_supported_hash("plaintext-password"));
+    }
+
+    #[tokio::test]
+    async fn verifies_hashes_of_passwords_as_typed_before_normalization() {
+        let hasher = Argon2PasswordHasher::default();
+        let as_typed = "cafe\u{301}-password";
+        let password = Password::parse(as_typed.to_owned()).unwrap();
+
+        let old_hash = hasher
+            .compute_password_hash(as_typed.to_owned())
+            .await
+            .unwrap();
+        assert_eq!(
+            hasher.verify_password(&old_hash, &password).await.ok(),
+            Some(true)
+        );
+
+        let hash = hasher
+            .compute_password_hash(password.as_ref().to_owned())
+            .await
+            .unwrap();
+        assert_eq!(
+            hasher.verify_password(&hash, &password).await.ok(),
+            Some(false)
+        );
+
+        let wrong_password = Password::parse("cafe\u{301}-wrong".to_owned()).unwrap();
+        assert!(hasher
+            .verify_password(&hash, &wrong_password)
+            .await
+            .is_err());
+    }
+}
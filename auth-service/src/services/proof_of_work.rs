@@ -0,0 +1,595 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::http::HeaderMap;
+use color_eyre::eyre::Report;
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+use crate::{
+    app_state::ProofOfWorkStoreType,
+    domain::{ProofOfWorkAction, ProofOfWorkMode},
+    services::data_stores::HashmapProofOfWorkStore,
+    utils::{
+        auth::derive_secret,
+        constants::{
+            POW_ATTACK_THRESHOLD, POW_BASE_DIFFICULTY, POW_CHALLENGE_HEADER, POW_MAX_DIFFICULTY,
+            POW_MODE, POW_NONCE_HEADER,
+        },
+    },
+};
+
+pub const CHALLENGE_TTL_SECONDS: u64 = 300;
+/// Attempts are counted in fixed windows of this length.
+pub const ATTEMPT_WINDOW_SECONDS: u64 = 60;
+/// Anything harder takes minutes in a browser, which is as good as locking everyone out.
+pub const MAX_DIFFICULTY_BITS: u8 = 32;
+const MAX_NONCE_CHARS: usize = 64;
+// Spent challenges are remembered until they'd fail validation anyway, including leeway
+const USED_CHALLENGE_TTL_SECONDS: u64 = CHALLENGE_TTL_SECONDS + 60;
+
+/// How hard the hashcash puzzles are and when they have to be solved.
+///
+/// Difficulty is the number of leading zero bits the SHA-256 of `challenge:nonce` must have, so
+/// every extra bit doubles the expected work. Once the attempts at an action in the last minute
+/// exceed `attack_threshold`, each further doubling of the rate adds another bit, up to
+/// `max_difficulty`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProofOfWorkConfig {
+    mode: ProofOfWorkMode,
+    base_difficulty: u8,
+    max_difficulty: u8,
+    attack_threshold: u64,
+}
+
+impl ProofOfWorkConfig {
+    pub fn new(
+        mode: ProofOfWorkMode,
+        base_difficulty: u8,
+        max_difficulty: u8,
+        attack_threshold: u64,
+    ) -> Result<Self, String> {
+        if base_difficulty > max_difficulty {
+            return Err(format!(
+                "Base proof-of-work difficulty {} is greater than the maximum {}",
+                base_difficulty, max_difficulty
+            ));
+        }
+        if max_difficulty > MAX_DIFFICULTY_BITS {
+            return Err(format!(
+                "Proof-of-work difficulty can be at most {} bits, got {}",
+                MAX_DIFFICULTY_BITS, max_difficulty
+            ));
+        }
+        if attack_threshold == 0 {
+            return Err("Proof-of-work attack threshold must be at least 1".to_owned());
+        }
+
+        Ok(Self {
+            mode,
+            base_difficulty,
+            max_difficulty,
+            attack_threshold,
+        })
+    }
+
+    pub fn mode(&self) -> ProofOfWorkMode {
+        self.mode
+    }
+
+    fn is_required(&self, recent_attempts: u64) -> bool {
+        match self.mode {
+            ProofOfWorkMode::Off => false,
+            ProofOfWorkMode::Adaptive => recent_attempts > self.attack_threshold,
+            ProofOfWorkMode::Always => true,
+        }
+    }
+
+    fn difficulty(&self, recent_attempts: u64) -> u8 {
+        let doublings = (recent_attempts / self.attack_threshold)
+            .checked_ilog2()
+            .unwrap_or_default();
+        let extra_bits = u8::try_from(doublings).unwrap_or(u8::MAX);
+
+        self.base_difficulty
+            .saturating_add(extra_bits)
+            .min(self.max_difficulty)
+    }
+}
+
+impl Default for ProofOfWorkConfig {
+    /// Uses the settings configured through the `POW_*` environment variables.
+    fn default() -> Self {
+        let mode = POW_MODE.parse().expect("Invalid POW_MODE");
+        Self::new(
+            mode,
+            *POW_BASE_DIFFICULTY,
+            *POW_MAX_DIFFICULTY,
+            *POW_ATTACK_THRESHOLD,
+        )
+        .expect("Invalid proof-of-work configuration")
+    }
+}
+
+/// Issues and checks the proof-of-work puzzles signup and login can demand.
+///
+/// Challenges are stateless JWTs, so issuing one costs nothing. Only solved challenges are
+/// recorded, to stop the same solution from being replayed.
+#[derive(Clone)]
+pub struct ProofOfWork {
+    config: ProofOfWorkConfig,
+    store: ProofOfWorkStoreType,
+    rejections: Arc<Mutex<Rejections>>,
+}
+
+/// Requests turned away per action in the current attempt window. They're only logged as one
+/// summary per window, as a log line or audit row each would add to the very flood proof of
+/// work is fending off.
+#[derive(Default)]
+struct Rejections {
+    window: u64,
+    counts: HashMap<ProofOfWorkAction, u64>,
+}
+
+impl Rejections {
+    fn count(&mut self, action: ProofOfWorkAction, window: u64) {
+        if window != self.window {
+            for (action, rejected) in self.counts.drain() {
+                tracing::warn!(
+                    action = %action,
+                    rejected,
+                    "Rejected requests without a valid proof of work in the last window"
+                );
+            }
+            self.window = window;
+        }
+
+        *self.counts.entry(action).or_default() += 1;
+    }
+}
+
+/// A freshly issued puzzle, and whether the action currently needs it solved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofOfWorkChallenge {
+    pub challenge: String,
+    pub difficulty: u8,
+    pub expires_in: u64,
+    pub required: bool,
+}
+
+/// A client's answer to a challenge, sent in the `X-PoW-Challenge` and `X-PoW-Nonce` headers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofOfWorkSolution {
+    pub challenge: String,
+    pub nonce: String,
+}
+
+impl ProofOfWorkSolution {
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(ToOwned::to_owned)
+        };
+
+        Some(Self {
+            challenge: header(POW_CHALLENGE_HEADER)?,
+            nonce: header(POW_NONCE_HEADER)?,
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ProofOfWorkError {
+    #[error("Proof of work required")]
+    Required,
+    #[error("Invalid proof of work")]
+    InvalidSolution,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChallengeClaims {
+    jti: String,
+    action: ProofOfWorkAction,
+    difficulty: u8,
+    exp: u64,
+}
+
+impl ProofOfWork {
+    pub fn new(config: ProofOfWorkConfig, store: ProofOfWorkStoreType) -> Self {
+        Self {
+            config,
+            store,
+            rejections: Arc::default(),
+        }
+    }
+
+    pub fn mode(&self) -> ProofOfWorkMode {
+        self.config.mode
+    }
+
+    #[tracing::instrument(name = "Issuing proof-of-work challenge", skip_all)]
+    pub async fn issue_challenge(
+        &self,
+        action: ProofOfWorkAction,
+    ) -> Result<ProofOfWorkChallenge, ProofOfWorkError> {
+        let recent_attempts = self.recent_attempts(action).await?;
+        let difficulty = self.config.difficulty(recent_attempts);
+
+        let claims = ChallengeClaims {
+            jti: uuid::Uuid::new_v4().to_string(),
+            action,
+            difficulty,
+            exp: now_seconds()? + CHALLENGE_TTL_SECONDS,
+        };
+        let challenge = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(&challenge_secret()),
+        )
+        .map_err(|e| ProofOfWorkError::UnexpectedError(e.into()))?;
+
+        Ok(ProofOfWorkChallenge {
+            challenge,
+            difficulty,
+            expires_in: CHALLENGE_TTL_SECONDS,
+            required: self.config.is_required(recent_attempts),
+        })
+    }
+
+    /// Counts an attempt at `action` and, if the current mode and attempt rate call for it,
+    /// checks and spends the solution.
+    #[tracing::instrument(name = "Checking proof of work", skip_all)]
+    pub async fn check(
+        &self,
+        action: ProofOfWorkAction,
+        solution: Option<&ProofOfWorkSolution>,
+    ) -> Result<(), ProofOfWorkError> {
+        let result = self.check_solution(action, solution).await;
+        if let Err(ProofOfWorkError::Required | ProofOfWorkError::InvalidSolution) = result {
+            if let (Ok(window), Ok(mut rejections)) = (current_window(), self.rejections.lock()) {
+                rejections.count(action, window);
+            }
+        }
+
+        result
+    }
+
+    async fn check_solution(
+        &self,
+        action: ProofOfWorkAction,
+        solution: Option<&ProofOfWorkSolution>,
+    ) -> Result<(), ProofOfWorkError> {
+        if self.config.mode == ProofOfWorkMode::Off {
+            return Ok(());
+        }
+
+        let recent_attempts = self.record_attempt(action).await?;
+        if !self.config.is_required(recent_attempts) {
+            return Ok(());
+        }
+
+        let solution = solution.ok_or(ProofOfWorkError::Required)?;
+        let claims = decode::<ChallengeClaims>(
+            &solution.challenge,
+            &DecodingKey::from_secret(&challenge_secret()),
+            &Validation::default(),
+        )
+        .map_err(|_| ProofOfWorkError::InvalidSolution)?
+        .claims;
+
+        // The difficulty is the one the challenge was issued with, even if it has gone up
+        // since, so clients aren't punished for an attack that started while they were solving
+        if claims.action != action
+            || solution.nonce.len() > MAX_NONCE_CHARS
+            || !is_solved(&solution.challenge, &solution.nonce, claims.difficulty)
+        {
+            return Err(ProofOfWorkError::InvalidSolution);
+        }
+
+        let unused = self
+            .store
+            .write()
+            .await
+            .use_challenge(&claims.jti, USED_CHALLENGE_TTL_SECONDS)
+            .await
+            .map_err(|e| ProofOfWorkError::UnexpectedError(e.into()))?;
+        if !unused {
+            return Err(ProofOfWorkError::InvalidSolution);
+        }
+
+        Ok(())
+    }
+
+    /// The busier of the current and previous windows, so the rate doesn't drop to zero
+    /// whenever a new window starts.
+    async fn recent_attempts(&self, action: ProofOfWorkAction) -> Result<u64, ProofOfWorkError> {
+        let window = current_window()?;
+        let store = self.store.read().await;
+
+        let current = store
+            .get_attempts(action, window)
+            .await
+            .map_err(|e| ProofOfWorkError::UnexpectedError(e.into()))?;
+        let previous = store
+            .get_attempts(action, window.saturating_sub(1))
+            .await
+            .map_err(|e| ProofOfWorkError::UnexpectedError(e.into()))?;
+
+        Ok(current.max(previous))
+    }
+
+    async fn record_attempt(&self, action: ProofOfWorkAction) -> Result<u64, ProofOfWorkError> {
+        let window = current_window()?;
+        let mut store = self.store.write().await;
+
+        let current = store
+            .increment_attempts(action, window, 2 * ATTEMPT_WINDOW_SECONDS)
+            .await
+            .map_err(|e| ProofOfWorkError::UnexpectedError(e.into()))?;
+        let previous = store
+            .get_attempts(action, window.saturating_sub(1))
+            .await
+            .map_err(|e| ProofOfWorkError::UnexpectedError(e.into()))?;
+
+        Ok(current.max(previous))
+    }
+}
+
+impl Default for ProofOfWork {
+    fn default() -> Self {
+        Self::new(
+            ProofOfWorkConfig::default(),
+            Arc::new(RwLock::new(HashmapProofOfWorkStore::default())),
+        )
+    }
+}
+
+/// Whether SHA-256 of `challenge:nonce` starts with at least `difficulty` zero bits.
+pub fn is_solved(challenge: &str, nonce: &str, difficulty: u8) -> bool {
+    let hash = Sha256::new()
+        .chain_update(challenge)
+        .chain_update(":")
+        .chain_update(nonce)
+        .finalize();
+
+    leading_zero_bits(&hash) >= u32::from(difficulty)
+}
+
+fn leading_zero_bits(bytes: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in bytes {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+fn challenge_secret() -> Vec<u8> {
+    derive_secret("proof-of-work")
+}
+
+fn now_seconds() -> Result<u64, ProofOfWorkError> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .map_err(|e| ProofOfWorkError::UnexpectedError(e.into()))
+}
+
+fn current_window() -> Result<u64, ProofOfWorkError> {
+    Ok(now_seconds()? / ATTEMPT_WINDOW_SECONDS)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::constants::JWT_SECRET;
+
+    use super::*;
+
+    fn proof_of_work(mode: ProofOfWorkMode, attack_threshold: u64) -> ProofOfWork {
+        ProofOfWork::new(
+            ProofOfWorkConfig::new(mode, 8, 12, attack_threshold).unwrap(),
+            Arc::new(RwLock::new(HashmapProofOfWorkStore::default())),
+        )
+    }
+
+    fn solve(challenge: &ProofOfWorkChallenge) -> ProofOfWorkSolution {
+        let nonce = (0u64..)
+            .map(|nonce| nonce.to_string())
+            .find(|nonce| is_solved(&challenge.challenge, nonce, challenge.difficulty))
+            .unwrap();
+
+        ProofOfWorkSolution {
+            challenge: challenge.challenge.clone(),
+            nonce,
+        }
+    }
+
+    #[test]
+    fn counts_leading_zero_bits() {
+        assert_eq!(leading_zero_bits(&[0xff]), 0);
+        assert_eq!(leading_zero_bits(&[0x00, 0x10]), 11);
+        assert_eq!(leading_zero_bits(&[0x00, 0x00]), 16);
+    }
+
+    #[test]
+    fn difficulty_grows_with_each_doubling_of_the_attempt_rate() {
+        let config = ProofOfWorkConfig::new(ProofOfWorkMode::Adaptive, 16, 20, 10).unwrap();
+
+        assert_eq!(config.difficulty(0), 16);
+        assert_eq!(config.difficulty(19), 16);
+        assert_eq!(config.difficulty(20), 17);
+        assert_eq!(config.difficulty(80), 19);
+        assert_eq!(config.difficulty(1_000_000), 20);
+    }
+
+    #[test]
+    fn rejections_are_counted_per_window() {
+        let mut rejections = Rejections::default();
+
+        rejections.count(ProofOfWorkAction::Login, 1);
+        rejections.count(ProofOfWorkAction::Login, 1);
+        rejections.count(ProofOfWorkAction::Signup, 1);
+        assert_eq!(rejections.counts[&ProofOfWorkAction::Login], 2);
+
+        rejections.count(ProofOfWorkAction::Signup, 2);
+        assert_eq!(rejections.window, 2);
+        assert_eq!(rejections.counts.get(&ProofOfWorkAction::Login), None);
+        assert_eq!(rejections.counts[&ProofOfWorkAction::Signup], 1);
+    }
+
+    #[test]
+    fn rejects_invalid_configuration() {
+        assert!(ProofOfWorkConfig::new(ProofOfWorkMode::Always, 20, 16, 10).is_err());
+        assert!(ProofOfWorkConfig::new(ProofOfWorkMode::Always, 16, 40, 10).is_err());
+        assert!(ProofOfWorkConfig::new(ProofOfWorkMode::Always, 16, 20, 0).is_err());
+    }
+
+    #[tokio::test]
+    async fn off_never_requires_a_solution() {
+        let proof_of_work = proof_of_work(ProofOfWorkMode::Off, 1);
+
+        for _ in 0..5 {
+            assert!(proof_of_work
+                .check(ProofOfWorkAction::Signup, None)
+                .await
+                .is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn always_requires_a_solved_challenge() {
+        let proof_of_work = proof_of_work(ProofOfWorkMode::Always, 100);
+
+        assert!(matches!(
+            proof_of_work.check(ProofOfWorkAction::Login, None).await,
+            Err(ProofOfWorkError::Required)
+        ));
+
+        let challenge = proof_of_work
+            .issue_challenge(ProofOfWorkAction::Login)
+            .await
+            .unwrap();
+        assert!(challenge.required);
+        assert_eq!(challenge.difficulty, 8);
+
+        let solution = solve(&challenge);
+        assert!(proof_of_work
+            .check(ProofOfWorkAction::Login, Some(&solution))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn adaptive_requires_a_solution_once_over_the_threshold() {
+        let proof_of_work = proof_of_work(ProofOfWorkMode::Adaptive, 2);
+
+        for _ in 0..2 {
+            assert!(proof_of_work
+                .check(ProofOfWorkAction::Signup, None)
+                .await
+                .is_ok());
+        }
+        assert!(
+            !proof_of_work
+                .issue_challenge(ProofOfWorkAction::Login)
+                .await
+                .unwrap()
+                .required
+        );
+
+        assert!(matches!(
+            proof_of_work.check(ProofOfWorkAction::Signup, None).await,
+            Err(ProofOfWorkError::Required)
+        ));
+        assert!(
+            proof_of_work
+                .issue_challenge(ProofOfWorkAction::Signup)
+                .await
+                .unwrap()
+                .required
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_nonces_actions_and_replays() {
+        let proof_of_work = proof_of_work(ProofOfWorkMode::Always, 100);
+        let challenge = proof_of_work
+            .issue_challenge(ProofOfWorkAction::Signup)
+            .await
+            .unwrap();
+        let solution = solve(&challenge);
+
+        let unsolved = (0u64..)
+            .map(|nonce| nonce.to_string())
+            .find(|nonce| !is_solved(&challenge.challenge, nonce, challenge.difficulty))
+            .unwrap();
+        let wrong_nonce = ProofOfWorkSolution {
+            nonce: unsolved,
+            ..solution.clone()
+        };
+        assert!(matches!(
+            proof_of_work
+                .check(ProofOfWorkAction::Signup, Some(&wrong_nonce))
+                .await,
+            Err(ProofOfWorkError::InvalidSolution)
+        ));
+
+        assert!(matches!(
+            proof_of_work
+                .check(ProofOfWorkAction::Login, Some(&solution))
+                .await,
+            Err(ProofOfWorkError::InvalidSolution)
+        ));
+
+        assert!(proof_of_work
+            .check(ProofOfWorkAction::Signup, Some(&solution))
+            .await
+            .is_ok());
+        assert!(matches!(
+            proof_of_work
+                .check(ProofOfWorkAction::Signup, Some(&solution))
+                .await,
+            Err(ProofOfWorkError::InvalidSolution)
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_challenges_not_signed_by_us() {
+        let proof_of_work = proof_of_work(ProofOfWorkMode::Always, 100);
+        let claims = ChallengeClaims {
+            jti: "forged".to_owned(),
+            action: ProofOfWorkAction::Signup,
+            difficulty: 0,
+            exp: now_seconds().unwrap() + 60,
+        };
+        let challenge = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(JWT_SECRET.as_bytes()),
+        )
+        .unwrap();
+
+        let solution = ProofOfWorkSolution {
+            challenge,
+            nonce: "0".to_owned(),
+        };
+        assert!(matches!(
+            proof_of_work
+                .check(ProofOfWorkAction::Signup, Some(&solution))
+                .await,
+            Err(ProofOfWorkError::InvalidSolution)
+        ));
+    }
+}
@@ -0,0 +1,80 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use color_eyre::eyre::Result;
+
+use crate::domain::{parse_email_domain, MxResolver};
+
+/// Answers MX lookups from a fixed table instead of DNS.
+///
+/// Stands in for a real resolver in tests and in deployments that can't reach DNS. Domains
+/// missing from the table have no MX records.
+#[derive(Debug, Clone, Default)]
+pub struct StaticMxResolver {
+    records: HashMap<String, Vec<String>>,
+}
+
+impl StaticMxResolver {
+    pub fn new(records: HashMap<String, Vec<String>>) -> Self {
+        Self { records }
+    }
+
+    /// Reads one domain per line followed by its MX hosts, separated by whitespace.
+    /// Empty lines and lines starting with `#` are skipped.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut records = HashMap::new();
+
+        for line in fs::read_to_string(path)?.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = line.split_whitespace();
+            let domain = fields.next().unwrap_or_default();
+            let domain = parse_email_domain(domain)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            records
+                .entry(domain)
+                .or_insert_with(Vec::new)
+                .extend(fields.map(str::to_owned));
+        }
+
+        Ok(Self { records })
+    }
+}
+
+#[async_trait::async_trait]
+impl MxResolver for StaticMxResolver {
+    async fn lookup_mx(&self, domain: &str) -> Result<Vec<String>> {
+        Ok(self.records.get(domain).cloned().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn reads_records_from_file() {
+        let path = std::env::temp_dir().join(format!("mx-{}.txt", Uuid::new_v4()));
+        let mut file = fs::File::create(&path).unwrap();
+        writeln!(file, "# domain mx-hosts...").unwrap();
+        writeln!(file, "Example.com mx1.example.com mx2.example.com").unwrap();
+        writeln!(file).unwrap();
+        writeln!(file, "null.example .").unwrap();
+
+        let resolver = StaticMxResolver::from_file(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(
+            resolver.lookup_mx("example.com").await.unwrap(),
+            vec!["mx1.example.com", "mx2.example.com"]
+        );
+        assert_eq!(resolver.lookup_mx("null.example").await.unwrap(), vec!["."]);
+        assert!(resolver.lookup_mx("other.com").await.unwrap().is_empty());
+    }
+}
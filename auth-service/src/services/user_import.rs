@@ -0,0 +1,250 @@
+use std::{
+    fmt,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    app_state::UserStoreType,
+    domain::{Email, Password, User, UserStoreError},
+    services::password_hasher::Argon2PasswordHasher,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// One JSON object per line.
+    JsonLines,
+    /// Comma-separated values with a header row.
+    Csv,
+}
+
+impl ImportFormat {
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+
+        match extension.as_deref() {
+            Some("jsonl") | Some("ndjson") => Ok(Self::JsonLines),
+            Some("csv") => Ok(Self::Csv),
+            _ => Err(format!(
+                "Cannot tell the format of {}, expected a .jsonl or .csv file",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// A user exported from another system, with the password already hashed.
+#[derive(Debug, Deserialize)]
+struct ImportedUser {
+    email: String,
+    password_hash: String,
+    #[serde(default)]
+    requires_2fa: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RejectedRecord {
+    pub line: u64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub imported: u64,
+    pub already_existing: u64,
+    pub rejected: Vec<RejectedRecord>,
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Imported {} users, skipped {} existing users, rejected {} records",
+            self.imported,
+            self.already_existing,
+            self.rejected.len()
+        )?;
+
+        for rejected in &self.rejected {
+            write!(f, "\n  line {}: {}", rejected.line, rejected.reason)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Loads users with pre-hashed passwords, keeping existing users untouched.
+///
+/// Malformed records are reported and skipped; only store failures abort the import.
+pub async fn import_users<R: Read>(
+    reader: R,
+    format: ImportFormat,
+    user_store: &UserStoreType,
+    password_hasher: &Argon2PasswordHasher,
+) -> Result<ImportReport, UserStoreError> {
+    let mut user_store = user_store.write().await;
+    let mut report = ImportReport::default();
+
+    for (line, record) in read_records(reader, format) {
+        let user = match record.and_then(|record| parse_user(record, password_hasher)) {
+            Ok(user) => user,
+            Err(reason) => {
+                report.rejected.push(RejectedRecord { line, reason });
+                continue;
+            }
+        };
+
+        match user_store.import_user(user).await {
+            Ok(()) => report.imported += 1,
+            Err(UserStoreError::UserAlreadyExists) => report.already_existing += 1,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(report)
+}
+
+type Record = (u64, Result<ImportedUser, String>);
+
+fn read_records<'a, R: Read + 'a>(
+    reader: R,
+    format: ImportFormat,
+) -> Box<dyn Iterator<Item = Record> + 'a> {
+    match format {
+        ImportFormat::JsonLines => Box::new(
+            BufReader::new(reader)
+                .lines()
+                .zip(1..)
+                .filter(|(line, _)| !matches!(line, Ok(line) if line.trim().is_empty()))
+                .map(|(line, number)| {
+                    let record = line
+                        .map_err(|e| e.to_string())
+                        .and_then(|line| serde_json::from_str(&line).map_err(|e| e.to_string()));
+                    (number, record)
+                }),
+        ),
+        ImportFormat::Csv => Box::new(
+            csv::Reader::from_reader(reader)
+                .into_deserialize()
+                // The header takes up the first line
+                .zip(2..)
+                .map(|(record, number)| (number, record.map_err(|e| e.to_string()))),
+        ),
+    }
+}
+
+fn parse_user(
+    record: ImportedUser,
+    password_hasher: &Argon2PasswordHasher,
+) -> Result<User, String> {
+    let email = Email::parse(record.email)?;
+
+    if !password_hasher.is_supported_hash(&record.password_hash) {
+        return Err("Unsupported password hash format".to_owned());
+    }
+    let password_hash = Password::parse(record.password_hash)?;
+
+    Ok(User::new(email, password_hash, record.requires_2fa))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::sync::RwLock;
+
+    use super::*;
+    use crate::services::data_stores::HashmapUserStore;
+
+    fn user_store() -> UserStoreType {
+        Arc::new(RwLock::new(HashmapUserStore::default()))
+    }
+
+    #[test]
+    fn detects_format_from_extension() {
+        assert_eq!(
+            ImportFormat::from_path(Path::new("users.jsonl")),
+            Ok(ImportFormat::JsonLines)
+        );
+        assert_eq!(
+            ImportFormat::from_path(Path::new("users.CSV")),
+            Ok(ImportFormat::Csv)
+        );
+        assert!(ImportFormat::from_path(Path::new("users.txt")).is_err());
+    }
+
+    #[tokio::test]
+    async fn imports_json_lines_and_reports_rejected_records() {
+        let user_store = user_store();
+        let bcrypt_hash = bcrypt::hash("password123", 4).unwrap();
+        let input = format!(
+            concat!(
+                r#"{{"email": "a@example.com", "password_hash": "{0}", "requires_2fa": true}}"#,
+                "\n\n",
+                r#"{{"email": "a@example.com", "password_hash": "{0}"}}"#,
+                "\n",
+                r#"{{"email": "b@example.com", "password_hash": "plaintext-password"}}"#,
+                "\n",
+                "not json\n",
+            ),
+            bcrypt_hash
+        );
+
+        let report = import_users(
+            input.as_bytes(),
+            ImportFormat::JsonLines,
+            &user_store,
+            &Argon2PasswordHasher::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.already_existing, 1);
+        let rejected_lines: Vec<u64> = report.rejected.iter().map(|r| r.line).collect();
+        assert_eq!(rejected_lines, vec![4, 5]);
+
+        let email = Email::parse("a@example.com".to_owned()).unwrap();
+        let user = user_store.read().await.get_user(&email).await.unwrap();
+        assert_eq!(user.password.as_ref(), bcrypt_hash);
+        assert!(user.requires_2fa);
+    }
+
+    #[tokio::test]
+    async fn imports_csv() {
+        let user_store = user_store();
+        let input = format!(
+            "email,password_hash,requires_2fa\na@example.com,{},false\ninvalid,{0},false\n",
+            bcrypt::hash("password123", 4).unwrap()
+        );
+
+        let report = import_users(
+            input.as_bytes(),
+            ImportFormat::Csv,
+            &user_store,
+            &Argon2PasswordHasher::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].line, 3);
+
+        let email = Email::parse("a@example.com".to_owned()).unwrap();
+        let password = Password::parse("password123".to_owned()).unwrap();
+        assert_eq!(
+            user_store
+                .read()
+                .await
+                .validate_user(&email, &password)
+                .await,
+            Ok(())
+        );
+    }
+}
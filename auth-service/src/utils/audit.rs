@@ -0,0 +1,25 @@
+use crate::{app_state::AuditLogStoreType, domain::AuditEvent};
+
+use super::request_context::RequestContext;
+
+/// Appends an event to the audit log. Failures are logged rather than failing the request.
+pub async fn record_audit_event(
+    audit_log_store: &AuditLogStoreType,
+    context: &RequestContext,
+    event: AuditEvent,
+) {
+    let event = AuditEvent {
+        ip_address: context.ip_address.clone(),
+        user_agent: context.user_agent.clone(),
+        request_id: context.request_id.clone(),
+        ..event
+    };
+
+    if let Err(e) = audit_log_store.write().await.add_event(event).await {
+        tracing::error!(
+            error.message = %e,
+            error.root_cause = ?std::error::Error::source(&e).map(ToString::to_string),
+            "Failed to record audit event"
+        );
+    }
+}
@@ -1,40 +1,53 @@
-use axum_extra::extract::cookie::{Cookie, SameSite};
+use axum::http::{header::AUTHORIZATION, HeaderMap};
+use axum_extra::extract::{cookie::Cookie, CookieJar};
 use chrono::Utc;
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
+use rand::{distributions::Alphanumeric, Rng};
+use ring::{hmac, rand::SystemRandom};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
 
-use crate::{app_state::BannedTokenStoreType, domain::email::Email};
+use crate::{
+    app_state::{AppState, BannedTokenStoreType},
+    domain::{email::Email, ApiKey, AuthAPIError},
+    services::api_keys::{is_api_key, ApiKeyError},
+};
 
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
+use super::{constants::JWT_SECRET, cookies::COOKIE_SETTINGS};
 
 pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>, GenerateTokenError> {
     let token = generate_auth_token(email)?;
     Ok(create_auth_cookie(token))
 }
 
+/// Expires with the token inside it.
 fn create_auth_cookie(token: String) -> Cookie<'static> {
-    let cookie = Cookie::build((JWT_COOKIE_NAME, token))
-        .path("/")
-        .http_only(true)
-        .same_site(SameSite::Lax)
-        .build();
-
-    cookie
+    COOKIE_SETTINGS.build(
+        COOKIE_SETTINGS.auth_cookie_name(),
+        token,
+        true,
+        TOKEN_TTL_SECONDS,
+    )
 }
 
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum GenerateTokenError {
-    TokenError(jsonwebtoken::errors::Error),
+    #[error("Failed to encode token")]
+    TokenError(#[source] jsonwebtoken::errors::Error),
+    #[error("Unexpected error")]
     UnexpectedError,
 }
 
 pub const TOKEN_TTL_SECONDS: i64 = 600;
+const SESSION_ID_CHARS: usize = 16;
 
-fn generate_auth_token(email: &Email) -> Result<String, GenerateTokenError> {
+pub fn generate_auth_token(email: &Email) -> Result<String, GenerateTokenError> {
     let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
         .ok_or(GenerateTokenError::UnexpectedError)?;
 
-    let exp = Utc::now()
+    let now = Utc::now();
+    let exp = now
         .checked_add_signed(delta)
         .ok_or(GenerateTokenError::UnexpectedError)?
         .timestamp();
@@ -42,33 +55,91 @@ fn generate_auth_token(email: &Email) -> Result<String, GenerateTokenError> {
     let exp: usize = exp
         .try_into()
         .map_err(|_| GenerateTokenError::UnexpectedError)?;
+    let iat: usize = now
+        .timestamp()
+        .try_into()
+        .map_err(|_| GenerateTokenError::UnexpectedError)?;
 
     let sub = email.as_ref().to_owned();
+    let sid = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(SESSION_ID_CHARS)
+        .map(char::from)
+        .collect();
 
-    let claims = Claims { sub, exp };
+    let claims = Claims { sub, exp, iat, sid };
 
     create_token(&claims).map_err(GenerateTokenError::TokenError)
 }
 
+/// A token a service gets for itself with the client credentials grant, only valid for
+/// `audience`.
+pub fn generate_service_token(
+    client_id: &str,
+    audience: &str,
+    scopes: &[String],
+) -> Result<String, GenerateTokenError> {
+    let iat = Utc::now().timestamp();
+    let exp = iat
+        .checked_add(TOKEN_TTL_SECONDS)
+        .ok_or(GenerateTokenError::UnexpectedError)?;
+
+    let claims = ServiceClaims {
+        sub: client_id.to_owned(),
+        aud: audience.to_owned(),
+        scope: scopes.join(" "),
+        iat: iat
+            .try_into()
+            .map_err(|_| GenerateTokenError::UnexpectedError)?,
+        exp: exp
+            .try_into()
+            .map_err(|_| GenerateTokenError::UnexpectedError)?,
+    };
+
+    create_token(&claims).map_err(GenerateTokenError::TokenError)
+}
+
+/// A token a registered client gets for a user with the code flow, only good for asking about
+/// the user. Being signed with a key of its own, it never passes for an auth token, so clients
+/// can't use it to manage the user's account.
+pub fn generate_access_token(
+    email: &str,
+    client_id: &str,
+    scopes: &[String],
+) -> Result<String, GenerateTokenError> {
+    let iat = Utc::now().timestamp();
+    let exp = iat
+        .checked_add(TOKEN_TTL_SECONDS)
+        .ok_or(GenerateTokenError::UnexpectedError)?;
+
+    let claims = AccessClaims {
+        sub: email.to_owned(),
+        aud: client_id.to_owned(),
+        scope: scopes.join(" "),
+        iat: iat
+            .try_into()
+            .map_err(|_| GenerateTokenError::UnexpectedError)?,
+        exp: exp
+            .try_into()
+            .map_err(|_| GenerateTokenError::UnexpectedError)?,
+    };
+
+    encode(
+        &jsonwebtoken::Header::default(),
+        &claims,
+        &EncodingKey::from_secret(&access_token_secret()),
+    )
+    .map_err(GenerateTokenError::TokenError)
+}
+
 pub async fn validate_token(
     token: &str,
     banned_token_store: BannedTokenStoreType,
 ) -> Result<Claims, jsonwebtoken::errors::Error> {
-    match banned_token_store.read().await.contains_token(token).await {
-        Ok(value) => {
-            if value {
-                return Err(jsonwebtoken::errors::Error::from(
-                    jsonwebtoken::errors::ErrorKind::InvalidToken,
-                ));
-            }
-        }
-        Err(_) => {
-            return Err(jsonwebtoken::errors::Error::from(
-                jsonwebtoken::errors::ErrorKind::InvalidToken,
-            ));
-        }
-    }
+    ensure_not_banned(token, &banned_token_store).await?;
 
+    // With no audience to expect, tokens carrying one are rejected, so service tokens never
+    // pass for auth tokens
     decode::<Claims>(
         token,
         &DecodingKey::from_secret(JWT_SECRET.as_bytes()),
@@ -77,7 +148,66 @@ pub async fn validate_token(
     .map(|data| data.claims)
 }
 
-fn create_token(claims: &Claims) -> Result<String, jsonwebtoken::errors::Error> {
+/// Checks a service token was issued for `audience`.
+pub async fn validate_service_token(
+    token: &str,
+    audience: &str,
+    banned_token_store: BannedTokenStoreType,
+) -> Result<ServiceClaims, jsonwebtoken::errors::Error> {
+    ensure_not_banned(token, &banned_token_store).await?;
+
+    let mut validation = Validation::default();
+    validation.set_audience(&[audience]);
+    validation.set_required_spec_claims(&["exp", "aud"]);
+
+    decode::<ServiceClaims>(
+        token,
+        &DecodingKey::from_secret(JWT_SECRET.as_bytes()),
+        &validation,
+    )
+    .map(|data| data.claims)
+}
+
+/// Checks an access token was issued to `client_id`, or to any client when it's `None`.
+pub async fn validate_access_token(
+    token: &str,
+    client_id: Option<&str>,
+    banned_token_store: BannedTokenStoreType,
+) -> Result<AccessClaims, jsonwebtoken::errors::Error> {
+    ensure_not_banned(token, &banned_token_store).await?;
+
+    let mut validation = Validation::default();
+    validation.set_required_spec_claims(&["exp", "aud"]);
+    match client_id {
+        Some(client_id) => validation.set_audience(&[client_id]),
+        None => validation.validate_aud = false,
+    }
+
+    decode::<AccessClaims>(
+        token,
+        &DecodingKey::from_secret(&access_token_secret()),
+        &validation,
+    )
+    .map(|data| data.claims)
+}
+
+fn access_token_secret() -> Vec<u8> {
+    derive_secret("access-token")
+}
+
+async fn ensure_not_banned(
+    token: &str,
+    banned_token_store: &BannedTokenStoreType,
+) -> Result<(), jsonwebtoken::errors::Error> {
+    match banned_token_store.read().await.contains_token(token).await {
+        Ok(false) => Ok(()),
+        Ok(true) | Err(_) => Err(jsonwebtoken::errors::Error::from(
+            jsonwebtoken::errors::ErrorKind::InvalidToken,
+        )),
+    }
+}
+
+fn create_token<T: Serialize>(claims: &T) -> Result<String, jsonwebtoken::errors::Error> {
     encode(
         &jsonwebtoken::Header::default(),
         &claims,
@@ -85,10 +215,202 @@ fn create_token(claims: &Claims) -> Result<String, jsonwebtoken::errors::Error>
     )
 }
 
+/// The token from an `Authorization: Bearer` header.
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+/// Compares a secret with the expected one in constant time, by comparing HMACs of both under
+/// a one-off key.
+pub fn secrets_match(secret: &[u8], expected: &[u8]) -> bool {
+    let Ok(key) = hmac::Key::generate(hmac::HMAC_SHA256, &SystemRandom::new()) else {
+        return false;
+    };
+    let tag = hmac::sign(&key, expected);
+
+    hmac::verify(&key, secret, tag.as_ref()).is_ok()
+}
+
+/// The token a request was made with, from an `Authorization: Bearer` header or else the auth
+/// cookie.
+pub fn request_token(jar: &CookieJar, headers: &HeaderMap) -> Option<String> {
+    bearer_token(headers).map(str::to_owned).or_else(|| {
+        jar.get(COOKIE_SETTINGS.auth_cookie_name())
+            .map(|cookie| cookie.value().to_owned())
+    })
+}
+
+/// What a request authenticated with.
+#[derive(Debug)]
+pub enum Credentials {
+    Session(Claims),
+    ApiKey(ApiKey),
+    Service(ServiceClaims),
+    Access(AccessClaims),
+}
+
+impl Credentials {
+    /// The user's email, or the client id of a service.
+    pub fn subject(&self) -> &str {
+        match self {
+            Self::Session(claims) => &claims.sub,
+            Self::ApiKey(api_key) => &api_key.email,
+            Self::Service(claims) => &claims.sub,
+            Self::Access(claims) => &claims.sub,
+        }
+    }
+
+    /// `session`, `api_key`, `service` or `access`.
+    pub fn token_type(&self) -> &'static str {
+        match self {
+            Self::Session(_) => "session",
+            Self::ApiKey(_) => "api_key",
+            Self::Service(_) => "service",
+            Self::Access(_) => "access",
+        }
+    }
+
+    /// The scopes granted, none for browser sessions.
+    pub fn scopes(&self) -> Vec<&str> {
+        match self {
+            Self::Session(_) => Vec::new(),
+            Self::ApiKey(api_key) => api_key.scopes.iter().map(String::as_str).collect(),
+            Self::Service(claims) => claims.scope.split_whitespace().collect(),
+            Self::Access(claims) => claims.scope.split_whitespace().collect(),
+        }
+    }
+
+    /// The scopes a registered client was granted, leaving out those users pick for their own
+    /// API keys.
+    pub fn client_scopes(&self) -> Vec<&str> {
+        match self {
+            Self::Session(_) | Self::ApiKey(_) => Vec::new(),
+            Self::Service(_) | Self::Access(_) => self.scopes(),
+        }
+    }
+
+    /// Whether all of `scopes` were granted. Browser sessions have none.
+    pub fn has_scopes(&self, scopes: &[String]) -> bool {
+        let granted = self.scopes();
+        scopes.iter().all(|scope| granted.contains(&scope.as_str()))
+    }
+
+    /// Whether a service naming its `audience` or asking for `scopes` may accept the token.
+    /// Only tokens issued to registered clients qualify then, as users' own sessions and API
+    /// keys never hold service scopes.
+    pub fn grants(&self, audience: Option<&str>, scopes: &[String]) -> bool {
+        if audience.is_none() && scopes.is_empty() {
+            return true;
+        }
+
+        matches!(self, Self::Service(_) | Self::Access(_)) && self.has_scopes(scopes)
+    }
+
+    /// The API key the request was made with, if it wasn't a browser session.
+    pub fn api_key(&self) -> Option<&ApiKey> {
+        match self {
+            Self::Session(_) | Self::Service(_) | Self::Access(_) => None,
+            Self::ApiKey(api_key) => Some(api_key),
+        }
+    }
+}
+
+/// Checks `token`, which may be an auth token or an API key. Service tokens are only accepted
+/// when the caller says which `audience` it is.
+pub async fn authenticate_token(
+    token: &str,
+    audience: Option<&str>,
+    state: &AppState,
+) -> Result<Credentials, AuthAPIError> {
+    if is_api_key(token) {
+        return match state.api_keys.authenticate(token).await {
+            Ok(api_key) => Ok(Credentials::ApiKey(api_key)),
+            Err(ApiKeyError::UnexpectedError(e)) => Err(AuthAPIError::UnexpectedError(e)),
+            Err(_) => Err(AuthAPIError::InvalidToken),
+        };
+    }
+
+    if let Some(audience) = audience {
+        if let Ok(claims) =
+            validate_service_token(token, audience, state.banned_token_store.clone()).await
+        {
+            return Ok(Credentials::Service(claims));
+        }
+    }
+
+    validate_token(token, state.banned_token_store.clone())
+        .await
+        .map(Credentials::Session)
+        .map_err(|_| AuthAPIError::InvalidToken)
+}
+
+/// Like `authenticate_token`, but also accepts the access tokens of the code flow issued to
+/// `client_id`, or to any client when it's `None`. Only for the endpoints telling clients
+/// about users, as those tokens aren't meant for anything else.
+pub async fn authenticate_access_token(
+    token: &str,
+    client_id: Option<&str>,
+    state: &AppState,
+) -> Result<Credentials, AuthAPIError> {
+    if let Ok(claims) =
+        validate_access_token(token, client_id, state.banned_token_store.clone()).await
+    {
+        return Ok(Credentials::Access(claims));
+    }
+
+    authenticate_token(token, client_id, state).await
+}
+
+/// A signing key for tokens other than auth tokens, derived from `JWT_SECRET`.
+///
+/// Each `purpose` gets its own key, so e.g. a magic link token can never pass for an auth
+/// token, even though both carry a `sub` and an `exp`.
+pub fn derive_secret(purpose: &str) -> Vec<u8> {
+    Sha256::new()
+        .chain_update(purpose)
+        .chain_update(":")
+        .chain_update(JWT_SECRET.as_bytes())
+        .finalize()
+        .to_vec()
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
     pub exp: usize,
+    /// Missing from tokens issued before it was added, like `sid`.
+    #[serde(default)]
+    pub iat: usize,
+    /// Tells apart the tokens of the same user, e.g. from different devices.
+    #[serde(default)]
+    pub sid: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ServiceClaims {
+    /// The client id of the service the token was issued to.
+    pub sub: String,
+    /// The service the token is meant for.
+    pub aud: String,
+    /// Space-separated, as in OAuth.
+    pub scope: String,
+    pub iat: usize,
+    pub exp: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccessClaims {
+    /// The email of the user who authorized the client.
+    pub sub: String,
+    /// The client id of the client the token was issued to.
+    pub aud: String,
+    /// Space-separated, the OIDC scopes the user authorized.
+    pub scope: String,
+    pub iat: usize,
+    pub exp: usize,
 }
 
 #[cfg(test)]
@@ -96,7 +418,12 @@ mod tests {
     use std::sync::Arc;
     use tokio::sync::RwLock;
 
-    use crate::{domain::BannedTokenStore, services::data_stores::HashsetBannedTokenStore};
+    use axum_extra::extract::cookie::SameSite;
+
+    use crate::{
+        domain::BannedTokenStore, services::data_stores::HashsetBannedTokenStore,
+        utils::constants::JWT_COOKIE_NAME,
+    };
 
     use super::*;
 
@@ -136,6 +463,8 @@ mod tests {
         let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
         let result = validate_token(&token, banned_token_store).await.unwrap();
         assert_eq!(result.sub, "test@example.com");
+        assert_eq!(result.sid.len(), SESSION_ID_CHARS);
+        assert!(result.iat <= Utc::now().timestamp() as usize);
 
         let exp = Utc::now()
             .checked_add_signed(chrono::Duration::try_minutes(9).expect("valid duration"))
@@ -145,6 +474,13 @@ mod tests {
         assert!(result.exp > exp as usize);
     }
 
+    #[test]
+    fn test_derive_secret() {
+        assert_eq!(derive_secret("magic-link"), derive_secret("magic-link"));
+        assert_ne!(derive_secret("magic-link"), derive_secret("proof-of-work"));
+        assert_ne!(derive_secret("magic-link"), JWT_SECRET.as_bytes());
+    }
+
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
         let token = "invalid_token".to_owned();
@@ -163,4 +499,93 @@ mod tests {
         let result = validate_token(&token, banned_token_store).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_validate_service_token() {
+        let scopes = vec!["read:users".to_owned()];
+        let token = generate_service_token("billing", "api", &scopes).unwrap();
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+
+        let claims = validate_service_token(&token, "api", banned_token_store.clone())
+            .await
+            .unwrap();
+        assert_eq!(claims.sub, "billing");
+        assert_eq!(claims.scope, "read:users");
+        let service = Credentials::Service(claims);
+        assert!(service.grants(Some("api"), &scopes));
+        assert!(!service.has_scopes(&["write:users".to_owned()]));
+
+        assert!(
+            validate_service_token(&token, "other", banned_token_store.clone())
+                .await
+                .is_err()
+        );
+        // Service tokens must never pass for auth tokens, and the other way around
+        assert!(validate_token(&token, banned_token_store.clone())
+            .await
+            .is_err());
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let auth_token = generate_auth_token(&email).unwrap();
+        assert!(
+            validate_service_token(&auth_token, "api", banned_token_store)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sessions_have_no_scopes() {
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let token = generate_auth_token(&email).unwrap();
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let session =
+            Credentials::Session(validate_token(&token, banned_token_store).await.unwrap());
+
+        assert!(session.grants(None, &[]));
+        assert!(!session.grants(None, &["read:users".to_owned()]));
+        assert!(!session.grants(Some("api"), &[]));
+    }
+
+    #[tokio::test]
+    async fn test_validate_access_token() {
+        let scopes = vec!["openid".to_owned(), "email".to_owned()];
+        let token = generate_access_token("test@example.com", "app", &scopes).unwrap();
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+
+        let claims = validate_access_token(&token, Some("app"), banned_token_store.clone())
+            .await
+            .unwrap();
+        assert_eq!(claims.sub, "test@example.com");
+        assert_eq!(claims.scope, "openid email");
+        assert!(
+            validate_access_token(&token, None, banned_token_store.clone())
+                .await
+                .is_ok()
+        );
+        assert!(
+            validate_access_token(&token, Some("other"), banned_token_store.clone())
+                .await
+                .is_err()
+        );
+
+        // Access tokens must never pass for auth or service tokens, and the other way around
+        assert!(validate_token(&token, banned_token_store.clone())
+            .await
+            .is_err());
+        assert!(
+            validate_service_token(&token, "app", banned_token_store.clone())
+                .await
+                .is_err()
+        );
+        let email = Email::parse("test@example.com".to_owned()).unwrap();
+        let auth_token = generate_auth_token(&email).unwrap();
+        let service_token = generate_service_token("billing", "app", &scopes).unwrap();
+        for other in [auth_token, service_token] {
+            assert!(
+                validate_access_token(&other, None, banned_token_store.clone())
+                    .await
+                    .is_err()
+            );
+        }
+    }
 }
@@ -1,11 +1,64 @@
 use dotenvy::dotenv;
 use lazy_static::lazy_static;
-use std::env as std_env;
+use std::{env as std_env, net::IpAddr};
 
 lazy_static! {
     pub static ref JWT_SECRET: String = set_token();
     pub static ref DATABASE_URL: String = set_db_url();
     pub static ref REDIS_HOST_NAME: String = set_redis_host();
+    pub static ref LOG_FORMAT: String = set_log_format();
+    pub static ref AUDIT_API_TOKEN: Option<String> = set_audit_api_token();
+    pub static ref TRUSTED_PROXIES: Vec<IpAddr> = set_trusted_proxies();
+    pub static ref BREACHED_PASSWORDS_FILE: Option<String> = set_breached_passwords_file();
+    pub static ref EMAIL_LOCAL_PART_CASE_FOLDING: bool = set_email_local_part_case_folding();
+    pub static ref MX_RECORDS_FILE: Option<String> = set_mx_records_file();
+    pub static ref TOKEN_STORE_BACKEND: String = set_token_store_backend();
+    pub static ref EXPIRED_ROWS_CLEANUP_INTERVAL_SECONDS: u64 = set_expired_rows_cleanup_interval();
+    pub static ref ARGON2_MEMORY_COST_KIB: u32 = set_argon2_param(
+        env::ARGON2_MEMORY_COST_KIB_ENV_VAR,
+        DEFAULT_ARGON2_MEMORY_COST_KIB
+    );
+    pub static ref ARGON2_ITERATIONS: u32 =
+        set_argon2_param(env::ARGON2_ITERATIONS_ENV_VAR, DEFAULT_ARGON2_ITERATIONS);
+    pub static ref ARGON2_PARALLELISM: u32 =
+        set_argon2_param(env::ARGON2_PARALLELISM_ENV_VAR, DEFAULT_ARGON2_PARALLELISM);
+    pub static ref PASSWORD_MIN_CHARS: usize =
+        set_password_policy_param(env::PASSWORD_MIN_CHARS_ENV_VAR, DEFAULT_PASSWORD_MIN_CHARS);
+    pub static ref PASSWORD_MAX_CHARS: usize =
+        set_password_policy_param(env::PASSWORD_MAX_CHARS_ENV_VAR, DEFAULT_PASSWORD_MAX_CHARS);
+    pub static ref PASSWORD_MIN_STRENGTH: u8 = set_password_policy_param(
+        env::PASSWORD_MIN_STRENGTH_ENV_VAR,
+        DEFAULT_PASSWORD_MIN_STRENGTH
+    );
+    pub static ref MAGIC_LINK_BASE_URL: String = set_magic_link_base_url();
+    pub static ref OAUTH_PROVIDERS_FILE: Option<String> = set_oauth_providers_file();
+    pub static ref OIDC_ISSUER: String = set_oidc_issuer();
+    pub static ref OIDC_CLIENTS_FILE: Option<String> =
+        set_optional_path(env::OIDC_CLIENTS_FILE_ENV_VAR);
+    pub static ref OIDC_SIGNING_KEY_FILE: Option<String> =
+        set_optional_path(env::OIDC_SIGNING_KEY_FILE_ENV_VAR);
+    pub static ref FORWARD_AUTH_LOGIN_URL: String = set_forward_auth_login_url();
+    pub static ref FORWARD_AUTH_ALLOWED_HOSTS: Vec<String> = set_forward_auth_allowed_hosts();
+    pub static ref GRPC_PORT: u16 = set_grpc_port();
+    pub static ref AUTH_COOKIE_SECURE: bool =
+        set_bool(env::AUTH_COOKIE_SECURE_ENV_VAR, DEFAULT_AUTH_COOKIE_SECURE);
+    pub static ref AUTH_COOKIE_DOMAIN: Option<String> = set_auth_cookie_domain();
+    pub static ref AUTH_COOKIE_SAME_SITE: String = set_auth_cookie_same_site();
+    pub static ref AUTH_COOKIE_HOST_PREFIX: bool = set_bool(
+        env::AUTH_COOKIE_HOST_PREFIX_ENV_VAR,
+        DEFAULT_AUTH_COOKIE_HOST_PREFIX
+    );
+    pub static ref POW_MODE: String = set_pow_mode();
+    pub static ref POW_BASE_DIFFICULTY: u8 = set_pow_param(
+        env::POW_BASE_DIFFICULTY_ENV_VAR,
+        DEFAULT_POW_BASE_DIFFICULTY
+    );
+    pub static ref POW_MAX_DIFFICULTY: u8 =
+        set_pow_param(env::POW_MAX_DIFFICULTY_ENV_VAR, DEFAULT_POW_MAX_DIFFICULTY);
+    pub static ref POW_ATTACK_THRESHOLD: u64 = set_pow_param(
+        env::POW_ATTACK_THRESHOLD_ENV_VAR,
+        DEFAULT_POW_ATTACK_THRESHOLD
+    );
 }
 
 fn set_token() -> String {
@@ -27,14 +80,268 @@ fn set_redis_host() -> String {
     std_env::var(env::REDIS_HOST_NAME_ENV_VAR).unwrap_or(DEFAULT_REDIS_HOSTNAME.to_owned())
 }
 
+fn set_log_format() -> String {
+    dotenv().ok();
+    std_env::var(env::LOG_FORMAT_ENV_VAR).unwrap_or(DEFAULT_LOG_FORMAT.to_owned())
+}
+
+fn set_audit_api_token() -> Option<String> {
+    dotenv().ok();
+    std_env::var(env::AUDIT_API_TOKEN_ENV_VAR)
+        .ok()
+        .filter(|token| !token.is_empty())
+}
+
+fn set_breached_passwords_file() -> Option<String> {
+    dotenv().ok();
+    std_env::var(env::BREACHED_PASSWORDS_FILE_ENV_VAR)
+        .ok()
+        .filter(|path| !path.is_empty())
+}
+
+fn set_email_local_part_case_folding() -> bool {
+    dotenv().ok();
+    std_env::var(env::EMAIL_LOCAL_PART_CASE_FOLDING_ENV_VAR)
+        .ok()
+        .map(|value| {
+            value
+                .parse()
+                .expect("EMAIL_LOCAL_PART_CASE_FOLDING must be true or false.")
+        })
+        .unwrap_or(DEFAULT_EMAIL_LOCAL_PART_CASE_FOLDING)
+}
+
+fn set_mx_records_file() -> Option<String> {
+    dotenv().ok();
+    std_env::var(env::MX_RECORDS_FILE_ENV_VAR)
+        .ok()
+        .filter(|path| !path.is_empty())
+}
+
+fn set_token_store_backend() -> String {
+    dotenv().ok();
+    std_env::var(env::TOKEN_STORE_BACKEND_ENV_VAR).unwrap_or(DEFAULT_TOKEN_STORE_BACKEND.to_owned())
+}
+
+fn set_expired_rows_cleanup_interval() -> u64 {
+    dotenv().ok();
+    std_env::var(env::EXPIRED_ROWS_CLEANUP_INTERVAL_SECONDS_ENV_VAR)
+        .ok()
+        .map(|interval| {
+            interval.parse().ok().filter(|&seconds| seconds > 0).expect(
+                "EXPIRED_ROWS_CLEANUP_INTERVAL_SECONDS must be a positive number of seconds.",
+            )
+        })
+        .unwrap_or(DEFAULT_EXPIRED_ROWS_CLEANUP_INTERVAL_SECONDS)
+}
+
+fn set_argon2_param(env_var: &str, default: u32) -> u32 {
+    dotenv().ok();
+    std_env::var(env_var)
+        .ok()
+        .map(|value| {
+            value
+                .parse()
+                .unwrap_or_else(|_| panic!("{} must be a positive integer.", env_var))
+        })
+        .unwrap_or(default)
+}
+
+fn set_password_policy_param<T: std::str::FromStr>(env_var: &str, default: T) -> T {
+    dotenv().ok();
+    std_env::var(env_var)
+        .ok()
+        .map(|value| {
+            value
+                .parse()
+                .unwrap_or_else(|_| panic!("{} must be a non-negative integer.", env_var))
+        })
+        .unwrap_or(default)
+}
+
+fn set_trusted_proxies() -> Vec<IpAddr> {
+    dotenv().ok();
+    std_env::var(env::TRUSTED_PROXIES_ENV_VAR)
+        .map(|proxies| {
+            proxies
+                .split(',')
+                .map(str::trim)
+                .filter(|proxy| !proxy.is_empty())
+                .map(|proxy| {
+                    proxy
+                        .parse()
+                        .expect("TRUSTED_PROXIES must be comma-separated IP addresses.")
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn set_magic_link_base_url() -> String {
+    dotenv().ok();
+    std_env::var(env::MAGIC_LINK_BASE_URL_ENV_VAR).unwrap_or(DEFAULT_MAGIC_LINK_BASE_URL.to_owned())
+}
+
+fn set_oauth_providers_file() -> Option<String> {
+    dotenv().ok();
+    std_env::var(env::OAUTH_PROVIDERS_FILE_ENV_VAR)
+        .ok()
+        .filter(|path| !path.is_empty())
+}
+
+fn set_oidc_issuer() -> String {
+    dotenv().ok();
+    std_env::var(env::OIDC_ISSUER_ENV_VAR)
+        .unwrap_or(DEFAULT_OIDC_ISSUER.to_owned())
+        .trim_end_matches('/')
+        .to_owned()
+}
+
+fn set_optional_path(env_var: &str) -> Option<String> {
+    dotenv().ok();
+    std_env::var(env_var).ok().filter(|path| !path.is_empty())
+}
+
+fn set_forward_auth_login_url() -> String {
+    dotenv().ok();
+    std_env::var(env::FORWARD_AUTH_LOGIN_URL_ENV_VAR)
+        .unwrap_or(DEFAULT_FORWARD_AUTH_LOGIN_URL.to_owned())
+}
+
+fn set_forward_auth_allowed_hosts() -> Vec<String> {
+    dotenv().ok();
+    std_env::var(env::FORWARD_AUTH_ALLOWED_HOSTS_ENV_VAR)
+        .map(|hosts| hosts.split(',').map(str::to_owned).collect())
+        .unwrap_or_default()
+}
+
+fn set_grpc_port() -> u16 {
+    dotenv().ok();
+    std_env::var(env::GRPC_PORT_ENV_VAR)
+        .ok()
+        .map(|port| port.parse().expect("GRPC_PORT must be a port number."))
+        .unwrap_or(DEFAULT_GRPC_PORT)
+}
+
+fn set_bool(env_var: &str, default: bool) -> bool {
+    dotenv().ok();
+    std_env::var(env_var)
+        .ok()
+        .map(|value| {
+            value
+                .parse()
+                .unwrap_or_else(|_| panic!("{} must be true or false.", env_var))
+        })
+        .unwrap_or(default)
+}
+
+fn set_auth_cookie_domain() -> Option<String> {
+    dotenv().ok();
+    std_env::var(env::AUTH_COOKIE_DOMAIN_ENV_VAR)
+        .ok()
+        .filter(|domain| !domain.is_empty())
+}
+
+fn set_auth_cookie_same_site() -> String {
+    dotenv().ok();
+    std_env::var(env::AUTH_COOKIE_SAME_SITE_ENV_VAR)
+        .unwrap_or(DEFAULT_AUTH_COOKIE_SAME_SITE.to_owned())
+}
+
+fn set_pow_mode() -> String {
+    dotenv().ok();
+    std_env::var(env::POW_MODE_ENV_VAR).unwrap_or(DEFAULT_POW_MODE.to_owned())
+}
+
+fn set_pow_param<T: std::str::FromStr>(env_var: &str, default: T) -> T {
+    dotenv().ok();
+    std_env::var(env_var)
+        .ok()
+        .map(|value| {
+            value
+                .parse()
+                .unwrap_or_else(|_| panic!("{} must be a non-negative integer.", env_var))
+        })
+        .unwrap_or(default)
+}
+
 pub mod env {
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
+    pub const LOG_FORMAT_ENV_VAR: &str = "LOG_FORMAT";
+    pub const AUDIT_API_TOKEN_ENV_VAR: &str = "AUDIT_API_TOKEN";
+    pub const TRUSTED_PROXIES_ENV_VAR: &str = "TRUSTED_PROXIES";
+    pub const BREACHED_PASSWORDS_FILE_ENV_VAR: &str = "BREACHED_PASSWORDS_FILE";
+    pub const EMAIL_LOCAL_PART_CASE_FOLDING_ENV_VAR: &str = "EMAIL_LOCAL_PART_CASE_FOLDING";
+    pub const MX_RECORDS_FILE_ENV_VAR: &str = "MX_RECORDS_FILE";
+    pub const TOKEN_STORE_BACKEND_ENV_VAR: &str = "TOKEN_STORE_BACKEND";
+    pub const EXPIRED_ROWS_CLEANUP_INTERVAL_SECONDS_ENV_VAR: &str =
+        "EXPIRED_ROWS_CLEANUP_INTERVAL_SECONDS";
+    pub const ARGON2_MEMORY_COST_KIB_ENV_VAR: &str = "ARGON2_MEMORY_COST_KIB";
+    pub const ARGON2_ITERATIONS_ENV_VAR: &str = "ARGON2_ITERATIONS";
+    pub const ARGON2_PARALLELISM_ENV_VAR: &str = "ARGON2_PARALLELISM";
+    pub const PASSWORD_MIN_CHARS_ENV_VAR: &str = "PASSWORD_MIN_CHARS";
+    pub const PASSWORD_MAX_CHARS_ENV_VAR: &str = "PASSWORD_MAX_CHARS";
+    pub const PASSWORD_MIN_STRENGTH_ENV_VAR: &str = "PASSWORD_MIN_STRENGTH";
+    pub const MAGIC_LINK_BASE_URL_ENV_VAR: &str = "MAGIC_LINK_BASE_URL";
+    pub const OAUTH_PROVIDERS_FILE_ENV_VAR: &str = "OAUTH_PROVIDERS_FILE";
+    pub const OIDC_ISSUER_ENV_VAR: &str = "OIDC_ISSUER";
+    pub const OIDC_CLIENTS_FILE_ENV_VAR: &str = "OIDC_CLIENTS_FILE";
+    pub const OIDC_SIGNING_KEY_FILE_ENV_VAR: &str = "OIDC_SIGNING_KEY_FILE";
+    pub const FORWARD_AUTH_LOGIN_URL_ENV_VAR: &str = "FORWARD_AUTH_LOGIN_URL";
+    pub const FORWARD_AUTH_ALLOWED_HOSTS_ENV_VAR: &str = "FORWARD_AUTH_ALLOWED_HOSTS";
+    pub const GRPC_PORT_ENV_VAR: &str = "GRPC_PORT";
+    pub const AUTH_COOKIE_SECURE_ENV_VAR: &str = "AUTH_COOKIE_SECURE";
+    pub const AUTH_COOKIE_DOMAIN_ENV_VAR: &str = "AUTH_COOKIE_DOMAIN";
+    pub const AUTH_COOKIE_SAME_SITE_ENV_VAR: &str = "AUTH_COOKIE_SAME_SITE";
+    pub const AUTH_COOKIE_HOST_PREFIX_ENV_VAR: &str = "AUTH_COOKIE_HOST_PREFIX";
+    pub const POW_MODE_ENV_VAR: &str = "POW_MODE";
+    pub const POW_BASE_DIFFICULTY_ENV_VAR: &str = "POW_BASE_DIFFICULTY";
+    pub const POW_MAX_DIFFICULTY_ENV_VAR: &str = "POW_MAX_DIFFICULTY";
+    pub const POW_ATTACK_THRESHOLD_ENV_VAR: &str = "POW_ATTACK_THRESHOLD";
 }
 
 pub const JWT_COOKIE_NAME: &str = "jwt";
+/// The auth cookie's name with `AUTH_COOKIE_HOST_PREFIX`.
+pub const HOST_PREFIXED_JWT_COOKIE_NAME: &str = "__Host-jwt";
+pub const CSRF_COOKIE_NAME: &str = "csrf_token";
+pub const CSRF_TOKEN_HEADER: &str = "x-csrf-token";
+/// Sites whose scripts may call this service with the auth cookie.
+pub const ALLOWED_ORIGINS: [&str; 2] = ["http://localhost:8000", "http://[YOUR_DROPLET_IP]:8000"];
+pub const OAUTH_STATE_COOKIE_NAME: &str = "oauth_state";
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+pub const X_AUTH_USER_HEADER: &str = "x-auth-user";
+pub const X_AUTH_ROLES_HEADER: &str = "x-auth-roles";
+pub const POW_CHALLENGE_HEADER: &str = "x-pow-challenge";
+pub const POW_NONCE_HEADER: &str = "x-pow-nonce";
 pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1";
+pub const DEFAULT_LOG_FORMAT: &str = "compact";
+pub const DEFAULT_TOKEN_STORE_BACKEND: &str = "redis";
+pub const DEFAULT_EMAIL_LOCAL_PART_CASE_FOLDING: bool = true;
+pub const DEFAULT_EXPIRED_ROWS_CLEANUP_INTERVAL_SECONDS: u64 = 300;
+pub const DEFAULT_ARGON2_MEMORY_COST_KIB: u32 = 15000;
+pub const DEFAULT_ARGON2_ITERATIONS: u32 = 2;
+pub const DEFAULT_ARGON2_PARALLELISM: u32 = 1;
+pub const DEFAULT_PASSWORD_MIN_CHARS: usize = 8;
+pub const DEFAULT_PASSWORD_MAX_CHARS: usize = 128;
+pub const DEFAULT_PASSWORD_MIN_STRENGTH: u8 = 3;
+pub const DEFAULT_MAGIC_LINK_BASE_URL: &str = "http://localhost:3000";
+/// Where clients reach this service as an OpenID Connect provider. Goes into every id_token.
+pub const DEFAULT_OIDC_ISSUER: &str = "http://localhost:3000";
+/// Where reverse proxies send users who aren't logged in.
+pub const DEFAULT_FORWARD_AUTH_LOGIN_URL: &str = "http://localhost:3000/";
+/// The gRPC API gets its own port, which should only be reachable from the internal network.
+pub const DEFAULT_GRPC_PORT: u16 = 50051;
+/// Off for local development over plain HTTP. Turn it on wherever HTTPS is used.
+pub const DEFAULT_AUTH_COOKIE_SECURE: bool = false;
+pub const DEFAULT_AUTH_COOKIE_SAME_SITE: &str = "lax";
+pub const DEFAULT_AUTH_COOKIE_HOST_PREFIX: bool = false;
+pub const DEFAULT_POW_MODE: &str = "off";
+pub const DEFAULT_POW_BASE_DIFFICULTY: u8 = 16;
+pub const DEFAULT_POW_MAX_DIFFICULTY: u8 = 24;
+/// Attempts per minute at signup or login, each counted separately.
+pub const DEFAULT_POW_ATTACK_THRESHOLD: u64 = 30;
 
 pub mod prod {
     pub const APP_ADDRESS: &str = "0.0.0.0:3000";
@@ -42,4 +349,5 @@ pub mod prod {
 
 pub mod test {
     pub const APP_ADDRESS: &str = "127.0.0.1:0";
+    pub const GRPC_ADDRESS: &str = "127.0.0.1:0";
 }
@@ -0,0 +1,156 @@
+use axum_extra::extract::cookie::{Cookie, SameSite};
+use lazy_static::lazy_static;
+
+use super::constants::{
+    AUTH_COOKIE_DOMAIN, AUTH_COOKIE_HOST_PREFIX, AUTH_COOKIE_SAME_SITE, AUTH_COOKIE_SECURE,
+    HOST_PREFIXED_JWT_COOKIE_NAME, JWT_COOKIE_NAME,
+};
+
+lazy_static! {
+    pub static ref COOKIE_SETTINGS: CookieSettings = CookieSettings::new(
+        *AUTH_COOKIE_SECURE,
+        AUTH_COOKIE_DOMAIN.clone(),
+        &AUTH_COOKIE_SAME_SITE,
+        *AUTH_COOKIE_HOST_PREFIX,
+    )
+    .unwrap_or_else(|e| panic!("Invalid auth cookie settings: {}", e));
+}
+
+/// The attributes of the cookies set at login, the auth cookie and the CSRF cookie, which are
+/// set and removed alike.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CookieSettings {
+    secure: bool,
+    domain: Option<String>,
+    same_site: SameSite,
+    host_prefix: bool,
+}
+
+impl CookieSettings {
+    /// `domain`, e.g. `example.com`, shares the cookies with its subdomains. `host_prefix` names
+    /// the auth cookie `__Host-jwt`, so browsers only accept it from this host over HTTPS, which
+    /// rules out a domain.
+    pub fn new(
+        secure: bool,
+        domain: Option<String>,
+        same_site: &str,
+        host_prefix: bool,
+    ) -> Result<Self, String> {
+        let same_site = match same_site.to_lowercase().as_str() {
+            "strict" => SameSite::Strict,
+            "lax" => SameSite::Lax,
+            "none" => SameSite::None,
+            other => return Err(format!("Unknown SameSite value: {}", other)),
+        };
+        let domain = domain.filter(|domain| !domain.is_empty());
+
+        // Browsers drop cookies breaking these rules
+        if same_site == SameSite::None && !secure {
+            return Err("SameSite=None requires Secure".to_owned());
+        }
+        if host_prefix && (!secure || domain.is_some()) {
+            return Err("The __Host- prefix requires Secure and no Domain".to_owned());
+        }
+
+        Ok(Self {
+            secure,
+            domain,
+            same_site,
+            host_prefix,
+        })
+    }
+
+    pub fn auth_cookie_name(&self) -> &'static str {
+        if self.host_prefix {
+            HOST_PREFIXED_JWT_COOKIE_NAME
+        } else {
+            JWT_COOKIE_NAME
+        }
+    }
+
+    /// A cookie expiring after `max_age_seconds`, along with the token it holds.
+    pub fn build(
+        &self,
+        name: &'static str,
+        value: String,
+        http_only: bool,
+        max_age_seconds: i64,
+    ) -> Cookie<'static> {
+        let mut cookie = self.base(name);
+        cookie.set_value(value);
+        cookie.set_http_only(http_only);
+        cookie.set_max_age(time::Duration::seconds(max_age_seconds));
+        cookie
+    }
+
+    /// For removing the cookie `name`, which only works with the attributes it was set with.
+    pub fn removal(&self, name: &'static str) -> Cookie<'static> {
+        self.base(name)
+    }
+
+    fn base(&self, name: &'static str) -> Cookie<'static> {
+        let mut cookie = Cookie::build(name)
+            .path("/")
+            .secure(self.secure)
+            .same_site(self.same_site)
+            .build();
+        if let Some(domain) = &self.domain {
+            cookie.set_domain(domain.clone());
+        }
+        cookie
+    }
+}
+
+impl Default for CookieSettings {
+    /// For local development over plain HTTP.
+    fn default() -> Self {
+        Self {
+            secure: false,
+            domain: None,
+            same_site: SameSite::Lax,
+            host_prefix: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_cookies_with_the_configured_attributes() {
+        let settings =
+            CookieSettings::new(true, Some("example.com".to_owned()), "Strict", false).unwrap();
+
+        let cookie = settings.build(JWT_COOKIE_NAME, "token".to_owned(), true, 600);
+        assert_eq!(
+            cookie.to_string(),
+            "jwt=token; HttpOnly; SameSite=Strict; Secure; Path=/; Domain=example.com; Max-Age=600"
+        );
+        assert_eq!(settings.auth_cookie_name(), "jwt");
+
+        let removal = settings.removal(JWT_COOKIE_NAME);
+        assert_eq!(removal.domain(), Some("example.com"));
+        assert_eq!(removal.secure(), Some(true));
+    }
+
+    #[test]
+    fn prefixes_host_only_cookies() {
+        let settings = CookieSettings::new(true, None, "lax", true).unwrap();
+
+        assert_eq!(settings.auth_cookie_name(), "__Host-jwt");
+        let cookie = settings.build(settings.auth_cookie_name(), "token".to_owned(), true, 600);
+        assert_eq!(cookie.domain(), None);
+        assert_eq!(cookie.path(), Some("/"));
+        assert_eq!(cookie.secure(), Some(true));
+    }
+
+    #[test]
+    fn rejects_settings_browsers_would_drop() {
+        assert!(CookieSettings::new(false, None, "none", false).is_err());
+        assert!(CookieSettings::new(false, None, "lax", true).is_err());
+        assert!(CookieSettings::new(true, Some("example.com".to_owned()), "lax", true).is_err());
+        assert!(CookieSettings::new(true, None, "sometimes", false).is_err());
+        assert!(CookieSettings::new(true, None, "none", false).is_ok());
+    }
+}
@@ -0,0 +1,218 @@
+use axum::{
+    extract::Request,
+    http::{
+        header::{HOST, ORIGIN, REFERER},
+        HeaderMap, Method,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_extra::extract::{cookie::Cookie, CookieJar};
+use ring::hmac;
+use url::Url;
+
+use crate::domain::{AuthAPIError, CsrfRejection};
+
+use super::{
+    auth::TOKEN_TTL_SECONDS,
+    constants::{ALLOWED_ORIGINS, CSRF_COOKIE_NAME, CSRF_TOKEN_HEADER, JWT_SECRET},
+    cookies::COOKIE_SETTINGS,
+};
+
+/// Endpoints that act on credentials in the request rather than on the session, so a stale
+/// auth cookie, e.g. an expired one or one issued before its CSRF cookie, mustn't lock users
+/// out of logging in again. `/forward-auth` only reads the auth cookie, and proxies send it
+/// the methods of the requests they check.
+const SESSIONLESS_PATHS: &[&str] = &[
+    "/signup",
+    "/login",
+    "/login/magic-link",
+    "/login/magic-link/callback",
+    "/verify-2fa",
+    "/verify-token",
+    "/oauth/token",
+    "/oauth/introspect",
+    "/forward-auth",
+];
+
+/// The CSRF token going with `auth_token`. Being derived from it, it needs no storage, and a
+/// token set by another site can't be made to match.
+pub fn csrf_token(auth_token: &str) -> String {
+    hex::encode(hmac::sign(&csrf_key(), auth_token.as_bytes()))
+}
+
+/// Issued along with the auth cookie, expiring with it. Unlike that, scripts can read it, to
+/// send the token back in the `X-CSRF-Token` header.
+pub fn create_csrf_cookie(auth_token: &str) -> Cookie<'static> {
+    COOKIE_SETTINGS.build(
+        CSRF_COOKIE_NAME,
+        csrf_token(auth_token),
+        false,
+        TOKEN_TTL_SECONDS,
+    )
+}
+
+/// Middleware rejecting state-changing requests that carry the auth cookie, unless they come
+/// from an allowed origin and send the CSRF token issued with the cookie. Requests
+/// authenticating with a bearer token only can't be forged by another site, so they pass.
+pub async fn csrf_protection(request: Request, next: Next) -> Response {
+    if let Err(rejection) = check_request(request.method(), request.uri().path(), request.headers())
+    {
+        tracing::warn!(reason = %rejection, "Rejected possible CSRF");
+        return AuthAPIError::CsrfCheckFailed(rejection).into_response();
+    }
+
+    next.run(request).await
+}
+
+fn check_request(method: &Method, path: &str, headers: &HeaderMap) -> Result<(), CsrfRejection> {
+    if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+        || SESSIONLESS_PATHS.contains(&path)
+    {
+        return Ok(());
+    }
+    let jar = CookieJar::from_headers(headers);
+    let Some(auth_cookie) = jar.get(COOKIE_SETTINGS.auth_cookie_name()) else {
+        return Ok(());
+    };
+
+    if !is_allowed_origin(headers) {
+        return Err(CsrfRejection::CrossOrigin);
+    }
+
+    let token = headers
+        .get(CSRF_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or(CsrfRejection::MissingToken)?;
+    let tag = hex::decode(token).map_err(|_| CsrfRejection::InvalidToken)?;
+
+    hmac::verify(&csrf_key(), auth_cookie.value().as_bytes(), &tag)
+        .map_err(|_| CsrfRejection::InvalidToken)
+}
+
+/// Whether `Origin`, or else `Referer`, is this service itself or one of `ALLOWED_ORIGINS`.
+/// Requests with neither are left to the token check.
+fn is_allowed_origin(headers: &HeaderMap) -> bool {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    let Some(origin) = header(ORIGIN).or_else(|| header(REFERER)) else {
+        return true;
+    };
+    // Including `Origin: null`, sent by sandboxed frames among others
+    let Ok(url) = Url::parse(origin) else {
+        return false;
+    };
+
+    if ALLOWED_ORIGINS.contains(&url.origin().ascii_serialization().as_str()) {
+        return true;
+    }
+
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    let authority = match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_owned(),
+    };
+    header(HOST).is_some_and(|expected| expected.eq_ignore_ascii_case(&authority))
+}
+
+fn csrf_key() -> hmac::Key {
+    // Separate from the key signing auth tokens, though derived from the same secret
+    let key = hmac::Key::new(hmac::HMAC_SHA256, JWT_SECRET.as_bytes());
+    let csrf_secret = hmac::sign(&key, b"csrf");
+    hmac::Key::new(hmac::HMAC_SHA256, csrf_secret.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.parse().unwrap(), value.parse().unwrap()))
+            .collect()
+    }
+
+    fn with_auth_cookie(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = headers(pairs);
+        headers.insert("cookie", "jwt=auth-token".parse().unwrap());
+        headers.insert("host", "auth.example.com".parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn only_checks_unsafe_requests_carrying_the_auth_cookie() {
+        assert!(check_request(&Method::POST, "/logout", &headers(&[])).is_ok());
+        assert!(check_request(&Method::GET, "/", &with_auth_cookie(&[])).is_ok());
+        for path in [
+            "/forward-auth",
+            "/login",
+            "/signup",
+            "/verify-2fa",
+            "/login/magic-link",
+            "/login/magic-link/callback",
+        ] {
+            assert!(
+                check_request(&Method::POST, path, &with_auth_cookie(&[])).is_ok(),
+                "{}",
+                path
+            );
+        }
+        assert_eq!(
+            check_request(&Method::POST, "/logout", &with_auth_cookie(&[])),
+            Err(CsrfRejection::MissingToken)
+        );
+        assert_eq!(
+            check_request(&Method::DELETE, "/api-keys/ak_1", &with_auth_cookie(&[])),
+            Err(CsrfRejection::MissingToken)
+        );
+    }
+
+    #[test]
+    fn requires_the_token_issued_with_the_auth_cookie() {
+        let token = csrf_token("auth-token");
+        let valid = with_auth_cookie(&[("x-csrf-token", &token)]);
+        assert!(check_request(&Method::POST, "/logout", &valid).is_ok());
+
+        for invalid in [csrf_token("other-auth-token"), "not-hex".to_owned()] {
+            let headers = with_auth_cookie(&[("x-csrf-token", &invalid)]);
+            assert_eq!(
+                check_request(&Method::POST, "/logout", &headers),
+                Err(CsrfRejection::InvalidToken)
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_other_origins() {
+        let token = csrf_token("auth-token");
+
+        for origin in [
+            ("origin", "https://auth.example.com"),
+            ("origin", "http://localhost:8000"),
+            ("referer", "https://auth.example.com/settings?tab=keys"),
+        ] {
+            let headers = with_auth_cookie(&[origin, ("x-csrf-token", &token)]);
+            assert!(
+                check_request(&Method::POST, "/logout", &headers).is_ok(),
+                "{:?}",
+                origin
+            );
+        }
+        for origin in [
+            ("origin", "https://evil.com"),
+            ("origin", "https://auth.example.com:8443"),
+            ("origin", "null"),
+            ("referer", "https://evil.com/auth.example.com"),
+        ] {
+            let headers = with_auth_cookie(&[origin, ("x-csrf-token", &token)]);
+            assert_eq!(
+                check_request(&Method::POST, "/logout", &headers),
+                Err(CsrfRejection::CrossOrigin),
+                "{:?}",
+                origin
+            );
+        }
+    }
+}
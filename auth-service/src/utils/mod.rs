@@ -1,3 +1,8 @@
+pub mod audit;
 pub mod auth;
 pub mod constants;
+pub mod cookies;
+pub mod csrf;
+pub mod redaction;
+pub mod request_context;
 pub mod tracing;
@@ -0,0 +1,182 @@
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts, Request},
+    http::{header::USER_AGENT, request::Parts, HeaderMap},
+};
+
+use super::constants::{REQUEST_ID_HEADER, TRUSTED_PROXIES};
+
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+const MAX_REQUEST_ID_CHARS: usize = 128;
+
+/// Who is making a request: client address, user agent and request id.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequestContext {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for RequestContext
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+
+        Ok(Self::new(&parts.headers, peer))
+    }
+}
+
+impl RequestContext {
+    /// For a request with `headers` from the directly connected `peer`. `X-Forwarded-For` is
+    /// only believed when `peer` is one of `TRUSTED_PROXIES`.
+    pub fn new(headers: &HeaderMap, peer: Option<IpAddr>) -> Self {
+        Self {
+            ip_address: client_ip(headers, peer, &TRUSTED_PROXIES).map(|ip| ip.to_string()),
+            user_agent: header_value(headers, USER_AGENT.as_str()),
+            request_id: header_value(headers, REQUEST_ID_HEADER)
+                .filter(|id| is_valid_request_id(id)),
+        }
+    }
+}
+
+/// Middleware dropping request ids clients sent that are too long or contain anything but
+/// letters, digits and `-_.:`, so one is generated instead. Runs before `SetRequestIdLayer`.
+pub async fn drop_invalid_request_id(mut request: Request) -> Request {
+    let valid = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .map(|value| value.to_str().is_ok_and(is_valid_request_id));
+    if valid == Some(false) {
+        request.headers_mut().remove(REQUEST_ID_HEADER);
+    }
+
+    request
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_CHARS
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.:".contains(c))
+}
+
+/// Each proxy appends the address it got the request from, so going from the right, the first
+/// address that isn't one of our proxies is the client. Anything further left was sent by the
+/// client and can't be trusted.
+fn client_ip(
+    headers: &HeaderMap,
+    peer: Option<IpAddr>,
+    trusted_proxies: &[IpAddr],
+) -> Option<IpAddr> {
+    let peer = peer?;
+    if !trusted_proxies.contains(&peer) {
+        return Some(peer);
+    }
+
+    let forwarded = header_value(headers, FORWARDED_FOR_HEADER).unwrap_or_default();
+    let mut client = peer;
+    for ip in forwarded.rsplit(',') {
+        let Ok(ip) = ip.trim().parse::<IpAddr>() else {
+            break;
+        };
+        client = ip;
+        if !trusted_proxies.contains(&ip) {
+            break;
+        }
+    }
+
+    Some(client)
+}
+
+fn header_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(ToOwned::to_owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn forwarded_for(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(FORWARDED_FOR_HEADER, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn request_ids_are_checked() {
+        for id in [
+            "b7c2a1e0-5f3d-4c8e-9a6b-2d1f0e3c4b5a",
+            "req_42",
+            "trace.1:2",
+        ] {
+            assert!(is_valid_request_id(id), "{:?}", id);
+        }
+        for id in ["", "<script>", "two words", "a\u{301}", &"a".repeat(129)] {
+            assert!(!is_valid_request_id(id), "{:?}", id);
+        }
+    }
+
+    #[test]
+    fn ignores_forwarded_addresses_from_untrusted_peers() {
+        let headers = forwarded_for("1.2.3.4");
+
+        assert_eq!(
+            client_ip(&headers, Some(ip("203.0.113.7")), &[]),
+            Some(ip("203.0.113.7"))
+        );
+        assert_eq!(
+            client_ip(&headers, Some(ip("203.0.113.7")), &[ip("10.0.0.1")]),
+            Some(ip("203.0.113.7"))
+        );
+        assert_eq!(client_ip(&headers, None, &[]), None);
+    }
+
+    #[test]
+    fn takes_the_right_most_untrusted_forwarded_address() {
+        let proxies = [ip("10.0.0.1"), ip("10.0.0.2")];
+
+        // The client made up the first address
+        let headers = forwarded_for("1.2.3.4, 198.51.100.9, 10.0.0.2");
+        assert_eq!(
+            client_ip(&headers, Some(ip("10.0.0.1")), &proxies),
+            Some(ip("198.51.100.9"))
+        );
+
+        let headers = forwarded_for("garbage, 198.51.100.9");
+        assert_eq!(
+            client_ip(&headers, Some(ip("10.0.0.1")), &proxies),
+            Some(ip("198.51.100.9"))
+        );
+
+        // Nothing past our own proxies
+        assert_eq!(
+            client_ip(&forwarded_for("10.0.0.2"), Some(ip("10.0.0.1")), &proxies),
+            Some(ip("10.0.0.2"))
+        );
+        assert_eq!(
+            client_ip(&HeaderMap::new(), Some(ip("10.0.0.1")), &proxies),
+            Some(ip("10.0.0.1"))
+        );
+    }
+}
@@ -1,20 +1,84 @@
 use axum::{body::Body, extract::Request, response::Response};
-use std::time::Duration;
-use tracing::{Level, Span};
+use color_eyre::eyre::{eyre, Result};
+use serde_json::{Map, Value};
+use std::{fmt, str::FromStr, time::Duration};
+use tracing::{
+    field::{Field, Visit},
+    span::Record,
+    Event, Level, Span, Subscriber,
+};
+use tracing_error::ErrorLayer;
+use tracing_subscriber::{
+    field::RecordFields,
+    fmt::{format::Writer, FmtContext, FormatEvent, FormatFields, FormattedFields},
+    prelude::*,
+    registry::LookupSpan,
+    EnvFilter,
+};
 
-pub fn init_tracing() {
-    let fmt_layer = fmt::layer().compact();
+use super::{
+    constants::{LOG_FORMAT, REQUEST_ID_HEADER},
+    redaction::{is_sensitive_field, redact_field, REDACTED},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Compact,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "compact" => Ok(Self::Compact),
+            "json" => Ok(Self::Json),
+            other => Err(eyre!("Unknown log format: {}", other)),
+        }
+    }
+}
+
+pub fn init_tracing() -> Result<()> {
+    let log_format = LOG_FORMAT.parse::<LogFormat>()?;
     let filter_layer = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new("info"))?;
 
+    let (compact_layer, json_layer) = match log_format {
+        LogFormat::Compact => (
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .compact()
+                    .fmt_fields(RedactingFields),
+            ),
+            None,
+        ),
+        LogFormat::Json => (
+            None,
+            Some(
+                tracing_subscriber::fmt::layer()
+                    .fmt_fields(RedactingJsonFields)
+                    .event_format(RedactingJsonFormat),
+            ),
+        ),
+    };
+
     tracing_subscriber::registry()
         .with(filter_layer)
-        .with(fmt_layer)
+        .with(compact_layer)
+        .with(json_layer)
         .with(ErrorLayer::default())
-        .init();
+        .try_init()?;
+
+    Ok(())
 }
 
 pub fn make_span_with_request_id(request: &Request<Body>) -> Span {
-    let request_id = uuid::Uuid::new_v4();
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(ToOwned::to_owned)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
 
     tracing::span!(
         Level::INFO,
@@ -54,3 +118,227 @@ pub fn on_response(response: &Response, latency: Duration, _span: &Span) {
         }
     };
 }
+
+/// Formats fields as `name=value` pairs, redacting sensitive values.
+pub struct RedactingFields;
+
+impl<'writer> FormatFields<'writer> for RedactingFields {
+    fn format_fields<R: RecordFields>(
+        &self,
+        mut writer: Writer<'writer>,
+        fields: R,
+    ) -> fmt::Result {
+        let mut visitor = KeyValueVisitor {
+            writer: &mut writer,
+            is_empty: true,
+            result: Ok(()),
+        };
+        fields.record(&mut visitor);
+        visitor.result
+    }
+}
+
+struct KeyValueVisitor<'a, 'writer> {
+    writer: &'a mut Writer<'writer>,
+    is_empty: bool,
+    result: fmt::Result,
+}
+
+impl Visit for KeyValueVisitor<'_, '_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if self.result.is_err() {
+            return;
+        }
+
+        let value = redact_field(field.name(), value);
+        let separator = if self.is_empty { "" } else { " " };
+        self.is_empty = false;
+
+        self.result = if field.name() == "message" {
+            write!(self.writer, "{}{}", separator, value)
+        } else {
+            write!(self.writer, "{}{}={}", separator, field.name(), value)
+        };
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record_str(field, &format!("{:?}", value));
+    }
+}
+
+/// Formats fields as a JSON object, redacting sensitive values.
+pub struct RedactingJsonFields;
+
+impl<'writer> FormatFields<'writer> for RedactingJsonFields {
+    fn format_fields<R: RecordFields>(
+        &self,
+        mut writer: Writer<'writer>,
+        fields: R,
+    ) -> fmt::Result {
+        let mut visitor = JsonVisitor::default();
+        fields.record(&mut visitor);
+        write!(writer, "{}", Value::Object(visitor.0))
+    }
+
+    fn add_fields(
+        &self,
+        current: &'writer mut FormattedFields<Self>,
+        fields: &Record<'_>,
+    ) -> fmt::Result {
+        let existing =
+            serde_json::from_str::<Map<String, Value>>(&current.fields).unwrap_or_default();
+        let mut visitor = JsonVisitor(existing);
+        fields.record(&mut visitor);
+        current.fields = Value::Object(visitor.0).to_string();
+        Ok(())
+    }
+}
+
+/// Fields recorded as a JSON array string, which are written out as an actual array so JSON log
+/// consumers can query their items.
+const JSON_ARRAY_FIELDS: [&str; 1] = ["error.causes"];
+
+#[derive(Default)]
+struct JsonVisitor(Map<String, Value>);
+
+impl JsonVisitor {
+    fn insert(&mut self, field: &Field, value: Value) {
+        let value = if is_sensitive_field(field.name()) {
+            Value::from(REDACTED)
+        } else {
+            value
+        };
+        self.0.insert(field.name().to_owned(), value);
+    }
+}
+
+impl Visit for JsonVisitor {
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, Value::from(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if JSON_ARRAY_FIELDS.contains(&field.name()) {
+            if let Ok(array @ Value::Array(_)) = serde_json::from_str(value) {
+                self.insert(field, array);
+                return;
+            }
+        }
+
+        let value = redact_field(field.name(), value).into_owned();
+        self.insert(field, Value::String(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record_str(field, &format!("{:?}", value));
+    }
+}
+
+/// Writes each event as a single JSON line with its span context.
+pub struct RedactingJsonFormat;
+
+impl<S> FormatEvent<S, RedactingJsonFields> for RedactingJsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, RedactingJsonFields>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let metadata = event.metadata();
+
+        let mut fields = JsonVisitor::default();
+        event.record(&mut fields);
+
+        let spans: Vec<Value> = ctx
+            .event_scope()
+            .map(|scope| {
+                scope
+                    .from_root()
+                    .map(|span| {
+                        let extensions = span.extensions();
+                        let mut object = extensions
+                            .get::<FormattedFields<RedactingJsonFields>>()
+                            .and_then(|f| {
+                                serde_json::from_str::<Map<String, Value>>(&f.fields).ok()
+                            })
+                            .unwrap_or_default();
+                        object.insert("name".to_owned(), Value::from(span.name()));
+                        Value::Object(object)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut line = Map::new();
+        line.insert(
+            "timestamp".to_owned(),
+            Value::from(chrono::Utc::now().to_rfc3339()),
+        );
+        line.insert("level".to_owned(), Value::from(metadata.level().as_str()));
+        line.insert("target".to_owned(), Value::from(metadata.target()));
+        line.insert("fields".to_owned(), Value::Object(fields.0));
+        if let Some(span) = spans.last() {
+            line.insert("span".to_owned(), span.clone());
+        }
+        line.insert("spans".to_owned(), Value::Array(spans));
+
+        writeln!(writer, "{}", Value::Object(line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn error_causes_are_json_arrays() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .fmt_fields(RedactingJsonFields)
+            .event_format(RedactingJsonFormat)
+            .with_writer(move || writer.clone())
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let causes = Value::from(vec!["connection reset", "broken pipe"]);
+            tracing::error!(error.causes = %causes, "Request failed");
+        });
+
+        let line = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let line: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            line["fields"]["error.causes"],
+            serde_json::json!(["connection reset", "broken pipe"])
+        );
+    }
+}
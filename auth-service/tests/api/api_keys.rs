@@ -0,0 +1,162 @@
+use auth_service::{
+    domain::MANAGE_API_KEYS_SCOPE,
+    routes::{ApiKeysResponse, CreateApiKeyResponse, UserInfoResponse},
+    ErrorResponse,
+};
+use test_helpers::api_test;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn sign_up_and_log_in(app: &TestApp) -> String {
+    let email = get_random_email();
+    let body = serde_json::json!({
+        "email": email,
+        "password": "spoon-galaxy-trumpet-47",
+        "requires2FA": false
+    });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+
+    let body = serde_json::json!({
+        "email": email,
+        "password": "spoon-galaxy-trumpet-47"
+    });
+    assert_eq!(app.post_login(&body).await.status().as_u16(), 200);
+
+    email
+}
+
+async fn create_key(app: &TestApp, bearer: Option<&str>, scopes: &[&str]) -> CreateApiKeyResponse {
+    let body = serde_json::json!({ "name": "CI", "scopes": scopes });
+    let response = app.post_api_key(bearer, &body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    response
+        .json::<CreateApiKeyResponse>()
+        .await
+        .expect("Could not deserialize response body to CreateApiKeyResponse")
+}
+
+async fn verify(app: &TestApp, token: &str) -> u16 {
+    let body = serde_json::json!({ "token": token });
+    app.post_verify_token(&body).await.status().as_u16()
+}
+
+async fn error_reason(response: reqwest::Response) -> serde_json::Value {
+    let error = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    error.details.expect("No error details")["reason"].clone()
+}
+
+#[api_test]
+async fn should_create_list_and_revoke_api_keys() {
+    sign_up_and_log_in(&app).await;
+
+    let created = create_key(&app, None, &["user:write", "user:read"]).await;
+    assert!(created.key.starts_with("ak_"));
+    assert_eq!(created.api_key.name, "CI");
+    assert_eq!(created.api_key.scopes, vec!["user:read", "user:write"]);
+    assert_eq!(verify(&app, &created.key).await, 200);
+
+    // The full key is never shown again
+    let response = app.get_api_keys(None).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response.text().await.unwrap();
+    assert!(!body.contains(&created.key));
+    let listed = serde_json::from_str::<ApiKeysResponse>(&body).unwrap();
+    assert_eq!(listed.api_keys.len(), 1);
+    assert_eq!(listed.api_keys[0].prefix, created.api_key.prefix);
+
+    let response = app.delete_api_key(None, &created.api_key.prefix).await;
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(verify(&app, &created.key).await, 401);
+
+    let response = app.delete_api_key(None, &created.api_key.prefix).await;
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[api_test]
+async fn should_authenticate_with_an_api_key_as_bearer_token() {
+    let email = sign_up_and_log_in(&app).await;
+    let created = create_key(&app, None, &["user:read"]).await;
+    let manager = create_key(&app, None, &[MANAGE_API_KEYS_SCOPE]).await;
+    assert_eq!(app.post_logout().await.status().as_u16(), 200);
+
+    // Managing keys needs its own scope
+    let response = app.get_api_keys(Some(&created.key)).await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app
+        .delete_api_key(Some(&created.key), &manager.api_key.prefix)
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app.get_api_keys(Some(&manager.key)).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_oidc_userinfo(&created.key).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let userinfo = response.json::<UserInfoResponse>().await.unwrap();
+    assert_eq!(userinfo.email, email);
+
+    // Keys made with a key can't grant more than it has
+    let body = serde_json::json!({ "name": "CI", "scopes": ["user:write"] });
+    let response = app.post_api_key(Some(&created.key), &body).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(error_reason(response).await, "scope_not_granted");
+    create_key(&app, Some(&created.key), &["user:read"]).await;
+}
+
+#[api_test]
+async fn should_return_400_for_invalid_key_requests() {
+    sign_up_and_log_in(&app).await;
+
+    let test_cases = [
+        (serde_json::json!({ "name": " " }), "invalid_name"),
+        (
+            serde_json::json!({ "name": "CI", "scopes": ["Read Users"] }),
+            "invalid_scope",
+        ),
+        (
+            serde_json::json!({ "name": "CI", "scopes": ["read:users"] }),
+            "invalid_scope",
+        ),
+        (
+            serde_json::json!({ "name": "CI", "expiresInDays": 0 }),
+            "invalid_expiry",
+        ),
+        (
+            serde_json::json!({ "name": "CI", "expiresInDays": 366 }),
+            "invalid_expiry",
+        ),
+    ];
+
+    for (body, reason) in test_cases {
+        let response = app.post_api_key(None, &body).await;
+        assert_eq!(response.status().as_u16(), 400, "{}", body);
+        assert_eq!(error_reason(response).await, reason);
+    }
+}
+
+#[api_test]
+async fn should_require_credentials() {
+    let response = app.get_api_keys(None).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    for token in ["ak_unknown_secret", "invalid_token"] {
+        let response = app.get_api_keys(Some(token)).await;
+        assert_eq!(response.status().as_u16(), 401);
+        assert_eq!(verify(&app, token).await, 401);
+    }
+}
+
+#[api_test]
+async fn should_not_revoke_keys_of_other_users() {
+    sign_up_and_log_in(&app).await;
+    let created = create_key(&app, None, &[]).await;
+
+    // Logging in as someone else replaces the auth cookie
+    sign_up_and_log_in(&app).await;
+    let response = app.delete_api_key(None, &created.api_key.prefix).await;
+    assert_eq!(response.status().as_u16(), 404);
+    assert_eq!(verify(&app, &created.key).await, 200);
+}
@@ -0,0 +1,178 @@
+use auth_service::{routes::AuditEventsResponse, ErrorResponse};
+use test_helpers::api_test;
+
+use crate::helpers::{get_random_email, TestApp, TEST_AUDIT_API_TOKEN};
+
+#[api_test]
+async fn should_record_signup_and_login_events() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "spoon-galaxy-trumpet-47",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "spoon-galaxy-trumpet-47",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .get_audit_events(TEST_AUDIT_API_TOKEN, &[("email", random_email.as_str())])
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let events = response
+        .json::<AuditEventsResponse>()
+        .await
+        .expect("Could not deserialize response body to AuditEventsResponse")
+        .events;
+
+    let event_types: Vec<&str> = events.iter().map(|e| e.event_type.as_str()).collect();
+    assert_eq!(event_types, vec!["login", "signup"]);
+
+    for event in events {
+        assert!(event.success);
+        assert_eq!(event.email.as_deref(), Some(random_email.as_str()));
+        assert_eq!(event.ip_address.as_deref(), Some("127.0.0.1"));
+        assert!(event.request_id.is_some());
+    }
+}
+
+#[api_test]
+async fn should_record_failed_login_with_reason() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "spoon-galaxy-trumpet-47",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    // Recorded with the canonical email, not as typed
+    let (local_part, domain) = random_email.split_once('@').unwrap();
+    let login_body = serde_json::json!({
+        "email": format!(" {}@{} ", local_part, domain.to_uppercase()),
+        "password": "wrong-password",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .get_audit_events(TEST_AUDIT_API_TOKEN, &[("email", random_email.as_str())])
+        .await;
+
+    let events = response
+        .json::<AuditEventsResponse>()
+        .await
+        .expect("Could not deserialize response body to AuditEventsResponse")
+        .events;
+
+    let login_event = events
+        .iter()
+        .find(|e| e.event_type == "login")
+        .expect("No login event recorded");
+
+    assert!(!login_event.success);
+    assert_eq!(login_event.reason.as_deref(), Some("incorrect_credentials"));
+    assert_eq!(login_event.email.as_deref(), Some(random_email.as_str()));
+}
+
+#[api_test]
+async fn should_record_magic_link_requests_with_the_canonical_email() {
+    let random_email = get_random_email();
+
+    let (local_part, domain) = random_email.split_once('@').unwrap();
+    let response = app
+        .post_magic_link(&serde_json::json!({
+            "email": format!(" {}@{} ", local_part, domain.to_uppercase())
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .get_audit_events(TEST_AUDIT_API_TOKEN, &[("email", random_email.as_str())])
+        .await;
+
+    let events = response
+        .json::<AuditEventsResponse>()
+        .await
+        .expect("Could not deserialize response body to AuditEventsResponse")
+        .events;
+
+    let event = events
+        .iter()
+        .find(|e| e.event_type == "magic_link_issued")
+        .expect("No magic link event recorded");
+
+    assert!(!event.success);
+    assert_eq!(event.reason.as_deref(), Some("user_not_found"));
+    assert_eq!(event.email.as_deref(), Some(random_email.as_str()));
+}
+
+#[api_test]
+async fn should_filter_events_by_time_range() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "spoon-galaxy-trumpet-47",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let from = (chrono::Utc::now() + chrono::Duration::minutes(1)).to_rfc3339();
+
+    let response = app
+        .get_audit_events(
+            TEST_AUDIT_API_TOKEN,
+            &[("email", random_email.as_str()), ("from", from.as_str())],
+        )
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let events = response
+        .json::<AuditEventsResponse>()
+        .await
+        .expect("Could not deserialize response body to AuditEventsResponse")
+        .events;
+
+    assert!(events.is_empty());
+}
+
+#[api_test]
+async fn should_return_401_if_invalid_api_token() {
+    let response = app.get_audit_events("invalid-token", &[]).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid auth token".to_owned()
+    );
+}
@@ -0,0 +1,150 @@
+use auth_service::{
+    domain::ProofOfWorkMode,
+    routes::ChallengeResponse,
+    services::proof_of_work::{is_solved, ProofOfWorkConfig},
+    ErrorResponse,
+};
+use test_helpers::api_test;
+
+use crate::helpers::{get_random_email, TestApp};
+
+fn solve(challenge: &ChallengeResponse) -> String {
+    (0u64..)
+        .map(|nonce| nonce.to_string())
+        .find(|nonce| is_solved(&challenge.challenge, nonce, challenge.difficulty))
+        .unwrap()
+}
+
+async fn get_challenge(app: &TestApp, action: &str) -> ChallengeResponse {
+    let response = app.get_challenge(action).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<ChallengeResponse>()
+        .await
+        .expect("Could not deserialize response body to ChallengeResponse")
+}
+
+#[api_test]
+async fn should_return_200_with_a_challenge() {
+    let challenge = get_challenge(&app, "signup").await;
+
+    assert!(!challenge.challenge.is_empty());
+    assert_eq!(challenge.algorithm, "sha256");
+    assert_eq!(challenge.expires_in, 300);
+    assert!(!challenge.required);
+}
+
+#[api_test]
+async fn should_return_400_if_action_is_unknown() {
+    for query in ["logout", ""] {
+        let response = app.get_challenge(query).await;
+        assert_eq!(response.status().as_u16(), 400, "Failed for: {}", query);
+    }
+}
+
+#[tokio::test]
+async fn should_require_a_solved_challenge_to_sign_up_and_log_in() {
+    let config = ProofOfWorkConfig::new(ProofOfWorkMode::Always, 8, 8, 100).unwrap();
+    let mut app = TestApp::with_proof_of_work(config).await;
+
+    let body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "spoon-galaxy-trumpet-47",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status().as_u16(), 428);
+    let error = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(
+        error.details,
+        Some(serde_json::json!({ "challengeUrl": "/challenge?action=signup" }))
+    );
+
+    let challenge = get_challenge(&app, "signup").await;
+    assert!(challenge.required);
+    assert_eq!(challenge.difficulty, 8);
+    let nonce = solve(&challenge);
+
+    let response = app
+        .post_with_proof_of_work("/signup", &body, &challenge.challenge, &nonce)
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    // Each solution is only good for one attempt
+    let response = app
+        .post_with_proof_of_work("/signup", &body, &challenge.challenge, &nonce)
+        .await;
+    assert_eq!(response.status().as_u16(), 428);
+
+    let login_body = serde_json::json!({
+        "email": body["email"],
+        "password": body["password"]
+    });
+
+    // A signup challenge can't be spent on login
+    let signup_challenge = get_challenge(&app, "signup").await;
+    let response = app
+        .post_with_proof_of_work(
+            "/login",
+            &login_body,
+            &signup_challenge.challenge,
+            &solve(&signup_challenge),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 428);
+
+    let challenge = get_challenge(&app, "login").await;
+    let response = app
+        .post_with_proof_of_work(
+            "/login",
+            &login_body,
+            &challenge.challenge,
+            &solve(&challenge),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_a_solved_challenge_once_under_attack() {
+    let config = ProofOfWorkConfig::new(ProofOfWorkMode::Adaptive, 8, 12, 2).unwrap();
+    let mut app = TestApp::with_proof_of_work(config).await;
+
+    let login_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "wrong-password"
+    });
+
+    for _ in 0..2 {
+        let response = app.post_login(&login_body).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 428);
+
+    let challenge = get_challenge(&app, "login").await;
+    assert!(challenge.required);
+    let response = app
+        .post_with_proof_of_work(
+            "/login",
+            &login_body,
+            &challenge.challenge,
+            &solve(&challenge),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Signups are counted separately
+    let challenge = get_challenge(&app, "signup").await;
+    assert!(!challenge.required);
+
+    app.clean_up().await;
+}
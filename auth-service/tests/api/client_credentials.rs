@@ -0,0 +1,155 @@
+use auth_service::{
+    services::oidc::{OidcClient, OidcClientConfig, ServiceTokenResponse},
+    utils::constants::JWT_COOKIE_NAME,
+};
+use sha2::{Digest, Sha256};
+
+use crate::helpers::{get_random_email, TestApp};
+
+const CLIENT_ID: &str = "billing";
+const CLIENT_SECRET: &str = "billing-secret";
+
+async fn setup() -> TestApp {
+    let client = OidcClient::from_config(OidcClientConfig {
+        client_id: CLIENT_ID.to_owned(),
+        client_secret_hash: hex::encode(Sha256::digest(CLIENT_SECRET)),
+        audiences: vec!["users-api".to_owned(), "orders-api".to_owned()],
+        scopes: vec!["read:users".to_owned(), "read:orders".to_owned()],
+        ..Default::default()
+    })
+    .unwrap();
+
+    TestApp::with_oidc_clients(vec![client]).await
+}
+
+async fn request_token(app: &TestApp, form: &[(&str, &str)]) -> reqwest::Response {
+    let mut form = form.to_vec();
+    form.push(("grant_type", "client_credentials"));
+    app.post_oidc_token(CLIENT_ID, CLIENT_SECRET, &form).await
+}
+
+async fn verify(app: &TestApp, body: serde_json::Value) -> u16 {
+    app.post_verify_token(&body).await.status().as_u16()
+}
+
+async fn oauth_error(response: reqwest::Response) -> String {
+    let body = response.json::<serde_json::Value>().await.unwrap();
+    body["error"].as_str().unwrap().to_owned()
+}
+
+#[tokio::test]
+async fn should_issue_audience_restricted_service_tokens() {
+    let mut app = setup().await;
+
+    let response = request_token(&app, &[("audience", "users-api"), ("scope", "read:users")]).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["cache-control"], "no-store");
+    let tokens = response.json::<ServiceTokenResponse>().await.unwrap();
+    assert_eq!(tokens.token_type, "Bearer");
+    assert_eq!(tokens.scope, "read:users");
+    let token = tokens.access_token;
+
+    let body = serde_json::json!({
+        "token": token,
+        "audience": "users-api",
+        "scopes": ["read:users"]
+    });
+    assert_eq!(verify(&app, body).await, 200);
+
+    let body = serde_json::json!({
+        "token": token,
+        "audience": "users-api",
+        "scopes": ["read:orders"]
+    });
+    assert_eq!(verify(&app, body).await, 403);
+
+    // Other services, and anything expecting a user, must not accept it
+    let body = serde_json::json!({ "token": token, "audience": "orders-api" });
+    assert_eq!(verify(&app, body).await, 401);
+    assert_eq!(
+        verify(&app, serde_json::json!({ "token": token })).await,
+        401
+    );
+    let response = app.get_oidc_userinfo(&token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_audiences_and_scopes_the_client_lacks() {
+    let mut app = setup().await;
+
+    let test_cases = [
+        (vec![], "invalid_target"),
+        (vec![("audience", "admin-api")], "invalid_target"),
+        (
+            vec![("audience", "users-api"), ("scope", "write:users")],
+            "invalid_scope",
+        ),
+    ];
+    for (form, error) in test_cases {
+        let response = request_token(&app, &form).await;
+        assert_eq!(response.status().as_u16(), 400, "{:?}", form);
+        assert_eq!(oauth_error(response).await, error);
+    }
+
+    let response = app
+        .post_oidc_token(
+            CLIENT_ID,
+            "wrong-secret",
+            &[
+                ("grant_type", "client_credentials"),
+                ("audience", "users-api"),
+            ],
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(oauth_error(response).await, "invalid_client");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_grant_service_scopes_to_user_tokens() {
+    let mut app = setup().await;
+
+    let email = get_random_email();
+    let body = serde_json::json!({
+        "email": email,
+        "password": "spoon-galaxy-trumpet-47",
+        "requires2FA": false
+    });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+    let body = serde_json::json!({ "email": email, "password": "spoon-galaxy-trumpet-47" });
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let body = serde_json::json!({ "token": token, "scopes": ["read:users"] });
+    assert_eq!(verify(&app, body).await, 403);
+    let body = serde_json::json!({ "token": token, "audience": "users-api" });
+    assert_eq!(verify(&app, body).await, 403);
+
+    // Users can't name service scopes for their keys, and their own don't count either
+    let body = serde_json::json!({ "name": "CI", "scopes": ["read:users"] });
+    assert_eq!(app.post_api_key(None, &body).await.status().as_u16(), 400);
+    let body = serde_json::json!({ "name": "CI", "scopes": ["user:read"] });
+    let response = app.post_api_key(None, &body).await;
+    let key = response.json::<serde_json::Value>().await.unwrap()["key"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+
+    let body = serde_json::json!({ "token": key });
+    assert_eq!(verify(&app, body).await, 200);
+    let body = serde_json::json!({ "token": key, "scopes": ["user:read"] });
+    assert_eq!(verify(&app, body).await, 403);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,119 @@
+use auth_service::{utils::constants::CSRF_COOKIE_NAME, ErrorResponse};
+use test_helpers::api_test;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn log_in(app: &TestApp) {
+    let email = get_random_email();
+    let body = serde_json::json!({
+        "email": email,
+        "password": "spoon-galaxy-trumpet-47",
+        "requires2FA": false
+    });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+
+    let body = serde_json::json!({ "email": email, "password": "spoon-galaxy-trumpet-47" });
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let csrf_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == CSRF_COOKIE_NAME)
+        .expect("No CSRF cookie found");
+    assert!(!csrf_cookie.http_only());
+}
+
+async fn post_logout_with(app: &TestApp, headers: &[(&str, &str)]) -> reqwest::Response {
+    let mut request = app.http_client.post(format!("{}/logout", &app.address));
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+
+    request.send().await.expect("Failed to execute request.")
+}
+
+async fn assert_rejected(response: reqwest::Response, reason: &str) {
+    assert_eq!(response.status().as_u16(), 403);
+    let body = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(body.details.unwrap()["reason"], reason);
+}
+
+#[api_test]
+async fn should_reject_cookie_requests_without_the_csrf_token() {
+    log_in(&app).await;
+    let token = app.csrf_token().expect("No CSRF token");
+
+    assert_rejected(post_logout_with(&app, &[]).await, "missing_token").await;
+    let other_token = "00".repeat(32);
+    assert_rejected(
+        post_logout_with(&app, &[("x-csrf-token", &other_token)]).await,
+        "invalid_token",
+    )
+    .await;
+    assert_rejected(
+        post_logout_with(
+            &app,
+            &[("x-csrf-token", &token), ("origin", "https://evil.com")],
+        )
+        .await,
+        "cross_origin",
+    )
+    .await;
+
+    let response = post_logout_with(
+        &app,
+        &[
+            ("x-csrf-token", &token),
+            ("origin", "http://localhost:8000"),
+        ],
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(app.csrf_token().is_none());
+}
+
+#[api_test]
+async fn should_let_users_with_a_stale_auth_cookie_log_in_again() {
+    let email = get_random_email();
+    let body = serde_json::json!({
+        "email": email,
+        "password": "spoon-galaxy-trumpet-47",
+        "requires2FA": false
+    });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+
+    // As if issued before CSRF protection, without a CSRF cookie
+    let url = app.address.parse().unwrap();
+    app.cookie_jar
+        .add_cookie_str("jwt=expired-token; HttpOnly; SameSite=Lax", &url);
+    assert!(app.csrf_token().is_none());
+
+    let body = serde_json::json!({ "email": email, "password": "spoon-galaxy-trumpet-47" });
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(app.csrf_token().is_some());
+}
+
+#[api_test]
+async fn should_not_check_requests_without_the_auth_cookie() {
+    log_in(&app).await;
+    let body = serde_json::json!({ "name": "CI", "scopes": ["user:read"] });
+    let response = app.post_api_key(None, &body).await;
+    assert_eq!(response.status().as_u16(), 201);
+    let key = response.json::<serde_json::Value>().await.unwrap()["key"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/api-keys", &app.address))
+        .bearer_auth(&key)
+        .header("origin", "https://evil.com")
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 201);
+}
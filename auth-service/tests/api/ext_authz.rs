@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+use auth_service::{
+    grpc::proto::envoy::service::auth::v3::{
+        attribute_context::{HttpRequest, Request},
+        check_response::HttpResponse,
+        AttributeContext, CheckRequest, CheckResponse,
+    },
+    utils::constants::JWT_COOKIE_NAME,
+};
+use test_helpers::api_test;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn log_in(app: &TestApp) -> (String, String) {
+    let email = get_random_email();
+    let body = serde_json::json!({
+        "email": email,
+        "password": "spoon-galaxy-trumpet-47",
+        "requires2FA": false
+    });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+
+    let body = serde_json::json!({ "email": email, "password": "spoon-galaxy-trumpet-47" });
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    (email, token)
+}
+
+async fn check(app: &TestApp, headers: &[(&str, &str)]) -> CheckResponse {
+    let request = CheckRequest {
+        attributes: Some(AttributeContext {
+            request: Some(Request {
+                http: Some(HttpRequest {
+                    method: "GET".to_owned(),
+                    path: "/protected".to_owned(),
+                    headers: headers
+                        .iter()
+                        .map(|(name, value)| (name.to_string(), value.to_string()))
+                        .collect(),
+                    ..Default::default()
+                }),
+            }),
+            context_extensions: HashMap::new(),
+        }),
+    };
+
+    app.ext_authz_client()
+        .await
+        .check(request)
+        .await
+        .expect("Check failed")
+        .into_inner()
+}
+
+/// The headers added to allowed requests, with removed ones as `None`, or `None` if the request
+/// was denied.
+fn upstream_headers(response: CheckResponse) -> Option<HashMap<String, Option<String>>> {
+    match response.http_response? {
+        HttpResponse::OkResponse(ok) => Some(
+            ok.headers
+                .into_iter()
+                .filter_map(|option| option.header)
+                .map(|header| (header.key, Some(header.value)))
+                .chain(ok.headers_to_remove.into_iter().map(|name| (name, None)))
+                .collect(),
+        ),
+        HttpResponse::DeniedResponse(_) => None,
+    }
+}
+
+#[api_test]
+async fn should_allow_requests_with_a_valid_cookie_or_bearer_token() {
+    let (email, token) = log_in(&app).await;
+
+    let cookie = format!("theme=dark; {}={}", JWT_COOKIE_NAME, token);
+    // Roles the client made up are removed
+    let response = check(&app, &[("cookie", &cookie), ("x-auth-roles", "admin")]).await;
+    assert_eq!(response.status.as_ref().unwrap().code, 0);
+    let headers = upstream_headers(response).expect("Request was denied");
+    assert_eq!(headers["x-auth-user"].as_ref(), Some(&email));
+    assert_eq!(headers["x-auth-roles"], None);
+
+    let body = serde_json::json!({ "name": "CI", "scopes": ["user:read"] });
+    let response = app.post_api_key(None, &body).await;
+    let key = response.json::<serde_json::Value>().await.unwrap()["key"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+
+    let bearer = format!("Bearer {}", key);
+    let response = check(&app, &[("authorization", &bearer)]).await;
+    let headers = upstream_headers(response).expect("Request was denied");
+    assert_eq!(headers["x-auth-user"].as_ref(), Some(&email));
+    // Users pick their keys' scopes, so they aren't roles
+    assert_eq!(headers["x-auth-roles"], None);
+}
+
+#[api_test]
+async fn should_deny_requests_without_a_valid_token() {
+    let (_, token) = log_in(&app).await;
+
+    let response = check(&app, &[]).await;
+    assert_eq!(response.status.as_ref().unwrap().code, 16);
+    match response.http_response {
+        Some(HttpResponse::DeniedResponse(denied)) => {
+            assert_eq!(denied.status.unwrap().code, 401);
+        }
+        _ => panic!("Request wasn't denied"),
+    }
+
+    let response = check(&app, &[("authorization", "Bearer invalid_token")]).await;
+    assert!(upstream_headers(response).is_none());
+
+    // Logged out tokens are banned
+    assert_eq!(app.post_logout().await.status().as_u16(), 200);
+    let bearer = format!("Bearer {}", token);
+    let response = check(&app, &[("authorization", &bearer)]).await;
+    assert!(upstream_headers(response).is_none());
+}
@@ -0,0 +1,106 @@
+use test_helpers::api_test;
+
+use crate::helpers::{get_random_email, TestApp, FORWARD_AUTH_TEST_LOGIN_URL};
+
+async fn sign_up_and_log_in(app: &TestApp) -> String {
+    let email = get_random_email();
+    let body = serde_json::json!({
+        "email": email,
+        "password": "spoon-galaxy-trumpet-47",
+        "requires2FA": false
+    });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+
+    let body = serde_json::json!({
+        "email": email,
+        "password": "spoon-galaxy-trumpet-47"
+    });
+    assert_eq!(app.post_login(&body).await.status().as_u16(), 200);
+
+    email
+}
+
+fn location(response: &reqwest::Response) -> &str {
+    assert_eq!(response.status().as_u16(), 303);
+    response.headers()["location"].to_str().unwrap()
+}
+
+#[api_test]
+async fn should_name_the_user_of_valid_tokens() {
+    let email = sign_up_and_log_in(&app).await;
+
+    let response = app.get_forward_auth("/forward-auth", &[]).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["x-auth-user"], email.as_str());
+    assert_eq!(response.headers()["x-auth-roles"], "");
+
+    let body = serde_json::json!({ "name": "CI", "scopes": ["user:wiki-write", "user:wiki-read"] });
+    let response = app.post_api_key(None, &body).await;
+    let key = response.json::<serde_json::Value>().await.unwrap()["key"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+    assert_eq!(app.post_logout().await.status().as_u16(), 200);
+
+    let bearer = format!("Bearer {}", key);
+    let response = app
+        .get_forward_auth("/forward-auth", &[("authorization", &bearer)])
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["x-auth-user"], email.as_str());
+    // Users pick their keys' scopes, so they aren't roles
+    assert_eq!(response.headers()["x-auth-roles"], "");
+}
+
+#[api_test]
+async fn should_return_401_without_a_valid_token() {
+    let response = app.get_forward_auth("/forward-auth", &[]).await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert!(response.headers().get("x-auth-user").is_none());
+
+    let response = app
+        .get_forward_auth(
+            "/forward-auth",
+            &[("authorization", "Bearer invalid_token")],
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_redirect_to_login_and_back_to_allowed_urls() {
+    // As Traefik sends it
+    let headers = [
+        ("x-forwarded-proto", "https"),
+        ("x-forwarded-host", "app.test"),
+        ("x-forwarded-uri", "/wiki?page=1"),
+    ];
+    let response = app
+        .get_forward_auth("/forward-auth?redirect=true", &headers)
+        .await;
+    let login_url = reqwest::Url::parse(location(&response)).unwrap();
+    assert!(login_url.as_str().starts_with(FORWARD_AUTH_TEST_LOGIN_URL));
+    let return_to = login_url
+        .query_pairs()
+        .find(|(name, _)| name == "return_to")
+        .map(|(_, value)| value.into_owned())
+        .unwrap();
+    assert!(return_to.starts_with("/forward-auth/return?"));
+
+    let response = app.get_forward_auth(&return_to, &[]).await;
+    assert_eq!(location(&response), "https://app.test/wiki?page=1");
+
+    // As nginx is usually set up to send it
+    let response = app
+        .get_forward_auth(
+            "/forward-auth?redirect=true",
+            &[("x-original-url", "https://evil.test/")],
+        )
+        .await;
+    assert_eq!(location(&response), FORWARD_AUTH_TEST_LOGIN_URL);
+
+    let response = app
+        .get_forward_auth("/forward-auth/return?url=https%3A%2F%2Fevil.test%2F", &[])
+        .await;
+    assert_eq!(location(&response), "/");
+}
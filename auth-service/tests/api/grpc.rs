@@ -0,0 +1,240 @@
+use auth_service::{
+    grpc::proto::{IntrospectTokenRequest, RevokeTokenRequest, VerifyTokenRequest},
+    services::oidc::{OidcClient, OidcClientConfig},
+    utils::constants::JWT_COOKIE_NAME,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha2::{Digest, Sha256};
+use test_helpers::api_test;
+use tonic::{Code, Request};
+
+use crate::helpers::{get_random_email, TestApp};
+
+const CLIENT_ID: &str = "users-api";
+const CLIENT_SECRET: &str = "users-api-secret";
+
+async fn setup() -> TestApp {
+    let client = OidcClient::from_config(OidcClientConfig {
+        client_id: CLIENT_ID.to_owned(),
+        client_secret_hash: hex::encode(Sha256::digest(CLIENT_SECRET)),
+        ..Default::default()
+    })
+    .unwrap();
+
+    TestApp::with_oidc_clients(vec![client]).await
+}
+
+/// `message` sent by the registered client, with `client_secret` as its secret.
+fn as_client<T>(message: T, client_secret: &str) -> Request<T> {
+    let mut request = Request::new(message);
+    let credentials = STANDARD.encode(format!("{}:{}", CLIENT_ID, client_secret));
+    request.metadata_mut().insert(
+        "authorization",
+        format!("Basic {}", credentials).parse().unwrap(),
+    );
+    request
+}
+
+async fn log_in(app: &TestApp) -> (String, String) {
+    let email = get_random_email();
+    let body = serde_json::json!({
+        "email": email,
+        "password": "spoon-galaxy-trumpet-47",
+        "requires2FA": false
+    });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+
+    let body = serde_json::json!({ "email": email, "password": "spoon-galaxy-trumpet-47" });
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    (email, token)
+}
+
+fn verify_request(token: &str, scopes: &[&str]) -> VerifyTokenRequest {
+    VerifyTokenRequest {
+        token: token.to_owned(),
+        audience: None,
+        scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+    }
+}
+
+#[api_test]
+async fn should_verify_auth_tokens_and_api_keys() {
+    let (email, token) = log_in(&app).await;
+    let mut client = app.grpc_client().await;
+
+    let response = client
+        .verify_token(verify_request(&token, &[]))
+        .await
+        .expect("Auth token was rejected")
+        .into_inner();
+    assert_eq!(response.subject, email);
+    assert_eq!(response.token_type, "session");
+    assert!(response.scopes.is_empty());
+
+    // Sessions have no scopes
+    let status = client
+        .verify_token(verify_request(&token, &["read:users"]))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+
+    let body = serde_json::json!({ "name": "CI", "scopes": ["user:read"] });
+    let response = app.post_api_key(None, &body).await;
+    let key = response.json::<serde_json::Value>().await.unwrap()["key"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+
+    let response = client
+        .verify_token(verify_request(&key, &[]))
+        .await
+        .expect("API key was rejected")
+        .into_inner();
+    assert_eq!(response.subject, email);
+    assert_eq!(response.token_type, "api_key");
+    assert_eq!(response.scopes, vec!["user:read"]);
+
+    // Only service tokens can satisfy a scope check
+    let status = client
+        .verify_token(verify_request(&key, &["user:read"]))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::PermissionDenied);
+
+    let status = client
+        .verify_token(verify_request("invalid_token", &[]))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+}
+
+#[tokio::test]
+async fn should_introspect_tokens() {
+    let mut app = setup().await;
+    let (email, token) = log_in(&app).await;
+    let mut client = app.grpc_client().await;
+
+    let response = client
+        .introspect_token(as_client(
+            IntrospectTokenRequest {
+                token: token.clone(),
+            },
+            CLIENT_SECRET,
+        ))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(response.active);
+    assert_eq!(response.sub.as_deref(), Some(email.as_str()));
+    assert_eq!(response.token_type.as_deref(), Some("session"));
+    assert!(response.sid.is_some());
+    assert!(response.exp.is_some());
+
+    let response = client
+        .introspect_token(as_client(
+            IntrospectTokenRequest {
+                token: "invalid_token".to_owned(),
+            },
+            CLIENT_SECRET,
+        ))
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(!response.active);
+    assert!(response.sub.is_none());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_revoke_auth_tokens() {
+    let mut app = setup().await;
+    let (_, token) = log_in(&app).await;
+    let mut client = app.grpc_client().await;
+
+    client
+        .revoke_token(as_client(
+            RevokeTokenRequest {
+                token: token.clone(),
+            },
+            CLIENT_SECRET,
+        ))
+        .await
+        .expect("Failed to revoke token");
+
+    assert!(app
+        .banned_token_store
+        .read()
+        .await
+        .contains_token(&token)
+        .await
+        .unwrap());
+    let status = client
+        .verify_token(verify_request(&token, &[]))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    // Already revoked
+    let status = client
+        .revoke_token(as_client(RevokeTokenRequest { token }, CLIENT_SECRET))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_client_credentials_to_introspect_and_revoke() {
+    let mut app = setup().await;
+    let (_, token) = log_in(&app).await;
+    let mut client = app.grpc_client().await;
+
+    for request in [
+        Request::new(IntrospectTokenRequest {
+            token: token.clone(),
+        }),
+        as_client(
+            IntrospectTokenRequest {
+                token: token.clone(),
+            },
+            "wrong-secret",
+        ),
+    ] {
+        let status = client.introspect_token(request).await.unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+    }
+
+    for request in [
+        Request::new(RevokeTokenRequest {
+            token: token.clone(),
+        }),
+        as_client(
+            RevokeTokenRequest {
+                token: token.clone(),
+            },
+            "wrong-secret",
+        ),
+    ] {
+        let status = client.revoke_token(request).await.unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+    }
+    assert!(!app
+        .banned_token_store
+        .read()
+        .await
+        .contains_token(&token)
+        .await
+        .unwrap());
+
+    app.clean_up().await;
+}
@@ -1,5 +1,5 @@
 use core::panic;
-use reqwest::cookie::Jar;
+use reqwest::cookie::{CookieStore, Jar};
 use sqlx::{
     postgres::{PgConnectOptions, PgPoolOptions},
     Connection, Executor, PgConnection, PgPool,
@@ -8,58 +8,222 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use auth_service::{
-    app_state::{AppState, BannedTokenStoreType, TwoFACodeStoreType},
+    app_state::{
+        AppState, BannedTokenStoreType, EmailDomainRuleStoreType, MagicLinkStoreType,
+        TwoFACodeStoreType,
+    },
+    domain::{Email, EmailClient},
     get_postgres_pool, get_redis_client,
+    grpc::{
+        proto::{
+            envoy::service::auth::v3::authorization_client::AuthorizationClient,
+            token_service_client::TokenServiceClient,
+        },
+        GrpcServer,
+    },
     services::{
-        data_stores::{PostgresUserStore, RedisBannedTokenStore, RedisTwoFACodeStore},
-        mock_email_client::MockEmailClient,
+        api_keys::ApiKeys,
+        data_stores::{
+            HashmapAuthorizationCodeStore, HashmapMagicLinkStore, HashmapOAuthStateStore,
+            HashmapProofOfWorkStore, PostgresApiKeyStore, PostgresAuditLogStore,
+            PostgresBannedTokenStore, PostgresEmailDomainRuleStore, PostgresTwoFACodeStore,
+            PostgresUserStore, RedisBannedTokenStore, RedisMagicLinkStore, RedisTwoFACodeStore,
+        },
+        email_domain_policy::EmailDomainPolicy,
+        forward_auth::ForwardAuth,
+        hashset_breached_password_checker::HashsetBreachedPasswordChecker,
+        magic_link::MagicLinks,
+        oauth::{OAuthClient, OAuthProvider},
+        oidc::{OidcClient, OidcProvider, SigningKey},
+        proof_of_work::{ProofOfWork, ProofOfWorkConfig},
+    },
+    utils::constants::{
+        env::AUDIT_API_TOKEN_ENV_VAR, test, CSRF_COOKIE_NAME, CSRF_TOKEN_HEADER, DATABASE_URL,
+        DEFAULT_REDIS_HOSTNAME, POW_CHALLENGE_HEADER, POW_NONCE_HEADER,
     },
-    utils::constants::{test, DATABASE_URL, DEFAULT_REDIS_HOSTNAME},
-    Application,
+    Application, TokenStoreBackend,
 };
 
 use std::str::FromStr;
 use uuid::Uuid;
 
+pub const TEST_AUDIT_API_TOKEN: &str = "test-audit-api-token";
+/// Strong enough for the password policy, but rejected as breached by every `TestApp`.
+pub const BREACHED_PASSWORD: &str = "correct-horse-battery-staple-93";
+
+pub const OIDC_TEST_ISSUER: &str = "http://localhost";
+pub const FORWARD_AUTH_TEST_LOGIN_URL: &str = "http://auth.test/";
+/// The only host users are sent back to after a forward-auth login.
+pub const FORWARD_AUTH_TEST_HOST: &str = "app.test";
+
+struct TestAppConfig {
+    backend: TokenStoreBackend,
+    proof_of_work: ProofOfWorkConfig,
+    oauth_providers: Vec<OAuthProvider>,
+    oidc_clients: Vec<OidcClient>,
+}
+
+impl Default for TestAppConfig {
+    fn default() -> Self {
+        Self {
+            backend: TokenStoreBackend::Redis,
+            proof_of_work: ProofOfWorkConfig::default(),
+            oauth_providers: Vec::new(),
+            oidc_clients: Vec::new(),
+        }
+    }
+}
+
 pub struct TestApp {
     pub address: String,
+    pub grpc_address: String,
     pub cookie_jar: Arc<Jar>,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
+    pub email_domain_rule_store: EmailDomainRuleStoreType,
+    pub email_client: Arc<RecordingEmailClient>,
     pub http_client: reqwest::Client,
+    pub pg_pool: PgPool,
     pub db_name: String,
     pub clean_up_called: bool,
 }
 
 impl TestApp {
     pub async fn new() -> Self {
+        Self::with_token_store_backend(TokenStoreBackend::Redis).await
+    }
+
+    pub async fn with_token_store_backend(backend: TokenStoreBackend) -> Self {
+        Self::build(TestAppConfig {
+            backend,
+            ..Default::default()
+        })
+        .await
+    }
+
+    pub async fn with_proof_of_work(config: ProofOfWorkConfig) -> Self {
+        Self::build(TestAppConfig {
+            proof_of_work: config,
+            ..Default::default()
+        })
+        .await
+    }
+
+    pub async fn with_oauth_providers(providers: Vec<OAuthProvider>) -> Self {
+        Self::build(TestAppConfig {
+            oauth_providers: providers,
+            ..Default::default()
+        })
+        .await
+    }
+
+    pub async fn with_oidc_clients(clients: Vec<OidcClient>) -> Self {
+        Self::build(TestAppConfig {
+            oidc_clients: clients,
+            ..Default::default()
+        })
+        .await
+    }
+
+    async fn build(config: TestAppConfig) -> Self {
+        let TestAppConfig {
+            backend,
+            proof_of_work: proof_of_work_config,
+            oauth_providers,
+            oidc_clients,
+        } = config;
+
+        std::env::set_var(AUDIT_API_TOKEN_ENV_VAR, TEST_AUDIT_API_TOKEN);
+
         let db_name = Uuid::new_v4().to_string();
         let pg_pool = configure_postgresql(&db_name).await;
-        let redis_connection = Arc::new(RwLock::new(configure_redis()));
 
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-        let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(
-            redis_connection.clone(),
-        )));
-        let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_connection)));
-
-        let email_client = Arc::new(MockEmailClient);
+        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone())));
+        let (banned_token_store, two_fa_code_store, magic_link_store): (
+            BannedTokenStoreType,
+            TwoFACodeStoreType,
+            MagicLinkStoreType,
+        ) = match backend {
+            TokenStoreBackend::Redis => {
+                let redis_connection = Arc::new(RwLock::new(configure_redis()));
+                (
+                    Arc::new(RwLock::new(RedisBannedTokenStore::new(
+                        redis_connection.clone(),
+                    ))),
+                    Arc::new(RwLock::new(RedisTwoFACodeStore::new(
+                        redis_connection.clone(),
+                    ))),
+                    Arc::new(RwLock::new(RedisMagicLinkStore::new(redis_connection))),
+                )
+            }
+            TokenStoreBackend::Postgres => (
+                Arc::new(RwLock::new(PostgresBannedTokenStore::new(pg_pool.clone()))),
+                Arc::new(RwLock::new(PostgresTwoFACodeStore::new(pg_pool.clone()))),
+                Arc::new(RwLock::new(HashmapMagicLinkStore::default())),
+            ),
+        };
+
+        let email_client = Arc::new(RecordingEmailClient::default());
+        let audit_log_store = Arc::new(RwLock::new(PostgresAuditLogStore::new(pg_pool.clone())));
+        let email_domain_rule_store: EmailDomainRuleStoreType = Arc::new(RwLock::new(
+            PostgresEmailDomainRuleStore::new(pg_pool.clone()),
+        ));
 
         let app_state = AppState::new(
             user_store,
             banned_token_store.clone(),
             two_fa_code_store.clone(),
-            email_client,
-        );
-
+            email_client.clone(),
+            audit_log_store,
+        )
+        .with_breached_password_checker(Arc::new(HashsetBreachedPasswordChecker::new([
+            BREACHED_PASSWORD,
+        ])))
+        .with_email_domain_policy(EmailDomainPolicy::new(email_domain_rule_store.clone()))
+        // Counters in Redis would be shared with every other test running at the same time
+        .with_proof_of_work(ProofOfWork::new(
+            proof_of_work_config,
+            Arc::new(RwLock::new(HashmapProofOfWorkStore::default())),
+        ))
+        .with_magic_links(MagicLinks::new(magic_link_store, "http://localhost"))
+        .with_oauth_client(
+            OAuthClient::new(
+                oauth_providers,
+                Arc::new(RwLock::new(HashmapOAuthStateStore::default())),
+            )
+            .expect("Invalid OAuth providers"),
+        )
+        .with_oidc_provider(
+            OidcProvider::new(
+                OIDC_TEST_ISSUER,
+                oidc_clients,
+                Arc::new(RwLock::new(HashmapAuthorizationCodeStore::default())),
+                SigningKey::generate(),
+            )
+            .expect("Invalid OIDC clients"),
+        )
+        .with_api_keys(ApiKeys::new(Arc::new(RwLock::new(
+            PostgresApiKeyStore::new(pg_pool.clone()),
+        ))))
+        .with_forward_auth(ForwardAuth::new(
+            FORWARD_AUTH_TEST_LOGIN_URL,
+            vec![FORWARD_AUTH_TEST_HOST.to_owned()],
+        ));
+
+        let grpc_server = GrpcServer::build(app_state.clone(), test::GRPC_ADDRESS)
+            .await
+            .expect("Failed to build gRPC server");
         let app = Application::build(app_state, test::APP_ADDRESS)
             .await
             .expect("Failed to build app");
 
         let address = format!("http://{}", app.address.clone());
+        let grpc_address = format!("http://{}", grpc_server.address.clone());
 
         #[allow(clippy::let_underscore_future)]
         let _ = tokio::spawn(app.run());
+        #[allow(clippy::let_underscore_future)]
+        let _ = tokio::spawn(grpc_server.run());
 
         let cookie_jar = Arc::new(Jar::default());
         let http_client = reqwest::Client::builder()
@@ -69,18 +233,34 @@ impl TestApp {
 
         Self {
             address,
+            grpc_address,
             cookie_jar,
             banned_token_store,
             two_fa_code_store,
+            email_domain_rule_store,
+            email_client,
             http_client,
+            pg_pool,
             db_name,
             clean_up_called: false,
         }
     }
 
+    pub async fn grpc_client(&self) -> TokenServiceClient<tonic::transport::Channel> {
+        TokenServiceClient::connect(self.grpc_address.clone())
+            .await
+            .expect("Failed to connect to gRPC server.")
+    }
+
+    pub async fn ext_authz_client(&self) -> AuthorizationClient<tonic::transport::Channel> {
+        AuthorizationClient::connect(self.grpc_address.clone())
+            .await
+            .expect("Failed to connect to gRPC server.")
+    }
+
     pub async fn get_root(&self) -> reqwest::Response {
         self.http_client
-            .get(&format!("{}/", &self.address))
+            .get(format!("{}/", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -90,8 +270,7 @@ impl TestApp {
     where
         Body: serde::Serialize,
     {
-        self.http_client
-            .post(&format!("{}/signup", &self.address))
+        self.post("/signup")
             .json(body)
             .send()
             .await
@@ -102,17 +281,173 @@ impl TestApp {
     where
         Body: serde::Serialize,
     {
+        self.post("/login")
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_challenge(&self, action: &str) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/login", &self.address))
+            .get(format!("{}/challenge", &self.address))
+            .query(&[("action", action)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Posts `body` to `path` along with a proof-of-work solution.
+    pub async fn post_with_proof_of_work<Body>(
+        &self,
+        path: &str,
+        body: &Body,
+        challenge: &str,
+        nonce: &str,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.post(path)
+            .header(POW_CHALLENGE_HEADER, challenge)
+            .header(POW_NONCE_HEADER, nonce)
             .json(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
-    pub async fn post_logout(&self) -> reqwest::Response {
+    pub async fn post_magic_link<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.post("/login/magic-link")
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_magic_link_page(&self, token: &str) -> reqwest::Response {
         self.http_client
-            .post(format!("{}/logout", &self.address))
+            .get(format!("{}/login/magic-link/callback", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_magic_link_callback(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/login/magic-link/callback", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Starts logging in with `provider`, following the redirect to it.
+    pub async fn get_oauth_authorize(&self, provider: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/oauth/{}/authorize", &self.address, provider))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_oauth_callback(
+        &self,
+        provider: &str,
+        query: &[(&str, &str)],
+    ) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/oauth/{}/callback", &self.address, provider))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Doesn't follow the redirect, so tests can read where it goes.
+    /// Doesn't follow redirects, like the reverse proxies this is meant for.
+    pub async fn get_forward_auth(
+        &self,
+        path_and_query: &str,
+        headers: &[(&str, &str)],
+    ) -> reqwest::Response {
+        let mut request = reqwest::Client::builder()
+            .cookie_provider(self.cookie_jar.clone())
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap()
+            .get(format!("{}{}", &self.address, path_and_query));
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn get_oidc_authorize(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        reqwest::Client::builder()
+            .cookie_provider(self.cookie_jar.clone())
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap()
+            .get(format!("{}/oauth/authorize", &self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_oidc_token(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        form: &[(&str, &str)],
+    ) -> reqwest::Response {
+        self.post("/oauth/token")
+            .basic_auth(client_id, Some(client_secret))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_oauth_introspect(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+        token: &str,
+    ) -> reqwest::Response {
+        self.post("/oauth/introspect")
+            .basic_auth(client_id, Some(client_secret))
+            .form(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_oidc_userinfo(&self, access_token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/oauth/userinfo", &self.address))
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_well_known(&self, document: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/.well-known/{}", &self.address, document))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_logout(&self) -> reqwest::Response {
+        self.post("/logout")
             .send()
             .await
             .expect("Failed to execute request.")
@@ -122,8 +457,7 @@ impl TestApp {
     where
         Body: serde::Serialize,
     {
-        self.http_client
-            .post(format!("{}/verify-2fa", &self.address))
+        self.post("/verify-2fa")
             .json(body)
             .send()
             .await
@@ -134,14 +468,94 @@ impl TestApp {
     where
         Body: serde::Serialize,
     {
-        self.http_client
-            .post(format!("{}/verify-token", &self.address))
+        self.post("/verify-token")
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Sends `bearer` as an `Authorization: Bearer` header if given, or else the auth cookie.
+    pub async fn post_api_key<Body>(&self, bearer: Option<&str>, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.with_bearer(self.post("/api-keys"), bearer)
             .json(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_api_keys(&self, bearer: Option<&str>) -> reqwest::Response {
+        self.with_bearer(
+            self.http_client.get(format!("{}/api-keys", &self.address)),
+            bearer,
+        )
+        .send()
+        .await
+        .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_api_key(&self, bearer: Option<&str>, prefix: &str) -> reqwest::Response {
+        let url = format!("{}/api-keys/{}", &self.address, prefix);
+        self.with_bearer(self.with_csrf_token(self.http_client.delete(url)), bearer)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// A POST sending back the CSRF token issued at login, as the login page does.
+    fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        self.with_csrf_token(self.http_client.post(format!("{}{}", &self.address, path)))
+    }
+
+    fn with_csrf_token(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self.csrf_token() {
+            Some(token) => request.header(CSRF_TOKEN_HEADER, token),
+            None => request,
+        }
+    }
+
+    /// The CSRF token from the cookie jar, if logged in.
+    pub fn csrf_token(&self) -> Option<String> {
+        let url = self.address.parse().unwrap();
+        let cookies = self.cookie_jar.cookies(&url)?;
+        let prefix = format!("{}=", CSRF_COOKIE_NAME);
+
+        cookies
+            .to_str()
+            .ok()?
+            .split("; ")
+            .find_map(|cookie| cookie.strip_prefix(&prefix))
+            .map(str::to_owned)
+    }
+
+    fn with_bearer(
+        &self,
+        request: reqwest::RequestBuilder,
+        bearer: Option<&str>,
+    ) -> reqwest::RequestBuilder {
+        match bearer {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    pub async fn get_audit_events(
+        &self,
+        api_token: &str,
+        query: &[(&str, &str)],
+    ) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/audit-events", &self.address))
+            .bearer_auth(api_token)
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn clean_up(&mut self) {
         if self.clean_up_called {
             return;
@@ -161,6 +575,49 @@ impl Drop for TestApp {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct SentEmail {
+    pub recipient: String,
+    pub subject: String,
+    pub content: String,
+}
+
+/// Keeps every email the app sends, so tests can read links and codes out of them.
+#[derive(Default)]
+pub struct RecordingEmailClient {
+    sent: std::sync::Mutex<Vec<SentEmail>>,
+}
+
+impl RecordingEmailClient {
+    pub fn sent_to(&self, recipient: &str) -> Vec<SentEmail> {
+        self.sent
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|email| email.recipient == recipient)
+            .cloned()
+            .collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailClient for RecordingEmailClient {
+    async fn send_email(
+        &self,
+        recipient: &Email,
+        subject: &str,
+        content: &str,
+    ) -> Result<(), String> {
+        self.sent.lock().unwrap().push(SentEmail {
+            recipient: recipient.as_ref().to_owned(),
+            subject: subject.to_owned(),
+            content: content.to_owned(),
+        });
+
+        Ok(())
+    }
+}
+
 pub fn get_random_email() -> String {
     format!("{}@example.com", Uuid::new_v4())
 }
@@ -0,0 +1,154 @@
+use auth_service::{
+    routes::IntrospectionResponse,
+    services::oidc::{OidcClient, OidcClientConfig, ServiceTokenResponse},
+    utils::constants::JWT_COOKIE_NAME,
+};
+use sha2::{Digest, Sha256};
+
+use crate::helpers::{get_random_email, TestApp};
+
+const RESOURCE_ID: &str = "users-api";
+const RESOURCE_SECRET: &str = "users-api-secret";
+const SERVICE_ID: &str = "billing";
+const SERVICE_SECRET: &str = "billing-secret";
+
+fn client(client_id: &str, client_secret: &str, audiences: &[&str]) -> OidcClient {
+    OidcClient::from_config(OidcClientConfig {
+        client_id: client_id.to_owned(),
+        client_secret_hash: hex::encode(Sha256::digest(client_secret)),
+        audiences: audiences.iter().map(|a| a.to_string()).collect(),
+        scopes: vec!["read:users".to_owned()],
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+async fn setup() -> TestApp {
+    TestApp::with_oidc_clients(vec![
+        client(RESOURCE_ID, RESOURCE_SECRET, &[]),
+        client(SERVICE_ID, SERVICE_SECRET, &[RESOURCE_ID]),
+    ])
+    .await
+}
+
+async fn introspect(app: &TestApp, token: &str) -> IntrospectionResponse {
+    let response = app
+        .post_oauth_introspect(RESOURCE_ID, RESOURCE_SECRET, token)
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["cache-control"], "no-store");
+
+    response
+        .json::<IntrospectionResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectionResponse")
+}
+
+async fn log_in(app: &TestApp) -> (String, String) {
+    let email = get_random_email();
+    let body = serde_json::json!({
+        "email": email,
+        "password": "spoon-galaxy-trumpet-47",
+        "requires2FA": false
+    });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+
+    let body = serde_json::json!({ "email": email, "password": "spoon-galaxy-trumpet-47" });
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    (email, token)
+}
+
+#[tokio::test]
+async fn should_describe_active_auth_tokens() {
+    let mut app = setup().await;
+    let (email, token) = log_in(&app).await;
+
+    let introspection = introspect(&app, &token).await;
+    assert!(introspection.active);
+    assert_eq!(introspection.sub.as_deref(), Some(email.as_str()));
+    assert_eq!(introspection.token_type.as_deref(), Some("session"));
+    assert!(introspection.sid.is_some());
+    assert!(introspection.exp.unwrap() > introspection.iat.unwrap());
+    assert_eq!(introspection.scope, None);
+
+    // Logging out bans the token
+    assert_eq!(app.post_logout().await.status().as_u16(), 200);
+    assert_eq!(
+        introspect(&app, &token).await,
+        IntrospectionResponse::default()
+    );
+    assert_eq!(
+        introspect(&app, "invalid_token").await,
+        IntrospectionResponse::default()
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_describe_api_keys_and_service_tokens() {
+    let mut app = setup().await;
+    let (email, _) = log_in(&app).await;
+
+    let body = serde_json::json!({ "name": "CI", "scopes": ["user:read"] });
+    let response = app.post_api_key(None, &body).await;
+    let key = response.json::<serde_json::Value>().await.unwrap()["key"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+    let introspection = introspect(&app, &key).await;
+    assert!(introspection.active);
+    assert_eq!(introspection.sub.as_deref(), Some(email.as_str()));
+    assert_eq!(introspection.token_type.as_deref(), Some("api_key"));
+    assert_eq!(introspection.scope.as_deref(), Some("user:read"));
+
+    let response = app
+        .post_oidc_token(
+            SERVICE_ID,
+            SERVICE_SECRET,
+            &[("grant_type", "client_credentials")],
+        )
+        .await;
+    let token = response
+        .json::<ServiceTokenResponse>()
+        .await
+        .unwrap()
+        .access_token;
+    let introspection = introspect(&app, &token).await;
+    assert!(introspection.active);
+    assert_eq!(introspection.token_type.as_deref(), Some("service"));
+    assert_eq!(introspection.client_id.as_deref(), Some(SERVICE_ID));
+    assert_eq!(introspection.aud.as_deref(), Some(RESOURCE_ID));
+
+    // It's not meant for the service that got it
+    let response = app
+        .post_oauth_introspect(SERVICE_ID, SERVICE_SECRET, &token)
+        .await;
+    let introspection = response.json::<IntrospectionResponse>().await.unwrap();
+    assert!(!introspection.active);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_client_credentials() {
+    let mut app = setup().await;
+    let (_, token) = log_in(&app).await;
+
+    let response = app
+        .post_oauth_introspect(RESOURCE_ID, "wrong-secret", &token)
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    let body = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(body["error"], "invalid_client");
+
+    app.clean_up().await;
+}
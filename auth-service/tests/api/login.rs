@@ -11,7 +11,7 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "spoon-galaxy-trumpet-47",
         "requires2FA": false
     });
 
@@ -21,7 +21,7 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "spoon-galaxy-trumpet-47",
     });
 
     let response = app.post_login(&login_body).await;
@@ -34,6 +34,35 @@ async fn should_return_200_if_valid_credentials_and_2fa_disabled() {
         .expect("No auth cookie found");
 
     assert!(!auth_cookie.value().is_empty());
+    // Expires with the token
+    assert_eq!(
+        auth_cookie.max_age(),
+        Some(std::time::Duration::from_secs(600))
+    );
+}
+
+#[api_test]
+async fn should_return_200_if_email_differs_only_in_case() {
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "spoon-galaxy-trumpet-47",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email.to_uppercase(),
+        "password": "spoon-galaxy-trumpet-47",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
 }
 
 #[api_test]
@@ -42,7 +71,7 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "spoon-galaxy-trumpet-47",
         "requires2FA": true
     });
 
@@ -52,7 +81,7 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123"
+        "password": "spoon-galaxy-trumpet-47"
     });
 
     let response = app.post_login(&login_body).await;
@@ -82,7 +111,7 @@ async fn should_return_400_if_invalid_input() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "spoon-galaxy-trumpet-47",
         "requires2FA": false
     });
 
@@ -91,9 +120,9 @@ async fn should_return_400_if_invalid_input() {
     assert_eq!(response.status().as_u16(), 201);
 
     let test_cases = vec![
-        ("invalid_email", "password123"),
+        ("invalid_email", "spoon-galaxy-trumpet-47"),
         (random_email.as_str(), "invalid"),
-        ("", "password123"),
+        ("", "spoon-galaxy-trumpet-47"),
         (random_email.as_str(), ""),
         ("", ""),
     ];
@@ -129,7 +158,7 @@ async fn should_return_401_if_incorrect_credentials() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "spoon-galaxy-trumpet-47",
         "requires2FA": false
     });
 
@@ -139,7 +168,7 @@ async fn should_return_401_if_incorrect_credentials() {
 
     let test_cases = vec![
         (random_email.as_str(), "wrong-password"),
-        ("wrong@email.com", "password123"),
+        ("wrong@email.com", "spoon-galaxy-trumpet-47"),
         ("wrong@email.com", "wrong-password"),
     ];
 
@@ -174,7 +203,7 @@ async fn should_return_422_if_malformed_credentials() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "spoon-galaxy-trumpet-47",
         "requires2FA": false
     });
 
@@ -184,7 +213,7 @@ async fn should_return_422_if_malformed_credentials() {
 
     let test_cases = [
         serde_json::json!({
-            "password": "password123",
+            "password": "spoon-galaxy-trumpet-47",
         }),
         serde_json::json!({
             "email": random_email,
@@ -1,4 +1,10 @@
-use auth_service::{utils::constants::JWT_COOKIE_NAME, ErrorResponse};
+use auth_service::{
+    utils::{
+        constants::{CSRF_COOKIE_NAME, JWT_COOKIE_NAME},
+        csrf::csrf_token,
+    },
+    ErrorResponse,
+};
 use reqwest::Url;
 use test_helpers::api_test;
 
@@ -10,7 +16,7 @@ async fn should_return_200_if_valid_jwt_cookie() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "spoon-galaxy-trumpet-47",
         "requires2FA": false
     });
 
@@ -20,7 +26,7 @@ async fn should_return_200_if_valid_jwt_cookie() {
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "spoon-galaxy-trumpet-47",
     });
 
     let response = app.post_login(&login_body).await;
@@ -46,6 +52,8 @@ async fn should_return_200_if_valid_jwt_cookie() {
         .expect("No auth cookie found");
 
     assert!(auth_cookie.value().is_empty());
+    assert_eq!(auth_cookie.path(), Some("/"));
+    assert_eq!(auth_cookie.max_age(), Some(std::time::Duration::ZERO));
 
     let banned_token_store = app.banned_token_store.read().await;
     let contains_token = banned_token_store
@@ -88,7 +96,7 @@ async fn should_return_400_if_logout_called_twice_in_a_row() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "spoon-galaxy-trumpet-47",
         "requires2FA": false
     });
 
@@ -98,7 +106,7 @@ async fn should_return_400_if_logout_called_twice_in_a_row() {
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "spoon-galaxy-trumpet-47",
     });
 
     let response = app.post_login(&login_body).await;
@@ -145,6 +153,11 @@ async fn should_return_401_if_invalid_token() {
         ),
         &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
     );
+    // So the request gets past the CSRF check
+    app.cookie_jar.add_cookie_str(
+        &format!("{}={}; Path=/", CSRF_COOKIE_NAME, csrf_token("invalid")),
+        &Url::parse("http://127.0.0.1").expect("Failed to parse URL"),
+    );
 
     let response = app.post_logout().await;
 
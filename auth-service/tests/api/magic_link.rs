@@ -0,0 +1,179 @@
+use auth_service::{
+    routes::{MagicLinkResponse, TwoFactorAuthResponse},
+    utils::constants::JWT_COOKIE_NAME,
+    ErrorResponse,
+};
+use test_helpers::api_test;
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn sign_up(app: &TestApp, email: &str, requires_2fa: bool) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "spoon-galaxy-trumpet-47",
+        "requires2FA": requires_2fa
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+/// Requests a magic link for `email` and returns the token from the emailed link.
+async fn request_link_token(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_magic_link(&serde_json::json!({ "email": email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let sent = app.email_client.sent_to(email);
+    let content = &sent.last().expect("No magic link was sent").content;
+    let link = content
+        .split_whitespace()
+        .find(|word| word.contains("/login/magic-link/callback?token="))
+        .expect("No magic link in email");
+
+    link.split_once("token=").unwrap().1.to_owned()
+}
+
+#[api_test]
+async fn should_log_in_with_a_magic_link() {
+    let random_email = get_random_email();
+    sign_up(&app, &random_email, false).await;
+
+    let token = request_link_token(&app, &random_email).await;
+
+    let response = app.post_magic_link_callback(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    assert!(!auth_cookie.value().is_empty());
+}
+
+#[api_test]
+async fn should_not_use_the_link_when_it_is_opened() {
+    let random_email = get_random_email();
+    sign_up(&app, &random_email, false).await;
+
+    let token = request_link_token(&app, &random_email).await;
+
+    for _ in 0..2 {
+        let response = app.get_magic_link_page(&token).await;
+        assert_eq!(response.status().as_u16(), 200);
+        assert!(response
+            .cookies()
+            .all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+        assert!(response
+            .text()
+            .await
+            .unwrap()
+            .contains("<form class=\"text-center\" method=\"post\">"));
+    }
+
+    let response = app.post_magic_link_callback(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[api_test]
+async fn should_send_the_link_to_the_address_as_stored() {
+    let random_email = get_random_email();
+    sign_up(&app, &random_email, false).await;
+
+    let response = app
+        .post_magic_link(&serde_json::json!({ "email": random_email.to_uppercase() }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    assert_eq!(app.email_client.sent_to(&random_email).len(), 1);
+}
+
+#[api_test]
+async fn should_return_401_if_link_is_reused() {
+    let random_email = get_random_email();
+    sign_up(&app, &random_email, false).await;
+
+    let token = request_link_token(&app, &random_email).await;
+
+    let response = app.post_magic_link_callback(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_magic_link_callback(&token).await;
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[api_test]
+async fn should_return_206_if_user_requires_2fa() {
+    let random_email = get_random_email();
+    sign_up(&app, &random_email, true).await;
+
+    let token = request_link_token(&app, &random_email).await;
+
+    let response = app.post_magic_link_callback(&token).await;
+    assert_eq!(response.status().as_u16(), 206);
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+
+    let body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+    assert_eq!(body.message, "2FA required");
+}
+
+#[api_test]
+async fn should_return_200_without_sending_email_if_user_does_not_exist() {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_magic_link(&serde_json::json!({ "email": random_email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response
+            .json::<MagicLinkResponse>()
+            .await
+            .expect("Could not deserialize response body to MagicLinkResponse")
+            .message,
+        "If the account exists, a login link has been sent"
+    );
+
+    assert!(app.email_client.sent_to(&random_email).is_empty());
+}
+
+#[api_test]
+async fn should_return_401_if_link_is_invalid() {
+    let random_email = get_random_email();
+    sign_up(&app, &random_email, false).await;
+
+    let token = request_link_token(&app, &random_email).await;
+
+    for token in [format!("{}x", token), "not-a-token".to_owned()] {
+        let response = app.post_magic_link_callback(&token).await;
+        assert_eq!(response.status().as_u16(), 401, "Failed for: {}", token);
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Invalid auth token"
+        );
+    }
+}
+
+#[api_test]
+async fn should_return_400_if_email_is_invalid() {
+    let response = app
+        .post_magic_link(&serde_json::json!({ "email": "invalid_email" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[api_test]
+async fn should_return_422_if_malformed_input() {
+    let response = app.post_magic_link(&serde_json::json!({})).await;
+    assert_eq!(response.status().as_u16(), 422);
+}
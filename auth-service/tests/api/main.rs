@@ -1,6 +1,19 @@
+mod api_keys;
+mod audit_events;
+mod challenge;
+mod client_credentials;
+mod csrf;
+mod ext_authz;
+mod forward_auth;
+mod grpc;
 mod helpers;
+mod introspect;
 mod login;
 mod logout;
+mod magic_link;
+mod oauth;
+mod oidc;
+mod postgres_token_stores;
 mod root;
 mod signup;
 mod verify_2fa;
@@ -0,0 +1,371 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use auth_service::{
+    domain::OAuthProviderKind,
+    routes::TwoFactorAuthResponse,
+    services::oauth::{OAuthProvider, OAuthProviderConfig},
+    utils::constants::{JWT_COOKIE_NAME, OAUTH_STATE_COOKIE_NAME},
+    ErrorResponse,
+};
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+    Form, Json, Router,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::helpers::{get_random_email, TestApp};
+
+const PROVIDER: &str = "mock";
+const CLIENT_ID: &str = "auth-service";
+const CLIENT_SECRET: &str = "mock-client-secret";
+const REDIRECT_URI: &str = "http://localhost/oauth/mock/callback";
+
+#[derive(Clone)]
+struct MockUser {
+    email: String,
+    email_verified: bool,
+}
+
+struct PendingCode {
+    code_challenge: String,
+    redirect_uri: String,
+    user: MockUser,
+}
+
+#[derive(Default)]
+struct MockProviderState {
+    user: Option<MockUser>,
+    codes: HashMap<String, PendingCode>,
+    access_tokens: HashMap<String, MockUser>,
+}
+
+type SharedState = Arc<Mutex<MockProviderState>>;
+
+/// A minimal OpenID Connect provider. Instead of showing a login page, `/authorize` logs in
+/// whoever `log_in_as` was given and answers with the code and state it would have sent back.
+struct MockProvider {
+    address: String,
+    state: SharedState,
+}
+
+impl MockProvider {
+    async fn start() -> Self {
+        let state = SharedState::default();
+        let router = Router::new()
+            .route("/authorize", get(authorize))
+            .route("/token", post(token))
+            .route("/userinfo", get(userinfo))
+            .with_state(state.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        Self { address, state }
+    }
+
+    fn log_in_as(&self, email: &str, email_verified: bool) {
+        self.state.lock().unwrap().user = Some(MockUser {
+            email: email.to_owned(),
+            email_verified,
+        });
+    }
+
+    fn oauth_provider(&self) -> OAuthProvider {
+        OAuthProvider::from_config(OAuthProviderConfig {
+            name: PROVIDER.to_owned(),
+            kind: OAuthProviderKind::Oidc,
+            client_id: CLIENT_ID.to_owned(),
+            client_secret: CLIENT_SECRET.to_owned(),
+            redirect_uri: REDIRECT_URI.to_owned(),
+            authorization_endpoint: Some(format!("{}/authorize", self.address)),
+            token_endpoint: Some(format!("{}/token", self.address)),
+            userinfo_endpoint: Some(format!("{}/userinfo", self.address)),
+            scopes: None,
+        })
+        .unwrap()
+    }
+}
+
+async fn authorize(
+    State(state): State<SharedState>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    assert_eq!(params["response_type"], "code");
+    assert_eq!(params["client_id"], CLIENT_ID);
+    assert_eq!(params["code_challenge_method"], "S256");
+
+    let mut state = state.lock().unwrap();
+    let user = state.user.clone().expect("Nobody to log in as");
+    let code = Uuid::new_v4().to_string();
+    state.codes.insert(
+        code.clone(),
+        PendingCode {
+            code_challenge: params["code_challenge"].clone(),
+            redirect_uri: params["redirect_uri"].clone(),
+            user,
+        },
+    );
+
+    Json(serde_json::json!({ "code": code, "state": params["state"] }))
+}
+
+async fn token(
+    State(state): State<SharedState>,
+    Form(params): Form<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let mut state = state.lock().unwrap();
+    let pending = state.codes.remove(&params["code"]);
+
+    let valid = match &pending {
+        Some(pending) => {
+            params["grant_type"] == "authorization_code"
+                && params["client_id"] == CLIENT_ID
+                && params["client_secret"] == CLIENT_SECRET
+                && params["redirect_uri"] == pending.redirect_uri
+                && URL_SAFE_NO_PAD.encode(Sha256::digest(&params["code_verifier"]))
+                    == pending.code_challenge
+        }
+        None => false,
+    };
+    if !valid {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": "invalid_grant" })),
+        );
+    }
+
+    let access_token = Uuid::new_v4().to_string();
+    state
+        .access_tokens
+        .insert(access_token.clone(), pending.unwrap().user);
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({ "access_token": access_token, "token_type": "Bearer" })),
+    )
+}
+
+async fn userinfo(State(state): State<SharedState>, headers: HeaderMap) -> impl IntoResponse {
+    let user = headers
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .and_then(|token| state.lock().unwrap().access_tokens.get(token).cloned());
+
+    match user {
+        Some(user) => (
+            StatusCode::OK,
+            Json(serde_json::json!({
+                "sub": user.email,
+                "email": user.email,
+                "email_verified": user.email_verified
+            })),
+        ),
+        None => (StatusCode::UNAUTHORIZED, Json(serde_json::json!({}))),
+    }
+}
+
+async fn setup() -> (TestApp, MockProvider) {
+    let provider = MockProvider::start().await;
+    let app = TestApp::with_oauth_providers(vec![provider.oauth_provider()]).await;
+
+    (app, provider)
+}
+
+/// Goes through the provider's side of the login, returning the code and state it hands back.
+async fn authorize_with_provider(app: &TestApp) -> (String, String) {
+    let response = app.get_oauth_authorize(PROVIDER).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let body = response.json::<serde_json::Value>().await.unwrap();
+    (
+        body["code"].as_str().unwrap().to_owned(),
+        body["state"].as_str().unwrap().to_owned(),
+    )
+}
+
+async fn log_in(app: &TestApp) -> reqwest::Response {
+    let (code, state) = authorize_with_provider(app).await;
+    app.get_oauth_callback(PROVIDER, &[("code", &code), ("state", &state)])
+        .await
+}
+
+async fn sign_up(app: &TestApp, email: &str, requires_2fa: bool) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "spoon-galaxy-trumpet-47",
+        "requires2FA": requires_2fa
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+fn has_auth_cookie(response: &reqwest::Response) -> bool {
+    response
+        .cookies()
+        .any(|cookie| cookie.name() == JWT_COOKIE_NAME && !cookie.value().is_empty())
+}
+
+async fn error_reason(response: reqwest::Response) -> serde_json::Value {
+    let error = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    error.details.expect("No error details")["reason"].clone()
+}
+
+#[tokio::test]
+async fn should_create_an_account_on_first_login() {
+    let (mut app, provider) = setup().await;
+    let random_email = get_random_email();
+    provider.log_in_as(&random_email, true);
+
+    let response = log_in(&app).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(has_auth_cookie(&response));
+
+    // Later logins find the account created the first time
+    let response = log_in(&app).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(has_auth_cookie(&response));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_link_to_an_existing_account_by_email() {
+    let (mut app, provider) = setup().await;
+    let random_email = get_random_email();
+    sign_up(&app, &random_email, false).await;
+    provider.log_in_as(&random_email.to_uppercase(), true);
+
+    let response = log_in(&app).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(has_auth_cookie(&response));
+
+    // The password keeps working
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "spoon-galaxy-trumpet-47"
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_still_require_2fa_when_enabled() {
+    let (mut app, provider) = setup().await;
+    let random_email = get_random_email();
+    sign_up(&app, &random_email, true).await;
+    provider.log_in_as(&random_email, true);
+
+    let response = log_in(&app).await;
+    assert_eq!(response.status().as_u16(), 206);
+    assert!(!has_auth_cookie(&response));
+
+    let json_body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+    assert_eq!(json_body.message, "2FA required".to_owned());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_the_email_is_not_verified() {
+    let (mut app, provider) = setup().await;
+    let random_email = get_random_email();
+    sign_up(&app, &random_email, false).await;
+    provider.log_in_as(&random_email, false);
+
+    let response = log_in(&app).await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert!(!has_auth_cookie(&response));
+    assert_eq!(error_reason(response).await, "email_not_verified");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_access_was_denied() {
+    let (mut app, provider) = setup().await;
+    provider.log_in_as(&get_random_email(), true);
+
+    let (_, state) = authorize_with_provider(&app).await;
+
+    let response = app
+        .get_oauth_callback(PROVIDER, &[("error", "access_denied"), ("state", &state)])
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(error_reason(response).await, "access_denied");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_the_state_does_not_match() {
+    let (mut app, provider) = setup().await;
+    provider.log_in_as(&get_random_email(), true);
+
+    let (code, state) = authorize_with_provider(&app).await;
+
+    let response = app
+        .get_oauth_callback(PROVIDER, &[("code", &code), ("state", "forged-state")])
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    // Without the cookie the login wasn't started in this browser
+    let response = reqwest::Client::new()
+        .get(format!("{}/oauth/{}/callback", app.address, PROVIDER))
+        .query(&[("code", &code), ("state", &state)])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_the_state_is_reused() {
+    let (mut app, provider) = setup().await;
+    provider.log_in_as(&get_random_email(), true);
+
+    let (code, state) = authorize_with_provider(&app).await;
+    let query = [("code", code.as_str()), ("state", state.as_str())];
+
+    let response = app.get_oauth_callback(PROVIDER, &query).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.cookie_jar.add_cookie_str(
+        &format!("{}={}; Path=/oauth", OAUTH_STATE_COOKIE_NAME, state),
+        &app.address.parse().unwrap(),
+    );
+    let response = app.get_oauth_callback(PROVIDER, &query).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_for_unknown_providers() {
+    let (mut app, _provider) = setup().await;
+
+    let response = app.get_oauth_authorize("unknown").await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.clean_up().await;
+}
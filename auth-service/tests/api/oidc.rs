@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+
+use auth_service::{
+    routes::{IntrospectionResponse, UserInfoResponse},
+    services::oidc::{IdTokenClaims, OidcClient, OidcClientConfig, TokenResponse},
+    utils::{constants::CSRF_TOKEN_HEADER, csrf::csrf_token},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+
+use crate::helpers::{get_random_email, TestApp, OIDC_TEST_ISSUER};
+
+const CLIENT_ID: &str = "test-app";
+const CLIENT_SECRET: &str = "test-app-secret";
+const REDIRECT_URI: &str = "http://app.test/callback";
+const CODE_VERIFIER: &str = "a-code-verifier-that-is-long-enough-for-pkce-43";
+
+async fn setup() -> TestApp {
+    let client = OidcClient::from_config(OidcClientConfig {
+        client_id: CLIENT_ID.to_owned(),
+        client_secret_hash: hex::encode(Sha256::digest(CLIENT_SECRET)),
+        redirect_uris: vec![REDIRECT_URI.to_owned()],
+        ..Default::default()
+    })
+    .unwrap();
+
+    TestApp::with_oidc_clients(vec![client]).await
+}
+
+async fn sign_up_and_log_in(app: &TestApp) -> String {
+    let email = get_random_email();
+    let body = serde_json::json!({
+        "email": email,
+        "password": "spoon-galaxy-trumpet-47",
+        "requires2FA": false
+    });
+    assert_eq!(app.post_signup(&body).await.status().as_u16(), 201);
+
+    let body = serde_json::json!({
+        "email": email,
+        "password": "spoon-galaxy-trumpet-47"
+    });
+    assert_eq!(app.post_login(&body).await.status().as_u16(), 200);
+
+    email
+}
+
+fn code_challenge() -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(CODE_VERIFIER))
+}
+
+fn authorize_query(code_challenge: &str) -> Vec<(&'static str, String)> {
+    vec![
+        ("client_id", CLIENT_ID.to_owned()),
+        ("redirect_uri", REDIRECT_URI.to_owned()),
+        ("response_type", "code".to_owned()),
+        ("scope", "openid email".to_owned()),
+        ("state", "client-state".to_owned()),
+        ("nonce", "client-nonce".to_owned()),
+        ("code_challenge", code_challenge.to_owned()),
+        ("code_challenge_method", "S256".to_owned()),
+    ]
+}
+
+async fn authorize(app: &TestApp, query: &[(&str, String)]) -> reqwest::Response {
+    let query: Vec<(&str, &str)> = query
+        .iter()
+        .map(|(name, value)| (*name, value.as_str()))
+        .collect();
+    app.get_oidc_authorize(&query).await
+}
+
+/// Where a redirect goes, along with its query parameters.
+fn redirect_location(response: &reqwest::Response) -> (Url, HashMap<String, String>) {
+    assert_eq!(response.status().as_u16(), 303);
+    let location = response.headers()["location"].to_str().unwrap();
+    let url = Url::parse(location)
+        .or_else(|_| Url::parse("http://auth.test").unwrap().join(location))
+        .unwrap();
+    let params = url.query_pairs().into_owned().collect();
+
+    (url, params)
+}
+
+async fn get_code(app: &TestApp) -> String {
+    let response = authorize(app, &authorize_query(&code_challenge())).await;
+    let (url, params) = redirect_location(&response);
+    assert!(url.as_str().starts_with(REDIRECT_URI));
+    assert_eq!(params["state"], "client-state");
+
+    params["code"].clone()
+}
+
+async fn redeem(app: &TestApp, code: &str, code_verifier: &str) -> reqwest::Response {
+    app.post_oidc_token(
+        CLIENT_ID,
+        CLIENT_SECRET,
+        &[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", REDIRECT_URI),
+            ("code_verifier", code_verifier),
+        ],
+    )
+    .await
+}
+
+async fn oauth_error(response: reqwest::Response) -> String {
+    let body = response.json::<serde_json::Value>().await.unwrap();
+    body["error"].as_str().unwrap().to_owned()
+}
+
+#[tokio::test]
+async fn should_publish_discovery_document_and_keys() {
+    let mut app = setup().await;
+
+    let response = app.get_well_known("openid-configuration").await;
+    assert_eq!(response.status().as_u16(), 200);
+    let document = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(document["issuer"], OIDC_TEST_ISSUER);
+    assert_eq!(
+        document["token_endpoint"],
+        format!("{}/oauth/token", OIDC_TEST_ISSUER)
+    );
+    assert_eq!(document["code_challenge_methods_supported"][0], "S256");
+
+    let response = app.get_well_known("jwks.json").await;
+    assert_eq!(response.status().as_u16(), 200);
+    let jwks = response.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(jwks["keys"][0]["alg"], "EdDSA");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_log_in_an_app_with_the_code_flow() {
+    let mut app = setup().await;
+    let email = sign_up_and_log_in(&app).await;
+
+    let code = get_code(&app).await;
+    let response = redeem(&app, &code, CODE_VERIFIER).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["cache-control"], "no-store");
+    let tokens = response.json::<TokenResponse>().await.unwrap();
+    assert_eq!(tokens.token_type, "Bearer");
+
+    // The id_token verifies with the published key
+    let jwks = app
+        .get_well_known("jwks.json")
+        .await
+        .json::<serde_json::Value>()
+        .await
+        .unwrap();
+    let jwk = &jwks["keys"][0];
+    assert_eq!(
+        decode_header(&tokens.id_token).unwrap().kid.as_deref(),
+        jwk["kid"].as_str()
+    );
+    let key = DecodingKey::from_ed_components(jwk["x"].as_str().unwrap()).unwrap();
+    let mut validation = Validation::new(Algorithm::EdDSA);
+    validation.set_audience(&[CLIENT_ID]);
+    validation.set_issuer(&[OIDC_TEST_ISSUER]);
+    let claims = decode::<IdTokenClaims>(&tokens.id_token, &key, &validation)
+        .unwrap()
+        .claims;
+    assert_eq!(claims.sub, email);
+    assert_eq!(claims.email.as_deref(), Some(email.as_str()));
+    assert_eq!(claims.nonce.as_deref(), Some("client-nonce"));
+    // When the user logged in
+    assert!(claims.auth_time > 0 && claims.auth_time <= claims.iat);
+
+    let response = app.get_oidc_userinfo(&tokens.access_token).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let userinfo = response.json::<UserInfoResponse>().await.unwrap();
+    assert_eq!(userinfo.sub, email);
+    assert_eq!(userinfo.email, email);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_send_users_who_are_not_logged_in_to_the_login_page() {
+    let mut app = setup().await;
+
+    let response = authorize(&app, &authorize_query(&code_challenge())).await;
+    let (url, params) = redirect_location(&response);
+    assert_eq!(url.path(), "/");
+    assert!(params["return_to"].starts_with("/oauth/authorize?"));
+    assert!(params["return_to"].contains("client_id=test-app"));
+
+    // Unless the app asked not to show a login
+    let mut query = authorize_query(&code_challenge());
+    query.push(("prompt", "none".to_owned()));
+    let response = authorize(&app, &query).await;
+    let (url, params) = redirect_location(&response);
+    assert!(url.as_str().starts_with(REDIRECT_URI));
+    assert_eq!(params["error"], "login_required");
+    assert_eq!(params["state"], "client-state");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_for_unknown_clients_and_redirect_uris() {
+    let mut app = setup().await;
+    sign_up_and_log_in(&app).await;
+
+    let mut query = authorize_query(&code_challenge());
+    query[0].1 = "unknown-app".to_owned();
+    let response = authorize(&app, &query).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_request");
+
+    let mut query = authorize_query(&code_challenge());
+    query[1].1 = "http://evil.test/callback".to_owned();
+    let response = authorize(&app, &query).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_pkce_and_the_openid_scope() {
+    let mut app = setup().await;
+    sign_up_and_log_in(&app).await;
+
+    let query: Vec<_> = authorize_query(&code_challenge())
+        .into_iter()
+        .filter(|(name, _)| !name.starts_with("code_challenge"))
+        .collect();
+    let response = authorize(&app, &query).await;
+    let (_, params) = redirect_location(&response);
+    assert_eq!(params["error"], "invalid_request");
+
+    let mut query = authorize_query(&code_challenge());
+    query[3].1 = "email".to_owned();
+    let response = authorize(&app, &query).await;
+    let (_, params) = redirect_location(&response);
+    assert_eq!(params["error"], "invalid_scope");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_wrong_client_secrets_and_verifiers() {
+    let mut app = setup().await;
+    sign_up_and_log_in(&app).await;
+
+    let code = get_code(&app).await;
+    let response = app
+        .post_oidc_token(
+            CLIENT_ID,
+            "wrong-secret",
+            &[
+                ("grant_type", "authorization_code"),
+                ("code", &code),
+                ("redirect_uri", REDIRECT_URI),
+                ("code_verifier", CODE_VERIFIER),
+            ],
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(oauth_error(response).await, "invalid_client");
+
+    let response = redeem(&app, &code, "a-different-verifier").await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_grant");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_only_redeem_a_code_once() {
+    let mut app = setup().await;
+    sign_up_and_log_in(&app).await;
+
+    let code = get_code(&app).await;
+    assert_eq!(
+        redeem(&app, &code, CODE_VERIFIER).await.status().as_u16(),
+        200
+    );
+
+    let response = redeem(&app, &code, CODE_VERIFIER).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_grant");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_only_accept_access_tokens_for_asking_about_the_user() {
+    let mut app = setup().await;
+    let email = sign_up_and_log_in(&app).await;
+
+    let code = get_code(&app).await;
+    let tokens = redeem(&app, &code, CODE_VERIFIER)
+        .await
+        .json::<TokenResponse>()
+        .await
+        .unwrap();
+
+    let response = app
+        .post_oauth_introspect(CLIENT_ID, CLIENT_SECRET, &tokens.access_token)
+        .await;
+    let introspection = response.json::<IntrospectionResponse>().await.unwrap();
+    assert!(introspection.active);
+    assert_eq!(introspection.token_type.as_deref(), Some("access"));
+    assert_eq!(introspection.sub.as_deref(), Some(email.as_str()));
+    assert_eq!(introspection.client_id.as_deref(), Some(CLIENT_ID));
+    assert_eq!(introspection.scope.as_deref(), Some("openid email"));
+
+    // Clients can't manage the user's account with it
+    let response = app.get_api_keys(Some(&tokens.access_token)).await;
+    assert_eq!(response.status().as_u16(), 401);
+    let body = serde_json::json!({ "name": "CI", "scopes": ["user:read"] });
+    let response = app.post_api_key(Some(&tokens.access_token), &body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/logout", &app.address))
+        .header("cookie", format!("jwt={}", tokens.access_token))
+        .header(CSRF_TOKEN_HEADER, csrf_token(&tokens.access_token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
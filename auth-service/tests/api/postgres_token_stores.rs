@@ -0,0 +1,152 @@
+use auth_service::{
+    domain::{BannedTokenStore, Email},
+    routes::TwoFactorAuthResponse,
+    services::data_stores::{PostgresBannedTokenStore, PostgresTwoFACodeStore},
+    utils::constants::JWT_COOKIE_NAME,
+    TokenStoreBackend,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+#[tokio::test]
+async fn should_verify_2fa_code_stored_in_postgres() {
+    let mut app = TestApp::with_token_store_backend(TokenStoreBackend::Postgres).await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "spoon-galaxy-trumpet-47",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "spoon-galaxy-trumpet-47"
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let response_body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    let code_tuple = app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&Email::parse(random_email.clone()).unwrap())
+        .await
+        .expect("Failed to get 2FA code");
+
+    assert_eq!(code_tuple.0.as_ref(), response_body.login_attempt_id);
+
+    let verify_2fa_body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": response_body.login_attempt_id,
+        "2FACode": code_tuple.1.as_ref(),
+    });
+
+    let response = app.post_verify_2fa(&verify_2fa_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_verify_2fa(&verify_2fa_body).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_token_banned_in_postgres() {
+    let mut app = TestApp::with_token_store_backend(TokenStoreBackend::Postgres).await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "spoon-galaxy-trumpet-47",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "spoon-galaxy-trumpet-47"
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    let response = app.post_logout().await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_delete_expired_rows() {
+    let mut app = TestApp::with_token_store_backend(TokenStoreBackend::Postgres).await;
+
+    sqlx::query(
+        "INSERT INTO banned_tokens (token, expires_at) VALUES ('expired', now() - interval '1 second')",
+    )
+    .execute(&app.pg_pool)
+    .await
+    .expect("Failed to insert expired token");
+
+    sqlx::query(
+        "INSERT INTO two_fa_codes (email, login_attempt_id, code, expires_at) \
+         VALUES ('expired@example.com', 'id', '123456', now() - interval '1 second')",
+    )
+    .execute(&app.pg_pool)
+    .await
+    .expect("Failed to insert expired 2FA code");
+
+    let mut banned_token_store = PostgresBannedTokenStore::new(app.pg_pool.clone());
+    banned_token_store
+        .add_token("active".to_owned())
+        .await
+        .expect("Failed to ban token");
+
+    assert!(!banned_token_store.contains_token("expired").await.unwrap());
+    assert!(banned_token_store.contains_token("active").await.unwrap());
+
+    assert_eq!(banned_token_store.delete_expired().await.unwrap(), 1);
+    assert_eq!(
+        PostgresTwoFACodeStore::new(app.pg_pool.clone())
+            .delete_expired()
+            .await
+            .unwrap(),
+        1
+    );
+    assert!(banned_token_store.contains_token("active").await.unwrap());
+
+    app.clean_up().await;
+}
@@ -1,7 +1,7 @@
-use auth_service::{routes::SignupResponse, ErrorResponse};
+use auth_service::{domain::EmailDomainRule, routes::SignupResponse, ErrorResponse};
 use test_helpers::api_test;
 
-use crate::helpers::{get_random_email, TestApp};
+use crate::helpers::{get_random_email, TestApp, BREACHED_PASSWORD};
 
 #[api_test]
 async fn should_return_201_if_valid_input() {
@@ -9,7 +9,7 @@ async fn should_return_201_if_valid_input() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "spoon-galaxy-trumpet-47",
         "requires2FA": true
     });
 
@@ -37,7 +37,7 @@ async fn should_return_400_if_invalid_input() {
     let input = [
         serde_json::json!({
             "email": "",
-            "password": "password123",
+            "password": "spoon-galaxy-trumpet-47",
             "requires2FA": true
         }),
         serde_json::json!({
@@ -52,7 +52,7 @@ async fn should_return_400_if_invalid_input() {
         }),
         serde_json::json!({
             "email": "invalid_email",
-            "password": "password123",
+            "password": "spoon-galaxy-trumpet-47",
             "requires2FA": true
         }),
         serde_json::json!({
@@ -77,13 +77,158 @@ async fn should_return_400_if_invalid_input() {
     }
 }
 
+#[api_test]
+async fn should_return_400_with_reasons_if_password_violates_policy() {
+    let random_email = get_random_email();
+    let local_part = random_email.split('@').next().unwrap().to_owned();
+
+    let test_cases = [
+        ("password123".to_owned(), "too_weak"),
+        ("aaaaaaaaaaaa".to_owned(), "too_weak"),
+        ("x".repeat(129), "too_long"),
+        (format!("{}-spoon-galaxy", local_part), "contains_email"),
+    ];
+
+    for (password, expected_code) in test_cases {
+        let signup_body = serde_json::json!({
+            "email": random_email,
+            "password": password,
+            "requires2FA": false
+        });
+
+        let response = app.post_signup(&signup_body).await;
+        assert_eq!(response.status().as_u16(), 400, "Failed for: {}", password);
+
+        let body = response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse");
+        assert_eq!(body.error, "Password does not meet the password policy");
+
+        let violations = body.details.expect("Missing error details")["violations"].clone();
+        let codes: Vec<&str> = violations
+            .as_array()
+            .expect("Violations should be a list")
+            .iter()
+            .map(|violation| violation["code"].as_str().unwrap())
+            .collect();
+        assert!(
+            codes.contains(&expected_code),
+            "Expected {} for {}, got {:?}",
+            expected_code,
+            password,
+            codes
+        );
+    }
+
+    let body = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "aaaaaaaaaaaa",
+            "requires2FA": false
+        }))
+        .await
+        .json::<serde_json::Value>()
+        .await
+        .unwrap();
+    let violation = &body["details"]["violations"][0];
+    assert_eq!(violation["minScore"], 3);
+    assert!(violation["warning"].is_string());
+    assert!(!violation["suggestions"].as_array().unwrap().is_empty());
+}
+
+#[api_test]
+async fn should_return_400_if_password_was_breached() {
+    let signup_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": BREACHED_PASSWORD,
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let body = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(body.error, "Password does not meet the password policy");
+    assert_eq!(
+        body.details,
+        Some(serde_json::json!({ "violations": [{ "code": "breached" }] }))
+    );
+}
+
+#[api_test]
+async fn should_return_400_if_email_domain_is_disposable() {
+    let signup_body = serde_json::json!({
+        "email": "someone@mailinator.com",
+        "password": "spoon-galaxy-trumpet-47",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let body = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(body.error, "Disposable email addresses are not allowed");
+    assert_eq!(
+        body.details,
+        Some(serde_json::json!({ "reason": "disposable" }))
+    );
+}
+
+#[api_test]
+async fn should_apply_admin_email_domain_rules() {
+    {
+        let mut rule_store = app.email_domain_rule_store.write().await;
+        rule_store
+            .set_rule("blocked.example.com", EmailDomainRule::Deny)
+            .await
+            .unwrap();
+        rule_store
+            .set_rule("mailinator.com", EmailDomainRule::Allow)
+            .await
+            .unwrap();
+    }
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": "someone@eu.blocked.example.com",
+            "password": "spoon-galaxy-trumpet-47",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .details,
+        Some(serde_json::json!({ "reason": "blocked" }))
+    );
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": "someone@mailinator.com",
+            "password": "spoon-galaxy-trumpet-47",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
 #[api_test]
 async fn should_return_409_if_email_already_exists() {
     let random_email = get_random_email();
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "spoon-galaxy-trumpet-47",
         "requires2FA": true
     });
 
@@ -105,13 +250,36 @@ async fn should_return_409_if_email_already_exists() {
     );
 }
 
+#[api_test]
+async fn should_return_409_if_email_exists_with_different_case() {
+    let random_email = get_random_email();
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email.to_uppercase(),
+            "password": "spoon-galaxy-trumpet-47",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "spoon-galaxy-trumpet-47",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 409);
+}
+
 #[api_test]
 async fn should_return_422_if_malformed_input() {
     let random_email = get_random_email();
 
     let test_cases = [
         serde_json::json!({
-            "password": "password123",
+            "password": "spoon-galaxy-trumpet-47",
             "requires2FA": true
         }),
         serde_json::json!({
@@ -120,11 +288,11 @@ async fn should_return_422_if_malformed_input() {
         }),
         serde_json::json!({
             "email": random_email,
-            "password": "password123",
+            "password": "spoon-galaxy-trumpet-47",
         }),
         serde_json::json!({
             "email": random_email,
-            "password": "password123",
+            "password": "spoon-galaxy-trumpet-47",
             "requires2FA": "true"
         }),
         serde_json::json!({}),
@@ -14,7 +14,7 @@ async fn should_return_200_if_correct_code() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "spoon-galaxy-trumpet-47",
         "requires2FA": true
     });
 
@@ -24,7 +24,7 @@ async fn should_return_200_if_correct_code() {
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123"
+        "password": "spoon-galaxy-trumpet-47"
     });
 
     let response = app.post_login(&login_body).await;
@@ -127,7 +127,7 @@ async fn should_return_401_if_incorrect_credentials() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "spoon-galaxy-trumpet-47",
         "requires2FA": true
     });
 
@@ -139,7 +139,7 @@ async fn should_return_401_if_incorrect_credentials() {
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123"
+        "password": "spoon-galaxy-trumpet-47"
     });
 
     let response = app.post_login(&login_body).await;
@@ -223,7 +223,7 @@ async fn should_return_401_if_old_code() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "spoon-galaxy-trumpet-47",
         "requires2FA": true
     });
 
@@ -235,7 +235,7 @@ async fn should_return_401_if_old_code() {
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123"
+        "password": "spoon-galaxy-trumpet-47"
     });
 
     let response = app.post_login(&login_body).await;
@@ -287,7 +287,7 @@ async fn should_return_401_if_same_code_twice() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "spoon-galaxy-trumpet-47",
         "requires2FA": true
     });
 
@@ -297,7 +297,7 @@ async fn should_return_401_if_same_code_twice() {
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123"
+        "password": "spoon-galaxy-trumpet-47"
     });
 
     let response = app.post_login(&login_body).await;
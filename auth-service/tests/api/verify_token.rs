@@ -1,4 +1,6 @@
-use auth_service::{utils::constants::JWT_COOKIE_NAME, ErrorResponse};
+use auth_service::{
+    routes::IntrospectionResponse, utils::constants::JWT_COOKIE_NAME, ErrorResponse,
+};
 use test_helpers::api_test;
 
 use crate::helpers::{get_random_email, TestApp};
@@ -9,7 +11,7 @@ async fn should_return_200_valid_token() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "spoon-galaxy-trumpet-47",
         "requires2FA": false
     });
 
@@ -19,7 +21,7 @@ async fn should_return_200_valid_token() {
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "spoon-galaxy-trumpet-47",
     });
 
     let response = app.post_login(&login_body).await;
@@ -42,6 +44,13 @@ async fn should_return_200_valid_token() {
     let response = app.post_verify_token(&verify_token_body).await;
 
     assert_eq!(response.status().as_u16(), 200);
+
+    let body = response
+        .json::<IntrospectionResponse>()
+        .await
+        .expect("Could not deserialize response body to IntrospectionResponse");
+    assert_eq!(body.sub.as_deref(), Some(random_email.as_str()));
+    assert_eq!(body.token_type.as_deref(), Some("session"));
 }
 
 #[api_test]
@@ -74,7 +83,7 @@ async fn should_return_401_if_banned_token() {
 
     let signup_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "spoon-galaxy-trumpet-47",
         "requires2FA": false
     });
 
@@ -84,7 +93,7 @@ async fn should_return_401_if_banned_token() {
 
     let login_body = serde_json::json!({
         "email": random_email,
-        "password": "password123",
+        "password": "spoon-galaxy-trumpet-47",
     });
 
     let response = app.post_login(&login_body).await;
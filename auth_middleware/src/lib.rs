@@ -0,0 +1,13 @@
+//! Lets other services check the tokens auth-service hands out, either locally with the JWT
+//! secret or by asking auth-service to introspect them.
+
+mod user;
+mod verifier;
+
+pub use user::*;
+pub use verifier::*;
+
+/// The cookie auth-service keeps the auth token in.
+pub const AUTH_COOKIE_NAME: &str = "jwt";
+/// The auth cookie's name when auth-service runs with `AUTH_COOKIE_HOST_PREFIX`.
+pub const HOST_PREFIXED_AUTH_COOKIE_NAME: &str = "__Host-jwt";
@@ -0,0 +1,199 @@
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts, Request, State},
+    http::{header::AUTHORIZATION, request::Parts, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_extra::extract::CookieJar;
+use serde::{Deserialize, Serialize};
+
+use crate::{TokenVerifier, VerifyError, AUTH_COOKIE_NAME, HOST_PREFIXED_AUTH_COOKIE_NAME};
+
+/// Who a request was made by. Extracting it from a request verifies the token from the
+/// `Authorization: Bearer` header, or else the auth cookie, and rejects the request when that
+/// fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthenticatedUser {
+    /// The user's email, or the client id of a service.
+    pub subject: String,
+    pub kind: TokenKind,
+    pub session_id: Option<String>,
+    /// Empty for browser sessions, which have none.
+    pub scopes: Vec<String>,
+    /// As a Unix timestamp.
+    pub expires_at: u64,
+}
+
+impl AuthenticatedUser {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|granted| granted == scope)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    Session,
+    ApiKey,
+    Service,
+    /// Issued to an app for a user with the code flow.
+    Access,
+}
+
+#[derive(Debug)]
+pub enum AuthRejection {
+    MissingToken,
+    InvalidToken,
+    Unavailable,
+}
+
+impl IntoResponse for AuthRejection {
+    fn into_response(self) -> Response {
+        match self {
+            Self::MissingToken | Self::InvalidToken => StatusCode::UNAUTHORIZED,
+            Self::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        }
+        .into_response()
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthenticatedUser
+where
+    TokenVerifier: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AuthRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // Already verified by `require_auth`
+        if let Some(user) = parts.extensions.get::<AuthenticatedUser>() {
+            return Ok(user.clone());
+        }
+
+        let token = request_token(parts).ok_or(AuthRejection::MissingToken)?;
+        TokenVerifier::from_ref(state)
+            .verify(&token)
+            .await
+            .map_err(|e| match e {
+                VerifyError::InvalidToken => AuthRejection::InvalidToken,
+                VerifyError::Unavailable(_) => AuthRejection::Unavailable,
+            })
+    }
+}
+
+/// Middleware rejecting requests without a valid token, for
+/// `axum::middleware::from_fn_with_state`. Handlers behind it can still extract the
+/// `AuthenticatedUser` without verifying the token again.
+pub async fn require_auth(
+    State(verifier): State<TokenVerifier>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AuthRejection> {
+    let (mut parts, body) = request.into_parts();
+    let user = AuthenticatedUser::from_request_parts(&mut parts, &verifier).await?;
+    parts.extensions.insert(user);
+
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
+
+fn request_token(parts: &Parts) -> Option<String> {
+    let bearer = parts
+        .headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    bearer.map(str::to_owned).or_else(|| {
+        let jar = CookieJar::from_headers(&parts.headers);
+        jar.get(HOST_PREFIXED_AUTH_COOKIE_NAME)
+            .or_else(|| jar.get(AUTH_COOKIE_NAME))
+            .map(|cookie| cookie.value().to_owned())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::{body::Body, middleware, routing::get, Router};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    const SECRET: &[u8] = b"secret";
+
+    fn token() -> String {
+        let claims = serde_json::json!({ "sub": "test@example.com", "exp": u32::MAX });
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(SECRET),
+        )
+        .unwrap()
+    }
+
+    async fn whoami(user: AuthenticatedUser) -> String {
+        user.subject
+    }
+
+    async fn status(app: Router, header: Option<(&str, String)>) -> StatusCode {
+        let mut request = Request::builder().uri("/");
+        if let Some((name, value)) = header {
+            request = request.header(name, value);
+        }
+
+        app.oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn extracts_users_from_bearer_tokens_and_cookies() {
+        let app = Router::new()
+            .route("/", get(whoami))
+            .with_state(TokenVerifier::local(SECRET));
+
+        let bearer = ("authorization", format!("Bearer {}", token()));
+        assert_eq!(status(app.clone(), Some(bearer)).await, StatusCode::OK);
+        let cookie = ("cookie", format!("{}={}", AUTH_COOKIE_NAME, token()));
+        assert_eq!(status(app.clone(), Some(cookie)).await, StatusCode::OK);
+        let cookie = (
+            "cookie",
+            format!("{}={}", HOST_PREFIXED_AUTH_COOKIE_NAME, token()),
+        );
+        assert_eq!(status(app.clone(), Some(cookie)).await, StatusCode::OK);
+
+        assert_eq!(status(app.clone(), None).await, StatusCode::UNAUTHORIZED);
+        let invalid = ("authorization", "Bearer invalid_token".to_owned());
+        assert_eq!(status(app, Some(invalid)).await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn middleware_guards_whole_routers() {
+        let verifier = TokenVerifier::local(SECRET);
+        let app = Router::new()
+            .route("/", get(|| async { "secret" }))
+            .layer(middleware::from_fn_with_state(verifier, require_auth));
+
+        assert_eq!(status(app.clone(), None).await, StatusCode::UNAUTHORIZED);
+        let bearer = ("authorization", format!("Bearer {}", token()));
+        assert_eq!(status(app, Some(bearer)).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn unreachable_auth_service_is_503() {
+        let verifier =
+            TokenVerifier::remote_unregistered("http://127.0.0.1:1", Duration::from_secs(30));
+        let app = Router::new().route("/", get(whoami)).with_state(verifier);
+
+        let bearer = ("authorization", format!("Bearer {}", token()));
+        assert_eq!(
+            status(app, Some(bearer)).await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
+}
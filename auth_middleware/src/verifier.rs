@@ -0,0 +1,380 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+use crate::{AuthenticatedUser, TokenKind};
+
+/// How long introspection answers are reused by default.
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(30);
+const MAX_CACHED_TOKENS: usize = 10_000;
+
+/// Checks tokens issued by auth-service.
+#[derive(Clone)]
+pub struct TokenVerifier {
+    mode: Arc<Mode>,
+}
+
+enum Mode {
+    Local(DecodingKey),
+    Remote(Introspector),
+}
+
+#[derive(Debug, Error)]
+pub enum VerifyError {
+    #[error("Invalid token")]
+    InvalidToken,
+    #[error("Auth service unavailable")]
+    Unavailable(#[source] reqwest::Error),
+}
+
+impl TokenVerifier {
+    /// Verifies auth tokens with auth-service's `JWT_SECRET`, without any requests to it. Tokens
+    /// of users who logged out still pass until they expire, and API keys are never accepted.
+    pub fn local(jwt_secret: &[u8]) -> Self {
+        Self {
+            mode: Arc::new(Mode::Local(DecodingKey::from_secret(jwt_secret))),
+        }
+    }
+
+    /// Asks auth-service at `auth_service_url`, e.g. `http://auth-service:3000`, to introspect
+    /// tokens, as the registered client `client_id`. Answers are reused for `cache_ttl`, so a
+    /// token may still pass for that long after it was revoked.
+    pub fn remote(
+        auth_service_url: &str,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+        cache_ttl: Duration,
+    ) -> Self {
+        let client = ClientCredentials {
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+        };
+        Self::introspecting(auth_service_url, Some(client), cache_ttl)
+    }
+
+    /// Like `remote`, for services that aren't registered clients of auth-service. Tokens are
+    /// checked at its `/verify-token` instead.
+    pub fn remote_unregistered(auth_service_url: &str, cache_ttl: Duration) -> Self {
+        Self::introspecting(auth_service_url, None, cache_ttl)
+    }
+
+    fn introspecting(
+        auth_service_url: &str,
+        client: Option<ClientCredentials>,
+        cache_ttl: Duration,
+    ) -> Self {
+        let path = match client {
+            Some(_) => "/oauth/introspect",
+            None => "/verify-token",
+        };
+
+        Self {
+            mode: Arc::new(Mode::Remote(Introspector {
+                http_client: reqwest::Client::new(),
+                url: format!("{}{}", auth_service_url.trim_end_matches('/'), path),
+                client,
+                cache_ttl,
+                cache: Mutex::new(HashMap::new()),
+            })),
+        }
+    }
+
+    pub async fn verify(&self, token: &str) -> Result<AuthenticatedUser, VerifyError> {
+        match self.mode.as_ref() {
+            Mode::Local(decoding_key) => verify_locally(token, decoding_key),
+            Mode::Remote(introspector) => introspector.verify(token).await,
+        }
+    }
+}
+
+/// The claims of auth-service's auth tokens.
+#[derive(Deserialize)]
+struct Claims {
+    sub: String,
+    exp: u64,
+    #[serde(default)]
+    sid: String,
+}
+
+fn verify_locally(
+    token: &str,
+    decoding_key: &DecodingKey,
+) -> Result<AuthenticatedUser, VerifyError> {
+    // Like auth-service, this rejects tokens with an audience, so service tokens don't pass
+    let claims = decode::<Claims>(token, decoding_key, &Validation::default())
+        .map_err(|_| VerifyError::InvalidToken)?
+        .claims;
+
+    Ok(AuthenticatedUser {
+        subject: claims.sub,
+        kind: TokenKind::Session,
+        session_id: Some(claims.sid).filter(|sid| !sid.is_empty()),
+        scopes: Vec::new(),
+        expires_at: claims.exp,
+    })
+}
+
+struct Introspector {
+    http_client: reqwest::Client,
+    url: String,
+    /// Without them, `url` is auth-service's `/verify-token`.
+    client: Option<ClientCredentials>,
+    cache_ttl: Duration,
+    cache: Mutex<HashMap<String, CachedAnswer>>,
+}
+
+struct ClientCredentials {
+    client_id: String,
+    client_secret: String,
+}
+
+struct CachedAnswer {
+    user: Option<AuthenticatedUser>,
+    until: Instant,
+}
+
+#[derive(Serialize)]
+struct VerifyTokenRequest<'a> {
+    token: &'a str,
+}
+
+#[derive(Deserialize)]
+struct IntrospectionResponse {
+    active: bool,
+    sub: Option<String>,
+    exp: Option<u64>,
+    scope: Option<String>,
+    sid: Option<String>,
+    token_type: Option<TokenKind>,
+}
+
+impl Introspector {
+    async fn verify(&self, token: &str) -> Result<AuthenticatedUser, VerifyError> {
+        let now = Instant::now();
+        if let Some(answer) = self.cache.lock().await.get(token) {
+            if answer.until > now {
+                return answer.user.clone().ok_or(VerifyError::InvalidToken);
+            }
+        }
+
+        let user = self.introspect(token).await?;
+
+        // Never past the token's expiry
+        let expires_in = user
+            .as_ref()
+            .map(|user| Duration::from_secs(user.expires_at.saturating_sub(now_seconds())))
+            .unwrap_or(self.cache_ttl);
+        let answer = CachedAnswer {
+            user: user.clone(),
+            until: now + self.cache_ttl.min(expires_in),
+        };
+
+        let mut cache = self.cache.lock().await;
+        if cache.len() >= MAX_CACHED_TOKENS {
+            cache.retain(|_, answer| answer.until > now);
+            if cache.len() >= MAX_CACHED_TOKENS {
+                cache.clear();
+            }
+        }
+        cache.insert(token.to_owned(), answer);
+
+        user.ok_or(VerifyError::InvalidToken)
+    }
+
+    async fn introspect(&self, token: &str) -> Result<Option<AuthenticatedUser>, VerifyError> {
+        let request = match &self.client {
+            Some(client) => self
+                .http_client
+                .post(&self.url)
+                .basic_auth(&client.client_id, Some(&client.client_secret))
+                .form(&[("token", token)]),
+            None => self
+                .http_client
+                .post(&self.url)
+                .json(&VerifyTokenRequest { token }),
+        };
+        let response = request.send().await.map_err(VerifyError::Unavailable)?;
+        // `/verify-token` turns invalid tokens away, where introspection calls them inactive
+        if self.client.is_none() && response.status() == StatusCode::UNAUTHORIZED {
+            return Ok(None);
+        }
+
+        let response = response
+            .error_for_status()
+            .map_err(VerifyError::Unavailable)?
+            .json::<IntrospectionResponse>()
+            .await
+            .map_err(VerifyError::Unavailable)?;
+
+        let IntrospectionResponse {
+            active: true,
+            sub: Some(subject),
+            exp: Some(expires_at),
+            ..
+        } = response
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(AuthenticatedUser {
+            subject,
+            kind: response.token_type.unwrap_or(TokenKind::Session),
+            session_id: response.sid,
+            scopes: response
+                .scope
+                .map(|scope| scope.split_whitespace().map(str::to_owned).collect())
+                .unwrap_or_default(),
+            expires_at,
+        }))
+    }
+}
+
+fn now_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::{routing::post, Json, Router};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    use super::*;
+
+    const SECRET: &[u8] = b"secret";
+
+    fn token(claims: serde_json::Value) -> String {
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(SECRET),
+        )
+        .unwrap()
+    }
+
+    /// Serves an introspection endpoint that finds every token active, counting the requests.
+    async fn introspection_server(requests: Arc<AtomicUsize>) -> String {
+        let app = Router::new().route(
+            "/oauth/introspect",
+            post(move || async move {
+                requests.fetch_add(1, Ordering::SeqCst);
+                Json(serde_json::json!({
+                    "active": true,
+                    "sub": "test@example.com",
+                    "exp": now_seconds() + 600,
+                    "scope": "user:read user:write",
+                    "token_type": "api_key",
+                }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        address
+    }
+
+    #[tokio::test]
+    async fn verifies_auth_tokens_locally() {
+        let verifier = TokenVerifier::local(SECRET);
+        let exp = now_seconds() + 600;
+
+        let user = verifier
+            .verify(&token(serde_json::json!({
+                "sub": "test@example.com",
+                "exp": exp,
+                "sid": "session",
+            })))
+            .await
+            .unwrap();
+        assert_eq!(user.subject, "test@example.com");
+        assert_eq!(user.kind, TokenKind::Session);
+        assert_eq!(user.session_id.as_deref(), Some("session"));
+        assert_eq!(user.expires_at, exp);
+
+        for claims in [
+            serde_json::json!({ "sub": "test@example.com", "exp": now_seconds() - 600 }),
+            serde_json::json!({ "sub": "billing", "aud": "users-api", "exp": exp }),
+        ] {
+            assert!(matches!(
+                verifier.verify(&token(claims)).await,
+                Err(VerifyError::InvalidToken)
+            ));
+        }
+        assert!(verifier.verify("invalid_token").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn caches_introspection_answers() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let address = introspection_server(requests.clone()).await;
+        let verifier = TokenVerifier::remote(&address, "app", "secret", DEFAULT_CACHE_TTL);
+
+        for _ in 0..3 {
+            let user = verifier.verify("ak_some_key").await.unwrap();
+            assert_eq!(user.kind, TokenKind::ApiKey);
+            assert!(user.has_scope("user:write"));
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        let verifier = TokenVerifier::remote(&address, "app", "secret", Duration::ZERO);
+        verifier.verify("ak_some_key").await.unwrap();
+        verifier.verify("ak_some_key").await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn verifies_tokens_at_verify_token_without_client_credentials() {
+        let app = Router::new().route(
+            "/verify-token",
+            post(|Json(body): Json<serde_json::Value>| async move {
+                if body["token"] != "valid_token" {
+                    return Err(axum::http::StatusCode::UNAUTHORIZED);
+                }
+                Ok(Json(serde_json::json!({
+                    "active": true,
+                    "sub": "test@example.com",
+                    "exp": now_seconds() + 600,
+                    "sid": "session",
+                    "token_type": "session",
+                })))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let verifier = TokenVerifier::remote_unregistered(&address, DEFAULT_CACHE_TTL);
+
+        let user = verifier.verify("valid_token").await.unwrap();
+        assert_eq!(user.subject, "test@example.com");
+        assert_eq!(user.kind, TokenKind::Session);
+        assert!(!user.has_scope("user:read"));
+        assert!(matches!(
+            verifier.verify("logged_out_token").await,
+            Err(VerifyError::InvalidToken)
+        ));
+    }
+
+    #[tokio::test]
+    async fn fails_when_auth_service_is_unreachable() {
+        let verifier =
+            TokenVerifier::remote("http://127.0.0.1:1", "app", "secret", DEFAULT_CACHE_TTL);
+
+        assert!(matches!(
+            verifier.verify("token").await,
+            Err(VerifyError::Unavailable(_))
+        ));
+    }
+}